@@ -0,0 +1,465 @@
+use {
+    crate::{
+        cache::{MapCache, ScoreCache, TokenCache, UserCache},
+        error::PpifyError,
+        ratelimit,
+        retry::{self, HttpFailure, osu_failure},
+    },
+    governor::DefaultDirectRateLimiter,
+    rosu_v2::{client::Token, error::OsuError, prelude::*},
+    serde::Deserialize,
+    std::time::Duration,
+    tracing::{debug, instrument},
+};
+
+/// The osu! api client with the settings of ppify's requests to osu.ppy.sh.
+pub struct Client {
+    pub osu: Osu,
+    /// How often a request that failed for a transient reason is retried.
+    pub max_retries: u32,
+    /// Stops every request to osu.ppy.sh, only cached beatmaps, scores and profiles are used.
+    pub offline: bool,
+    /// Shared by every request and download of this client, see [`ratelimit::limiter`].
+    pub rate_limiter: DefaultDirectRateLimiter,
+}
+
+impl Client {
+    pub fn new(osu: Osu) -> Self {
+        Self {
+            osu,
+            max_retries: retry::DEFAULT_MAX_RETRIES,
+            offline: false,
+            rate_limiter: ratelimit::limiter(None, None),
+        }
+    }
+
+    /// Fails with what couldn't be done in offline mode, before a request would be sent.
+    pub(crate) fn ensure_online(&self, what: impl FnOnce() -> String) -> Result<(), PpifyError> {
+        if self.offline {
+            return Err(PpifyError::Offline(what()));
+        }
+
+        Ok(())
+    }
+}
+
+/// A client that never authenticates, for offline mode where every request fails before
+/// it is sent and for commands that only download beatmaps.
+pub async fn connect_offline() -> Result<Osu, PpifyError> {
+    Osu::builder()
+        .client_id(0)
+        .client_secret("")
+        .with_token(Token::new("", None), Some(i64::from(u32::MAX)))
+        .build()
+        .await
+        .map_err(PpifyError::AuthFailed)
+}
+
+/// Connects to the osu! api, reusing the access token in `cache` while it is valid.
+pub async fn connect(
+    client_id: u64,
+    client_secret: impl Into<String>,
+    cache: Option<&TokenCache>,
+) -> Result<Osu, PpifyError> {
+    let client_secret = client_secret.into();
+
+    let cached = cache.and_then(|cache| cache.get(client_id));
+
+    let (access_token, expires_in) = match cached {
+        Some(cached) => {
+            debug!(expires_in = ?cached.1, "reusing the cached access token");
+            cached
+        }
+        None => match request_token(client_id, &client_secret).await {
+            Ok(token) => {
+                debug!(expires_in = ?token.1, "requested a new access token");
+
+                if let Some(cache) = cache {
+                    // a failed cache write only costs a token request next time
+                    let _ = cache.put(client_id, &token.0, token.1);
+                }
+
+                token
+            }
+            // let rosu-v2 retry the grant, its error says what went wrong
+            Err(err) => {
+                debug!(%err, "token request failed, letting rosu-v2 authenticate");
+
+                return Osu::new(client_id, client_secret)
+                    .await
+                    .map_err(PpifyError::AuthFailed);
+            }
+        },
+    };
+
+    Osu::builder()
+        .client_id(client_id)
+        .client_secret(client_secret)
+        .with_token(
+            Token::new(&access_token, None),
+            Some(expires_in.as_secs() as i64),
+        )
+        .build()
+        .await
+        .map_err(PpifyError::AuthFailed)
+}
+
+#[derive(Deserialize)]
+struct TokenResponse {
+    access_token: String,
+    expires_in: u64,
+}
+
+// the client credentials grant `Osu::new` does, but keeping the token
+#[instrument(skip(client_secret))]
+async fn request_token(
+    client_id: u64,
+    client_secret: &str,
+) -> Result<(String, Duration), reqwest::Error> {
+    let client_id = client_id.to_string();
+
+    let token: TokenResponse = reqwest::Client::new()
+        .post("https://osu.ppy.sh/oauth/token")
+        .form(&[
+            ("client_id", client_id.as_str()),
+            ("client_secret", client_secret),
+            ("grant_type", "client_credentials"),
+            ("scope", "public"),
+        ])
+        .send()
+        .await?
+        .error_for_status()?
+        .json()
+        .await?;
+
+    Ok((token.access_token, Duration::from_secs(token.expires_in)))
+}
+
+#[instrument(skip(client))]
+pub async fn fetch_user_best_scores(
+    client: &Client,
+    user_input: &str,
+    mode: GameMode,
+) -> Result<Vec<Score>, PpifyError> {
+    let trimmed = user_input.trim();
+    client.ensure_online(|| format!("the top scores of `{trimmed}` aren't cached"))?;
+    let user = user_id(trimmed);
+
+    retry::retry(
+        client.max_retries,
+        &client.rate_limiter,
+        || {
+            client
+                .osu
+                .user_scores(user.clone())
+                .mode(mode)
+                .best()
+                .limit(100)
+                .into_future()
+        },
+        osu_failure,
+    )
+    .await
+    .map_err(|err| match err {
+        OsuError::NotFound => PpifyError::UserNotFound(trimmed.to_owned()),
+        err => PpifyError::network("failed to fetch user top scores", err),
+    })
+}
+
+/// Loads a user's top scores from `cache` if they are fresh enough, fetching them otherwise.
+///
+/// With `refresh` the cached scores are ignored and overwritten. Offline, cached scores
+/// are used no matter how old they are.
+pub async fn fetch_user_best_scores_cached(
+    client: &Client,
+    user_input: &str,
+    mode: GameMode,
+    cache: Option<&ScoreCache>,
+    refresh: bool,
+) -> Result<Vec<Score>, PpifyError> {
+    if client.offline
+        && let Some(cache) = cache
+        && let Some(scores) = cache.get_any_age(user_input, mode)
+    {
+        debug!(user_input, ?mode, "top scores cache hit (offline)");
+        return Ok(scores);
+    }
+
+    if let Some(cache) = cache
+        && !refresh
+        && let Some(scores) = cache.get(user_input, mode)
+    {
+        debug!(user_input, ?mode, "top scores cache hit");
+        return Ok(scores);
+    }
+
+    debug!(user_input, ?mode, refresh, "top scores cache miss");
+
+    let scores = fetch_user_best_scores(client, user_input, mode).await?;
+
+    if let Some(cache) = cache {
+        // a failed cache write only costs a refetch next time
+        let _ = cache.put(user_input, mode, &scores);
+    }
+
+    Ok(scores)
+}
+
+#[instrument(skip(client))]
+pub async fn fetch_user_recent_scores(
+    client: &Client,
+    user_input: &str,
+    mode: GameMode,
+) -> Result<Vec<Score>, PpifyError> {
+    let trimmed = user_input.trim();
+    client.ensure_online(|| format!("the recent scores of `{trimmed}` aren't cached"))?;
+    let user = user_id(trimmed);
+
+    retry::retry(
+        client.max_retries,
+        &client.rate_limiter,
+        || {
+            client
+                .osu
+                .user_scores(user.clone())
+                .mode(mode)
+                .recent()
+                .include_fails(true)
+                .limit(50)
+                .into_future()
+        },
+        osu_failure,
+    )
+    .await
+    .map_err(|err| match err {
+        OsuError::NotFound => PpifyError::UserNotFound(trimmed.to_owned()),
+        err => PpifyError::network("failed to fetch recent scores", err),
+    })
+}
+
+#[instrument(skip(client))]
+pub async fn fetch_user(
+    client: &Client,
+    user_input: &str,
+    mode: GameMode,
+) -> Result<UserExtended, PpifyError> {
+    let trimmed = user_input.trim();
+    client.ensure_online(|| format!("the profile of `{trimmed}` isn't cached"))?;
+    let user = user_id(trimmed);
+
+    retry::retry(
+        client.max_retries,
+        &client.rate_limiter,
+        || client.osu.user(user.clone()).mode(mode).into_future(),
+        osu_failure,
+    )
+    .await
+    .map_err(|err| match err {
+        OsuError::NotFound => PpifyError::UserNotFound(trimmed.to_owned()),
+        err => PpifyError::network("failed to fetch user", err),
+    })
+}
+
+/// Loads a user's profile from `cache` if it is fresh enough, fetching it otherwise.
+///
+/// Same rules as [`fetch_user_best_scores_cached`].
+pub async fn fetch_user_cached(
+    client: &Client,
+    user_input: &str,
+    mode: GameMode,
+    cache: Option<&UserCache>,
+    refresh: bool,
+) -> Result<UserExtended, PpifyError> {
+    if client.offline
+        && let Some(cache) = cache
+        && let Some(user) = cache.get_any_age(user_input, mode)
+    {
+        debug!(user_input, ?mode, "profile cache hit (offline)");
+        return Ok(user);
+    }
+
+    if let Some(cache) = cache
+        && !refresh
+        && let Some(user) = cache.get(user_input, mode)
+    {
+        debug!(user_input, ?mode, "profile cache hit");
+        return Ok(user);
+    }
+
+    debug!(user_input, ?mode, refresh, "profile cache miss");
+
+    let user = fetch_user(client, user_input, mode).await?;
+
+    if let Some(cache) = cache {
+        // a failed cache write only costs a refetch next time
+        let _ = cache.put(user_input, mode, &user);
+    }
+
+    Ok(user)
+}
+
+#[instrument(skip(client))]
+pub async fn fetch_score(
+    client: &Client,
+    score_id: u64,
+    mode: Option<GameMode>,
+) -> Result<Score, PpifyError> {
+    client.ensure_online(|| format!("score {score_id} can't be fetched"))?;
+
+    let request = || {
+        let request = client.osu.score(score_id);
+
+        // legacy score ids are only unique per mode
+        match mode {
+            Some(mode) => request.mode(mode),
+            None => request,
+        }
+        .into_future()
+    };
+
+    retry::retry(
+        client.max_retries,
+        &client.rate_limiter,
+        request,
+        osu_failure,
+    )
+    .await
+    .map_err(|err| match err {
+        OsuError::NotFound => PpifyError::BadInput(format!("score {score_id} was not found")),
+        err => PpifyError::network("failed to fetch score", err),
+    })
+}
+
+#[instrument(skip(client))]
+pub async fn fetch_beatmapset(
+    client: &Client,
+    set_id: u32,
+) -> Result<BeatmapsetExtended, PpifyError> {
+    client.ensure_online(|| format!("beatmapset {set_id} can't be looked up"))?;
+
+    retry::retry(
+        client.max_retries,
+        &client.rate_limiter,
+        || client.osu.beatmapset(set_id).into_future(),
+        osu_failure,
+    )
+    .await
+    .map_err(|err| match err {
+        OsuError::NotFound => PpifyError::BadInput(format!("beatmapset {set_id} was not found")),
+        err => PpifyError::network("failed to fetch beatmapset", err),
+    })
+}
+
+#[instrument(skip(client))]
+pub async fn search_beatmapsets(
+    client: &Client,
+    query: &str,
+    mode: Option<GameMode>,
+) -> Result<Vec<BeatmapsetExtended>, PpifyError> {
+    client.ensure_online(|| "beatmaps can't be searched".to_owned())?;
+
+    let search = || {
+        let search = client.osu.beatmapset_search().query(query);
+
+        match mode {
+            Some(mode) => search.mode(mode),
+            None => search,
+        }
+        .into_future()
+    };
+
+    retry::retry(
+        client.max_retries,
+        &client.rate_limiter,
+        search,
+        osu_failure,
+    )
+    .await
+    .map(|result| result.mapsets)
+    .map_err(|err| PpifyError::network("beatmap search failed", err))
+}
+
+#[instrument(skip(client))]
+pub async fn download_osu_file(client: &Client, map_id: u32) -> Result<Vec<u8>, PpifyError> {
+    client.ensure_online(|| format!("beatmap {map_id} isn't cached"))?;
+
+    let url = format!("https://osu.ppy.sh/osu/{map_id}");
+
+    let bytes = retry::retry(
+        client.max_retries,
+        &client.rate_limiter,
+        || download(&url),
+        HttpFailure::failure,
+    )
+    .await
+    .map_err(|err| match err.source.status() {
+        Some(reqwest::StatusCode::NOT_FOUND) => PpifyError::MapNotFound(map_id),
+        Some(_) => PpifyError::network(format!("{url} returned non-success status"), err.source),
+        None => PpifyError::network(format!("GET {url} failed"), err.source),
+    })?;
+
+    // osu.ppy.sh answers unknown ids with an empty 200 instead of a 404
+    if bytes.is_empty() {
+        return Err(PpifyError::MapNotFound(map_id));
+    }
+
+    Ok(bytes)
+}
+
+async fn download(url: &str) -> Result<Vec<u8>, HttpFailure> {
+    let res = reqwest::get(url).await?;
+    let retry_after = retry::retry_after(res.headers());
+
+    let res = res.error_for_status().map_err(|source| HttpFailure {
+        source,
+        retry_after,
+    })?;
+
+    Ok(res.bytes().await?.to_vec())
+}
+
+fn user_id(user_input: &str) -> UserId {
+    match user_input.parse::<u32>() {
+        Ok(id) => id.into(),
+        Err(_) => user_input.into(),
+    }
+}
+
+/// The api's md5 of the beatmap of `score`, if the score includes it.
+pub fn map_checksum(score: &Score) -> Option<&str> {
+    score.map.as_ref()?.checksum.as_deref()
+}
+
+/// Loads a `.osu` file from `cache` if possible, downloading it otherwise.
+///
+/// `checksum` is the api's md5 of the beatmap if known, a cached copy of an older
+/// version is downloaded again then. With `refresh` the cached copy is ignored and
+/// overwritten, except offline.
+pub async fn fetch_osu_file(
+    client: &Client,
+    map_id: u32,
+    checksum: Option<&str>,
+    cache: Option<&MapCache>,
+    refresh: bool,
+) -> Result<Vec<u8>, PpifyError> {
+    if let Some(cache) = cache
+        && (!refresh || client.offline)
+        && let Some(bytes) = cache.get(map_id, checksum)
+    {
+        debug!(map_id, "beatmap cache hit");
+        return Ok(bytes);
+    }
+
+    debug!(map_id, refresh, "beatmap cache miss");
+
+    let bytes = download_osu_file(client, map_id).await?;
+
+    if let Some(cache) = cache
+        && let Err(err) = cache.put(map_id, &bytes, checksum)
+    {
+        // a failed cache write only costs a re-download next time
+        debug!(map_id, %err, "beatmap not cached");
+    }
+
+    Ok(bytes)
+}
@@ -0,0 +1,487 @@
+use {
+    crate::{
+        cli::{Cli, ModeArg},
+        fetch_profile, map_cache, markdown, modes_for,
+        pipe::native_mode,
+        progress, read_mode,
+    },
+    color_eyre::{
+        Result,
+        eyre::{self, Context},
+    },
+    futures_util::{StreamExt, stream},
+    ppify::{
+        PpifyError,
+        api::{self, Client},
+        batch_file::{self, BatchResult, BatchRow},
+        calc::{self, BeatmapMetadata, BonusPp, DetailedJudgements, Judgements, PlayInputs, Rules},
+        export, mods,
+    },
+    rayon::prelude::*,
+    rosu_pp::model::mode::GameMode as PpGameMode,
+    rosu_v2::prelude::{GameMode, Score, UserExtended},
+    serde::{Deserialize, Serialize},
+    std::{
+        collections::{HashMap, HashSet},
+        fmt::Display,
+        fs::{self, File},
+        path::Path,
+    },
+};
+
+/// Beatmap downloads in flight at once, the rate limit still applies.
+const CONCURRENT_DOWNLOADS: usize = 8;
+
+pub async fn run(osu: &Client, cli: &Cli, input: &Path, output: Option<&Path>) -> Result<()> {
+    let file = File::open(input).with_context(|| format!("failed to open {}", input.display()))?;
+    let rows = batch_file::read_rows(file)?;
+
+    let (api_mode, pp_mode) = match cli.mode {
+        Some(mode) => mode.modes(),
+        None => read_mode()?,
+    };
+
+    // rows often repeat a map with different mods or accuracies
+    let map_ids: HashSet<u32> = rows.iter().map(|row| row.map_id).collect();
+    let maps = download_maps(osu, cli, map_ids).await;
+
+    let calculations = progress::bar(rows.len(), "Rows");
+    let rules = Rules::new(cli.stable);
+
+    // the difficulty calculation is CPU-bound, so it spreads over all cores
+    let results: Vec<_> = rows
+        .par_iter()
+        .map(|row| {
+            let result = match &maps[&row.map_id] {
+                Ok(bytes) => calculate_row(row, bytes, api_mode, pp_mode, rules),
+                Err(err) => Err(eyre::eyre!("{err}")),
+            };
+
+            calculations.inc(1);
+
+            result.unwrap_or_else(|err| BatchResult {
+                map_id: row.map_id,
+                mods: row.mods.clone(),
+                accuracy: row.accuracy,
+                misses: row.misses,
+                combo: row.combo,
+                beatmap: None,
+                stars: None,
+                pp: None,
+                error: Some(format!("line {}: {err}", row.line)),
+            })
+        })
+        .collect();
+
+    calculations.finish_and_clear();
+
+    if let Some(path) = output {
+        export::write_csv(path, &results)
+            .with_context(|| format!("failed to write {}", path.display()))?;
+    }
+
+    if cli.json {
+        let json = serde_json::to_string_pretty(&results).context("failed to serialize results")?;
+        println!("{json}");
+    } else if cli.markdown() {
+        print!("{}", markdown::batch(&results));
+    } else {
+        print_results(&results);
+    }
+
+    Ok(())
+}
+
+/// One element of a `--batch-json` file.
+#[derive(Clone, Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct BatchPlay {
+    map_id: u32,
+    #[serde(default)]
+    mods: String,
+    /// The map's own mode without it, or `--mode`.
+    mode: Option<ModeArg>,
+    /// Percent, 100 when neither it nor counts are given.
+    acc: Option<f64>,
+    #[serde(default)]
+    misses: u32,
+    /// Max combo when left out.
+    combo: Option<u32>,
+    n320: Option<u32>,
+    n300: Option<u32>,
+    n200: Option<u32>,
+    n100: Option<u32>,
+    n50: Option<u32>,
+    fruits: Option<u32>,
+    droplets: Option<u32>,
+    tiny_droplets: Option<u32>,
+    tiny_droplet_misses: Option<u32>,
+}
+
+impl BatchPlay {
+    fn judgements(&self, mode: GameMode) -> Result<Judgements> {
+        let counts = [
+            ("n320", self.n320),
+            ("n300", self.n300),
+            ("n200", self.n200),
+            ("n100", self.n100),
+            ("n50", self.n50),
+            ("fruits", self.fruits),
+            ("droplets", self.droplets),
+            ("tiny_droplets", self.tiny_droplets),
+            ("tiny_droplet_misses", self.tiny_droplet_misses),
+        ];
+
+        if counts.iter().all(|(_, count)| count.is_none()) {
+            let accuracy = self.acc.unwrap_or(100.0);
+
+            if !(0.0..=100.0).contains(&accuracy) {
+                eyre::bail!("acc must be between 0 and 100, got {accuracy}");
+            }
+
+            return Ok(Judgements::Accuracy {
+                accuracy,
+                misses: self.misses,
+            });
+        }
+
+        if self.acc.is_some() {
+            eyre::bail!("give either acc or judgement counts, not both");
+        }
+
+        let allowed: &[&str] = match mode {
+            GameMode::Osu => &["n300", "n100", "n50"],
+            GameMode::Taiko => &["n300", "n100"],
+            GameMode::Catch => &["fruits", "droplets", "tiny_droplets", "tiny_droplet_misses"],
+            GameMode::Mania => &["n320", "n300", "n200", "n100", "n50"],
+        };
+
+        if let Some((name, _)) = counts
+            .iter()
+            .find(|(name, count)| count.is_some() && !allowed.contains(name))
+        {
+            eyre::bail!("{name} is not a {} judgement", mode.as_str());
+        }
+
+        let misses = self.misses;
+        let judgements = match mode {
+            GameMode::Osu => DetailedJudgements::Osu {
+                n300: self.n300.unwrap_or(0),
+                n100: self.n100.unwrap_or(0),
+                n50: self.n50.unwrap_or(0),
+                misses,
+                slider_end_hits: None,
+                large_tick_hits: None,
+            },
+            GameMode::Taiko => DetailedJudgements::Taiko {
+                n300: self.n300.unwrap_or(0),
+                n100: self.n100.unwrap_or(0),
+                misses,
+            },
+            GameMode::Catch => DetailedJudgements::Catch {
+                fruits: self.fruits.unwrap_or(0),
+                droplets: self.droplets.unwrap_or(0),
+                tiny_droplets: self.tiny_droplets.unwrap_or(0),
+                tiny_droplet_misses: self.tiny_droplet_misses.unwrap_or(0),
+                misses,
+            },
+            GameMode::Mania => DetailedJudgements::Mania {
+                n320: self.n320.unwrap_or(0),
+                n300: self.n300.unwrap_or(0),
+                n200: self.n200.unwrap_or(0),
+                n100: self.n100.unwrap_or(0),
+                n50: self.n50.unwrap_or(0),
+                misses,
+            },
+        };
+
+        Ok(Judgements::Detailed(judgements))
+    }
+}
+
+/// The outcome of one element of a `--batch-json` file, in the order of the file.
+#[derive(Clone, Debug, Serialize)]
+struct BatchPlayResult {
+    index: usize,
+    map_id: Option<u32>,
+    mode: Option<&'static str>,
+    #[serde(skip)]
+    api_mode: Option<GameMode>,
+    mods: Option<String>,
+    beatmap: Option<String>,
+    stars: Option<f64>,
+    pp: Option<f64>,
+    /// Change of the total pp of `--user`, `None` without it.
+    gain: Option<f64>,
+    warnings: Vec<String>,
+    /// Set instead of the other results when the element couldn't be calculated.
+    error: Option<String>,
+}
+
+impl BatchPlayResult {
+    fn failed(index: usize, map_id: Option<u32>, err: impl Display) -> Self {
+        Self {
+            index,
+            map_id,
+            mode: None,
+            api_mode: None,
+            mods: None,
+            beatmap: None,
+            stars: None,
+            pp: None,
+            gain: None,
+            warnings: Vec::new(),
+            error: Some(format!("{err:#}")),
+        }
+    }
+}
+
+/// `--batch-json`: calculates every play of a JSON array and prints the results as one.
+/// Elements that can't be read or calculated get an error, the others still run.
+pub async fn run_json(osu: &Client, cli: &Cli, input: &Path) -> Result<()> {
+    let content =
+        fs::read_to_string(input).with_context(|| format!("failed to read {}", input.display()))?;
+    let elements: Vec<serde_json::Value> = serde_json::from_str(&content).map_err(|err| {
+        PpifyError::BadInput(format!("{} is not a JSON array: {err}", input.display()))
+    })?;
+
+    let plays: Vec<Result<BatchPlay, serde_json::Error>> =
+        elements.into_iter().map(serde_json::from_value).collect();
+
+    let map_ids: HashSet<u32> = plays
+        .iter()
+        .filter_map(|play| play.as_ref().ok().map(|play| play.map_id))
+        .collect();
+    let maps = download_maps(osu, cli, map_ids).await;
+    let rules = Rules::new(cli.stable);
+
+    let mut results: Vec<_> = plays
+        .par_iter()
+        .enumerate()
+        .map(|(index, play)| {
+            let play = match play {
+                Ok(play) => play,
+                Err(err) => return BatchPlayResult::failed(index, None, err),
+            };
+
+            let result = match &maps[&play.map_id] {
+                Ok(bytes) => calculate_play(index, play, bytes, cli, rules),
+                Err(err) => Err(eyre::eyre!("{err}")),
+            };
+
+            result.unwrap_or_else(|err| BatchPlayResult::failed(index, Some(play.map_id), err))
+        })
+        .collect();
+
+    match cli.user.as_deref() {
+        Some(username) => add_gains(osu, cli, username, &mut results).await?,
+        None => eprintln!("Add --user for the profile gain of each play."),
+    }
+
+    let json = serde_json::to_string_pretty(&results).context("failed to serialize results")?;
+    println!("{json}");
+
+    Ok(())
+}
+
+fn calculate_play(
+    index: usize,
+    play: &BatchPlay,
+    bytes: &[u8],
+    cli: &Cli,
+    rules: Rules,
+) -> Result<BatchPlayResult> {
+    let native = calc::parse_native_beatmap(bytes)?;
+    let native_mode = native_mode(native.mode);
+    let (api_mode, pp_mode) = match play.mode.or(cli.mode) {
+        Some(mode) => mode.modes(),
+        None => modes_for(native_mode),
+    };
+    calc::check_map_mode(&native, pp_mode)?;
+
+    let selected_mods = mods::parse_acronyms(&play.mods, api_mode)?;
+    let game_mods = mods::lazer_mods(selected_mods.iter().copied(), api_mode);
+
+    let mut warnings = Vec::new();
+
+    if native_mode != api_mode {
+        warnings.push(format!(
+            "calculated as a {} convert of an {} map",
+            api_mode.as_str(),
+            native_mode.as_str()
+        ));
+    }
+
+    let max_combo = calc::max_combo(&native, pp_mode, &game_mods, rules);
+
+    if let Some(combo) = play.combo.filter(|&combo| combo > max_combo) {
+        warnings.push(format!(
+            "combo {combo} is above the max combo of {max_combo}"
+        ));
+    }
+
+    let inputs = PlayInputs {
+        judgements: play.judgements(api_mode)?,
+        combo: play.combo,
+    };
+    let perf_attrs = calc::calculate_play(&native, pp_mode, &game_mods, None, rules, &inputs);
+
+    Ok(BatchPlayResult {
+        index,
+        map_id: Some(play.map_id),
+        mode: Some(api_mode.as_str()),
+        api_mode: Some(api_mode),
+        mods: Some(selected_mods.iter().map(|m| m.acronym).collect()),
+        beatmap: Some(BeatmapMetadata::from_osu_bytes(bytes).to_string()),
+        stars: Some(perf_attrs.stars()),
+        pp: Some(perf_attrs.pp()),
+        gain: None,
+        warnings,
+        error: None,
+    })
+}
+
+// one profile per mode in the file, each play counted on its own
+async fn add_gains(
+    osu: &Client,
+    cli: &Cli,
+    username: &str,
+    results: &mut [BatchPlayResult],
+) -> Result<()> {
+    let mut profiles: Vec<(GameMode, Vec<Score>, UserExtended)> = Vec::new();
+
+    for mode in results.iter().filter_map(|result| result.api_mode) {
+        if profiles.iter().all(|(fetched, ..)| *fetched != mode) {
+            let (scores, user) = fetch_profile(osu, cli, username, mode).await?;
+            profiles.push((mode, scores, user));
+        }
+    }
+
+    let weighting = cli.weighting();
+
+    for result in results {
+        let (Some(mode), Some(pp)) = (result.api_mode, result.pp) else {
+            continue;
+        };
+        let Some((_, scores, user)) = profiles.iter().find(|(fetched, ..)| *fetched == mode) else {
+            continue;
+        };
+        let bonus = BonusPp::for_user(user, scores, &[result.map_id]);
+
+        result.gain =
+            Some(calc::project_gain(scores, pp, "", result.map_id, bonus, &weighting).gain);
+    }
+
+    Ok(())
+}
+
+pub async fn download_maps(
+    osu: &Client,
+    cli: &Cli,
+    map_ids: HashSet<u32>,
+) -> HashMap<u32, Result<Vec<u8>, PpifyError>> {
+    let cache = map_cache(cli);
+    let cache = cache.as_ref();
+    let downloads = progress::bar(map_ids.len(), "Beatmaps");
+
+    let maps: HashMap<u32, Result<Vec<u8>, PpifyError>> = stream::iter(map_ids)
+        .map(|map_id| async move {
+            let bytes = api::fetch_osu_file(osu, map_id, None, cache, cli.refresh).await;
+
+            (map_id, bytes)
+        })
+        .buffer_unordered(CONCURRENT_DOWNLOADS)
+        .inspect(|(map_id, bytes)| {
+            match bytes {
+                Ok(_) => downloads.set_message(format!("{map_id}")),
+                Err(err) => downloads.set_message(format!("{map_id} failed: {err}")),
+            }
+
+            downloads.inc(1);
+        })
+        .collect()
+        .await;
+
+    downloads.finish_and_clear();
+
+    maps
+}
+
+fn calculate_row(
+    row: &BatchRow,
+    bytes: &[u8],
+    api_mode: GameMode,
+    pp_mode: PpGameMode,
+    rules: Rules,
+) -> Result<BatchResult> {
+    let selected_mods = mods::parse_acronyms(&row.mods, api_mode)?;
+    let game_mods = mods::lazer_mods(selected_mods.iter().copied(), api_mode);
+    let map = calc::parse_beatmap(bytes, pp_mode)?;
+
+    let play = PlayInputs {
+        judgements: Judgements::Accuracy {
+            accuracy: row.accuracy,
+            misses: row.misses,
+        },
+        combo: row.combo,
+    };
+
+    let perf_attrs = calc::calculate_play(&map, pp_mode, &game_mods, None, rules, &play);
+
+    Ok(BatchResult {
+        map_id: row.map_id,
+        mods: selected_mods.iter().map(|m| m.acronym).collect(),
+        accuracy: row.accuracy,
+        misses: row.misses,
+        combo: row.combo,
+        beatmap: Some(BeatmapMetadata::from_osu_bytes(bytes).to_string()),
+        stars: Some(perf_attrs.stars()),
+        pp: Some(perf_attrs.pp()),
+        error: None,
+    })
+}
+
+fn print_results(results: &[BatchResult]) {
+    println!();
+    println!("  map id   mods       acc   misses  combo    stars        pp");
+
+    for result in results {
+        let combo = result
+            .combo
+            .map_or_else(|| "FC".to_owned(), |combo| format!("{combo}x"));
+
+        print!(
+            "  {:<8} {:<7} {:>6.2}%  {:>6}  {:>6}",
+            result.map_id,
+            if result.mods.is_empty() {
+                "NM"
+            } else {
+                &result.mods
+            },
+            result.accuracy,
+            result.misses,
+            combo
+        );
+
+        match (&result.error, result.stars, result.pp) {
+            (Some(err), ..) => println!("  {err}"),
+            (None, Some(stars), Some(pp)) => {
+                println!(
+                    "  {stars:>6.2}★  {pp:>8.2}pp  {}",
+                    result.beatmap.as_deref().unwrap_or("")
+                )
+            }
+            (None, ..) => println!(),
+        }
+    }
+
+    let failed = results
+        .iter()
+        .filter(|result| result.error.is_some())
+        .count();
+
+    if failed > 0 {
+        println!();
+        println!("{failed} of {} rows failed.", results.len());
+    }
+}
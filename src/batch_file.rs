@@ -0,0 +1,293 @@
+use {crate::error::PpifyError, serde::Serialize, std::io};
+
+/// One line of a batch file: `map_id,mods,accuracy,misses,combo`.
+///
+/// `misses` and `combo` may be left empty, meaning no misses and full combo.
+#[derive(Clone, Debug)]
+pub struct BatchRow {
+    /// Line in the input file, for error messages.
+    pub line: u64,
+    pub map_id: u32,
+    pub mods: String,
+    pub accuracy: f64,
+    pub misses: u32,
+    pub combo: Option<u32>,
+}
+
+/// The outcome of a [`BatchRow`], flat so it can be written back out as CSV.
+#[derive(Clone, Debug, Serialize)]
+pub struct BatchResult {
+    pub map_id: u32,
+    pub mods: String,
+    pub accuracy: f64,
+    pub misses: u32,
+    pub combo: Option<u32>,
+    pub beatmap: Option<String>,
+    pub stars: Option<f64>,
+    pub pp: Option<f64>,
+    /// Set instead of `stars` and `pp` when the row couldn't be calculated.
+    pub error: Option<String>,
+}
+
+/// A `--matrix` file: beatmap ids in the header, then one player per line.
+#[derive(Clone, Debug)]
+pub struct MatrixFile {
+    pub map_ids: Vec<u32>,
+    /// Usernames or user ids.
+    pub players: Vec<String>,
+}
+
+/// Reads a matrix file. The first cell of the header names the player column, the others
+/// are beatmap ids. Of the other lines only the first cell is read; empty ones and `#`
+/// comments are skipped.
+pub fn read_matrix(reader: impl io::Read) -> Result<MatrixFile, PpifyError> {
+    let mut reader = csv::ReaderBuilder::new()
+        .has_headers(false)
+        .flexible(true)
+        .trim(csv::Trim::All)
+        .from_reader(reader);
+
+    let mut records = reader.records();
+    let invalid = |err: csv::Error| PpifyError::BadInput(format!("invalid CSV: {err}"));
+
+    let header = records
+        .next()
+        .ok_or_else(|| PpifyError::BadInput("the matrix file is empty".to_owned()))?
+        .map_err(invalid)?;
+
+    let mut map_ids = Vec::new();
+
+    for (column, cell) in header.iter().enumerate().skip(1) {
+        let map_id: u32 = cell.parse().map_err(|_| {
+            PpifyError::BadInput(format!(
+                "column {} of the header: invalid beatmap id `{cell}`",
+                column + 1
+            ))
+        })?;
+
+        if map_ids.contains(&map_id) {
+            return Err(PpifyError::BadInput(format!(
+                "beatmap {map_id} is in the header twice"
+            )));
+        }
+
+        map_ids.push(map_id);
+    }
+
+    if map_ids.is_empty() {
+        return Err(PpifyError::BadInput(
+            "the header has no beatmap ids, e.g. `player,129891,1860169`".to_owned(),
+        ));
+    }
+
+    let mut players = Vec::new();
+
+    for record in records {
+        let record = record.map_err(invalid)?;
+        let player = record.get(0).unwrap_or("");
+
+        if player.is_empty() || player.starts_with('#') {
+            continue;
+        }
+
+        players.push(player.to_owned());
+    }
+
+    if players.is_empty() {
+        return Err(PpifyError::BadInput(
+            "the matrix file has no players, one per line under the header".to_owned(),
+        ));
+    }
+
+    Ok(MatrixFile { map_ids, players })
+}
+
+/// Reads batch rows from CSV. A header line, blank lines and `#` comments are skipped.
+pub fn read_rows(reader: impl io::Read) -> Result<Vec<BatchRow>, PpifyError> {
+    let mut reader = csv::ReaderBuilder::new()
+        .has_headers(false)
+        .flexible(true)
+        .trim(csv::Trim::All)
+        .from_reader(reader);
+
+    let mut rows = Vec::new();
+
+    for (i, record) in reader.records().enumerate() {
+        let record = record.map_err(|err| PpifyError::BadInput(format!("invalid CSV: {err}")))?;
+        let line = record.position().map_or(i as u64 + 1, csv::Position::line);
+        let field = |idx: usize| record.get(idx).unwrap_or("");
+
+        if field(0).starts_with('#') || record.iter().all(str::is_empty) {
+            continue;
+        }
+
+        // `map_id,mods,...` as the first line
+        if i == 0 && field(0).parse::<u32>().is_err() {
+            continue;
+        }
+
+        let bad = |what: &str, value: &str| {
+            PpifyError::BadInput(format!("line {line}: invalid {what} `{value}`"))
+        };
+
+        let accuracy = field(2)
+            .trim_end_matches('%')
+            .parse::<f64>()
+            .map_err(|_| bad("accuracy", field(2)))?;
+
+        if !(0.0..=100.0).contains(&accuracy) {
+            return Err(bad("accuracy", field(2)));
+        }
+
+        rows.push(BatchRow {
+            line,
+            map_id: field(0).parse().map_err(|_| bad("beatmap id", field(0)))?,
+            mods: field(1).to_owned(),
+            accuracy,
+            misses: match field(3) {
+                "" => 0,
+                misses => misses.parse().map_err(|_| bad("miss count", misses))?,
+            },
+            combo: match field(4).trim_end_matches('x') {
+                "" => None,
+                combo => Some(combo.parse().map_err(|_| bad("combo", field(4)))?),
+            },
+        });
+    }
+
+    Ok(rows)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn matrix_error(csv: &str) -> String {
+        match read_matrix(csv.as_bytes()) {
+            Err(PpifyError::BadInput(err)) => err,
+            Err(err) => panic!("expected a bad matrix, got {err}"),
+            Ok(matrix) => panic!("expected a bad matrix, read {matrix:?}"),
+        }
+    }
+
+    fn row_error(csv: &str) -> String {
+        match read_rows(csv.as_bytes()) {
+            Err(PpifyError::BadInput(err)) => err,
+            Err(err) => panic!("expected a bad row, got {err}"),
+            Ok(rows) => panic!("expected a bad row, read {rows:?}"),
+        }
+    }
+
+    #[test]
+    fn read_matrix_reads_maps_and_players() {
+        let matrix = read_matrix(
+            "player, 129891, 1860169
+# the team
+peppy
+
+ 2 , ignored, cells
+mrekk,
+"
+            .as_bytes(),
+        )
+        .unwrap();
+
+        assert_eq!(matrix.map_ids, [129891, 1860169]);
+        assert_eq!(matrix.players, ["peppy", "2", "mrekk"]);
+    }
+
+    #[test]
+    fn read_matrix_rejects_bad_headers_and_missing_players() {
+        let cases = [
+            ("", "the matrix file is empty"),
+            (
+                "player\npeppy",
+                "the header has no beatmap ids, e.g. `player,129891,1860169`",
+            ),
+            (
+                "player,129891,freedom dive\npeppy",
+                "column 3 of the header: invalid beatmap id `freedom dive`",
+            ),
+            (
+                "player,129891,,1860169\npeppy",
+                "column 3 of the header: invalid beatmap id ``",
+            ),
+            (
+                "player,129891,129891\npeppy",
+                "beatmap 129891 is in the header twice",
+            ),
+            (
+                "player,129891\n# nobody yet\n,129891",
+                "the matrix file has no players, one per line under the header",
+            ),
+        ];
+
+        for (csv, expected) in cases {
+            assert_eq!(matrix_error(csv), expected, "{csv}");
+        }
+    }
+
+    #[test]
+    fn read_rows_reads_each_play() {
+        let rows = read_rows(
+            "map_id,mods,accuracy,misses,combo
+# warmup
+129891,HDDT,98.5%,2,1500x
+
+  1860169 , , 100 , ,
+   ,  ,
+"
+            .as_bytes(),
+        )
+        .unwrap();
+
+        assert_eq!(rows.len(), 2);
+
+        assert_eq!(rows[0].line, 3);
+        assert_eq!(rows[0].map_id, 129891);
+        assert_eq!(rows[0].mods, "HDDT");
+        assert_eq!(rows[0].accuracy, 98.5);
+        assert_eq!(rows[0].misses, 2);
+        assert_eq!(rows[0].combo, Some(1500));
+
+        // empty misses and combo are no misses and a full combo
+        assert_eq!(rows[1].line, 5);
+        assert_eq!(rows[1].map_id, 1860169);
+        assert_eq!(rows[1].mods, "");
+        assert_eq!(rows[1].accuracy, 100.0);
+        assert_eq!(rows[1].misses, 0);
+        assert_eq!(rows[1].combo, None);
+    }
+
+    #[test]
+    fn read_rows_without_a_header() {
+        let rows = read_rows("129891,NM,99\n".as_bytes()).unwrap();
+
+        assert_eq!(rows.len(), 1);
+        assert_eq!(rows[0].line, 1);
+        assert_eq!(rows[0].map_id, 129891);
+    }
+
+    #[test]
+    fn read_rows_names_the_bad_line_and_column() {
+        let cases = [
+            ("129891,HD,abc", "line 1: invalid accuracy `abc`"),
+            ("129891,HD,100.5", "line 1: invalid accuracy `100.5`"),
+            ("129891,HD", "line 1: invalid accuracy ``"),
+            ("129891,HD,98,one", "line 1: invalid miss count `one`"),
+            ("129891,HD,98,0,full", "line 1: invalid combo `full`"),
+            (
+                "map_id,mods,accuracy\n\n# comment\nmap,HD,98",
+                "line 4: invalid beatmap id `map`",
+            ),
+            (
+                "129891,HD,98\n129891,HD,98,-1",
+                "line 2: invalid miss count `-1`",
+            ),
+        ];
+
+        for (csv, expected) in cases {
+            assert_eq!(row_error(csv), expected, "{csv}");
+        }
+    }
+}
@@ -0,0 +1,308 @@
+use {
+    rosu_v2::prelude::{GameMode, Score, UserExtended},
+    serde::{Deserialize, Serialize, de::DeserializeOwned},
+    std::{
+        fs, io,
+        path::{Path, PathBuf},
+        time::{Duration, SystemTime, UNIX_EPOCH},
+    },
+};
+
+/// On-disk cache of downloaded `.osu` files, keyed by beatmap id.
+///
+/// Every file is stored next to its md5 checksum so truncated or otherwise
+/// corrupted entries are detected and re-downloaded. When the api's checksum of
+/// the beatmap is known, outdated versions of updated maps are detected as well.
+pub struct MapCache {
+    dir: PathBuf,
+}
+
+impl MapCache {
+    pub fn new(dir: impl Into<PathBuf>) -> Self {
+        Self { dir: dir.into() }
+    }
+
+    /// `~/.cache/ppify/maps` or the platform equivalent.
+    pub fn default_dir() -> Option<PathBuf> {
+        dirs::cache_dir().map(|dir| dir.join("ppify").join("maps"))
+    }
+
+    pub fn dir(&self) -> &Path {
+        &self.dir
+    }
+
+    /// The cached file, `None` if it is corrupted or doesn't match `checksum`.
+    pub fn get(&self, map_id: u32, checksum: Option<&str>) -> Option<Vec<u8>> {
+        let bytes = fs::read(self.osu_path(map_id)).ok()?;
+        let expected = fs::read_to_string(self.checksum_path(map_id)).ok()?;
+        let actual = md5_hex(&bytes);
+
+        (actual == expected.trim() && checksum.is_none_or(|checksum| actual == checksum))
+            .then_some(bytes)
+    }
+
+    /// Fails without writing anything if `bytes` don't match `checksum`.
+    pub fn put(&self, map_id: u32, bytes: &[u8], checksum: Option<&str>) -> io::Result<()> {
+        let actual = md5_hex(bytes);
+
+        if let Some(checksum) = checksum
+            && actual != checksum
+        {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                format!("beatmap {map_id} has md5 {actual} instead of {checksum}"),
+            ));
+        }
+
+        fs::create_dir_all(&self.dir)?;
+        fs::write(self.osu_path(map_id), bytes)?;
+        fs::write(self.checksum_path(map_id), actual)
+    }
+
+    fn osu_path(&self, map_id: u32) -> PathBuf {
+        self.dir.join(format!("{map_id}.osu"))
+    }
+
+    fn checksum_path(&self, map_id: u32) -> PathBuf {
+        self.dir.join(format!("{map_id}.md5"))
+    }
+}
+
+/// On-disk cache of users' top scores, keyed by user and mode.
+///
+/// Entries older than the TTL are treated as missing.
+pub struct ScoreCache {
+    dir: PathBuf,
+    ttl: Duration,
+}
+
+impl ScoreCache {
+    pub const DEFAULT_TTL: Duration = Duration::from_secs(60 * 60);
+
+    pub fn new(dir: impl Into<PathBuf>, ttl: Duration) -> Self {
+        Self {
+            dir: dir.into(),
+            ttl,
+        }
+    }
+
+    /// `~/.cache/ppify/scores` or the platform equivalent.
+    pub fn default_dir() -> Option<PathBuf> {
+        dirs::cache_dir().map(|dir| dir.join("ppify").join("scores"))
+    }
+
+    pub fn get(&self, user: &str, mode: GameMode) -> Option<Vec<Score>> {
+        read_json(&user_path(&self.dir, user, mode), Some(self.ttl))
+    }
+
+    /// Like [`ScoreCache::get`], but expired entries are returned too, e.g. when the api
+    /// can't be reached.
+    pub fn get_any_age(&self, user: &str, mode: GameMode) -> Option<Vec<Score>> {
+        read_json(&user_path(&self.dir, user, mode), None)
+    }
+
+    pub fn put(&self, user: &str, mode: GameMode, scores: &[Score]) -> io::Result<()> {
+        fs::create_dir_all(&self.dir)?;
+        fs::write(
+            user_path(&self.dir, user, mode),
+            serde_json::to_vec(scores)?,
+        )
+    }
+}
+
+/// On-disk cache of user profiles, keyed by user and mode like [`ScoreCache`].
+pub struct UserCache {
+    dir: PathBuf,
+    ttl: Duration,
+}
+
+impl UserCache {
+    pub fn new(dir: impl Into<PathBuf>, ttl: Duration) -> Self {
+        Self {
+            dir: dir.into(),
+            ttl,
+        }
+    }
+
+    /// `~/.cache/ppify/users` or the platform equivalent.
+    pub fn default_dir() -> Option<PathBuf> {
+        dirs::cache_dir().map(|dir| dir.join("ppify").join("users"))
+    }
+
+    pub fn get(&self, user: &str, mode: GameMode) -> Option<UserExtended> {
+        read_json(&user_path(&self.dir, user, mode), Some(self.ttl))
+    }
+
+    /// Like [`UserCache::get`], but expired entries are returned too.
+    pub fn get_any_age(&self, user: &str, mode: GameMode) -> Option<UserExtended> {
+        read_json(&user_path(&self.dir, user, mode), None)
+    }
+
+    pub fn put(&self, user: &str, mode: GameMode, profile: &UserExtended) -> io::Result<()> {
+        fs::create_dir_all(&self.dir)?;
+        fs::write(
+            user_path(&self.dir, user, mode),
+            serde_json::to_vec(profile)?,
+        )
+    }
+}
+
+/// On-disk cache of pages of the performance rankings, keyed by mode, country and page.
+pub struct RankingsCache {
+    dir: PathBuf,
+    ttl: Duration,
+}
+
+/// One page of the performance rankings as cached.
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct RankingsPage {
+    /// Users in the whole rankings, not only on this page.
+    pub total: u32,
+    /// Total pp of every user on the page, best first.
+    pub pps: Vec<f64>,
+}
+
+impl RankingsCache {
+    pub fn new(dir: impl Into<PathBuf>, ttl: Duration) -> Self {
+        Self {
+            dir: dir.into(),
+            ttl,
+        }
+    }
+
+    /// `~/.cache/ppify/rankings` or the platform equivalent.
+    pub fn default_dir() -> Option<PathBuf> {
+        dirs::cache_dir().map(|dir| dir.join("ppify").join("rankings"))
+    }
+
+    /// `country` is a country code, `None` for the global rankings.
+    pub fn get(&self, mode: GameMode, country: Option<&str>, page: u32) -> Option<RankingsPage> {
+        read_json(
+            &rankings_path(&self.dir, mode, country, page),
+            Some(self.ttl),
+        )
+    }
+
+    pub fn put(
+        &self,
+        mode: GameMode,
+        country: Option<&str>,
+        page: u32,
+        rankings: &RankingsPage,
+    ) -> io::Result<()> {
+        fs::create_dir_all(&self.dir)?;
+        fs::write(
+            rankings_path(&self.dir, mode, country, page),
+            serde_json::to_vec(rankings)?,
+        )
+    }
+}
+
+fn rankings_path(dir: &Path, mode: GameMode, country: Option<&str>, page: u32) -> PathBuf {
+    let country = country.map_or_else(|| "global".to_owned(), str::to_lowercase);
+
+    dir.join(format!("{}-{country}-{page}.json", mode.as_str()))
+}
+
+// usernames are case-insensitive and may contain characters that aren't valid in paths
+fn user_path(dir: &Path, user: &str, mode: GameMode) -> PathBuf {
+    let key = md5_hex(user.trim().to_lowercase().as_bytes());
+
+    dir.join(format!("{key}-{}.json", mode.as_str()))
+}
+
+/// `None` for missing or unreadable files and ones older than `ttl`.
+fn read_json<T: DeserializeOwned>(path: &Path, ttl: Option<Duration>) -> Option<T> {
+    if let Some(ttl) = ttl {
+        let age = fs::metadata(path).ok()?.modified().ok()?.elapsed().ok()?;
+
+        if age > ttl {
+            return None;
+        }
+    }
+
+    serde_json::from_slice(&fs::read(path).ok()?).ok()
+}
+
+/// The osu! api access token of the last run, reused until it expires.
+pub struct TokenCache {
+    path: PathBuf,
+}
+
+#[derive(Deserialize, Serialize)]
+struct CachedToken {
+    client_id: u64,
+    access_token: String,
+    /// Unix timestamp in seconds.
+    expires_at: u64,
+}
+
+impl TokenCache {
+    // don't hand out tokens that expire mid-run
+    const MARGIN: Duration = Duration::from_secs(5 * 60);
+
+    pub fn new(path: impl Into<PathBuf>) -> Self {
+        Self { path: path.into() }
+    }
+
+    /// `~/.cache/ppify/token.json` or the platform equivalent.
+    pub fn default_path() -> Option<PathBuf> {
+        dirs::cache_dir().map(|dir| dir.join("ppify").join("token.json"))
+    }
+
+    /// The cached token of `client_id` and how long it stays valid.
+    pub fn get(&self, client_id: u64) -> Option<(String, Duration)> {
+        let cached: CachedToken = serde_json::from_slice(&fs::read(&self.path).ok()?).ok()?;
+
+        if cached.client_id != client_id {
+            return None;
+        }
+
+        let remaining = Duration::from_secs(cached.expires_at).checked_sub(unix_now())?;
+
+        (remaining > Self::MARGIN).then_some((cached.access_token, remaining))
+    }
+
+    pub fn put(&self, client_id: u64, access_token: &str, expires_in: Duration) -> io::Result<()> {
+        let cached = CachedToken {
+            client_id,
+            access_token: access_token.to_owned(),
+            expires_at: (unix_now() + expires_in).as_secs(),
+        };
+
+        if let Some(dir) = self.path.parent() {
+            fs::create_dir_all(dir)?;
+        }
+
+        write_private(&self.path, &serde_json::to_vec(&cached)?)
+    }
+}
+
+fn unix_now() -> Duration {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or_default()
+}
+
+// the token grants api access, so other users shouldn't be able to read it
+#[cfg(unix)]
+fn write_private(path: &Path, bytes: &[u8]) -> io::Result<()> {
+    use std::{io::Write, os::unix::fs::OpenOptionsExt};
+
+    fs::OpenOptions::new()
+        .write(true)
+        .create(true)
+        .truncate(true)
+        .mode(0o600)
+        .open(path)?
+        .write_all(bytes)
+}
+
+#[cfg(not(unix))]
+fn write_private(path: &Path, bytes: &[u8]) -> io::Result<()> {
+    fs::write(path, bytes)
+}
+
+pub fn md5_hex(bytes: &[u8]) -> String {
+    format!("{:x}", md5::compute(bytes))
+}
@@ -0,0 +1,2376 @@
+use {
+    crate::{error::PpifyError, osu_db::DbScore},
+    rosu_pp::{
+        Beatmap as PpBeatmap, Difficulty, Performance,
+        any::{PerformanceAttributes, ScoreState, Strains},
+        model::mode::{ConvertError, GameMode as PpGameMode},
+    },
+    rosu_v2::prelude::{GameMod, GameModIntermode, GameMode, GameMods, Score, UserExtended},
+    serde::Serialize,
+    std::{cmp::Ordering, time::Instant},
+    tracing::debug,
+};
+
+pub const TOP_PLAYS_CAP: usize = 100;
+
+/// How far a total has to be above another to pass it; the profile shows hundredths.
+pub const PASS_MARGIN: f64 = 0.01;
+
+#[derive(Clone, Copy, Debug, Serialize)]
+#[serde(untagged)]
+pub enum DetailedJudgements {
+    Osu {
+        n300: u32,
+        n100: u32,
+        n50: u32,
+        misses: u32,
+        /// Lazer only, `None` assumes every slider end was hit.
+        #[serde(skip_serializing_if = "Option::is_none")]
+        slider_end_hits: Option<u32>,
+        /// Lazer only, slider ticks and repeats. `None` assumes all of them were hit.
+        #[serde(skip_serializing_if = "Option::is_none")]
+        large_tick_hits: Option<u32>,
+    },
+    Taiko {
+        n300: u32,
+        n100: u32,
+        misses: u32,
+    },
+    Catch {
+        fruits: u32,
+        droplets: u32,
+        tiny_droplets: u32,
+        tiny_droplet_misses: u32,
+        misses: u32,
+    },
+    Mania {
+        n320: u32,
+        n300: u32,
+        n200: u32,
+        n100: u32,
+        n50: u32,
+        misses: u32,
+    },
+}
+
+impl DetailedJudgements {
+    /// The accuracy formula of the mode, and the same with these counts put in.
+    pub fn accuracy_formula(&self) -> AccuracyFormula {
+        let (template, counts): (&str, &[(&str, u32)]) = match *self {
+            Self::Osu {
+                n300,
+                n100,
+                n50,
+                misses,
+                ..
+            } => (
+                "(300*{n300} + 100*{n100} + 50*{n50}) / (300*({n300} + {n100} + {n50} + {misses}))",
+                &[
+                    ("n300", n300),
+                    ("n100", n100),
+                    ("n50", n50),
+                    ("misses", misses),
+                ],
+            ),
+            Self::Taiko { n300, n100, misses } => (
+                "({n300} + 0.5*{n100}) / ({n300} + {n100} + {misses})",
+                &[("n300", n300), ("n100", n100), ("misses", misses)],
+            ),
+            Self::Catch {
+                fruits,
+                droplets,
+                tiny_droplets,
+                tiny_droplet_misses,
+                misses,
+            } => (
+                "({fruits} + {droplets} + {tiny_droplets}) / ({fruits} + {droplets} + \
+                 {tiny_droplets} + {tiny_droplet_misses} + {misses})",
+                &[
+                    ("fruits", fruits),
+                    ("droplets", droplets),
+                    ("tiny_droplets", tiny_droplets),
+                    ("tiny_droplet_misses", tiny_droplet_misses),
+                    ("misses", misses),
+                ],
+            ),
+            Self::Mania {
+                n320,
+                n300,
+                n200,
+                n100,
+                n50,
+                misses,
+            } => (
+                "(300*({n320} + {n300}) + 200*{n200} + 100*{n100} + 50*{n50}) / \
+                 (300*({n320} + {n300} + {n200} + {n100} + {n50} + {misses}))",
+                &[
+                    ("n320", n320),
+                    ("n300", n300),
+                    ("n200", n200),
+                    ("n100", n100),
+                    ("n50", n50),
+                    ("misses", misses),
+                ],
+            ),
+        };
+
+        let mut formula = template.to_owned();
+        let mut substituted = template.to_owned();
+
+        for (name, count) in counts {
+            let placeholder = format!("{{{name}}}");
+            formula = formula.replace(&placeholder, name);
+            substituted = substituted.replace(&placeholder, &count.to_string());
+        }
+
+        let play = PlayInputs {
+            judgements: Judgements::Detailed(*self),
+            combo: None,
+        };
+
+        AccuracyFormula {
+            formula,
+            substituted,
+            accuracy: play.accuracy(),
+        }
+    }
+}
+
+/// How the accuracy of a play follows from its counts, see
+/// [`DetailedJudgements::accuracy_formula`]. These are osu!stable's formulas.
+#[derive(Clone, Debug, Serialize)]
+pub struct AccuracyFormula {
+    /// e.g. `(n300 + 0.5*n100) / (n300 + n100 + misses)` for osu!taiko.
+    pub formula: String,
+    /// The formula with the counts in place of their names.
+    pub substituted: String,
+    pub accuracy: f64,
+}
+
+#[derive(Clone, Copy, Debug, Serialize)]
+#[serde(untagged)]
+pub enum Judgements {
+    Accuracy { accuracy: f64, misses: u32 },
+    Detailed(DetailedJudgements),
+}
+
+/// A (hypothetical) play on a beatmap, independent of mods and mode.
+#[derive(Clone, Copy, Debug, Serialize)]
+pub struct PlayInputs {
+    #[serde(flatten)]
+    pub judgements: Judgements,
+    pub combo: Option<u32>,
+}
+
+impl PlayInputs {
+    /// The judgements and combo of an api score.
+    pub fn from_score(score: &Score) -> Self {
+        let stats = &score.statistics;
+
+        // stable scores don't count slider ends and ticks
+        let lazer = !score.mods.contains_intermode(GameModIntermode::Classic);
+
+        let judgements = match score.mode {
+            GameMode::Osu => DetailedJudgements::Osu {
+                n300: stats.great,
+                n100: stats.ok,
+                n50: stats.meh,
+                misses: stats.miss,
+                slider_end_hits: lazer.then_some(stats.slider_tail_hit),
+                large_tick_hits: lazer.then_some(stats.large_tick_hit),
+            },
+            GameMode::Taiko => DetailedJudgements::Taiko {
+                n300: stats.great,
+                n100: stats.ok,
+                misses: stats.miss,
+            },
+            GameMode::Catch => DetailedJudgements::Catch {
+                fruits: stats.great,
+                droplets: stats.large_tick_hit,
+                tiny_droplets: stats.small_tick_hit,
+                tiny_droplet_misses: stats.small_tick_miss,
+                misses: stats.miss,
+            },
+            GameMode::Mania => DetailedJudgements::Mania {
+                n320: stats.perfect,
+                n300: stats.great,
+                n200: stats.good,
+                n100: stats.ok,
+                n50: stats.meh,
+                misses: stats.miss,
+            },
+        };
+
+        Self {
+            judgements: Judgements::Detailed(judgements),
+            combo: Some(score.max_combo),
+        }
+    }
+
+    /// The judgements and combo of a score from osu! stable's `scores.db`.
+    pub fn from_db_score(score: &DbScore) -> Self {
+        let judgements = match GameMode::from(score.mode) {
+            GameMode::Osu => DetailedJudgements::Osu {
+                n300: score.n300,
+                n100: score.n100,
+                n50: score.n50,
+                misses: score.misses,
+                slider_end_hits: None,
+                large_tick_hits: None,
+            },
+            GameMode::Taiko => DetailedJudgements::Taiko {
+                n300: score.n300,
+                n100: score.n100,
+                misses: score.misses,
+            },
+            GameMode::Catch => DetailedJudgements::Catch {
+                fruits: score.n300,
+                droplets: score.n100,
+                tiny_droplets: score.n50,
+                tiny_droplet_misses: score.n_katu,
+                misses: score.misses,
+            },
+            GameMode::Mania => DetailedJudgements::Mania {
+                n320: score.n_geki,
+                n300: score.n300,
+                n200: score.n_katu,
+                n100: score.n100,
+                n50: score.n50,
+                misses: score.misses,
+            },
+        };
+
+        Self {
+            judgements: Judgements::Detailed(judgements),
+            combo: Some(score.max_combo),
+        }
+    }
+
+    /// Accuracy in percent, from the judgements with osu!stable's formulas when there
+    /// are some.
+    pub fn accuracy(&self) -> f64 {
+        let ratio = |hits: f64, max: f64| if max > 0.0 { hits / max * 100.0 } else { 100.0 };
+
+        let detailed = match self.judgements {
+            Judgements::Accuracy { accuracy, .. } => return accuracy,
+            Judgements::Detailed(detailed) => detailed,
+        };
+
+        match detailed {
+            DetailedJudgements::Osu {
+                n300,
+                n100,
+                n50,
+                misses,
+                ..
+            } => ratio(
+                f64::from(6 * n300 + 2 * n100 + n50),
+                f64::from(6 * (n300 + n100 + n50 + misses)),
+            ),
+            DetailedJudgements::Taiko { n300, n100, misses } => ratio(
+                f64::from(2 * n300 + n100),
+                f64::from(2 * (n300 + n100 + misses)),
+            ),
+            DetailedJudgements::Catch {
+                fruits,
+                droplets,
+                tiny_droplets,
+                tiny_droplet_misses,
+                misses,
+            } => {
+                let hits = fruits + droplets + tiny_droplets;
+                ratio(
+                    f64::from(hits),
+                    f64::from(hits + tiny_droplet_misses + misses),
+                )
+            }
+            DetailedJudgements::Mania {
+                n320,
+                n300,
+                n200,
+                n100,
+                n50,
+                misses,
+            } => ratio(
+                f64::from(6 * (n320 + n300) + 4 * n200 + 2 * n100 + n50),
+                f64::from(6 * (n320 + n300 + n200 + n100 + n50 + misses)),
+            ),
+        }
+    }
+
+    pub fn misses(&self) -> u32 {
+        match self.judgements {
+            Judgements::Accuracy { misses, .. } => misses,
+            Judgements::Detailed(
+                DetailedJudgements::Osu { misses, .. }
+                | DetailedJudgements::Taiko { misses, .. }
+                | DetailedJudgements::Catch { misses, .. }
+                | DetailedJudgements::Mania { misses, .. },
+            ) => misses,
+        }
+    }
+
+    /// The same play with `misses` misses, taken from or given back to the best
+    /// judgement. The combo is left to rosu-pp.
+    pub fn with_misses(&self, misses: u32) -> Self {
+        let moved = |best: u32, old: u32| (best + old).saturating_sub(misses);
+
+        let judgements = match self.judgements {
+            Judgements::Accuracy { accuracy, .. } => Judgements::Accuracy { accuracy, misses },
+            Judgements::Detailed(detailed) => Judgements::Detailed(match detailed {
+                DetailedJudgements::Osu {
+                    n300,
+                    n100,
+                    n50,
+                    misses: old,
+                    slider_end_hits,
+                    large_tick_hits,
+                } => DetailedJudgements::Osu {
+                    n300: moved(n300, old),
+                    n100,
+                    n50,
+                    misses,
+                    slider_end_hits,
+                    large_tick_hits,
+                },
+                DetailedJudgements::Taiko {
+                    n300,
+                    n100,
+                    misses: old,
+                } => DetailedJudgements::Taiko {
+                    n300: moved(n300, old),
+                    n100,
+                    misses,
+                },
+                DetailedJudgements::Catch {
+                    fruits,
+                    droplets,
+                    tiny_droplets,
+                    tiny_droplet_misses,
+                    misses: old,
+                } => DetailedJudgements::Catch {
+                    fruits: moved(fruits, old),
+                    droplets,
+                    tiny_droplets,
+                    tiny_droplet_misses,
+                    misses,
+                },
+                DetailedJudgements::Mania {
+                    n320,
+                    n300,
+                    n200,
+                    n100,
+                    n50,
+                    misses: old,
+                } => DetailedJudgements::Mania {
+                    n320: moved(n320, old),
+                    n300,
+                    n200,
+                    n100,
+                    n50,
+                    misses,
+                },
+            }),
+        };
+
+        Self {
+            judgements,
+            combo: None,
+        }
+    }
+
+    /// The same play with its misses turned into the best judgement and full combo.
+    pub fn full_combo(&self) -> Self {
+        let judgements = match self.judgements {
+            Judgements::Accuracy { accuracy, .. } => Judgements::Accuracy {
+                accuracy,
+                misses: 0,
+            },
+            Judgements::Detailed(detailed) => Judgements::Detailed(match detailed {
+                // a full combo hits every slider end and tick, left to rosu-pp
+                DetailedJudgements::Osu {
+                    n300,
+                    n100,
+                    n50,
+                    misses,
+                    ..
+                } => DetailedJudgements::Osu {
+                    n300: n300 + misses,
+                    n100,
+                    n50,
+                    misses: 0,
+                    slider_end_hits: None,
+                    large_tick_hits: None,
+                },
+                DetailedJudgements::Taiko { n300, n100, misses } => DetailedJudgements::Taiko {
+                    n300: n300 + misses,
+                    n100,
+                    misses: 0,
+                },
+                DetailedJudgements::Catch {
+                    fruits,
+                    droplets,
+                    tiny_droplets,
+                    tiny_droplet_misses,
+                    misses,
+                } => DetailedJudgements::Catch {
+                    // misses can be fruits or droplets, count them as fruits
+                    fruits: fruits + misses,
+                    droplets,
+                    tiny_droplets,
+                    tiny_droplet_misses,
+                    misses: 0,
+                },
+                DetailedJudgements::Mania {
+                    n320,
+                    n300,
+                    n200,
+                    n100,
+                    n50,
+                    misses,
+                } => DetailedJudgements::Mania {
+                    n320: n320 + misses,
+                    n300,
+                    n200,
+                    n100,
+                    n50,
+                    misses: 0,
+                },
+            }),
+        };
+
+        Self {
+            judgements,
+            combo: None,
+        }
+    }
+
+    pub fn apply<'a>(&self, mut perf: Performance<'a>) -> Performance<'a> {
+        if let Some(c) = self.combo {
+            perf = perf.combo(c);
+        }
+
+        match self.judgements {
+            Judgements::Accuracy { accuracy, misses } => perf.accuracy(accuracy).misses(misses),
+            Judgements::Detailed(detailed) => apply_detailed_judgements(perf, detailed),
+        }
+    }
+
+    /// Whether the play describes the given api score on `map_id` with `mods`.
+    pub fn matches_score(&self, score: &Score, map_id: u32, mods: &GameMods) -> bool {
+        // mod settings aren't compared, the api leaves out default values
+        let same_mods = score
+            .mods
+            .iter()
+            .map(GameMod::acronym)
+            .eq(mods.iter().map(GameMod::acronym));
+
+        if score.map_id != map_id || !same_mods {
+            return false;
+        }
+
+        if self.combo.is_some_and(|combo| combo != score.max_combo) {
+            return false;
+        }
+
+        let stats = &score.statistics;
+
+        match self.judgements {
+            Judgements::Detailed(DetailedJudgements::Osu {
+                n300,
+                n100,
+                n50,
+                misses,
+                slider_end_hits,
+                large_tick_hits,
+            }) => {
+                (n300, n100, n50, misses) == (stats.great, stats.ok, stats.meh, stats.miss)
+                    && slider_end_hits.is_none_or(|n| n == stats.slider_tail_hit)
+                    && large_tick_hits.is_none_or(|n| n == stats.large_tick_hit)
+            }
+            Judgements::Detailed(DetailedJudgements::Taiko { n300, n100, misses }) => {
+                (n300, n100, misses) == (stats.great, stats.ok, stats.miss)
+            }
+            Judgements::Detailed(DetailedJudgements::Catch {
+                fruits,
+                droplets,
+                tiny_droplets,
+                tiny_droplet_misses,
+                misses,
+            }) => {
+                (fruits, droplets, tiny_droplets, tiny_droplet_misses, misses)
+                    == (
+                        stats.great,
+                        stats.large_tick_hit,
+                        stats.small_tick_hit,
+                        stats.small_tick_miss,
+                        stats.miss,
+                    )
+            }
+            Judgements::Detailed(DetailedJudgements::Mania {
+                n320,
+                n300,
+                n200,
+                n100,
+                n50,
+                misses,
+            }) => {
+                (n320, n300, n200, n100, n50, misses)
+                    == (
+                        stats.perfect,
+                        stats.great,
+                        stats.good,
+                        stats.ok,
+                        stats.meh,
+                        stats.miss,
+                    )
+            }
+            // the api reports accuracy rounded to two decimals
+            Judgements::Accuracy { accuracy, misses } => {
+                (f64::from(score.accuracy) - accuracy).abs() < 0.005 && misses == stats.miss
+            }
+        }
+    }
+}
+
+fn apply_detailed_judgements(
+    perf: Performance<'_>,
+    detailed: DetailedJudgements,
+) -> Performance<'_> {
+    match detailed {
+        DetailedJudgements::Osu {
+            n300,
+            n100,
+            n50,
+            misses,
+            slider_end_hits,
+            large_tick_hits,
+        } => {
+            let mut perf = perf.n300(n300).n100(n100).n50(n50).misses(misses);
+
+            if let Some(n) = slider_end_hits {
+                perf = perf.slider_end_hits(n);
+            }
+
+            if let Some(n) = large_tick_hits {
+                perf = perf.large_tick_hits(n);
+            }
+
+            perf
+        }
+
+        DetailedJudgements::Taiko { n300, n100, misses } => {
+            perf.n300(n300).n100(n100).misses(misses)
+        }
+
+        DetailedJudgements::Catch {
+            fruits,
+            droplets,
+            tiny_droplets,
+            tiny_droplet_misses,
+            misses,
+        } => perf
+            .n300(fruits)
+            .large_tick_hits(droplets)
+            .small_tick_hits(tiny_droplets)
+            .n_katu(tiny_droplet_misses)
+            .misses(misses),
+
+        DetailedJudgements::Mania {
+            n320,
+            n300,
+            n200,
+            n100,
+            n50,
+            misses,
+        } => perf
+            .n_geki(n320)
+            .n300(n300)
+            .n_katu(n200)
+            .n100(n100)
+            .n50(n50)
+            .misses(misses),
+    }
+}
+
+pub fn pp_mode_name(mode: PpGameMode) -> &'static str {
+    match mode {
+        PpGameMode::Osu => "osu!standard",
+        PpGameMode::Taiko => "osu!taiko",
+        PpGameMode::Catch => "osu!catch",
+        PpGameMode::Mania => "osu!mania",
+    }
+}
+
+/// Checks that `map` can be calculated in `mode`, i.e. is a map of that mode or a convert.
+pub fn check_map_mode(map: &PpBeatmap, mode: PpGameMode) -> Result<(), PpifyError> {
+    if map.mode != mode && map.mode != PpGameMode::Osu {
+        return Err(PpifyError::BadInput(format!(
+            "beatmap is an {} map and cannot be converted to {} (only osu!standard maps have converts)",
+            pp_mode_name(map.mode),
+            pp_mode_name(mode)
+        )));
+    }
+
+    Ok(())
+}
+
+// rosu-pp reads a `Mode:` it doesn't know, e.g. of a future mode, as osu!standard, so the
+// map would be calculated as something it isn't. Without one a map is osu!standard.
+fn check_declared_mode(bytes: &[u8]) -> Result<(), PpifyError> {
+    let content = String::from_utf8_lossy(bytes);
+    let mut in_general = false;
+
+    for line in content.lines().map(str::trim) {
+        if line.starts_with('[') {
+            if in_general {
+                break;
+            }
+
+            in_general = line == "[General]";
+            continue;
+        }
+
+        let Some((key, value)) = line.split_once(':') else {
+            continue;
+        };
+
+        if in_general && key.trim() == "Mode" {
+            return match value.trim() {
+                "0" | "1" | "2" | "3" => Ok(()),
+                mode => Err(PpifyError::BadInput(format!(
+                    "beatmap has the unsupported mode `{mode}`, only osu!standard (0), \
+                     osu!taiko (1), osu!catch (2) and osu!mania (3) can be calculated"
+                ))),
+            };
+        }
+    }
+
+    Ok(())
+}
+
+/// The number of keys of `map` in osu!mania, for converts after key count mods.
+///
+/// `None` if the map can't be played in osu!mania.
+pub fn mania_key_count(map: &PpBeatmap, mods: &GameMods) -> Option<u32> {
+    let map = map
+        .convert_ref(PpGameMode::Mania, &mods.clone().into())
+        .ok()?;
+
+    Some(map.cs.round() as u32)
+}
+
+/// Parses `.osu` file contents and checks that they can be calculated in `mode`.
+pub fn parse_beatmap(bytes: &[u8], mode: PpGameMode) -> Result<PpBeatmap, PpifyError> {
+    let map = parse_native_beatmap(bytes)?;
+    check_map_mode(&map, mode)?;
+
+    Ok(map)
+}
+
+/// Parses `.osu` file contents in the map's own mode.
+pub fn parse_native_beatmap(bytes: &[u8]) -> Result<PpBeatmap, PpifyError> {
+    check_declared_mode(bytes)?;
+
+    let started = Instant::now();
+    let map = PpBeatmap::from_bytes(bytes)
+        .map_err(|err| PpifyError::Calculation(format!("failed to parse .osu file: {err}")))?;
+    debug!(elapsed = ?started.elapsed(), objects = map.hit_objects.len(), "parsed a beatmap");
+
+    if let Err(suspicion) = map.check_suspicion() {
+        return Err(PpifyError::SuspiciousMap(format!("{suspicion:?}")));
+    }
+
+    Ok(map)
+}
+
+#[derive(Clone, Debug, Default, Serialize)]
+pub struct BeatmapMetadata {
+    pub beatmap_id: Option<u32>,
+    pub artist: String,
+    pub title: String,
+    pub version: String,
+    pub creator: String,
+}
+
+impl BeatmapMetadata {
+    /// Reads the `[Metadata]` section of `.osu` file contents.
+    pub fn from_osu_bytes(bytes: &[u8]) -> Self {
+        let content = String::from_utf8_lossy(bytes);
+        let mut metadata = Self::default();
+        let mut in_metadata = false;
+
+        for line in content.lines().map(str::trim) {
+            if line.starts_with('[') {
+                if in_metadata {
+                    break;
+                }
+
+                in_metadata = line == "[Metadata]";
+                continue;
+            }
+
+            if !in_metadata {
+                continue;
+            }
+
+            let Some((key, value)) = line.split_once(':') else {
+                continue;
+            };
+
+            if key.trim() == "BeatmapID" {
+                // unsubmitted maps use 0 or -1
+                metadata.beatmap_id = value.trim().parse().ok().filter(|&id| id > 0);
+                continue;
+            }
+
+            let field = match key.trim() {
+                "Artist" => &mut metadata.artist,
+                "Title" => &mut metadata.title,
+                "Version" => &mut metadata.version,
+                "Creator" => &mut metadata.creator,
+                _ => continue,
+            };
+
+            *field = value.trim().to_owned();
+        }
+
+        metadata
+    }
+}
+
+impl std::fmt::Display for BeatmapMetadata {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{} - {} [{}] (mapped by {})",
+            self.artist, self.title, self.version, self.creator
+        )
+    }
+}
+
+#[derive(Clone, Debug, Serialize)]
+pub struct DifficultySummary {
+    pub stars: f64,
+    pub max_combo: u32,
+    #[serde(flatten)]
+    pub skills: SkillDifficulty,
+}
+
+#[derive(Clone, Debug, Serialize)]
+#[serde(untagged)]
+pub enum SkillDifficulty {
+    Osu {
+        aim: f64,
+        speed: f64,
+        flashlight: f64,
+        slider_factor: f64,
+        speed_note_count: f64,
+        ar: f64,
+        hp: f64,
+        n_circles: u32,
+        n_sliders: u32,
+        n_spinners: u32,
+    },
+    Taiko {
+        stamina: f64,
+        rhythm: f64,
+        color: f64,
+    },
+    Catch {
+        ar: f64,
+        n_fruits: u32,
+        n_droplets: u32,
+        n_tiny_droplets: u32,
+    },
+    Mania {
+        n_objects: u32,
+    },
+}
+
+impl DifficultySummary {
+    pub fn from_performance(attrs: &PerformanceAttributes) -> Self {
+        let skills = match attrs {
+            PerformanceAttributes::Osu(attrs) => {
+                let d = &attrs.difficulty;
+
+                SkillDifficulty::Osu {
+                    aim: d.aim,
+                    speed: d.speed,
+                    flashlight: d.flashlight,
+                    slider_factor: d.slider_factor,
+                    speed_note_count: d.speed_note_count,
+                    ar: d.ar,
+                    hp: d.hp,
+                    n_circles: d.n_circles,
+                    n_sliders: d.n_sliders,
+                    n_spinners: d.n_spinners,
+                }
+            }
+            PerformanceAttributes::Taiko(attrs) => SkillDifficulty::Taiko {
+                stamina: attrs.difficulty.stamina,
+                rhythm: attrs.difficulty.rhythm,
+                color: attrs.difficulty.color,
+            },
+            PerformanceAttributes::Catch(attrs) => SkillDifficulty::Catch {
+                ar: attrs.difficulty.ar,
+                n_fruits: attrs.difficulty.n_fruits,
+                n_droplets: attrs.difficulty.n_droplets,
+                n_tiny_droplets: attrs.difficulty.n_tiny_droplets,
+            },
+            PerformanceAttributes::Mania(attrs) => SkillDifficulty::Mania {
+                n_objects: attrs.difficulty.n_objects,
+            },
+        };
+
+        Self {
+            stars: attrs.stars(),
+            max_combo: attrs.max_combo(),
+            skills,
+        }
+    }
+}
+
+/// The pp of each skill, before they are combined into the total.
+#[derive(Clone, Copy, Debug, Serialize)]
+#[serde(untagged)]
+pub enum PpBreakdown {
+    Osu {
+        aim: f64,
+        speed: f64,
+        accuracy: f64,
+        flashlight: f64,
+    },
+    Taiko {
+        difficulty: f64,
+        accuracy: f64,
+    },
+    /// osu!catch pp isn't split by skill.
+    Catch,
+    Mania {
+        difficulty: f64,
+    },
+}
+
+impl PpBreakdown {
+    pub fn from_performance(attrs: &PerformanceAttributes) -> Self {
+        match attrs {
+            PerformanceAttributes::Osu(attrs) => Self::Osu {
+                aim: attrs.pp_aim,
+                speed: attrs.pp_speed,
+                accuracy: attrs.pp_acc,
+                flashlight: attrs.pp_flashlight,
+            },
+            PerformanceAttributes::Taiko(attrs) => Self::Taiko {
+                difficulty: attrs.pp_difficulty,
+                accuracy: attrs.pp_acc,
+            },
+            PerformanceAttributes::Catch(_) => Self::Catch,
+            PerformanceAttributes::Mania(attrs) => Self::Mania {
+                difficulty: attrs.pp_difficulty,
+            },
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, Serialize)]
+pub struct MapSettings {
+    pub ar: f64,
+    pub od: f64,
+    pub cs: f64,
+    pub hp: f64,
+    pub bpm: f64,
+    pub drain_secs: f64,
+}
+
+/// A map's settings as written in the file and after mods and clock rate.
+#[derive(Clone, Copy, Debug, Serialize)]
+pub struct ModdedSettings {
+    pub raw: MapSettings,
+    pub modded: MapSettings,
+    /// osu!mania only, after key count mods on converts.
+    pub keys: Option<u32>,
+}
+
+impl ModdedSettings {
+    /// The settings of `map` as played in `mode`, i.e. of the convert for other modes.
+    pub fn new(
+        map: &PpBeatmap,
+        mode: PpGameMode,
+        mods: &GameMods,
+        clock_rate: Option<f64>,
+    ) -> Result<Self, PpifyError> {
+        let converted = map
+            .convert_ref(mode, &mods.clone().into())
+            .map_err(|err| PpifyError::Calculation(format!("failed to convert beatmap: {err}")))?;
+
+        let raw = MapSettings {
+            ar: f64::from(converted.ar),
+            od: f64::from(converted.od),
+            cs: f64::from(converted.cs),
+            hp: f64::from(converted.hp),
+            bpm: converted.bpm(),
+            drain_secs: drain_time_ms(&converted) / 1000.0,
+        };
+
+        let mut builder = converted.attributes().mods(mods.clone());
+
+        if let Some(clock_rate) = clock_rate {
+            builder = builder.clock_rate(clock_rate);
+        }
+
+        let attrs = builder.build();
+
+        let modded = MapSettings {
+            ar: attrs.ar,
+            od: attrs.od,
+            cs: attrs.cs,
+            hp: attrs.hp,
+            bpm: raw.bpm * attrs.clock_rate,
+            drain_secs: raw.drain_secs / attrs.clock_rate,
+        };
+
+        let keys = match mode {
+            PpGameMode::Mania => mania_key_count(map, mods),
+            PpGameMode::Osu | PpGameMode::Taiko | PpGameMode::Catch => None,
+        };
+
+        Ok(Self { raw, modded, keys })
+    }
+}
+
+// Time between the first and last object, without breaks.
+fn drain_time_ms(map: &PpBeatmap) -> f64 {
+    let (Some(first), Some(last)) = (map.hit_objects.first(), map.hit_objects.last()) else {
+        return 0.0;
+    };
+
+    let breaks: f64 = map.breaks.iter().map(|b| b.end_time - b.start_time).sum();
+
+    (last.start_time - first.start_time - breaks).max(0.0)
+}
+
+/// The scoring rules plays are calculated with.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum Rules {
+    #[default]
+    Lazer,
+    /// osu!stable's, e.g. without slider end accuracy.
+    Stable,
+}
+
+impl Rules {
+    pub fn new(stable: bool) -> Self {
+        if stable { Self::Stable } else { Self::Lazer }
+    }
+
+    /// The rules plays with `mods` are calculated with, CL always uses stable's.
+    pub fn for_mods(self, mods: &GameMods) -> Self {
+        if mods.contains_intermode(GameModIntermode::Classic) {
+            Self::Stable
+        } else {
+            self
+        }
+    }
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Self::Lazer => "lazer",
+            Self::Stable => "stable",
+        }
+    }
+
+    // rosu-pp's flag for plays with `mods`
+    fn lazer(self, mods: &GameMods) -> bool {
+        self.for_mods(mods) == Self::Lazer
+    }
+}
+
+pub fn max_combo(map: &PpBeatmap, mode: PpGameMode, mods: &GameMods, rules: Rules) -> u32 {
+    Performance::new(map)
+        .mods(mods.clone())
+        .lazer(rules.lazer(mods))
+        .mode_or_ignore(mode)
+        .calculate()
+        .max_combo()
+}
+
+pub fn calculate_play(
+    map: &PpBeatmap,
+    mode: PpGameMode,
+    mods: &GameMods,
+    clock_rate: Option<f64>,
+    rules: Rules,
+    play: &PlayInputs,
+) -> PerformanceAttributes {
+    let mut perf = Performance::new(map)
+        .mods(mods.clone())
+        .lazer(rules.lazer(mods))
+        .mode_or_ignore(mode);
+
+    // a custom rate replaces the one of DT/HT
+    if let Some(clock_rate) = clock_rate {
+        perf = perf.clock_rate(clock_rate);
+    }
+
+    let started = Instant::now();
+    let attrs = play.apply(perf).calculate();
+    debug!(elapsed = ?started.elapsed(), %mods, pp = attrs.pp(), "calculated a play");
+
+    attrs
+}
+
+/// Calculates another play on the map of `attrs`, reusing its difficulty
+/// attributes instead of running the difficulty calculation again.
+///
+/// `mods`, `clock_rate` and `rules` must not change the difficulty compared to `attrs`.
+pub fn recalculate(
+    attrs: &PerformanceAttributes,
+    mods: &GameMods,
+    clock_rate: Option<f64>,
+    rules: Rules,
+    play: &PlayInputs,
+) -> PerformanceAttributes {
+    let mut perf = Performance::new(attrs.clone())
+        .mods(mods.clone())
+        .lazer(rules.lazer(mods));
+
+    if let Some(clock_rate) = clock_rate {
+        perf = perf.clock_rate(clock_rate);
+    }
+
+    play.apply(perf).calculate()
+}
+
+#[derive(Clone, Copy, Debug, Serialize)]
+pub struct AccuracyRow {
+    pub accuracy: f64,
+    pub misses: u32,
+    pub pp: f64,
+}
+
+/// The accuracies of [`accuracy_table`] in `mode` by default, closer to 100% for mania and
+/// catch, where plays usually are.
+pub fn sweep_points(mode: PpGameMode) -> &'static [f64] {
+    match mode {
+        PpGameMode::Osu | PpGameMode::Taiko => &[95.0, 96.0, 97.0, 98.0, 99.0, 100.0],
+        PpGameMode::Mania => &[96.0, 97.0, 97.5, 98.0, 98.5, 99.0, 99.5, 100.0],
+        PpGameMode::Catch => &[98.0, 98.5, 99.0, 99.25, 99.5, 99.75, 100.0],
+    }
+}
+
+/// Sorts custom accuracies of [`accuracy_table`] and drops duplicates.
+pub fn check_sweep_points(points: &[f64]) -> Result<Vec<f64>, PpifyError> {
+    if let Some(point) = points.iter().find(|point| !(0.0..=100.0).contains(*point)) {
+        return Err(PpifyError::BadInput(format!(
+            "sweep point {point}% is not between 0 and 100"
+        )));
+    }
+
+    let mut points = points.to_vec();
+    points.sort_by(f64::total_cmp);
+    points.dedup();
+
+    Ok(points)
+}
+
+/// PP at each of `accuracies` with the misses and combo of `play`, plus an SS as the last row.
+pub fn accuracy_table(
+    attrs: &PerformanceAttributes,
+    mods: &GameMods,
+    clock_rate: Option<f64>,
+    rules: Rules,
+    play: &PlayInputs,
+    accuracies: &[f64],
+) -> Vec<AccuracyRow> {
+    let misses = play.misses();
+
+    let mut rows: Vec<_> = accuracies
+        .iter()
+        .map(|&accuracy| {
+            let row_play = PlayInputs {
+                judgements: Judgements::Accuracy { accuracy, misses },
+                combo: play.combo,
+            };
+
+            AccuracyRow {
+                accuracy,
+                misses,
+                pp: recalculate(attrs, mods, clock_rate, rules, &row_play).pp(),
+            }
+        })
+        .collect();
+
+    let ss = PlayInputs {
+        judgements: Judgements::Accuracy {
+            accuracy: 100.0,
+            misses: 0,
+        },
+        combo: None,
+    };
+
+    rows.push(AccuracyRow {
+        accuracy: 100.0,
+        misses: 0,
+        pp: recalculate(attrs, mods, clock_rate, rules, &ss).pp(),
+    });
+
+    rows
+}
+
+/// PP from `from`% to 100% accuracy in steps of `step`, with the misses and combo of `play`.
+pub fn accuracy_curve(
+    attrs: &PerformanceAttributes,
+    mods: &GameMods,
+    clock_rate: Option<f64>,
+    rules: Rules,
+    play: &PlayInputs,
+    from: f64,
+    step: f64,
+) -> Vec<AccuracyRow> {
+    let misses = play.misses();
+    let steps = ((100.0 - from) / step).round() as u32;
+
+    (0..=steps)
+        .map(|i| {
+            let accuracy = (from + f64::from(i) * step).min(100.0);
+            let row_play = PlayInputs {
+                judgements: Judgements::Accuracy { accuracy, misses },
+                combo: play.combo,
+            };
+
+            AccuracyRow {
+                accuracy,
+                misses,
+                pp: recalculate(attrs, mods, clock_rate, rules, &row_play).pp(),
+            }
+        })
+        .collect()
+}
+
+pub const MISS_TABLE_COUNTS: [u32; 5] = [0, 1, 2, 5, 10];
+
+#[derive(Clone, Copy, Debug, Serialize)]
+pub struct MissRow {
+    pub misses: u32,
+    pub pp: f64,
+}
+
+/// PP of `play` with each of [`MISS_TABLE_COUNTS`] misses.
+pub fn miss_table(
+    attrs: &PerformanceAttributes,
+    mods: &GameMods,
+    clock_rate: Option<f64>,
+    rules: Rules,
+    play: &PlayInputs,
+) -> Vec<MissRow> {
+    MISS_TABLE_COUNTS
+        .iter()
+        .map(|&misses| MissRow {
+            misses,
+            pp: recalculate(attrs, mods, clock_rate, rules, &play.with_misses(misses)).pp(),
+        })
+        .collect()
+}
+
+#[derive(Clone, Debug, Serialize)]
+pub struct ModComboRow {
+    pub mods: String,
+    pub stars: f64,
+    pub pp: f64,
+}
+
+/// The same play under each of `combos`, best first.
+pub fn mod_matrix(
+    map: &PpBeatmap,
+    mode: PpGameMode,
+    combos: &[(String, GameMods)],
+    clock_rate: Option<f64>,
+    rules: Rules,
+    play: &PlayInputs,
+) -> Vec<ModComboRow> {
+    let mut rows: Vec<_> = combos
+        .iter()
+        .map(|(label, mods)| {
+            let attrs = calculate_play(map, mode, mods, clock_rate, rules, play);
+
+            ModComboRow {
+                mods: label.clone(),
+                stars: attrs.stars(),
+                pp: attrs.pp(),
+            }
+        })
+        .collect();
+
+    rows.sort_by(|a, b| b.pp.total_cmp(&a.pp));
+
+    rows
+}
+
+/// The lowest accuracy with `misses` that is worth at least `target_pp`, `None` if
+/// not even 100% is.
+pub fn accuracy_for_pp(
+    map: &PpBeatmap,
+    mode: PpGameMode,
+    mods: &GameMods,
+    clock_rate: Option<f64>,
+    rules: Rules,
+    misses: u32,
+    target_pp: f64,
+) -> Option<f64> {
+    let play_at = |accuracy| PlayInputs {
+        judgements: Judgements::Accuracy { accuracy, misses },
+        combo: None,
+    };
+
+    let max_attrs = calculate_play(map, mode, mods, clock_rate, rules, &play_at(100.0));
+
+    if max_attrs.pp() < target_pp {
+        return None;
+    }
+
+    let pp_at =
+        |accuracy| recalculate(&max_attrs, mods, clock_rate, rules, &play_at(accuracy)).pp();
+
+    let (mut lo, mut hi) = (0.0, 100.0);
+
+    while hi - lo > 0.001 {
+        let mid = (lo + hi) / 2.0;
+
+        if pp_at(mid) >= target_pp {
+            hi = mid;
+        } else {
+            lo = mid;
+        }
+    }
+
+    Some(hi)
+}
+
+pub const PROGRESSION_PERCENTS: [f64; 4] = [25.0, 50.0, 75.0, 100.0];
+
+#[derive(Clone, Copy, Debug, Serialize)]
+pub struct ProgressionPoint {
+    pub percent: f64,
+    /// Hit objects passed at this point.
+    pub objects: usize,
+    pub pp: f64,
+}
+
+/// Where a play ends early, for `--fail-at`.
+#[derive(Clone, Copy, Debug)]
+pub enum FailAt {
+    /// 1-based hit object.
+    Object(usize),
+    /// Percent of the hit objects, in (0, 100].
+    Percent(f64),
+}
+
+impl FailAt {
+    fn objects(self, total_objects: usize) -> usize {
+        let objects = match self {
+            Self::Object(object) => object,
+            Self::Percent(percent) => (total_objects as f64 * percent / 100.0).ceil() as usize,
+        };
+
+        objects.max(1)
+    }
+}
+
+/// PP of `play` if it ended after each of `percents` of the hit objects, e.g. by failing.
+///
+/// The judgements and combo of the whole play are spread evenly over the map.
+pub fn pp_progression(
+    map: &PpBeatmap,
+    mode: PpGameMode,
+    mods: &GameMods,
+    clock_rate: Option<f64>,
+    rules: Rules,
+    play: &PlayInputs,
+    percents: &[f64],
+) -> Result<Vec<ProgressionPoint>, PpifyError> {
+    if let Some(percent) = percents.iter().find(|&&p| !(p > 0.0 && p <= 100.0)) {
+        return Err(PpifyError::BadInput(format!(
+            "progression point {percent}% is not between 0 and 100"
+        )));
+    }
+
+    let ends: Vec<_> = percents.iter().map(|&p| FailAt::Percent(p)).collect();
+
+    gradual_points(map, mode, mods, clock_rate, rules, play, &ends)
+}
+
+/// PP of `play` if it failed at `fail_at`, like a point of [`pp_progression`].
+///
+/// Failed plays give no ranked pp, this is only the pp of the part that was played.
+pub fn pp_at_fail(
+    map: &PpBeatmap,
+    mode: PpGameMode,
+    mods: &GameMods,
+    clock_rate: Option<f64>,
+    rules: Rules,
+    play: &PlayInputs,
+    fail_at: FailAt,
+) -> Result<ProgressionPoint, PpifyError> {
+    let mut points = gradual_points(map, mode, mods, clock_rate, rules, play, &[fail_at])?;
+
+    points
+        .pop()
+        .ok_or_else(|| PpifyError::Calculation("the beatmap has no hit objects".to_owned()))
+}
+
+fn gradual_points(
+    map: &PpBeatmap,
+    mode: PpGameMode,
+    mods: &GameMods,
+    clock_rate: Option<f64>,
+    rules: Rules,
+    play: &PlayInputs,
+    ends: &[FailAt],
+) -> Result<Vec<ProgressionPoint>, PpifyError> {
+    let convert_err =
+        |err: ConvertError| PpifyError::Calculation(format!("failed to convert beatmap: {err}"));
+    let map = map
+        .convert_ref(mode, &mods.clone().into())
+        .map_err(convert_err)?;
+
+    let lazer = rules.lazer(mods);
+    let mut perf = Performance::new(map.as_ref())
+        .mods(mods.clone())
+        .lazer(lazer);
+    let mut difficulty = Difficulty::new().mods(mods.clone()).lazer(lazer);
+
+    if let Some(clock_rate) = clock_rate {
+        perf = perf.clock_rate(clock_rate);
+        difficulty = difficulty.clock_rate(clock_rate);
+    }
+
+    let full_state = play.apply(perf).generate_state().map_err(convert_err)?;
+
+    let mut gradual = difficulty.gradual_performance(map.as_ref());
+    let total_objects = gradual.len();
+
+    if let Some(FailAt::Object(object)) = ends
+        .iter()
+        .find(|end| matches!(end, FailAt::Object(object) if *object > total_objects))
+    {
+        return Err(PpifyError::BadInput(format!(
+            "the beatmap has {total_objects} hit objects, it can't fail at object {object}"
+        )));
+    }
+
+    let mut ends: Vec<_> = ends
+        .iter()
+        .map(|&end| {
+            let objects = end.objects(total_objects);
+            let percent = match end {
+                FailAt::Percent(percent) => percent,
+                FailAt::Object(_) => objects as f64 / total_objects as f64 * 100.0,
+            };
+
+            (objects, percent)
+        })
+        .collect();
+    ends.sort_by(|a, b| a.0.cmp(&b.0).then(a.1.total_cmp(&b.1)));
+
+    let mut passed = 0;
+    let mut points = Vec::with_capacity(ends.len());
+
+    for (objects, percent) in ends {
+        if objects <= passed {
+            continue;
+        }
+
+        let state = scaled_state(&full_state, objects as f64 / total_objects as f64);
+
+        // `nth` is zero-indexed, 0 processes one more object
+        let Some(attrs) = gradual.nth(state, objects - passed - 1) else {
+            break;
+        };
+
+        passed = objects;
+        points.push(ProgressionPoint {
+            percent,
+            objects,
+            pp: attrs.pp(),
+        });
+    }
+
+    Ok(points)
+}
+
+fn scaled_state(state: &ScoreState, fraction: f64) -> ScoreState {
+    let scale = |count: u32| (f64::from(count) * fraction).round() as u32;
+
+    ScoreState {
+        max_combo: scale(state.max_combo),
+        osu_large_tick_hits: scale(state.osu_large_tick_hits),
+        osu_small_tick_hits: scale(state.osu_small_tick_hits),
+        slider_end_hits: scale(state.slider_end_hits),
+        n_geki: scale(state.n_geki),
+        n_katu: scale(state.n_katu),
+        n300: scale(state.n300),
+        n100: scale(state.n100),
+        n50: scale(state.n50),
+        misses: scale(state.misses),
+    }
+}
+
+/// Combined strain of the map's skills over time.
+#[derive(Clone, Debug, Serialize)]
+pub struct StrainGraph {
+    /// Map time where the first section starts.
+    pub start_ms: f64,
+    /// Length of a section in map time, i.e. before the clock rate.
+    pub section_ms: f64,
+    /// Peak strain of each section.
+    pub strains: Vec<f64>,
+}
+
+impl StrainGraph {
+    /// Index of the hardest section, `None` for maps without strains.
+    pub fn peak(&self) -> Option<usize> {
+        self.strains
+            .iter()
+            .enumerate()
+            .max_by(|(_, a), (_, b)| a.total_cmp(b))
+            .map(|(i, _)| i)
+    }
+
+    /// Map time where section `index` starts.
+    pub fn section_start_ms(&self, index: usize) -> f64 {
+        self.start_ms + index as f64 * self.section_ms
+    }
+
+    /// Up to `count` sections whose strain rises the most over the one before, the biggest
+    /// rise first. A rise right next to a bigger one is part of the same spike.
+    pub fn spikes(&self, count: usize) -> Vec<StrainSpike> {
+        let mut rises: Vec<(usize, f64)> = self
+            .strains
+            .windows(2)
+            .enumerate()
+            .map(|(i, pair)| (i + 1, pair[1] - pair[0]))
+            .filter(|(_, rise)| *rise > 0.0)
+            .collect();
+        rises.sort_by(|a, b| b.1.total_cmp(&a.1));
+
+        let mut picked: Vec<usize> = Vec::with_capacity(count);
+
+        for (index, _) in rises {
+            if picked.len() == count {
+                break;
+            }
+
+            if picked.iter().all(|&other| index.abs_diff(other) > 1) {
+                picked.push(index);
+            }
+        }
+
+        picked
+            .into_iter()
+            .map(|index| StrainSpike {
+                start_ms: self.section_start_ms(index),
+                from: self.strains[index - 1],
+                to: self.strains[index],
+            })
+            .collect()
+    }
+}
+
+/// A sudden rise of the strain, see [`StrainGraph::spikes`].
+#[derive(Clone, Copy, Debug, Serialize)]
+pub struct StrainSpike {
+    /// Map time where the section of the rise starts.
+    pub start_ms: f64,
+    /// Strain of the section before.
+    pub from: f64,
+    pub to: f64,
+}
+
+pub fn strain_graph(
+    map: &PpBeatmap,
+    mode: PpGameMode,
+    mods: &GameMods,
+    clock_rate: Option<f64>,
+    rules: Rules,
+) -> Result<StrainGraph, PpifyError> {
+    let map = map
+        .convert_ref(mode, &mods.clone().into())
+        .map_err(|err| PpifyError::Calculation(format!("failed to convert beatmap: {err}")))?;
+
+    let mut difficulty = Difficulty::new()
+        .mods(mods.clone())
+        .lazer(rules.lazer(mods));
+    let mut builder = map.attributes().mods(mods.clone());
+
+    if let Some(clock_rate) = clock_rate {
+        difficulty = difficulty.clock_rate(clock_rate);
+        builder = builder.clock_rate(clock_rate);
+    }
+
+    let strains = difficulty.strains(map.as_ref());
+
+    // strain sections are measured in time after the clock rate
+    let section_ms = strains.section_len() * builder.build().clock_rate;
+
+    // summed per section, the skills are weighted alike in the star rating
+    let sum = |skills: &[&Vec<f64>]| -> Vec<f64> {
+        let len = skills.iter().map(|s| s.len()).max().unwrap_or(0);
+
+        (0..len)
+            .map(|i| skills.iter().filter_map(|s| s.get(i)).sum())
+            .collect()
+    };
+
+    let strains = match &strains {
+        Strains::Osu(s) => sum(&[&s.aim, &s.speed]),
+        Strains::Taiko(s) => sum(&[&s.color, &s.rhythm, &s.stamina]),
+        Strains::Catch(s) => s.movement.clone(),
+        Strains::Mania(s) => s.strains.clone(),
+    };
+
+    let first_ms = map.hit_objects.first().map_or(0.0, |h| h.start_time);
+
+    Ok(StrainGraph {
+        start_ms: (first_ms / section_ms).floor() * section_ms,
+        section_ms,
+        strains,
+    })
+}
+
+#[derive(Clone, Debug, Serialize)]
+pub struct FlashlightContribution {
+    pub pp_flashlight: f64,
+    pub pp_without_fl: f64,
+    pub fl_attributable_pp: f64,
+}
+
+/// Recomputes an osu!standard FL play without FL, `None` for other plays.
+pub fn flashlight_contribution(
+    attrs: &PerformanceAttributes,
+    mods: &GameMods,
+    clock_rate: Option<f64>,
+    rules: Rules,
+    play: &PlayInputs,
+) -> Option<FlashlightContribution> {
+    let PerformanceAttributes::Osu(osu_attrs) = attrs else {
+        return None;
+    };
+
+    let is_fl = |m: &GameMod| m.intermode() == GameModIntermode::Flashlight;
+
+    if !mods.iter().any(is_fl) {
+        return None;
+    }
+
+    let without_fl: GameMods = mods.iter().filter(|m| !is_fl(m)).cloned().collect();
+
+    // FL doesn't change the clock rate, so the aim/speed attributes of the
+    // FL calculation are valid for the NoFL one as well
+    let pp_without_fl = recalculate(attrs, &without_fl, clock_rate, rules, play).pp();
+
+    Some(FlashlightContribution {
+        pp_flashlight: osu_attrs.pp_flashlight,
+        pp_without_fl,
+        fl_attributable_pp: osu_attrs.pp - pp_without_fl,
+    })
+}
+
+#[derive(Clone, Debug, Serialize)]
+pub struct RoundTrip {
+    pub api_pp: f64,
+    pub recomputed_pp: f64,
+    /// How many top scores matched the play; the highest one is compared.
+    pub matches: usize,
+}
+
+impl RoundTrip {
+    pub fn delta(&self) -> f64 {
+        self.recomputed_pp - self.api_pp
+    }
+}
+
+pub fn round_trip(
+    play: &PlayInputs,
+    scores: &[Score],
+    map_id: u32,
+    mods: &GameMods,
+    recomputed_pp: f64,
+) -> Option<RoundTrip> {
+    let mut matching = scores
+        .iter()
+        .filter(|score| play.matches_score(score, map_id, mods));
+
+    let api_pp = matching.next()?.pp?;
+
+    Some(RoundTrip {
+        api_pp: f64::from(api_pp),
+        recomputed_pp,
+        matches: 1 + matching.count(),
+    })
+}
+
+#[derive(Clone, Debug, Serialize)]
+pub struct TopPlay {
+    pub pp: f64,
+    pub label: String,
+    pub map_id: Option<u32>,
+    pub old_rank: Option<usize>,
+}
+
+impl TopPlay {
+    pub fn from_score(score: &Score) -> Option<Self> {
+        Some(Self {
+            pp: score.pp? as f64,
+            label: score_label(score),
+            map_id: Some(score.map_id),
+            old_rank: None,
+        })
+    }
+}
+
+/// Whether the score was set on a convert, i.e. its map's own mode differs from the mode it
+/// was played in. `None` if the score doesn't include the map.
+pub fn is_convert(score: &Score) -> Option<bool> {
+    score.map.as_ref().map(|map| map.mode != score.mode)
+}
+
+/// "Artist - Title [Diff] +MODS" as far as the score includes the map.
+pub fn score_label(score: &Score) -> String {
+    let title = match (&score.mapset, &score.map) {
+        (Some(mapset), Some(map)) => {
+            format!("{} - {} [{}]", mapset.artist, mapset.title, map.version)
+        }
+        (Some(mapset), None) => format!("{} - {}", mapset.artist, mapset.title),
+        _ => format!("beatmap {}", score.map_id),
+    };
+
+    if score.mods.is_empty() {
+        title
+    } else {
+        format!("{title} +{}", score.mods)
+    }
+}
+
+// Orders by descending pp. On an exact tie, existing plays rank above the
+// hypothetical one and keep their previous relative order.
+fn cmp_top_plays(a: &TopPlay, b: &TopPlay) -> Ordering {
+    b.pp.total_cmp(&a.pp)
+        .then_with(|| match (a.old_rank, b.old_rank) {
+            (Some(a), Some(b)) => a.cmp(&b),
+            (Some(_), None) => Ordering::Less,
+            (None, Some(_)) => Ordering::Greater,
+            (None, None) => Ordering::Equal,
+        })
+}
+
+pub fn top_play_pps(plays: &[TopPlay]) -> Vec<f64> {
+    plays.iter().map(|p| p.pp).collect()
+}
+
+/// The effect of inserting a new play into a user's top plays.
+#[derive(Clone, Debug, Serialize)]
+pub struct GainProjection {
+    pub old_total_pp: f64,
+    pub new_total_pp: f64,
+    pub gain: f64,
+    /// Top plays after the insertion, the new play has no `old_rank`.
+    pub top_plays: Vec<TopPlay>,
+    /// The play pushed out of the top 100 by the insertion, if any.
+    pub dropped: Option<TopPlay>,
+    /// The previous play on the same map, replaced by the new one.
+    pub replaced: Option<TopPlay>,
+    /// A better play on the same map; the new play changes nothing then.
+    pub outscored_by: Option<TopPlay>,
+    /// 1-based position of the new play, `None` if it misses the top 100.
+    pub new_play_rank: Option<usize>,
+    /// What the new play contributes at that position.
+    pub new_play_weighted_pp: Option<f64>,
+    /// Included in both totals when known.
+    pub bonus: Option<BonusPp>,
+    /// How the totals weight the top plays, e.g. `0.95^i`.
+    pub weighting: String,
+    /// Weight of each rank of the top plays.
+    #[serde(skip)]
+    weights: Vec<f64>,
+}
+
+impl GainProjection {
+    /// Weight of the play at `index` of the top plays.
+    pub fn play_weight(&self, index: usize) -> f64 {
+        self.weights.get(index).copied().unwrap_or(0.0)
+    }
+
+    /// Weighted pp lost from the play that fell out of the top 100.
+    pub fn dropped_weighted_pp(&self) -> Option<f64> {
+        self.dropped
+            .as_ref()
+            .map(|play| play.pp * self.play_weight(TOP_PLAYS_CAP - 1))
+    }
+
+    /// Plays moved down by the new play with their new rank and the weighted pp they lose.
+    pub fn shifted_plays(&self) -> impl Iterator<Item = (usize, &TopPlay, f64)> {
+        self.top_plays.iter().enumerate().filter_map(|(i, play)| {
+            let old = play.old_rank?;
+            let lost = play.pp * (self.play_weight(old - 1) - self.play_weight(i));
+
+            (i + 1 > old).then_some((i + 1, play, lost))
+        })
+    }
+}
+
+/// The profile's bonus pp for the number of ranked scores, before and after the play.
+#[derive(Clone, Copy, Debug, Serialize)]
+pub struct BonusPp {
+    pub old: f64,
+    pub new: f64,
+}
+
+impl BonusPp {
+    /// `new_maps` is how many ranked scores the plays add, i.e. on maps the user has none on yet.
+    pub fn new(ranked_scores: u32, new_maps: u32) -> Self {
+        Self {
+            old: bonus_pp(ranked_scores),
+            new: bonus_pp(ranked_scores + new_maps),
+        }
+    }
+
+    /// The bonus of `user` before and after plays on `map_ids`, given their top `scores`.
+    ///
+    /// The api has no count of ranked scores, so the SS, S and A grade counts stand in for
+    /// it. Users with many B ranks or worse get a lower bonus than osu! gives them.
+    pub fn for_user(
+        user: &UserExtended,
+        scores: &[Score],
+        map_ids: &[Option<u32>],
+    ) -> Option<Self> {
+        let grades = &user.statistics.as_ref()?.grade_counts;
+        let ranked_scores = (grades.ss + grades.ssh + grades.s + grades.sh + grades.a).max(0);
+
+        let mut new_maps: Vec<Option<u32>> = map_ids
+            .iter()
+            .copied()
+            .filter(|map_id| {
+                map_id.is_none_or(|map_id| scores.iter().all(|score| score.map_id != map_id))
+            })
+            .collect();
+
+        // several plays on the same map only add one score; local files without an id are
+        // all counted
+        new_maps.sort_unstable();
+        new_maps.dedup_by(|a, b| a.is_some() && a == b);
+
+        Some(Self::new(ranked_scores as u32, new_maps.len() as u32))
+    }
+}
+
+pub fn bonus_pp(ranked_scores: u32) -> f64 {
+    416.6667 * (1.0 - 0.995_f64.powi(ranked_scores.min(1000) as i32))
+}
+
+/// Inserts a new play into the top plays of `scores`.
+///
+/// Only the best play per map counts, so a play on `new_play_map` replaces the
+/// existing one there if it is worth more and is ignored otherwise.
+pub fn project_gain(
+    scores: &[Score],
+    new_play_pp: f64,
+    new_play_label: &str,
+    new_play_map: Option<u32>,
+    bonus: Option<BonusPp>,
+    weighting: &dyn Weighting,
+) -> GainProjection {
+    let top_plays = scores.iter().filter_map(TopPlay::from_score).collect();
+
+    insert_play(
+        top_plays,
+        new_play_pp,
+        new_play_label,
+        new_play_map,
+        bonus,
+        weighting,
+    )
+}
+
+// `project_gain` on top plays that aren't ranked yet
+fn insert_play(
+    mut top_plays: Vec<TopPlay>,
+    new_play_pp: f64,
+    new_play_label: &str,
+    new_play_map: Option<u32>,
+    bonus: Option<BonusPp>,
+    weighting: &dyn Weighting,
+) -> GainProjection {
+    top_plays.sort_by(cmp_top_plays);
+    for (i, play) in top_plays.iter_mut().enumerate() {
+        play.old_rank = Some(i + 1);
+    }
+    let old_total_pp =
+        weighting.total(&top_play_pps(&top_plays)) + bonus.map_or(0.0, |bonus| bonus.old);
+
+    let same_map = new_play_map.and_then(|map_id| {
+        top_plays
+            .iter()
+            .position(|play| play.map_id == Some(map_id))
+    });
+
+    let (replaced, outscored_by) = match same_map {
+        Some(i) if top_plays[i].pp >= new_play_pp => (None, Some(top_plays[i].clone())),
+        Some(i) => (Some(top_plays.remove(i)), None),
+        None => (None, None),
+    };
+
+    let mut dropped = None;
+
+    if outscored_by.is_none() {
+        top_plays.push(TopPlay {
+            pp: new_play_pp,
+            label: new_play_label.to_owned(),
+            map_id: new_play_map,
+            old_rank: None,
+        });
+        top_plays.sort_by(cmp_top_plays);
+        dropped = drop_overflowing_play(&mut top_plays);
+    }
+
+    let new_play_index = top_plays.iter().position(|play| play.old_rank.is_none());
+    let new_total_pp =
+        weighting.total(&top_play_pps(&top_plays)) + bonus.map_or(0.0, |bonus| bonus.new);
+
+    GainProjection {
+        old_total_pp,
+        new_total_pp,
+        gain: new_total_pp - old_total_pp,
+        top_plays,
+        dropped,
+        replaced,
+        outscored_by,
+        new_play_rank: new_play_index.map(|i| i + 1),
+        new_play_weighted_pp: new_play_index.map(|i| new_play_pp * weighting.weight(i)),
+        bonus,
+        weighting: weighting.to_string(),
+        weights: (0..TOP_PLAYS_CAP).map(|i| weighting.weight(i)).collect(),
+    }
+}
+
+/// The current total of `scores`, weighted like on the profile, plus the bonus.
+pub fn total_pp(scores: &[Score], bonus: Option<BonusPp>, weighting: &dyn Weighting) -> f64 {
+    let mut pps: Vec<f64> = scores
+        .iter()
+        .filter_map(|score| score.pp)
+        .map(f64::from)
+        .collect();
+    pps.sort_by(|a, b| b.total_cmp(a));
+
+    weighting.total(&pps) + bonus.map_or(0.0, |bonus| bonus.old)
+}
+
+/// The pp of the play at 1-based `position` of the top plays, `None` if there are fewer.
+pub fn pp_at_position(scores: &[Score], position: usize) -> Option<f64> {
+    let mut pps: Vec<f64> = scores
+        .iter()
+        .filter_map(|score| score.pp)
+        .map(f64::from)
+        .collect();
+    pps.sort_by(|a, b| b.total_cmp(a));
+
+    pps.get(position.checked_sub(1)?).copied()
+}
+
+/// The raw pp a single new play needs for the total to reach `target_total_pp`.
+///
+/// `Some(0.0)` if the target is already reached, `None` if no single play gets there.
+pub fn required_play_pp(
+    scores: &[Score],
+    target_total_pp: f64,
+    map_id: Option<u32>,
+    bonus: Option<BonusPp>,
+    weighting: &dyn Weighting,
+) -> Option<f64> {
+    const MAX_PLAY_PP: f64 = 100_000.0;
+
+    let total_with = |pp: f64| project_gain(scores, pp, "", map_id, bonus, weighting).new_total_pp;
+
+    if total_with(0.0) >= target_total_pp {
+        return Some(0.0);
+    }
+
+    if total_with(MAX_PLAY_PP) < target_total_pp {
+        return None;
+    }
+
+    let (mut lo, mut hi) = (0.0, MAX_PLAY_PP);
+
+    // the total grows monotonically with the new play's pp
+    while hi - lo > 0.005 {
+        let mid = (lo + hi) / 2.0;
+
+        if total_with(mid) >= target_total_pp {
+            hi = mid;
+        } else {
+            lo = mid;
+        }
+    }
+
+    Some(hi)
+}
+
+/// A hypothetical play to insert with [`project_session`].
+#[derive(Clone, Debug, Serialize)]
+pub struct NewPlay {
+    pub pp: f64,
+    pub label: String,
+    pub map_id: Option<u32>,
+}
+
+/// The combined effect of several new plays.
+#[derive(Clone, Debug, Serialize)]
+pub struct SessionProjection {
+    pub old_total_pp: f64,
+    pub new_total_pp: f64,
+    pub gain: f64,
+    /// Top plays after the insertions, new plays have no `old_rank`.
+    pub top_plays: Vec<TopPlay>,
+    /// Plays pushed out of the top 100, new ones included.
+    pub dropped: Vec<TopPlay>,
+    pub bonus: Option<BonusPp>,
+}
+
+/// Inserts all `plays` into the top plays of `scores` before re-weighting, keeping
+/// only the best play per map like [`project_gain`].
+pub fn project_session(
+    scores: &[Score],
+    plays: &[NewPlay],
+    bonus: Option<BonusPp>,
+    weighting: &dyn Weighting,
+) -> SessionProjection {
+    let mut top_plays: Vec<TopPlay> = scores.iter().filter_map(TopPlay::from_score).collect();
+
+    top_plays.sort_by(cmp_top_plays);
+    for (i, play) in top_plays.iter_mut().enumerate() {
+        play.old_rank = Some(i + 1);
+    }
+    let old_total_pp =
+        weighting.total(&top_play_pps(&top_plays)) + bonus.map_or(0.0, |bonus| bonus.old);
+
+    for play in plays {
+        let same_map = play
+            .map_id
+            .and_then(|map_id| top_plays.iter().position(|top| top.map_id == Some(map_id)));
+
+        match same_map {
+            Some(i) if top_plays[i].pp >= play.pp => continue,
+            Some(i) => {
+                top_plays.remove(i);
+            }
+            None => {}
+        }
+
+        top_plays.push(TopPlay {
+            pp: play.pp,
+            label: play.label.clone(),
+            map_id: play.map_id,
+            old_rank: None,
+        });
+    }
+
+    top_plays.sort_by(cmp_top_plays);
+    let dropped = top_plays.split_off(top_plays.len().min(TOP_PLAYS_CAP));
+    let new_total_pp =
+        weighting.total(&top_play_pps(&top_plays)) + bonus.map_or(0.0, |bonus| bonus.new);
+
+    SessionProjection {
+        old_total_pp,
+        new_total_pp,
+        gain: new_total_pp - old_total_pp,
+        top_plays,
+        dropped,
+        bonus,
+    }
+}
+
+// `plays` must already be sorted by descending pp
+fn drop_overflowing_play(plays: &mut Vec<TopPlay>) -> Option<TopPlay> {
+    if plays.len() > TOP_PLAYS_CAP {
+        let dropped = plays.remove(TOP_PLAYS_CAP);
+        plays.truncate(TOP_PLAYS_CAP);
+
+        Some(dropped)
+    } else {
+        None
+    }
+}
+
+/// How top plays add up to a profile's total, displayed like `0.95^i`.
+pub trait Weighting: std::fmt::Display {
+    /// Weight of the play at `index` of the top plays, best first.
+    fn weight(&self, index: usize) -> f64;
+
+    /// The total of `sorted_pps`, best first. Only the top 100 count.
+    fn total(&self, sorted_pps: &[f64]) -> f64 {
+        sorted_pps
+            .iter()
+            .take(TOP_PLAYS_CAP)
+            .enumerate()
+            .map(|(i, pp)| pp * self.weight(i))
+            .sum()
+    }
+}
+
+/// The weightings of `--weighting`.
+#[derive(Clone, Copy, Debug)]
+pub enum BuiltinWeighting {
+    /// osu!'s, every play is worth 95% of the one above.
+    Classic,
+    /// From 100% for the best play down to 1% for the 100th in equal steps.
+    Linear,
+    /// Like classic with another factor than 0.95.
+    Factor(f64),
+}
+
+impl Weighting for BuiltinWeighting {
+    fn weight(&self, index: usize) -> f64 {
+        match self {
+            Self::Classic => 0.95_f64.powi(index as i32),
+            Self::Linear => TOP_PLAYS_CAP.saturating_sub(index) as f64 / TOP_PLAYS_CAP as f64,
+            Self::Factor(factor) => factor.powi(index as i32),
+        }
+    }
+}
+
+impl std::fmt::Display for BuiltinWeighting {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Classic => f.write_str("0.95^i"),
+            Self::Linear => f.write_str("linear"),
+            Self::Factor(factor) => write!(f, "{factor}^i"),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // a few objects of `mode`, enough for rosu-pp to parse and calculate
+    fn osu_file(mode: u8) -> Vec<u8> {
+        osu_file_with_general(&format!("Mode: {mode}"))
+    }
+
+    fn osu_file_with_general(general: &str) -> Vec<u8> {
+        format!(
+            "osu file format v14
+
+[General]
+AudioFilename: audio.mp3
+{general}
+
+[Metadata]
+Title:Test
+Artist:ppify
+Creator:ppify
+Version:Test
+BeatmapID:1
+
+[Difficulty]
+HPDrainRate:5
+CircleSize:4
+OverallDifficulty:8
+ApproachRate:9
+SliderMultiplier:1.4
+SliderTickRate:1
+
+[TimingPoints]
+0,300,4,2,0,100,1,0
+
+[HitObjects]
+64,192,1000,1,0,0:0:0:0:
+192,192,1300,1,0,0:0:0:0:
+320,192,1600,1,0,0:0:0:0:
+448,192,1900,1,0,0:0:0:0:
+64,96,2200,1,0,0:0:0:0:
+448,288,2500,1,0,0:0:0:0:
+"
+        )
+        .into_bytes()
+    }
+
+    fn mode_error(bytes: &[u8], mode: PpGameMode) -> String {
+        match parse_beatmap(bytes, mode) {
+            Err(PpifyError::BadInput(err)) => err,
+            Err(err) => panic!("expected a mode error, got {err}"),
+            Ok(_) => panic!("expected a mode error, the map parsed"),
+        }
+    }
+
+    #[test]
+    fn parse_beatmap_rejects_another_mode() {
+        assert_eq!(
+            mode_error(&osu_file(1), PpGameMode::Mania),
+            "beatmap is an osu!taiko map and cannot be converted to osu!mania \
+             (only osu!standard maps have converts)"
+        );
+        assert_eq!(
+            mode_error(&osu_file(3), PpGameMode::Taiko),
+            "beatmap is an osu!mania map and cannot be converted to osu!taiko \
+             (only osu!standard maps have converts)"
+        );
+
+        // nothing converts to osu!standard
+        let map = parse_native_beatmap(&osu_file(2)).unwrap();
+        assert!(check_map_mode(&map, PpGameMode::Osu).is_err());
+    }
+
+    #[test]
+    fn parse_beatmap_accepts_the_own_mode() {
+        for (mode, pp_mode) in [
+            (0, PpGameMode::Osu),
+            (1, PpGameMode::Taiko),
+            (2, PpGameMode::Catch),
+            (3, PpGameMode::Mania),
+        ] {
+            let map = parse_beatmap(&osu_file(mode), pp_mode).unwrap();
+            assert_eq!(map.mode, pp_mode);
+        }
+    }
+
+    #[test]
+    fn parse_beatmap_accepts_osu_standard_converts() {
+        for pp_mode in [PpGameMode::Taiko, PpGameMode::Catch, PpGameMode::Mania] {
+            // the map keeps its own mode, the calculation converts it
+            let map = parse_beatmap(&osu_file(0), pp_mode).unwrap();
+            assert_eq!(map.mode, PpGameMode::Osu);
+        }
+    }
+
+    #[test]
+    fn parse_beatmap_rejects_an_unknown_mode() {
+        for general in ["Mode: 4", "Mode: 255", "Mode: mania", "Mode:"] {
+            let bytes = osu_file_with_general(general);
+            let err = match parse_native_beatmap(&bytes) {
+                Err(PpifyError::BadInput(err)) => err,
+                Err(err) => panic!("expected a mode error for `{general}`, got {err}"),
+                Ok(_) => panic!("expected a mode error for `{general}`, the map parsed"),
+            };
+            let mode = general.trim_start_matches("Mode:").trim();
+            assert!(err.contains(&format!("unsupported mode `{mode}`")), "{err}");
+        }
+    }
+
+    #[test]
+    fn parse_beatmap_reads_a_missing_mode_as_osu_standard() {
+        let map = parse_native_beatmap(&osu_file_with_general("")).unwrap();
+        assert_eq!(map.mode, PpGameMode::Osu);
+    }
+
+    #[test]
+    fn parse_beatmap_only_reads_the_general_mode() {
+        // `Mode:` outside of [General] isn't the map's mode
+        let bytes = osu_file_with_general("Mode: 1")
+            .into_iter()
+            .chain(b"\n[Editor]\nMode: 4\n".iter().copied())
+            .collect::<Vec<_>>();
+        let map = parse_native_beatmap(&bytes).unwrap();
+        assert_eq!(map.mode, PpGameMode::Taiko);
+    }
+
+    fn top_plays(pps: impl IntoIterator<Item = f64>) -> Vec<TopPlay> {
+        pps.into_iter()
+            .enumerate()
+            .map(|(i, pp)| TopPlay {
+                pp,
+                label: format!("play {i}"),
+                map_id: Some(i as u32),
+                old_rank: None,
+            })
+            .collect()
+    }
+
+    #[test]
+    fn a_full_top_100_drops_the_101st_play() {
+        // 200pp down to 101pp
+        let plays = top_plays((0..100).map(|i| 200.0 - f64::from(i)));
+        let weighting = BuiltinWeighting::Classic;
+
+        let projection = insert_play(plays, 150.5, "new", Some(1000), None, &weighting);
+
+        assert_eq!(projection.top_plays.len(), TOP_PLAYS_CAP);
+        assert_eq!(projection.new_play_rank, Some(51));
+        assert_eq!(
+            projection.new_play_weighted_pp,
+            Some(150.5 * 0.95_f64.powi(50))
+        );
+
+        let dropped = projection.dropped.as_ref().unwrap();
+        assert_eq!(dropped.pp, 101.0);
+        assert_eq!(dropped.old_rank, Some(100));
+
+        let dropped_weighted = projection.dropped_weighted_pp().unwrap();
+        assert!((dropped_weighted - 101.0 * 0.95_f64.powi(99)).abs() < 1e-9);
+
+        // the new play adds its weighted pp, the plays below it lose a step of weight each
+        // and the last one falls out entirely
+        let shifted_loss: f64 = projection.shifted_plays().map(|(_, _, lost)| lost).sum();
+        let expected = projection.old_total_pp + projection.new_play_weighted_pp.unwrap()
+            - shifted_loss
+            - dropped_weighted;
+        assert!((projection.new_total_pp - expected).abs() < 1e-9);
+
+        // the new play lands between 151pp and 150pp
+        let mut pps: Vec<f64> = (0..99).map(|i| 200.0 - f64::from(i)).collect();
+        pps.insert(50, 150.5);
+        assert_eq!(top_play_pps(&projection.top_plays), pps);
+
+        assert!((projection.new_total_pp - weighting.total(&pps)).abs() < 1e-9);
+        assert!(
+            (projection.gain - (projection.new_total_pp - projection.old_total_pp)).abs() < 1e-9
+        );
+    }
+
+    #[test]
+    fn a_play_below_a_full_top_100_changes_nothing() {
+        let plays = top_plays((0..100).map(|i| 200.0 - f64::from(i)));
+
+        let projection = insert_play(
+            plays,
+            50.0,
+            "new",
+            Some(1000),
+            None,
+            &BuiltinWeighting::Classic,
+        );
+
+        assert_eq!(projection.new_play_rank, None);
+        assert!(
+            projection
+                .top_plays
+                .iter()
+                .all(|play| play.old_rank.is_some())
+        );
+        assert_eq!(projection.dropped.as_ref().unwrap().old_rank, None);
+        assert_eq!(projection.gain, 0.0);
+    }
+
+    #[test]
+    fn existing_plays_rank_above_a_new_play_with_the_same_pp() {
+        let plays = top_plays([300.0, 200.0, 100.0]);
+
+        let projection = insert_play(
+            plays,
+            200.0,
+            "new",
+            Some(1000),
+            None,
+            &BuiltinWeighting::Classic,
+        );
+
+        assert_eq!(projection.new_play_rank, Some(3));
+        let ranks: Vec<_> = projection
+            .top_plays
+            .iter()
+            .map(|play| play.old_rank)
+            .collect();
+        assert_eq!(ranks, [Some(1), Some(2), None, Some(3)]);
+        assert!(
+            projection
+                .shifted_plays()
+                .all(|(_, play, _)| play.pp < 200.0)
+        );
+    }
+
+    #[test]
+    fn cmp_top_plays_keeps_the_old_order_of_ties() {
+        let mut plays = top_plays([100.0, 100.0, 100.0]);
+        plays[0].old_rank = Some(2);
+        plays[1].old_rank = Some(1);
+
+        plays.sort_by(cmp_top_plays);
+
+        let ranks: Vec<_> = plays.iter().map(|play| play.old_rank).collect();
+        assert_eq!(ranks, [Some(1), Some(2), None]);
+    }
+
+    #[test]
+    fn cmp_top_plays_orders_nan_consistently() {
+        let mut plays = top_plays([100.0, f64::NAN, 300.0, 200.0]);
+
+        // `partial_cmp` would have no answer for NaN, `total_cmp` puts it above every pp
+        plays.sort_by(cmp_top_plays);
+
+        assert!(plays[0].pp.is_nan());
+        assert_eq!(top_play_pps(&plays[1..]), [300.0, 200.0, 100.0]);
+        assert_eq!(cmp_top_plays(&plays[0], &plays[0]), Ordering::Equal);
+        assert_eq!(cmp_top_plays(&plays[0], &plays[1]), Ordering::Less);
+    }
+
+    #[test]
+    fn bonus_pp_grows_towards_its_cap() {
+        assert_eq!(bonus_pp(0), 0.0);
+        assert!(bonus_pp(1) > 0.0 && bonus_pp(1) < bonus_pp(2));
+        assert!((bonus_pp(1000) - 416.6667 * (1.0 - 0.995_f64.powi(1000))).abs() < 1e-9);
+        // scores past the 1000th add nothing
+        assert_eq!(bonus_pp(1000), bonus_pp(5000));
+
+        let bonus = BonusPp::new(999, 2);
+        assert_eq!(bonus.old, bonus_pp(999));
+        assert_eq!(bonus.new, bonus_pp(1000));
+    }
+
+    #[test]
+    fn required_play_pp_finds_the_play_for_a_total() {
+        let weighting = BuiltinWeighting::Classic;
+
+        // without top plays the new one counts fully
+        let pp = required_play_pp(&[], 250.0, None, None, &weighting).unwrap();
+        assert!((pp - 250.0).abs() < 0.01, "{pp}");
+
+        let bonus = Some(BonusPp {
+            old: 100.0,
+            new: 100.0,
+        });
+        assert_eq!(
+            required_play_pp(&[], 80.0, None, bonus, &weighting),
+            Some(0.0)
+        );
+        assert_eq!(required_play_pp(&[], 1e9, None, None, &weighting), None);
+    }
+
+    #[test]
+    fn accuracy_for_pp_finds_the_lowest_accuracy() {
+        let map = parse_native_beatmap(&osu_file(0)).unwrap();
+        let mods = GameMods::new();
+        let pp_at = |accuracy| {
+            let play = PlayInputs {
+                judgements: Judgements::Accuracy {
+                    accuracy,
+                    misses: 0,
+                },
+                combo: None,
+            };
+
+            calculate_play(&map, PpGameMode::Osu, &mods, None, Rules::Lazer, &play).pp()
+        };
+
+        let target = pp_at(97.0);
+        let accuracy =
+            accuracy_for_pp(&map, PpGameMode::Osu, &mods, None, Rules::Lazer, 0, target).unwrap();
+
+        assert!(accuracy <= 97.0 + 0.001, "{accuracy}");
+        assert!(pp_at(accuracy) >= target);
+
+        // not even an SS is worth more than an SS
+        let ss = pp_at(100.0);
+        assert_eq!(
+            accuracy_for_pp(
+                &map,
+                PpGameMode::Osu,
+                &mods,
+                None,
+                Rules::Lazer,
+                0,
+                ss + 1.0
+            ),
+            None
+        );
+    }
+}
@@ -0,0 +1,123 @@
+use {
+    color_eyre::{Result, eyre::eyre},
+    plotters::{
+        coord::Shift,
+        prelude::*,
+        style::text_anchor::{HPos, Pos, VPos},
+    },
+    ppify::report::CalculationReport,
+    std::path::Path,
+};
+
+const SIZE: (u32, u32) = (900, 300);
+const MARGIN: i32 = 32;
+
+const BACKGROUND: RGBColor = RGBColor(30, 30, 46);
+const PANEL: RGBColor = RGBColor(49, 50, 68);
+const TEXT: RGBColor = RGBColor(205, 214, 244);
+const DIMMED: RGBColor = RGBColor(147, 153, 178);
+const ACCENT: RGBColor = RGBColor(245, 194, 231);
+const GAIN: RGBColor = RGBColor(166, 227, 161);
+const LOSS: RGBColor = RGBColor(243, 139, 168);
+
+/// Draws `report` as a PNG card like the ones score bots post, for sharing the result
+/// without a screenshot.
+pub fn write(path: &Path, username: &str, report: &CalculationReport) -> Result<()> {
+    draw(
+        BitMapBackend::new(path, SIZE).into_drawing_area(),
+        username,
+        report,
+    )
+    .map_err(|err| eyre!("failed to write {}: {err}", path.display()))
+}
+
+fn draw<DB: DrawingBackend>(
+    root: DrawingArea<DB, Shift>,
+    username: &str,
+    report: &CalculationReport,
+) -> Result<(), DrawingAreaErrorKind<DB::ErrorType>> {
+    let (width, height) = (SIZE.0 as i32, SIZE.1 as i32);
+    let font = |size: u32, color: &RGBColor| ("sans-serif", size).into_font().color(color);
+    let right = Pos::new(HPos::Right, VPos::Top);
+
+    root.fill(&BACKGROUND)?;
+    root.draw(&Rectangle::new(
+        [(0, 0), (width, 6)],
+        ShapeStyle::from(&ACCENT).filled(),
+    ))?;
+
+    // the beatmap across the top, cut short so it doesn't run into the pp
+    let beatmap = shorten(&report.beatmap.to_string(), 60);
+    root.draw_text(&beatmap, &font(26, &TEXT), (MARGIN, MARGIN))?;
+
+    let details = format!(
+        "{:.2} stars · {} · {} rules",
+        report.difficulty.stars, report.mode, report.rules
+    );
+    root.draw_text(&details, &font(18, &DIMMED), (MARGIN, MARGIN + 40))?;
+
+    // the play on the left
+    root.draw(&Rectangle::new(
+        [(MARGIN, 120), (width / 2, height - MARGIN)],
+        ShapeStyle::from(&PANEL).filled(),
+    ))?;
+
+    root.draw_text(username, &font(30, &ACCENT), (MARGIN + 20, 136))?;
+
+    let mods = if report.mods.is_empty() {
+        "NM".to_owned()
+    } else {
+        format!("+{}", report.mods.concat())
+    };
+    let mods = match report.clock_rate {
+        Some(rate) => format!("{mods} at {rate}x"),
+        None => mods,
+    };
+    root.draw_text(&mods, &font(22, &TEXT), (MARGIN + 20, 180))?;
+
+    let combo = report
+        .play
+        .combo
+        .map_or_else(|| "FC".to_owned(), |combo| format!("{combo}x"));
+    let misses = match report.play.misses() {
+        1 => "1 miss".to_owned(),
+        misses => format!("{misses} misses"),
+    };
+    let play = format!("{:.2}% · {combo} · {misses}", report.play.accuracy());
+    root.draw_text(&play, &font(22, &TEXT), (MARGIN + 20, 218))?;
+
+    // the pp and what they do to the profile on the right
+    let profile = &report.profile;
+    let gain_color = if profile.gain < 0.0 { &LOSS } else { &GAIN };
+
+    root.draw_text(
+        &format!("{:.0}pp", report.pp),
+        &font(72, &TEXT).pos(right),
+        (width - MARGIN, 116),
+    )?;
+    root.draw_text(
+        &format!("{:+.2}pp", profile.gain),
+        &font(30, gain_color).pos(right),
+        (width - MARGIN, 200),
+    )?;
+    root.draw_text(
+        &format!(
+            "{:.2}pp → {:.2}pp",
+            profile.old_total_pp, profile.new_total_pp
+        ),
+        &font(18, &DIMMED).pos(right),
+        (width - MARGIN, 242),
+    )?;
+
+    root.present()
+}
+
+fn shorten(text: &str, max_chars: usize) -> String {
+    if text.chars().count() <= max_chars {
+        return text.to_owned();
+    }
+
+    let mut short: String = text.chars().take(max_chars - 1).collect();
+    short.push('…');
+    short
+}
@@ -0,0 +1,693 @@
+use {
+    clap::{
+        Args, Parser, Subcommand, ValueEnum,
+        builder::{PossibleValue, StringValueParser, TypedValueParser},
+    },
+    clap_complete::Shell,
+    ppify::{
+        PpifyError,
+        calc::BuiltinWeighting,
+        config::{Config, NowPlayingSource, OutputFormat},
+        mods::{MODS_LAZER, ModSettings},
+    },
+    rosu_pp::model::mode::GameMode as PpGameMode,
+    rosu_v2::prelude::GameMode,
+    serde::Deserialize,
+    std::{ffi::OsStr, path::PathBuf},
+};
+
+#[derive(Debug, Parser)]
+#[command(version, about = "osu! PP gains calculator")]
+pub struct Cli {
+    #[command(subcommand)]
+    pub command: Option<Command>,
+
+    /// osu! username or user id
+    #[arg(short, long, global = true)]
+    pub user: Option<String>,
+
+    /// Game mode to calculate for
+    #[arg(long, value_enum, global = true)]
+    pub mode: Option<ModeArg>,
+
+    /// Use the user and mode of a saved profile, see `ppify profile`
+    #[arg(short, long, global = true, conflicts_with = "user")]
+    pub profile: Option<String>,
+
+    /// Beatmap (difficulty) id, beatmap link, path to a local .osu or .osz file or a search
+    /// query
+    #[arg(short, long)]
+    pub map: Option<String>,
+
+    /// Beatmapset id; the difficulty is picked from a list
+    #[arg(long, conflicts_with = "map")]
+    pub set: Option<u32>,
+
+    /// Difficulty to calculate when --map is a .osz file, by name ignoring case. Picked from a
+    /// list without it
+    #[arg(long, value_name = "NAME")]
+    pub diff_name: Option<String>,
+
+    /// Pick the beatmap from the local osu! Songs folder, see `songs_dir` in the config
+    #[arg(long, conflicts_with_all = ["map", "set"])]
+    pub local: bool,
+
+    /// Recalculate the play of the flags on this local .osu file whenever it is saved, e.g.
+    /// while mapping. Needs no credentials
+    #[arg(long, value_name = "FILE", conflicts_with_all = ["map", "set", "local", "tui", "stdin"])]
+    pub watch: Option<PathBuf>,
+
+    /// Calculate every play of a JSON array and print the results as one, see the README for
+    /// the fields. Add --user for the profile gain of each play
+    #[arg(long, value_name = "FILE", conflicts_with_all = ["map", "set", "local", "watch", "tui", "stdin"])]
+    pub batch_json: Option<PathBuf>,
+
+    /// Project the same play (--mods, --acc and --misses, an SS by default) onto several
+    /// players' totals: a CSV file with beatmap ids in the header and a player per line
+    #[arg(long, value_name = "FILE", conflicts_with_all = ["map", "set", "local", "watch", "tui", "stdin", "batch_json"])]
+    pub matrix: Option<PathBuf>,
+
+    /// Mod acronyms, e.g. HDDT or HD,HR. Use NM for NoMod
+    #[arg(long, value_parser = ModsParser, hide_possible_values = true)]
+    pub mods: Option<String>,
+
+    /// Calculate as if these of the selected mods had no pp effect, e.g. FL to see what it is
+    /// worth. The report still lists them
+    #[arg(long, value_name = "MODS", value_parser = ModsParser, hide_possible_values = true)]
+    pub ignore_mod: Option<String>,
+
+    /// Only count the top plays set with these mods, e.g. HDHR for a HDHR-only profile.
+    /// Plays with more mods count too unless --baseline-exact is given
+    #[arg(
+        long,
+        global = true,
+        value_name = "MODS",
+        value_parser = ModsParser,
+        hide_possible_values = true
+    )]
+    pub baseline_mods: Option<String>,
+
+    /// With --baseline-mods, only count plays with exactly those mods
+    #[arg(long, global = true, requires = "baseline_mods")]
+    pub baseline_exact: bool,
+
+    /// Only count top plays on maps made for the mode, leaving out converts of osu!standard
+    /// maps
+    #[arg(long, global = true)]
+    pub no_converts: bool,
+
+    /// Rate of DT/NC/HT/DC, e.g. 1.3
+    #[arg(long)]
+    pub speed: Option<f32>,
+
+    /// Custom clock rate, e.g. 1.15; replaces the rate of DT/HT
+    #[arg(long)]
+    pub rate: Option<f64>,
+
+    /// Difficulty Adjust approach rate
+    #[arg(long)]
+    pub da_ar: Option<f32>,
+
+    /// Difficulty Adjust overall difficulty
+    #[arg(long)]
+    pub da_od: Option<f32>,
+
+    /// Difficulty Adjust circle size
+    #[arg(long)]
+    pub da_cs: Option<f32>,
+
+    /// Difficulty Adjust HP drain
+    #[arg(long)]
+    pub da_hp: Option<f32>,
+
+    /// Recompute an existing score from its id or link instead of a hypothetical play
+    #[arg(long = "score", value_name = "ID_OR_URL", conflicts_with_all = ["map", "set", "mods", "acc", "misses", "combo", "counts"])]
+    pub score_ref: Option<String>,
+
+    #[command(flatten)]
+    pub score: ScoreArgs,
+
+    /// Don't read or write the local beatmap, top score and profile caches
+    #[arg(long, global = true)]
+    pub no_cache: bool,
+
+    /// Never contact osu.ppy.sh, only use cached beatmaps, top scores and profiles
+    #[arg(long, global = true, conflicts_with_all = ["no_cache", "refresh", "refresh_scores"])]
+    pub offline: bool,
+
+    /// Re-download the beatmap even if it is cached
+    #[arg(long, global = true, conflicts_with = "no_cache")]
+    pub refresh: bool,
+
+    /// Refetch the user's top scores and profile even if they are cached
+    #[arg(long, global = true, conflicts_with = "no_cache")]
+    pub refresh_scores: bool,
+
+    /// How long cached top scores and profiles stay valid, in minutes
+    #[arg(long, global = true, value_name = "MINUTES", default_value_t = 60)]
+    pub scores_ttl: u64,
+
+    /// When the play is one of your top scores (same map, mods and judgements), compare the
+    /// recomputed pp with the api's
+    #[arg(long)]
+    pub round_trip: bool,
+
+    /// Only check whether the play is worth more than this many pp, e.g. your current #1.
+    /// Skips the profile, so no --user is needed
+    #[arg(long, value_name = "PP", conflicts_with = "tui")]
+    pub beat: Option<f64>,
+
+    /// Also show the pp by accuracy and for an SS. The accuracies depend on the mode, e.g.
+    /// 95% to 100% for osu!standard and 98% to 100% in finer steps for catch
+    #[arg(long)]
+    pub acc_table: bool,
+
+    /// Accuracies of --acc-table instead of the mode's, e.g. `--sweep-points 97 98.5 99.5`
+    #[arg(long, num_args = 1.., value_name = "PERCENT", requires = "acc_table")]
+    pub sweep_points: Option<Vec<f64>>,
+
+    /// Also show the pp with 0, 1, 2, 5 and 10 misses
+    #[arg(long)]
+    pub miss_table: bool,
+
+    /// Also compare the play under other mod combinations, e.g. `--compare-mods NM HD HDHR`.
+    /// Without values a list of common combinations is used
+    #[arg(long, num_args = 0.., value_name = "MODS")]
+    pub compare_mods: Option<Vec<String>>,
+
+    /// Also show the pp if the play ended (e.g. failed) at some points of the map, in percent
+    /// of its objects. Without values 25, 50, 75 and 100 are used
+    #[arg(long, num_args = 0.., value_name = "PERCENT")]
+    pub progression: Option<Vec<f64>>,
+
+    /// Also show the pp if the play failed at this hit object, or at a percentage of them
+    /// like 40%. Failed plays give no ranked pp
+    #[arg(long, value_name = "OBJECT|PERCENT")]
+    pub fail_at: Option<String>,
+
+    /// Also draw the map's strain over time and show where the hardest section is
+    #[arg(long)]
+    pub strain_graph: bool,
+
+    /// Also list the N places where the map's strain rises the most (5 without a value),
+    /// i.e. where it suddenly gets harder
+    #[arg(long, num_args = 0..=1, default_missing_value = "5", value_name = "N")]
+    pub spikes: Option<usize>,
+
+    /// Also show how much of an osu!standard FL play's pp comes from FL
+    #[arg(long)]
+    pub fl_breakdown: bool,
+
+    /// Also show the accuracy formula of the mode with the entered counts put in
+    #[arg(long)]
+    pub explain: bool,
+
+    /// Also show what each selected mod does to the pp in this calculation
+    #[arg(long)]
+    pub explain_mods: bool,
+
+    /// Draw the strain over time and the pp by accuracy to an image, SVG for `.svg` paths
+    /// and PNG otherwise
+    #[arg(long, value_name = "FILE")]
+    pub plot: Option<PathBuf>,
+
+    /// Render the result as a PNG card with the player, map, mods, accuracy, pp and gain,
+    /// for sharing without a screenshot
+    #[arg(long, value_name = "FILE")]
+    pub card: Option<PathBuf>,
+
+    /// Calculate with osu!stable's rules instead of lazer's. CL implies this
+    #[arg(long)]
+    pub stable: bool,
+
+    /// How top plays add up to the total: classic is osu!'s 0.95^i, linear goes from 100%
+    /// down to 1% for the 100th play and custom-factor uses the config's weight_decay.
+    /// Defaults to custom-factor when weight_decay is set and classic otherwise
+    #[arg(long, value_enum, global = true)]
+    pub weighting: Option<WeightingArg>,
+
+    /// Show the current calculation as your Discord activity, as the app in DISCORD_APP_ID
+    #[cfg(feature = "rich-presence")]
+    #[arg(long, global = true)]
+    pub rich_presence: bool,
+
+    /// Skip the global and country rank estimates, which take a few extra api requests
+    #[arg(long)]
+    pub no_rank: bool,
+
+    /// When done, print the command that calculates the last play again without prompts.
+    /// It never contains credentials, those still come from the environment or keyring
+    #[arg(long)]
+    pub print_command: bool,
+
+    /// How many of the top plays to show after the new play, and of the plays it moves down
+    /// [default: 10]
+    #[arg(long, global = true, value_name = "N")]
+    pub top_n_diff: Option<usize>,
+
+    /// Write the table of `batch`, `if-fc`, `collection` or `local-scores`, or the one of
+    /// --acc-table, --miss-table or --compare-mods, to this CSV file
+    #[arg(long, global = true, value_name = "FILE")]
+    pub out: Option<PathBuf>,
+
+    /// Print the result as JSON instead of the formatted report
+    #[arg(long, global = true)]
+    pub json: bool,
+
+    /// Output format: text, json (like --json) or markdown for tables to paste into Discord
+    /// or GitHub
+    #[arg(long, global = true, value_name = "FORMAT", conflicts_with = "json")]
+    pub format: Option<OutputFormat>,
+
+    /// Copy the result to the clipboard: the JSON or Markdown of --json and --format instead
+    /// of printing it, or a one-line summary of the report
+    #[arg(long, conflicts_with = "tui")]
+    pub clipboard: bool,
+
+    /// Log api requests, cache hits, token refreshes and calculation times to stderr, -vv
+    /// also the logs of the http client and the osu! api crate
+    #[arg(short, long, global = true, action = clap::ArgAction::Count)]
+    pub verbose: u8,
+
+    /// Print the report without colors, also the case when NO_COLOR is set or stdout isn't a
+    /// terminal
+    #[arg(long, global = true)]
+    pub no_color: bool,
+
+    /// Show the calculation full-screen and recalculate live while accuracy, misses, combo
+    /// and mods are changed with the arrow keys
+    #[arg(long, conflicts_with_all = ["json", "format", "stdin", "score_ref"])]
+    pub tui: bool,
+
+    /// Answer JSON-line calculation requests from stdin with JSON lines on stdout until EOF
+    #[arg(long, conflicts_with_all = ["map", "set", "score_ref"])]
+    pub stdin: bool,
+
+    /// Values from the config file, already merged into the flags by `apply_config`
+    #[arg(skip)]
+    pub config: Config,
+}
+
+#[derive(Debug, Subcommand)]
+pub enum Command {
+    /// Recompute every top play as a full combo and show the pp lost to chokes
+    IfFc,
+    /// Pick a recent score, failed ones included, and see what it or its FC would be worth
+    Recent,
+    /// Queue several hypothetical plays and see their combined effect on your total pp
+    Session,
+    /// Show or change the defaults in the config file
+    Config {
+        #[command(subcommand)]
+        action: Option<ConfigAction>,
+    },
+    /// Save users under a short name for `--profile`
+    Profile {
+        #[command(subcommand)]
+        action: ProfileAction,
+    },
+    /// Store or remove the osu! api credentials in the system keyring
+    Auth {
+        #[command(subcommand)]
+        action: AuthAction,
+    },
+    /// Calculate every `map_id,mods,accuracy,misses,combo` row of a CSV file
+    Batch {
+        /// CSV file with one play per line
+        input: PathBuf,
+
+        /// Also write the results to this CSV file, like --out
+        #[arg(short, long)]
+        output: Option<PathBuf>,
+    },
+    /// Calculate --acc and --mods on every beatmap of an osu! stable collection, sorted by pp
+    Collection {
+        /// Name of the collection; picked from a list when left out
+        name: Option<String>,
+    },
+    /// Recompute the pp of the scores in osu! stable's scores.db, e.g. unsubmitted ones
+    LocalScores {
+        /// All beatmaps instead of one picked from a list
+        #[arg(long)]
+        all: bool,
+    },
+    /// Work out the pp one more play needs to pass another user, optionally as accuracy on --map
+    Overtake {
+        /// Username or user id of the user to pass
+        rival: String,
+    },
+    /// Put two users' totals side by side and work out the play the first needs to pass the second
+    Compare {
+        /// Username or user id of the first user
+        user_a: String,
+        /// Username or user id of the second user
+        user_b: String,
+    },
+    /// Work out the pp one more play needs to reach a total, optionally as accuracy on --map
+    Target {
+        /// Total pp to reach, e.g. 5000
+        #[arg(required_unless_present_any = ["target_rank", "position"])]
+        total: Option<f64>,
+
+        /// Reach the total of this global rank instead, looked up in the rankings
+        #[arg(long, value_name = "RANK", conflicts_with_all = ["total", "position"])]
+        target_rank: Option<u32>,
+
+        /// Aim for a new top play at this position instead, e.g. 20 for a new #20
+        #[arg(long, value_name = "N", conflicts_with = "total")]
+        position: Option<usize>,
+    },
+    /// Serve calculations over HTTP on localhost, `POST /calculate` and `GET /user/{name}/top`
+    Serve {
+        #[arg(long, default_value_t = 8080)]
+        port: u16,
+    },
+    /// Follow the beatmap and mods selected in osu! through gosumemory, tosu or
+    /// StreamCompanion and answer accuracies typed in with their pp
+    #[command(alias = "np")]
+    NowPlaying {
+        /// gosumemory (or tosu) or streamcompanion, defaults to the config's `now_playing`
+        #[arg(long)]
+        source: Option<NowPlayingSource>,
+        /// The source's websocket, if it doesn't run on the default port
+        #[arg(long)]
+        url: Option<String>,
+    },
+    /// Save a user's top 100 and totals, to compare them later with `diff`
+    Snapshot {
+        /// Username or user id, defaults to --user
+        user: Option<String>,
+    },
+    /// Compare the latest two snapshots of a user: new top plays, pp gained and rank movement
+    Diff {
+        /// Username or user id, defaults to --user
+        user: Option<String>,
+    },
+    /// List, search and re-run past calculations
+    History {
+        #[command(subcommand)]
+        action: Option<HistoryAction>,
+    },
+    /// Print the completion script for bash, zsh, fish, powershell or elvish
+    Completions { shell: Shell },
+    /// Run a Discord bot with a /ppcalc command, using the DISCORD_TOKEN bot token
+    #[cfg(feature = "discord")]
+    Discord,
+}
+
+#[derive(Debug, Subcommand)]
+pub enum AuthAction {
+    /// Ask for the client id and secret, check them and store them in the keyring
+    Login,
+    /// Remove the stored credentials
+    Logout,
+    /// Check the credentials ppify would use with the osu! api and say where they come from
+    Check,
+}
+
+#[derive(Debug, Subcommand)]
+pub enum ConfigAction {
+    /// Print the config file (the default)
+    Show,
+    /// Print where the config file is
+    Path,
+    /// Set a value, e.g. `ppify config set user peppy`
+    Set { key: String, value: String },
+    /// Remove a value
+    Unset { key: String },
+}
+
+#[derive(Debug, Subcommand)]
+pub enum HistoryAction {
+    /// Show the most recent calculations (the default)
+    List {
+        /// How many to show
+        #[arg(short = 'n', long, default_value_t = 20)]
+        limit: u32,
+    },
+    /// Show calculations whose user, beatmap, mods or flags contain a text
+    Search {
+        text: String,
+        /// How many to show at most
+        #[arg(short = 'n', long, default_value_t = 20)]
+        limit: u32,
+    },
+    /// Calculate a past play again with the current top scores, by its id from `list`
+    Rerun { id: i64 },
+    /// Write every calculation to a JSON file, e.g. to import it on another machine
+    Export {
+        /// Defaults to stdout
+        file: Option<PathBuf>,
+    },
+    /// Add the calculations of a JSON file from `export`, skipping ones already recorded
+    Import { file: PathBuf },
+}
+
+#[derive(Debug, Subcommand)]
+pub enum ProfileAction {
+    /// Save a profile, e.g. `ppify profile add main peppy --mode osu`
+    Add {
+        name: String,
+        /// osu! username or user id
+        user: String,
+    },
+    /// Delete a profile
+    Remove { name: String },
+    /// List all profiles
+    List,
+}
+
+#[derive(Debug, Default, Args)]
+pub struct ScoreArgs {
+    /// Accuracy in percent, e.g. 98.75
+    #[arg(long, conflicts_with = "counts")]
+    pub acc: Option<f64>,
+
+    /// Number of misses (defaults to 0 when --acc is given)
+    #[arg(long)]
+    pub misses: Option<u32>,
+
+    /// Combo, either absolute (1200) or relative to the max combo (85%)
+    #[arg(long)]
+    pub combo: Option<String>,
+
+    /// Calculate the entered play as a full combo, its misses turned into the best judgement
+    /// at max combo. The report still shows the play as entered
+    #[arg(long)]
+    pub assume_fc: bool,
+
+    #[command(flatten)]
+    pub counts: CountArgs,
+}
+
+impl Cli {
+    /// Fills flags that weren't given from the config file.
+    ///
+    /// A `--profile` wins over the plain config defaults.
+    pub fn apply_config(&mut self, config: Config) -> Result<(), PpifyError> {
+        let profile = match &self.profile {
+            Some(name) => Some(config.profile(name)?),
+            None => None,
+        };
+
+        self.user = self
+            .user
+            .take()
+            .or_else(|| profile.map(|profile| profile.user.clone()))
+            .or_else(|| config.user.clone());
+
+        let mode = profile
+            .and_then(|profile| profile.mode.as_deref())
+            .or(config.mode.as_deref());
+
+        if self.mode.is_none()
+            && let Some(mode) = mode
+        {
+            let mode = ModeArg::from_str(mode, true).map_err(|_| {
+                PpifyError::BadInput(format!("unknown mode `{mode}` in the config"))
+            })?;
+
+            self.mode = Some(mode);
+        }
+
+        if matches!(self.weighting, Some(WeightingArg::CustomFactor))
+            && config.weight_decay.is_none()
+        {
+            return Err(PpifyError::BadInput(
+                "--weighting custom-factor needs weight_decay in the config".to_owned(),
+            ));
+        }
+
+        self.format = self.format.or(config.output);
+        self.json |= self.format == Some(OutputFormat::Json);
+        self.config = config;
+
+        Ok(())
+    }
+
+    pub fn markdown(&self) -> bool {
+        self.format == Some(OutputFormat::Markdown)
+    }
+
+    pub fn weighting(&self) -> BuiltinWeighting {
+        match (self.weighting, self.config.weight_decay) {
+            (Some(WeightingArg::Classic), _) | (None, None) => BuiltinWeighting::Classic,
+            (Some(WeightingArg::Linear), _) => BuiltinWeighting::Linear,
+            // `apply_config` made sure custom-factor has a factor
+            (Some(WeightingArg::CustomFactor) | None, decay) => {
+                BuiltinWeighting::Factor(decay.unwrap_or(0.95))
+            }
+        }
+    }
+
+    pub fn mod_settings(&self) -> ModSettings {
+        ModSettings {
+            speed_change: self.speed,
+            ar: self.da_ar,
+            od: self.da_od,
+            cs: self.da_cs,
+            hp: self.da_hp,
+        }
+    }
+}
+
+impl ScoreArgs {
+    pub fn is_specified(&self) -> bool {
+        self.acc.is_some() || self.counts.is_specified()
+    }
+}
+
+#[derive(Debug, Default, Args)]
+#[group(id = "counts", multiple = true)]
+pub struct CountArgs {
+    /// Number of 320s / MAX (mania)
+    #[arg(long)]
+    pub n320: Option<u32>,
+
+    /// Number of 300s (osu!, taiko, mania)
+    #[arg(long)]
+    pub n300: Option<u32>,
+
+    /// Number of 200s (mania)
+    #[arg(long)]
+    pub n200: Option<u32>,
+
+    /// Number of 100s (osu!, taiko, mania)
+    #[arg(long)]
+    pub n100: Option<u32>,
+
+    /// Number of 50s (osu!, mania)
+    #[arg(long)]
+    pub n50: Option<u32>,
+
+    /// Fruits caught (catch)
+    #[arg(long)]
+    pub fruits: Option<u32>,
+
+    /// Droplets caught (catch)
+    #[arg(long)]
+    pub droplets: Option<u32>,
+
+    /// Tiny droplets caught (catch)
+    #[arg(long)]
+    pub tiny_droplets: Option<u32>,
+
+    /// Tiny droplet misses (catch)
+    #[arg(long)]
+    pub tiny_droplet_misses: Option<u32>,
+
+    /// Slider ends hit (osu!, lazer rules)
+    #[arg(long)]
+    pub slider_ends: Option<u32>,
+
+    /// Slider ticks and repeats hit (osu!, lazer rules)
+    #[arg(long)]
+    pub large_ticks: Option<u32>,
+}
+
+impl CountArgs {
+    pub fn is_specified(&self) -> bool {
+        [
+            self.n320,
+            self.n300,
+            self.n200,
+            self.n100,
+            self.n50,
+            self.fruits,
+            self.droplets,
+            self.tiny_droplets,
+            self.tiny_droplet_misses,
+            self.slider_ends,
+            self.large_ticks,
+        ]
+        .iter()
+        .any(Option::is_some)
+    }
+}
+
+/// Takes `--mods` as it is, the acronyms are checked once the mode is known. The single
+/// acronyms are its possible values, so shells complete them.
+#[derive(Clone)]
+struct ModsParser;
+
+impl TypedValueParser for ModsParser {
+    type Value = String;
+
+    fn parse_ref(
+        &self,
+        cmd: &clap::Command,
+        arg: Option<&clap::Arg>,
+        value: &OsStr,
+    ) -> Result<Self::Value, clap::Error> {
+        StringValueParser::new().parse_ref(cmd, arg, value)
+    }
+
+    fn possible_values(&self) -> Option<Box<dyn Iterator<Item = PossibleValue> + '_>> {
+        let values = MODS_LAZER
+            .iter()
+            .map(|m| PossibleValue::new(m.acronym).help(m.description));
+
+        Some(Box::new(values))
+    }
+}
+
+#[derive(Clone, Copy, Debug, ValueEnum)]
+pub enum WeightingArg {
+    Classic,
+    Linear,
+    CustomFactor,
+}
+
+#[derive(Clone, Copy, Debug, Deserialize, ValueEnum)]
+#[serde(rename_all = "lowercase")]
+pub enum ModeArg {
+    #[value(alias = "std", alias = "standard")]
+    #[serde(alias = "std", alias = "standard")]
+    Osu,
+    Taiko,
+    #[value(alias = "ctb", alias = "fruits")]
+    #[serde(alias = "ctb", alias = "fruits")]
+    Catch,
+    Mania,
+}
+
+impl ModeArg {
+    /// The name `--mode` takes, e.g. `osu`.
+    pub fn name(self) -> &'static str {
+        match self {
+            Self::Osu => "osu",
+            Self::Taiko => "taiko",
+            Self::Catch => "catch",
+            Self::Mania => "mania",
+        }
+    }
+
+    pub fn modes(self) -> (GameMode, PpGameMode) {
+        match self {
+            Self::Osu => (GameMode::Osu, PpGameMode::Osu),
+            Self::Taiko => (GameMode::Taiko, PpGameMode::Taiko),
+            Self::Catch => (GameMode::Catch, PpGameMode::Catch),
+            Self::Mania => (GameMode::Mania, PpGameMode::Mania),
+        }
+    }
+}
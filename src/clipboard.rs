@@ -0,0 +1,51 @@
+//! `--clipboard`: copies the result, for pasting it elsewhere.
+
+use {arboard::Clipboard, ppify::report::CalculationReport, std::env};
+
+/// Copies `text` to the clipboard, or prints it with a warning when there is none, e.g. over
+/// SSH or in CI.
+pub fn copy(text: &str) {
+    if !has_display() {
+        eprintln!("Warning: no clipboard without a display, printing instead");
+        print!("{text}");
+
+        return;
+    }
+
+    match Clipboard::new().and_then(|mut clipboard| clipboard.set_text(text)) {
+        Ok(()) => eprintln!("Copied to the clipboard."),
+        Err(err) => {
+            eprintln!("Warning: failed to copy to the clipboard ({err}), printing instead");
+            print!("{text}");
+        }
+    }
+}
+
+/// The play in one line, what `--clipboard` copies of the text report.
+pub fn summary(report: &CalculationReport) -> String {
+    let mods = if report.mods.is_empty() {
+        String::new()
+    } else {
+        format!(" +{}", report.mods.concat())
+    };
+
+    let misses = match report.play.misses() {
+        1 => "1 miss".to_owned(),
+        misses => format!("{misses} misses"),
+    };
+
+    format!(
+        "{}{mods} {:.2}% {misses}: {:.2}pp ({:+.2}pp to the profile)\n",
+        report.beatmap,
+        report.play.accuracy(),
+        report.pp,
+        report.profile.gain
+    )
+}
+
+// X11 and Wayland clipboards need a display, other platforms always have a clipboard
+fn has_display() -> bool {
+    !cfg!(target_os = "linux")
+        || env::var_os("DISPLAY").is_some()
+        || env::var_os("WAYLAND_DISPLAY").is_some()
+}
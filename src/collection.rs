@@ -0,0 +1,245 @@
+use {
+    crate::{checked_clock_rate, cli::Cli, markdown, modes_for, progress, songs_dir},
+    color_eyre::{
+        Result,
+        eyre::{self, Context},
+    },
+    demand::{DemandOption, Select},
+    ppify::{
+        PpifyError,
+        calc::{self, Judgements, PlayInputs, Rules},
+        export,
+        library::{self, LocalMap},
+        mods,
+        osu_db::{self, Collection},
+        report::{CollectionMap, CollectionReport},
+    },
+    rayon::prelude::*,
+    rosu_pp::model::mode::GameMode as PpGameMode,
+    rosu_v2::prelude::GameMode,
+    serde::Serialize,
+    std::{collections::HashMap, fs},
+};
+
+pub fn run(cli: &Cli, name: Option<&str>) -> Result<()> {
+    let songs_dir = songs_dir(cli)?;
+    let db_path = library::collection_db_path(&songs_dir).ok_or_else(|| {
+        eyre::eyre!(
+            "no collection.db next to {}, set `songs_dir` in the config",
+            songs_dir.display()
+        )
+    })?;
+
+    let collections = osu_db::read_collection_db(&db_path)
+        .with_context(|| format!("failed to read {}", db_path.display()))?;
+
+    let collection = match name {
+        Some(name) => collections
+            .into_iter()
+            .find(|collection| collection.name.eq_ignore_ascii_case(name))
+            .ok_or_else(|| PpifyError::BadInput(format!("no collection named `{name}`")))?,
+        None => read_collection(collections)?,
+    };
+
+    let library: HashMap<_, _> = library::load_songs(&songs_dir)
+        .with_context(|| format!("failed to read {}", songs_dir.display()))?
+        .into_iter()
+        .map(|map| (map.md5.clone(), map))
+        .collect();
+
+    let accuracy = cli.score.acc.unwrap_or(100.0);
+    let misses = cli.score.misses.unwrap_or(0);
+    let clock_rate = checked_clock_rate(cli)?;
+    let play = PlayInputs {
+        judgements: Judgements::Accuracy { accuracy, misses },
+        combo: None,
+    };
+
+    let locals: Vec<&LocalMap> = collection
+        .md5s
+        .iter()
+        .filter_map(|md5| library.get(md5))
+        .collect();
+    let missing = collection.md5s.len() - locals.len();
+
+    let calculations = progress::bar(locals.len(), "Beatmaps");
+
+    let calculate = |local: &&LocalMap| {
+        // maps keep their own mode unless --mode asks for converts
+        let modes = cli
+            .mode
+            .map(|mode| mode.modes())
+            .unwrap_or_else(|| modes_for(local.mode));
+
+        let (stars, pp, error) = match calculate_map(cli, local, modes, clock_rate, &play) {
+            Ok((stars, pp)) => (Some(stars), Some(pp), None),
+            Err(err) => (None, None, Some(err.to_string())),
+        };
+
+        calculations.inc(1);
+        calculations.set_message(local.metadata.to_string());
+
+        CollectionMap {
+            beatmap: local.metadata.clone(),
+            mode: modes.0.as_str(),
+            stars,
+            pp,
+            error,
+        }
+    };
+
+    let mut maps: Vec<_> = locals.par_iter().map(calculate).collect();
+    calculations.finish_and_clear();
+
+    // failed maps last
+    maps.sort_by(|a, b| {
+        b.pp.unwrap_or(f64::NEG_INFINITY)
+            .total_cmp(&a.pp.unwrap_or(f64::NEG_INFINITY))
+    });
+
+    let report = CollectionReport {
+        collection: collection.name,
+        mods: cli.mods.clone().unwrap_or_default(),
+        accuracy,
+        misses,
+        maps,
+        missing,
+    };
+
+    if let Some(path) = &cli.out {
+        let rows: Vec<_> = report.maps.iter().map(CsvRow::from).collect();
+
+        export::write_csv(path, &rows)
+            .with_context(|| format!("failed to write {}", path.display()))?;
+    }
+
+    if cli.json {
+        let json = serde_json::to_string_pretty(&report).context("failed to serialize report")?;
+        println!("{json}");
+    } else if cli.markdown() {
+        print!("{}", markdown::collection(&report));
+    } else {
+        print_report(&report);
+    }
+
+    Ok(())
+}
+
+// `CollectionMap` flattened, CSV has no nested columns
+#[derive(Serialize)]
+struct CsvRow<'a> {
+    beatmap_id: Option<u32>,
+    artist: &'a str,
+    title: &'a str,
+    version: &'a str,
+    mode: &'a str,
+    stars: Option<f64>,
+    pp: Option<f64>,
+    error: Option<&'a str>,
+}
+
+impl<'a> From<&'a CollectionMap> for CsvRow<'a> {
+    fn from(map: &'a CollectionMap) -> Self {
+        Self {
+            beatmap_id: map.beatmap.beatmap_id,
+            artist: &map.beatmap.artist,
+            title: &map.beatmap.title,
+            version: &map.beatmap.version,
+            mode: map.mode,
+            stars: map.stars,
+            pp: map.pp,
+            error: map.error.as_deref(),
+        }
+    }
+}
+
+fn read_collection(mut collections: Vec<Collection>) -> Result<Collection> {
+    if collections.is_empty() {
+        return Err(PpifyError::BadInput("collection.db has no collections".to_owned()).into());
+    }
+
+    let labels: Vec<_> = collections
+        .iter()
+        .map(|collection| format!("{} ({} maps)", collection.name, collection.md5s.len()))
+        .collect();
+
+    let mut select = Select::new("Collection").filterable(true);
+
+    for (i, label) in labels.iter().enumerate() {
+        select = select.option(DemandOption::new(i).label(label));
+    }
+
+    let picked = select.run().context("failed to read collection")?;
+
+    // the options are the indices of `collections`
+    Ok(collections.swap_remove(picked))
+}
+
+fn calculate_map(
+    cli: &Cli,
+    local: &LocalMap,
+    (api_mode, pp_mode): (GameMode, PpGameMode),
+    clock_rate: Option<f64>,
+    play: &PlayInputs,
+) -> Result<(f64, f64)> {
+    let bytes = fs::read(&local.path)
+        .with_context(|| format!("failed to read {}", local.path.display()))?;
+    let map = calc::parse_beatmap(&bytes, pp_mode)?;
+
+    let selected_mods = mods::parse_acronyms(cli.mods.as_deref().unwrap_or(""), api_mode)?;
+    let game_mods = mods::apply_settings(
+        mods::lazer_mods(selected_mods.iter().copied(), api_mode),
+        api_mode,
+        &cli.mod_settings(),
+    )?;
+
+    let perf_attrs = calc::calculate_play(
+        &map,
+        pp_mode,
+        &game_mods,
+        clock_rate,
+        Rules::new(cli.stable),
+        play,
+    );
+
+    Ok((perf_attrs.stars(), perf_attrs.pp()))
+}
+
+fn print_report(report: &CollectionReport) {
+    let mods = if report.mods.is_empty() {
+        "NM"
+    } else {
+        &report.mods
+    };
+
+    println!();
+    println!(
+        "{}: {:.2}% with {} misses, {mods}",
+        report.collection, report.accuracy, report.misses
+    );
+    println!();
+    println!("        pp    stars  mode    beatmap");
+
+    for map in &report.maps {
+        match (&map.error, map.stars, map.pp) {
+            (None, Some(stars), Some(pp)) => println!(
+                "  {pp:>8.2}  {stars:>6.2}★  {:<6}  {}",
+                map.mode, map.beatmap
+            ),
+            (error, ..) => println!(
+                "         -        -   {:<6}  {}: {}",
+                map.mode,
+                map.beatmap,
+                error.as_deref().unwrap_or("not calculated")
+            ),
+        }
+    }
+
+    if report.missing > 0 {
+        println!();
+        println!(
+            "{} beatmaps of the collection aren't in the Songs folder.",
+            report.missing
+        );
+    }
+}
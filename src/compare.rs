@@ -0,0 +1,105 @@
+use {
+    crate::{cli::Cli, fetch_profile, read_mode},
+    color_eyre::{Result, eyre::Context},
+    ppify::{
+        api::Client,
+        calc::{self, BonusPp, PASS_MARGIN},
+        report::{CompareReport, ComparedUser},
+    },
+    rosu_v2::prelude::{GameMode, Score},
+};
+
+pub async fn run(osu: &Client, cli: &Cli, user_a: &str, user_b: &str) -> Result<()> {
+    let (api_mode, _) = match cli.mode {
+        Some(mode) => mode.modes(),
+        None => read_mode()?,
+    };
+
+    let ((a, scores_a, bonus_a), (b, ..)) = tokio::try_join!(
+        compared_user(osu, cli, user_a, api_mode),
+        compared_user(osu, cli, user_b, api_mode)
+    )?;
+
+    let report = CompareReport {
+        mode: api_mode.as_str(),
+        difference: a.total_pp - b.total_pp,
+        required_play_pp: calc::required_play_pp(
+            &scores_a,
+            b.total_pp + PASS_MARGIN,
+            None,
+            bonus_a,
+            &cli.weighting(),
+        ),
+        a,
+        b,
+    };
+
+    if cli.json {
+        let json = serde_json::to_string_pretty(&report).context("failed to serialize report")?;
+        println!("{json}");
+    } else {
+        print_report(&report);
+    }
+
+    Ok(())
+}
+
+async fn compared_user(
+    osu: &Client,
+    cli: &Cli,
+    user: &str,
+    mode: GameMode,
+) -> Result<(ComparedUser, Vec<Score>, Option<BonusPp>)> {
+    let (scores, profile) = fetch_profile(osu, cli, user, mode).await?;
+    // the new play may be on any map, so assume one the user hasn't played
+    let bonus = BonusPp::for_user(&profile, &scores, &[None]);
+
+    let compared = ComparedUser {
+        username: profile.username.to_string(),
+        profile_pp: profile.statistics.as_ref().map(|stats| f64::from(stats.pp)),
+        total_pp: calc::total_pp(&scores, bonus, &cli.weighting()),
+        top_play_pp: scores
+            .iter()
+            .filter_map(|score| score.pp)
+            .map(f64::from)
+            .reduce(f64::max),
+    };
+
+    Ok((compared, scores, bonus))
+}
+
+fn print_report(report: &CompareReport) {
+    let (a, b) = (&report.a, &report.b);
+    let width = a.username.len().max(b.username.len()).max(4);
+    let pp = |pp: Option<f64>| pp.map_or_else(|| "-".to_owned(), |pp| format!("{pp:.2}pp"));
+
+    println!();
+    println!(
+        "  {:<width$}  {:>12}  {:>12}  {:>10}",
+        "", "profile", "recomputed", "top play"
+    );
+
+    for user in [a, b] {
+        println!(
+            "  {:<width$}  {:>12}  {:>12}  {:>10}",
+            user.username,
+            pp(user.profile_pp),
+            format!("{:.2}pp", user.total_pp),
+            pp(user.top_play_pp)
+        );
+    }
+
+    println!();
+    println!("Difference: {:+.2}pp", report.difference);
+
+    match report.required_play_pp {
+        Some(required) if required == 0.0 => {
+            println!("{} is already ahead of {}.", a.username, b.username)
+        }
+        Some(required) => println!(
+            "{} needs one new play worth {required:.2}pp to pass {}.",
+            a.username, b.username
+        ),
+        None => println!("No single play gets {} past {}.", a.username, b.username),
+    }
+}
@@ -0,0 +1,314 @@
+//! Defaults from `~/.config/ppify/config.toml`, see `ppify config`.
+
+use {
+    crate::error::PpifyError,
+    serde::{Deserialize, Serialize},
+    std::{
+        collections::BTreeMap,
+        fs, io,
+        num::NonZeroU32,
+        path::{Path, PathBuf},
+        str::FromStr,
+    },
+};
+
+#[derive(Clone, Debug, Default, Deserialize, Serialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Config {
+    /// Username or user id used when `--user` isn't given.
+    pub user: Option<String>,
+    /// Mode used when `--mode` isn't given, e.g. `osu` or `mania`.
+    pub mode: Option<String>,
+    pub output: Option<OutputFormat>,
+    /// `false` prints the report without colors, like `--no-color`.
+    pub color: Option<bool>,
+    /// Colors of the report, `dark`, `light` or `mono`.
+    pub theme: Option<Theme>,
+    /// Replaces the platform cache directory for beatmaps, top scores and the access token.
+    pub cache_dir: Option<PathBuf>,
+    /// `false` stops recording calculations for `ppify history`.
+    pub history: Option<bool>,
+    /// Per-rank weight decay of top plays, 0.95 on osu!.
+    pub weight_decay: Option<f64>,
+    /// How often failed api requests and beatmap downloads are retried, 3 by default.
+    pub retries: Option<u32>,
+    /// osu! api requests and beatmap downloads per minute, 300 by default.
+    pub rate_limit: Option<NonZeroU32>,
+    /// How many requests may go out at once before `rate_limit` applies, 20 by default.
+    pub rate_limit_burst: Option<NonZeroU32>,
+    /// osu! OAuth client id, used when `OSU_CLIENT_ID` isn't set.
+    pub client_id: Option<u64>,
+    /// Environment variable holding the client secret, used when `OSU_CLIENT_SECRET` isn't set.
+    pub client_secret_env: Option<String>,
+    /// osu!'s Songs folder for `--local`.
+    pub songs_dir: Option<PathBuf>,
+    /// Where `ppify now-playing` reads the selected beatmap from.
+    pub now_playing: Option<NowPlayingSource>,
+    /// Named users for `--profile`, see `ppify profile`.
+    pub profiles: BTreeMap<String, Profile>,
+}
+
+#[derive(Clone, Debug, Deserialize, Serialize)]
+#[serde(deny_unknown_fields)]
+pub struct Profile {
+    pub user: String,
+    pub mode: Option<String>,
+}
+
+#[derive(Clone, Copy, Debug, Deserialize, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum OutputFormat {
+    Text,
+    Json,
+    Markdown,
+}
+
+impl FromStr for OutputFormat {
+    type Err = PpifyError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_ascii_lowercase().as_str() {
+            "text" => Ok(Self::Text),
+            "json" => Ok(Self::Json),
+            "markdown" | "md" => Ok(Self::Markdown),
+            _ => Err(PpifyError::BadInput(format!(
+                "unknown output format `{s}`, expected text, json or markdown"
+            ))),
+        }
+    }
+}
+
+/// Colors of the text report.
+#[derive(Clone, Copy, Debug, Default, Deserialize, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Theme {
+    /// Bright colors for dark terminal backgrounds.
+    #[default]
+    Dark,
+    /// Darker colors that stay readable on light backgrounds.
+    Light,
+    /// Only bold and dimmed text.
+    Mono,
+}
+
+impl FromStr for Theme {
+    type Err = PpifyError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_ascii_lowercase().as_str() {
+            "dark" => Ok(Self::Dark),
+            "light" => Ok(Self::Light),
+            "mono" => Ok(Self::Mono),
+            _ => Err(PpifyError::BadInput(format!(
+                "unknown theme `{s}`, expected dark, light or mono"
+            ))),
+        }
+    }
+}
+
+/// A program that tells which beatmap is selected in the osu! client.
+#[derive(Clone, Copy, Debug, Default, Deserialize, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum NowPlayingSource {
+    /// gosumemory, or tosu which serves the same websocket.
+    #[default]
+    #[serde(alias = "tosu")]
+    Gosumemory,
+    StreamCompanion,
+}
+
+impl NowPlayingSource {
+    pub fn default_url(self) -> &'static str {
+        match self {
+            Self::Gosumemory => "ws://127.0.0.1:24050/ws",
+            Self::StreamCompanion => "ws://127.0.0.1:20727/tokens",
+        }
+    }
+}
+
+impl FromStr for NowPlayingSource {
+    type Err = PpifyError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_ascii_lowercase().as_str() {
+            "gosumemory" | "tosu" => Ok(Self::Gosumemory),
+            "streamcompanion" => Ok(Self::StreamCompanion),
+            _ => Err(PpifyError::BadInput(format!(
+                "unknown now playing source `{s}`, expected gosumemory, tosu or streamcompanion"
+            ))),
+        }
+    }
+}
+
+impl Config {
+    pub const KEYS: &[&str] = &[
+        "user",
+        "mode",
+        "output",
+        "color",
+        "theme",
+        "cache_dir",
+        "history",
+        "weight_decay",
+        "retries",
+        "rate_limit",
+        "rate_limit_burst",
+        "client_id",
+        "client_secret_env",
+        "songs_dir",
+        "now_playing",
+    ];
+
+    /// `~/.config/ppify/config.toml` or the platform equivalent.
+    pub fn default_path() -> Option<PathBuf> {
+        dirs::config_dir().map(|dir| dir.join("ppify").join("config.toml"))
+    }
+
+    /// Reads the config at `path`, a missing file is an empty config.
+    pub fn load(path: &Path) -> Result<Self, PpifyError> {
+        let content = match fs::read_to_string(path) {
+            Ok(content) => content,
+            Err(err) if err.kind() == io::ErrorKind::NotFound => return Ok(Self::default()),
+            Err(err) => {
+                return Err(PpifyError::BadInput(format!(
+                    "failed to read {}: {err}",
+                    path.display()
+                )));
+            }
+        };
+
+        let config: Self = toml::from_str(&content)
+            .map_err(|err| PpifyError::BadInput(format!("invalid {}: {err}", path.display())))?;
+
+        config.validate()?;
+
+        Ok(config)
+    }
+
+    pub fn save(&self, path: &Path) -> io::Result<()> {
+        let content = toml::to_string_pretty(self).map_err(io::Error::other)?;
+
+        if let Some(dir) = path.parent() {
+            fs::create_dir_all(dir)?;
+        }
+
+        fs::write(path, content)
+    }
+
+    /// Sets one of [`Config::KEYS`] from its string form.
+    pub fn set(&mut self, key: &str, value: &str) -> Result<(), PpifyError> {
+        let invalid = || PpifyError::BadInput(format!("invalid value `{value}` for `{key}`"));
+        let mut updated = self.clone();
+
+        match key {
+            "user" => updated.user = Some(value.to_owned()),
+            "mode" => updated.mode = Some(value.to_owned()),
+            "output" => updated.output = Some(value.parse()?),
+            "color" => updated.color = Some(value.parse().map_err(|_| invalid())?),
+            "theme" => updated.theme = Some(value.parse()?),
+            "cache_dir" => updated.cache_dir = Some(value.into()),
+            "history" => updated.history = Some(value.parse().map_err(|_| invalid())?),
+            "weight_decay" => updated.weight_decay = Some(value.parse().map_err(|_| invalid())?),
+            "retries" => updated.retries = Some(value.parse().map_err(|_| invalid())?),
+            "rate_limit" => updated.rate_limit = Some(value.parse().map_err(|_| invalid())?),
+            "rate_limit_burst" => {
+                updated.rate_limit_burst = Some(value.parse().map_err(|_| invalid())?)
+            }
+            "client_id" => updated.client_id = Some(value.parse().map_err(|_| invalid())?),
+            "client_secret_env" => updated.client_secret_env = Some(value.to_owned()),
+            "songs_dir" => updated.songs_dir = Some(value.into()),
+            "now_playing" => updated.now_playing = Some(value.parse()?),
+            _ => return Err(unknown_key(key)),
+        }
+
+        // only keep valid values
+        updated.validate()?;
+        *self = updated;
+
+        Ok(())
+    }
+
+    pub fn unset(&mut self, key: &str) -> Result<(), PpifyError> {
+        match key {
+            "user" => self.user = None,
+            "mode" => self.mode = None,
+            "output" => self.output = None,
+            "color" => self.color = None,
+            "theme" => self.theme = None,
+            "cache_dir" => self.cache_dir = None,
+            "history" => self.history = None,
+            "weight_decay" => self.weight_decay = None,
+            "retries" => self.retries = None,
+            "rate_limit" => self.rate_limit = None,
+            "rate_limit_burst" => self.rate_limit_burst = None,
+            "client_id" => self.client_id = None,
+            "client_secret_env" => self.client_secret_env = None,
+            "songs_dir" => self.songs_dir = None,
+            "now_playing" => self.now_playing = None,
+            _ => return Err(unknown_key(key)),
+        }
+
+        Ok(())
+    }
+
+    /// Adds or replaces the profile `name`.
+    pub fn add_profile(&mut self, name: &str, profile: Profile) -> Result<(), PpifyError> {
+        check_mode(profile.mode.as_deref())?;
+        self.profiles.insert(name.to_owned(), profile);
+
+        Ok(())
+    }
+
+    /// Returns whether there was such a profile.
+    pub fn remove_profile(&mut self, name: &str) -> bool {
+        self.profiles.remove(name).is_some()
+    }
+
+    pub fn profile(&self, name: &str) -> Result<&Profile, PpifyError> {
+        self.profiles.get(name).ok_or_else(|| {
+            PpifyError::BadInput(format!(
+                "no profile named `{name}`, add it with `ppify profile add`"
+            ))
+        })
+    }
+
+    fn validate(&self) -> Result<(), PpifyError> {
+        check_mode(self.mode.as_deref())?;
+
+        for profile in self.profiles.values() {
+            check_mode(profile.mode.as_deref())?;
+        }
+
+        if let Some(decay) = self.weight_decay
+            && !(decay > 0.0 && decay <= 1.0)
+        {
+            return Err(PpifyError::BadInput(format!(
+                "weight_decay must be above 0 and at most 1, got {decay}"
+            )));
+        }
+
+        Ok(())
+    }
+}
+
+// the names `--mode` accepts
+const MODE_NAMES: &[&str] = &[
+    "osu", "std", "standard", "taiko", "catch", "ctb", "fruits", "mania",
+];
+
+fn check_mode(mode: Option<&str>) -> Result<(), PpifyError> {
+    match mode {
+        Some(mode) if !MODE_NAMES.contains(&mode) => Err(PpifyError::BadInput(format!(
+            "unknown mode `{mode}`, expected one of {}",
+            MODE_NAMES.join(", ")
+        ))),
+        _ => Ok(()),
+    }
+}
+
+fn unknown_key(key: &str) -> PpifyError {
+    PpifyError::BadInput(format!(
+        "unknown config key `{key}`, expected one of {}",
+        Config::KEYS.join(", ")
+    ))
+}
@@ -0,0 +1,60 @@
+//! osu! OAuth client credentials in the system keyring, see `ppify auth login`.
+
+use {crate::error::PpifyError, keyring::Entry};
+
+const SERVICE: &str = "ppify";
+const CLIENT_ID: &str = "client_id";
+const CLIENT_SECRET: &str = "client_secret";
+
+pub fn stored_client_id() -> Result<Option<u64>, PpifyError> {
+    let Some(id) = get(CLIENT_ID)? else {
+        return Ok(None);
+    };
+
+    id.parse().map(Some).map_err(|_| {
+        PpifyError::BadInput(format!(
+            "the keyring holds an invalid client id `{id}`, run `ppify auth login` again"
+        ))
+    })
+}
+
+pub fn stored_client_secret() -> Result<Option<String>, PpifyError> {
+    get(CLIENT_SECRET)
+}
+
+pub fn store(client_id: u64, client_secret: &str) -> Result<(), PpifyError> {
+    entry(CLIENT_ID)?
+        .set_password(&client_id.to_string())
+        .map_err(PpifyError::Keyring)?;
+
+    entry(CLIENT_SECRET)?
+        .set_password(client_secret)
+        .map_err(PpifyError::Keyring)
+}
+
+/// Removes the stored credentials, returns whether there were any.
+pub fn delete() -> Result<bool, PpifyError> {
+    let mut deleted = false;
+
+    for key in [CLIENT_ID, CLIENT_SECRET] {
+        match entry(key)?.delete_credential() {
+            Ok(()) => deleted = true,
+            Err(keyring::Error::NoEntry) => {}
+            Err(err) => return Err(PpifyError::Keyring(err)),
+        }
+    }
+
+    Ok(deleted)
+}
+
+fn get(key: &str) -> Result<Option<String>, PpifyError> {
+    match entry(key)?.get_password() {
+        Ok(value) => Ok(Some(value)),
+        Err(keyring::Error::NoEntry) => Ok(None),
+        Err(err) => Err(PpifyError::Keyring(err)),
+    }
+}
+
+fn entry(key: &str) -> Result<Entry, PpifyError> {
+    Entry::new(SERVICE, key).map_err(PpifyError::Keyring)
+}
@@ -0,0 +1,212 @@
+use {
+    crate::{cli::Cli, map_cache, modes_for, pipe::native_mode, score_cache},
+    color_eyre::{
+        Result,
+        eyre::{self, Context as _},
+    },
+    ppify::{
+        api::{self, Client},
+        cache::{MapCache, ScoreCache},
+        calc::{self, BeatmapMetadata, BonusPp, BuiltinWeighting, Judgements, PlayInputs, Rules},
+        input::MapSource,
+        mods,
+    },
+    serenity::{
+        all::{
+            Command, CommandInteraction, CommandOptionType, Context, CreateCommand,
+            CreateCommandOption, CreateEmbed, EditInteractionResponse, EventHandler,
+            GatewayIntents, Interaction, Ready, ResolvedValue,
+        },
+        async_trait,
+    },
+    std::env,
+};
+
+struct Handler {
+    osu: Client,
+    map_cache: Option<MapCache>,
+    score_cache: Option<ScoreCache>,
+    /// For commands without `stable`.
+    rules: Rules,
+    weighting: BuiltinWeighting,
+}
+
+pub async fn run(osu: Client, cli: &Cli) -> Result<()> {
+    let token = env::var("DISCORD_TOKEN").context("DISCORD_TOKEN must be set to run the bot")?;
+
+    let handler = Handler {
+        osu,
+        map_cache: map_cache(cli),
+        score_cache: score_cache(cli),
+        rules: Rules::new(cli.stable),
+        weighting: cli.weighting(),
+    };
+
+    // slash commands need no privileged intents
+    let mut client = serenity::Client::builder(&token, GatewayIntents::empty())
+        .event_handler(handler)
+        .await
+        .context("failed to create the Discord client")?;
+
+    client.start().await.context("Discord connection failed")
+}
+
+#[async_trait]
+impl EventHandler for Handler {
+    async fn ready(&self, ctx: Context, ready: Ready) {
+        let option = |kind, name, description| CreateCommandOption::new(kind, name, description);
+
+        let command = CreateCommand::new("ppcalc")
+            .description("Calculate the pp of a play")
+            .add_option(
+                option(CommandOptionType::String, "map", "Beatmap link or id").required(true),
+            )
+            .add_option(option(CommandOptionType::String, "mods", "Mods, e.g. HDDT"))
+            .add_option(option(
+                CommandOptionType::Number,
+                "acc",
+                "Accuracy in percent",
+            ))
+            .add_option(option(
+                CommandOptionType::Integer,
+                "misses",
+                "Number of misses",
+            ))
+            .add_option(option(
+                CommandOptionType::String,
+                "user",
+                "osu! user to project the play onto",
+            ))
+            .add_option(option(
+                CommandOptionType::Boolean,
+                "stable",
+                "Calculate with osu!stable's rules instead of lazer's",
+            ));
+
+        if let Err(err) = Command::create_global_command(&ctx.http, command).await {
+            eprintln!("Error: failed to register /ppcalc: {err}");
+        }
+
+        eprintln!("Connected to Discord as {}", ready.user.name);
+    }
+
+    async fn interaction_create(&self, ctx: Context, interaction: Interaction) {
+        let Interaction::Command(command) = interaction else {
+            return;
+        };
+
+        if command.data.name != "ppcalc" {
+            return;
+        }
+
+        // downloads and api requests can take longer than Discord waits for an answer
+        if let Err(err) = command.defer(&ctx.http).await {
+            eprintln!("Error: failed to acknowledge /ppcalc: {err}");
+            return;
+        }
+
+        let embed = self.ppcalc(&command).await.unwrap_or_else(|err| {
+            CreateEmbed::new()
+                .title("Calculation failed")
+                .description(format!("{err:#}"))
+        });
+
+        let response = EditInteractionResponse::new().embed(embed);
+
+        if let Err(err) = command.edit_response(&ctx.http, response).await {
+            eprintln!("Error: failed to answer /ppcalc: {err}");
+        }
+    }
+}
+
+impl Handler {
+    async fn ppcalc(&self, command: &CommandInteraction) -> Result<CreateEmbed> {
+        let (mut map, mut acronyms, mut accuracy, mut misses, mut user) =
+            (None, "", 100.0, 0, None);
+        let mut rules = self.rules;
+
+        for option in command.data.options() {
+            match (option.name, option.value) {
+                ("map", ResolvedValue::String(value)) => map = Some(value),
+                ("mods", ResolvedValue::String(value)) => acronyms = value,
+                ("acc", ResolvedValue::Number(value)) => accuracy = value,
+                ("misses", ResolvedValue::Integer(value)) => {
+                    misses = u32::try_from(value).context("misses can't be negative")?;
+                }
+                ("user", ResolvedValue::String(value)) => user = Some(value),
+                ("stable", ResolvedValue::Boolean(value)) => rules = Rules::new(value),
+                _ => {}
+            }
+        }
+
+        let map = map.ok_or_else(|| eyre::eyre!("a beatmap is required"))?;
+
+        let MapSource::Id { map_id, mode } = MapSource::parse(map)? else {
+            eyre::bail!("`{map}` is not a beatmap link or id");
+        };
+
+        let bytes =
+            api::fetch_osu_file(&self.osu, map_id, None, self.map_cache.as_ref(), false).await?;
+        let beatmap = calc::parse_native_beatmap(&bytes)?;
+
+        let (api_mode, pp_mode) = modes_for(mode.unwrap_or_else(|| native_mode(beatmap.mode)));
+        calc::check_map_mode(&beatmap, pp_mode)?;
+
+        let selected_mods = mods::parse_acronyms(acronyms, api_mode)?;
+        let game_mods = mods::lazer_mods(selected_mods.iter().copied(), api_mode);
+
+        let play = PlayInputs {
+            judgements: Judgements::Accuracy { accuracy, misses },
+            combo: None,
+        };
+
+        let attrs = calc::calculate_play(&beatmap, pp_mode, &game_mods, None, rules, &play);
+        let pp = attrs.pp();
+
+        let mods = if selected_mods.is_empty() {
+            "NM".to_owned()
+        } else {
+            selected_mods.iter().map(|m| m.acronym).collect()
+        };
+
+        let mut embed = CreateEmbed::new()
+            .title(BeatmapMetadata::from_osu_bytes(&bytes).to_string())
+            .url(format!("https://osu.ppy.sh/b/{map_id}"))
+            .field("PP", format!("{pp:.2}pp"), true)
+            .field("Stars", format!("{:.2}★", attrs.stars()), true)
+            .field("Max combo", format!("{}x", attrs.max_combo()), true)
+            .field("Play", format!("+{mods} {accuracy:.2}% {misses}❌"), false);
+
+        if let Some(user) = user {
+            let scores = api::fetch_user_best_scores_cached(
+                &self.osu,
+                user,
+                api_mode,
+                self.score_cache.as_ref(),
+                false,
+            )
+            .await?;
+            let profile = api::fetch_user(&self.osu, user, api_mode).await?;
+            let bonus = BonusPp::for_user(&profile, &scores, &[Some(map_id)]);
+            let gain = calc::project_gain(
+                &scores,
+                pp,
+                "Hypothetical play",
+                Some(map_id),
+                bonus,
+                &self.weighting,
+            );
+
+            embed = embed.field(
+                format!("Profile of {}", profile.username),
+                format!(
+                    "{:.2}pp → {:.2}pp ({:+.2}pp)",
+                    gain.old_total_pp, gain.new_total_pp, gain.gain
+                ),
+                false,
+            );
+        }
+
+        Ok(embed)
+    }
+}
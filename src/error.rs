@@ -0,0 +1,66 @@
+use {
+    rosu_v2::error::OsuError,
+    std::{error::Error as StdError, process::ExitCode},
+    thiserror::Error,
+};
+
+type BoxedError = Box<dyn StdError + Send + Sync + 'static>;
+
+#[derive(Debug, Error)]
+pub enum PpifyError {
+    #[error("failed to authenticate with the osu! api (check OSU_CLIENT_ID / OSU_CLIENT_SECRET)")]
+    AuthFailed(#[source] OsuError),
+
+    #[error("beatmap {0} was not found")]
+    MapNotFound(u32),
+
+    #[error("user `{0}` was not found")]
+    UserNotFound(String),
+
+    #[error("{context}")]
+    Network {
+        context: String,
+        #[source]
+        source: BoxedError,
+    },
+
+    #[error("{0}")]
+    BadInput(String),
+
+    #[error("beatmap is suspicious: {0}")]
+    SuspiciousMap(String),
+
+    #[error("{0}")]
+    Calculation(String),
+
+    #[error("failed to access the system keyring")]
+    Keyring(#[source] keyring::Error),
+
+    #[error("{0} (offline mode)")]
+    Offline(String),
+}
+
+impl PpifyError {
+    pub fn network(context: impl Into<String>, source: impl Into<BoxedError>) -> Self {
+        Self::Network {
+            context: context.into(),
+            source: source.into(),
+        }
+    }
+
+    pub fn exit_code(&self) -> ExitCode {
+        let code = match self {
+            Self::BadInput(_) => 2,
+            Self::AuthFailed(_) => 3,
+            Self::MapNotFound(_) => 4,
+            Self::Network { .. } => 5,
+            Self::SuspiciousMap(_) => 6,
+            Self::Calculation(_) => 7,
+            Self::Keyring(_) => 8,
+            Self::Offline(_) => 9,
+            Self::UserNotFound(_) => 10,
+        };
+
+        ExitCode::from(code)
+    }
+}
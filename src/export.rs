@@ -0,0 +1,16 @@
+//! Tables written to files, e.g. with `--out`.
+
+use {serde::Serialize, std::path::Path};
+
+/// Writes `rows` as CSV with a header line of their field names.
+pub fn write_csv<T: Serialize>(path: &Path, rows: &[T]) -> csv::Result<()> {
+    let mut writer = csv::Writer::from_path(path)?;
+
+    for row in rows {
+        writer.serialize(row)?;
+    }
+
+    writer.flush()?;
+
+    Ok(())
+}
@@ -0,0 +1,256 @@
+//! Past calculations in a local SQLite database, for `ppify history`.
+
+use {
+    crate::report::CalculationReport,
+    rusqlite::{Connection, OptionalExtension, Row, params},
+    serde::{Deserialize, Serialize},
+    std::{
+        path::{Path, PathBuf},
+        time::{SystemTime, UNIX_EPOCH},
+    },
+};
+
+const SCHEMA: &str = "
+    CREATE TABLE IF NOT EXISTS calculations (
+        id INTEGER PRIMARY KEY,
+        created_at INTEGER NOT NULL,
+        user TEXT NOT NULL,
+        mode TEXT NOT NULL,
+        beatmap TEXT NOT NULL,
+        mods TEXT NOT NULL,
+        play TEXT NOT NULL,
+        args TEXT NOT NULL,
+        pp REAL NOT NULL,
+        gain REAL NOT NULL
+    );
+";
+
+// the timestamp as local time, SQLite knows the timezone
+const COLUMNS: &str = "id, strftime('%Y-%m-%d %H:%M', created_at, 'unixepoch', 'localtime'), \
+    created_at, user, mode, beatmap, mods, play, args, pp, gain";
+
+/// A past calculation, also the format of `ppify history export`.
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct HistoryEntry {
+    /// Only unique in one database, imports get new ids.
+    #[serde(default)]
+    pub id: i64,
+    /// Local time, e.g. `2025-01-31 18:04`.
+    #[serde(default)]
+    pub time: String,
+    /// Unix timestamp in seconds.
+    pub created_at: i64,
+    pub user: String,
+    pub mode: String,
+    pub beatmap: String,
+    /// Acronyms, empty for NoMod.
+    pub mods: String,
+    /// Accuracy, misses and combo, e.g. `98.50% · 2 misses · 1200x`.
+    pub play: String,
+    /// The flags of a `ppify` run that calculates the same play.
+    pub args: Vec<String>,
+    pub pp: f64,
+    pub gain: f64,
+}
+
+impl HistoryEntry {
+    fn from_row(row: &Row<'_>) -> rusqlite::Result<Self> {
+        let args: String = row.get(8)?;
+
+        Ok(Self {
+            id: row.get(0)?,
+            time: row.get(1)?,
+            created_at: row.get(2)?,
+            user: row.get(3)?,
+            mode: row.get(4)?,
+            beatmap: row.get(5)?,
+            mods: row.get(6)?,
+            play: row.get(7)?,
+            args: serde_json::from_str(&args).map_err(|err| {
+                rusqlite::Error::FromSqlConversionFailure(
+                    8,
+                    rusqlite::types::Type::Text,
+                    err.into(),
+                )
+            })?,
+            pp: row.get(9)?,
+            gain: row.get(10)?,
+        })
+    }
+}
+
+pub struct History {
+    conn: Connection,
+}
+
+impl History {
+    /// `~/.local/share/ppify/history.db` or the platform equivalent.
+    pub fn default_path() -> Option<PathBuf> {
+        dirs::data_dir().map(|dir| dir.join("ppify").join("history.db"))
+    }
+
+    /// Opens the database at `path`, creating it if needed. The directory has to exist.
+    pub fn open(path: &Path) -> rusqlite::Result<Self> {
+        let conn = Connection::open(path)?;
+        conn.execute_batch(SCHEMA)?;
+
+        Ok(Self { conn })
+    }
+
+    /// Saves the calculation of `report` for `user`, `args` being the flags that repeat it.
+    /// Returns the id of the entry, an existing one if the same calculation was already
+    /// saved in the same second.
+    pub fn record(
+        &self,
+        user: &str,
+        report: &CalculationReport,
+        args: &[String],
+    ) -> rusqlite::Result<i64> {
+        let created_at = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map_or(0, |since| since.as_secs() as i64);
+
+        let play = &report.play;
+        let misses = match play.misses() {
+            1 => "1 miss".to_owned(),
+            misses => format!("{misses} misses"),
+        };
+        // no combo means the map's max combo, which is only an FC without misses
+        let combo = match play.combo {
+            Some(combo) => format!("{combo}x"),
+            None if play.misses() == 0 => "FC".to_owned(),
+            None => "max combo".to_owned(),
+        };
+        let play = format!("{:.2}% · {misses} · {combo}", play.accuracy());
+
+        let entry = HistoryEntry {
+            id: 0,
+            time: String::new(),
+            created_at,
+            user: user.to_owned(),
+            mode: report.mode.to_owned(),
+            beatmap: report.beatmap.to_string(),
+            mods: report.mods.concat(),
+            play,
+            args: args.to_vec(),
+            pp: report.pp,
+            gain: report.profile.gain,
+        };
+
+        if insert(&self.conn, &entry)? > 0 {
+            return Ok(self.conn.last_insert_rowid());
+        }
+
+        // skipped as a duplicate, `last_insert_rowid` is of an older insert then
+        self.conn.query_row(
+            "SELECT id FROM calculations WHERE created_at = ?1 AND user = ?2 AND args = ?3",
+            params![
+                entry.created_at,
+                entry.user,
+                serde_json::to_string(&entry.args).unwrap_or_default()
+            ],
+            |row| row.get(0),
+        )
+    }
+
+    /// Adds `entries` from another database, skipping the ones that are already in this
+    /// one. Returns how many were added.
+    pub fn import(&mut self, entries: &[HistoryEntry]) -> rusqlite::Result<usize> {
+        let transaction = self.conn.transaction()?;
+        let mut added = 0;
+
+        for entry in entries {
+            added += insert(&transaction, entry)?;
+        }
+
+        transaction.commit()?;
+
+        Ok(added)
+    }
+
+    /// Every entry, oldest first.
+    pub fn all(&self) -> rusqlite::Result<Vec<HistoryEntry>> {
+        self.query(
+            &format!("SELECT {COLUMNS} FROM calculations ORDER BY created_at, id"),
+            [],
+        )
+    }
+
+    /// The `limit` most recent entries, newest first.
+    pub fn list(&self, limit: u32) -> rusqlite::Result<Vec<HistoryEntry>> {
+        self.query(
+            &format!(
+                "SELECT {COLUMNS} FROM calculations ORDER BY created_at DESC, id DESC LIMIT ?1"
+            ),
+            params![limit],
+        )
+    }
+
+    /// Like [`History::list`], but only entries whose user, beatmap, mods or flags contain
+    /// `text`, ignoring ASCII case.
+    pub fn search(&self, text: &str, limit: u32) -> rusqlite::Result<Vec<HistoryEntry>> {
+        let pattern = format!(
+            "%{}%",
+            text.trim()
+                .replace('\\', "\\\\")
+                .replace('%', "\\%")
+                .replace('_', "\\_")
+        );
+
+        self.query(
+            &format!(
+                "SELECT {COLUMNS} FROM calculations
+                 WHERE user LIKE ?1 ESCAPE '\\' OR beatmap LIKE ?1 ESCAPE '\\'
+                    OR mods LIKE ?1 ESCAPE '\\' OR args LIKE ?1 ESCAPE '\\'
+                 ORDER BY created_at DESC, id DESC LIMIT ?2"
+            ),
+            params![pattern, limit],
+        )
+    }
+
+    pub fn get(&self, id: i64) -> rusqlite::Result<Option<HistoryEntry>> {
+        self.conn
+            .query_row(
+                &format!("SELECT {COLUMNS} FROM calculations WHERE id = ?1"),
+                params![id],
+                HistoryEntry::from_row,
+            )
+            .optional()
+    }
+
+    fn query(
+        &self,
+        sql: &str,
+        params: impl rusqlite::Params,
+    ) -> rusqlite::Result<Vec<HistoryEntry>> {
+        let mut statement = self.conn.prepare(sql)?;
+        let entries = statement.query_map(params, HistoryEntry::from_row)?;
+
+        entries.collect()
+    }
+}
+
+// the same calculation at the same time is the same entry, so imports can be repeated
+fn insert(conn: &Connection, entry: &HistoryEntry) -> rusqlite::Result<usize> {
+    // a list of strings always serializes
+    let args = serde_json::to_string(&entry.args).unwrap_or_default();
+
+    conn.execute(
+        "INSERT INTO calculations (created_at, user, mode, beatmap, mods, play, args, pp, gain)
+         SELECT ?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9
+         WHERE NOT EXISTS (
+             SELECT 1 FROM calculations WHERE created_at = ?1 AND user = ?2 AND args = ?7
+         )",
+        params![
+            entry.created_at,
+            entry.user,
+            entry.mode,
+            entry.beatmap,
+            entry.mods,
+            entry.play,
+            args,
+            entry.pp,
+            entry.gain,
+        ],
+    )
+}
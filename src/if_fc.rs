@@ -0,0 +1,92 @@
+use {
+    crate::{cli::Cli, fetch_best_scores, map_cache, markdown, read_mode, read_username},
+    color_eyre::{Result, eyre::Context},
+    ppify::{
+        api::{self, Client},
+        calc::{self, PlayInputs, Rules, TopPlay},
+        export,
+        report::{IfFcPlay, IfFcReport},
+    },
+};
+
+pub async fn run(osu: &Client, cli: &Cli) -> Result<()> {
+    let username = read_username(cli.user.as_deref())?;
+
+    let (api_mode, pp_mode) = match cli.mode {
+        Some(mode) => mode.modes(),
+        None => read_mode()?,
+    };
+
+    let scores = fetch_best_scores(osu, cli, &username, api_mode).await?;
+    let cache = map_cache(cli);
+    let rules = Rules::new(cli.stable);
+    let mut plays = Vec::with_capacity(scores.len());
+
+    for score in &scores {
+        let Some(top_play) = TopPlay::from_score(score) else {
+            continue;
+        };
+
+        let fc_pp = if score.is_perfect_combo {
+            top_play.pp
+        } else {
+            let checksum = api::map_checksum(score);
+            let bytes =
+                api::fetch_osu_file(osu, score.map_id, checksum, cache.as_ref(), cli.refresh)
+                    .await?;
+            let map = calc::parse_beatmap(&bytes, pp_mode)?;
+            let fc = PlayInputs::from_score(score).full_combo();
+
+            calc::calculate_play(&map, pp_mode, &score.mods, None, rules, &fc).pp()
+        };
+
+        plays.push(IfFcPlay {
+            label: top_play.label,
+            pp: top_play.pp,
+            fc_pp,
+        });
+    }
+
+    let report = IfFcReport::new(api_mode.as_str(), plays, &cli.weighting());
+
+    if let Some(path) = &cli.out {
+        export::write_csv(path, &report.plays)
+            .with_context(|| format!("failed to write {}", path.display()))?;
+    }
+
+    if cli.json {
+        let json = serde_json::to_string_pretty(&report).context("failed to serialize report")?;
+        println!("{json}");
+    } else if cli.markdown() {
+        print!("{}", markdown::if_fc(&report));
+    } else {
+        print_report(&report);
+    }
+
+    Ok(())
+}
+
+fn print_report(report: &IfFcReport) {
+    println!();
+    println!("  #           pp      FC pp     choked");
+
+    for (i, play) in report.plays.iter().enumerate() {
+        println!(
+            "  #{:<3} {:>8.2}pp {:>8.2}pp {:>+8.2}pp  {}",
+            i + 1,
+            play.pp,
+            play.fc_pp,
+            play.choked_pp(),
+            play.label
+        );
+    }
+
+    println!();
+    println!("Top play pp (weighted):   {:.2}pp", report.total_pp);
+    println!("If every play was an FC:  {:.2}pp", report.fc_total_pp);
+    println!(
+        "PP lost to chokes:        {:+.2}pp",
+        report.fc_total_pp - report.total_pp
+    );
+    println!("(bonus pp is not included)");
+}
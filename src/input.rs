@@ -0,0 +1,340 @@
+use {
+    crate::error::PpifyError,
+    rosu_v2::prelude::GameMode,
+    std::path::{Path, PathBuf},
+};
+
+/// Where the beatmap to calculate comes from.
+#[derive(Clone, Debug, PartialEq)]
+pub enum MapSource {
+    /// A difficulty id, with the mode if it came from a link that names one.
+    Id {
+        map_id: u32,
+        mode: Option<GameMode>,
+    },
+    /// A whole beatmapset; the difficulty still has to be picked.
+    Set(u32),
+    File(PathBuf),
+    /// A difficulty in a local `.osz` archive, by the name of its `.osu` file there.
+    Archive {
+        path: PathBuf,
+        file_name: String,
+    },
+    /// Free text to look up with the beatmapset search.
+    Search(String),
+}
+
+impl MapSource {
+    /// Accepts a beatmap id, a beatmap link or the path of a local `.osu` or `.osz` file.
+    /// Anything else is taken as a search query. The difficulty of a `.osz` file still has to
+    /// be picked.
+    pub fn parse(raw: &str) -> Result<Self, PpifyError> {
+        // terminals wrap drag-and-dropped paths in quotes
+        let trimmed = raw.trim().trim_matches(['"', '\'']);
+
+        if let Ok(map_id) = trimmed.parse() {
+            return Ok(Self::Id { map_id, mode: None });
+        }
+
+        if let Some(source) = Self::parse_url(trimmed)? {
+            return Ok(source);
+        }
+
+        let path = Path::new(trimmed);
+
+        if path.is_file() {
+            return Ok(Self::File(path.to_owned()));
+        }
+
+        // don't turn a mistyped path into a search
+        if trimmed.ends_with(".osu") || trimmed.ends_with(".osz") || trimmed.contains(['/', '\\']) {
+            return Err(PpifyError::BadInput(format!(
+                "`{trimmed}` is not an existing .osu or .osz file"
+            )));
+        }
+
+        if trimmed.is_empty() {
+            return Err(PpifyError::BadInput("no beatmap given".to_owned()));
+        }
+
+        Ok(Self::Search(trimmed.to_owned()))
+    }
+
+    /// The mode named by the input, if any.
+    pub fn mode(&self) -> Option<GameMode> {
+        match self {
+            Self::Id { mode, .. } => *mode,
+            Self::Set(_) | Self::File(_) | Self::Archive { .. } | Self::Search(_) => None,
+        }
+    }
+
+    // Handles the link shapes osu.ppy.sh hands out: `/beatmapsets/{set}#{mode}/{map}`,
+    // `/beatmaps/{map}?mode={mode}`, `/b/{map}?m={n}` and the set-only `/beatmapsets/{set}`
+    // and `/s/{set}`, and the client's `osu://b/{map}` and `osu://s/{set}`.
+    fn parse_url(raw: &str) -> Result<Option<Self>, PpifyError> {
+        let Some(rest) = ["https://", "http://", ""]
+            .iter()
+            .filter_map(|scheme| raw.strip_prefix(scheme))
+            .find_map(|rest| {
+                rest.strip_prefix("osu.ppy.sh/")
+                    .or_else(|| rest.strip_prefix("old.ppy.sh/"))
+            })
+            .or_else(|| raw.strip_prefix("osu://"))
+        else {
+            return Ok(None);
+        };
+
+        let bad_link = || PpifyError::BadInput(format!("unsupported beatmap link `{raw}`"));
+
+        let (rest, fragment) = rest.split_once('#').unwrap_or((rest, ""));
+        let (path, query) = rest.split_once('?').unwrap_or((rest, ""));
+        let mut segments = path.split('/').filter(|segment| !segment.is_empty());
+
+        let source = match (segments.next(), segments.next()) {
+            (Some("beatmapsets"), Some(set_id)) => match fragment.split_once('/') {
+                Some((mode, map_id)) => Self::Id {
+                    map_id: map_id.parse().map_err(|_| bad_link())?,
+                    mode: mode_from_name(mode),
+                },
+                None => Self::Set(set_id.parse().map_err(|_| bad_link())?),
+            },
+            (Some("s"), Some(set_id)) => Self::Set(set_id.parse().map_err(|_| bad_link())?),
+            (Some("beatmaps" | "b"), Some(map_id)) => {
+                let mode = query
+                    .split('&')
+                    .filter_map(|pair| pair.split_once('='))
+                    .find_map(|(key, value)| match key {
+                        "mode" => mode_from_name(value),
+                        "m" => mode_from_number(value),
+                        _ => None,
+                    });
+
+                Self::Id {
+                    map_id: map_id.parse().map_err(|_| bad_link())?,
+                    mode,
+                }
+            }
+            _ => return Err(bad_link()),
+        };
+
+        Ok(Some(source))
+    }
+}
+
+/// Accepts a score id or a score link, either `/scores/{id}` or the legacy
+/// `/scores/{mode}/{id}`.
+pub fn parse_score_ref(raw: &str) -> Result<(u64, Option<GameMode>), PpifyError> {
+    let trimmed = raw.trim();
+    let bad_score = || PpifyError::BadInput(format!("`{trimmed}` is not a score id or score link"));
+
+    if let Ok(score_id) = trimmed.parse() {
+        return Ok((score_id, None));
+    }
+
+    let path = ["https://", "http://", ""]
+        .iter()
+        .find_map(|scheme| {
+            trimmed
+                .strip_prefix(scheme)?
+                .strip_prefix("osu.ppy.sh/scores/")
+        })
+        .ok_or_else(bad_score)?;
+
+    let path = path.split(['?', '#']).next().unwrap_or_default();
+
+    match path.split_once('/') {
+        Some((mode, score_id)) => Ok((
+            score_id.parse().map_err(|_| bad_score())?,
+            Some(mode_from_name(mode).ok_or_else(bad_score)?),
+        )),
+        None => Ok((path.parse().map_err(|_| bad_score())?, None)),
+    }
+}
+
+fn mode_from_name(name: &str) -> Option<GameMode> {
+    match name {
+        "osu" => Some(GameMode::Osu),
+        "taiko" => Some(GameMode::Taiko),
+        "fruits" => Some(GameMode::Catch),
+        "mania" => Some(GameMode::Mania),
+        _ => None,
+    }
+}
+
+fn mode_from_number(number: &str) -> Option<GameMode> {
+    match number {
+        "0" => Some(GameMode::Osu),
+        "1" => Some(GameMode::Taiko),
+        "2" => Some(GameMode::Catch),
+        "3" => Some(GameMode::Mania),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn id(map_id: u32, mode: Option<GameMode>) -> MapSource {
+        MapSource::Id { map_id, mode }
+    }
+
+    #[test]
+    fn parse_reads_ids_and_links() {
+        let cases = [
+            ("129891", id(129891, None)),
+            ("  129891\n", id(129891, None)),
+            (
+                "https://osu.ppy.sh/beatmapsets/39804#osu/129891",
+                id(129891, Some(GameMode::Osu)),
+            ),
+            (
+                "osu.ppy.sh/beatmapsets/39804#taiko/129891",
+                id(129891, Some(GameMode::Taiko)),
+            ),
+            (
+                "http://osu.ppy.sh/beatmapsets/39804#fruits/129891",
+                id(129891, Some(GameMode::Catch)),
+            ),
+            (
+                "https://osu.ppy.sh/beatmapsets/39804#mania/129891",
+                id(129891, Some(GameMode::Mania)),
+            ),
+            // a mode the link doesn't know is left to the map
+            (
+                "https://osu.ppy.sh/beatmapsets/39804#lazer/129891",
+                id(129891, None),
+            ),
+            (
+                "https://osu.ppy.sh/beatmapsets/39804",
+                MapSource::Set(39804),
+            ),
+            (
+                "https://osu.ppy.sh/beatmapsets/39804/",
+                MapSource::Set(39804),
+            ),
+            ("https://osu.ppy.sh/beatmaps/129891", id(129891, None)),
+            (
+                "https://osu.ppy.sh/beatmaps/129891?mode=mania",
+                id(129891, Some(GameMode::Mania)),
+            ),
+            ("https://osu.ppy.sh/b/129891", id(129891, None)),
+            (
+                "https://osu.ppy.sh/b/129891?m=1",
+                id(129891, Some(GameMode::Taiko)),
+            ),
+            (
+                "https://old.ppy.sh/b/129891?foo=bar&m=2",
+                id(129891, Some(GameMode::Catch)),
+            ),
+            ("https://osu.ppy.sh/s/39804", MapSource::Set(39804)),
+            ("old.ppy.sh/s/39804", MapSource::Set(39804)),
+            ("freedom dive", MapSource::Search("freedom dive".to_owned())),
+            ("osu://b/129891", id(129891, None)),
+            ("osu://s/39804", MapSource::Set(39804)),
+        ];
+
+        for (raw, expected) in cases {
+            assert_eq!(MapSource::parse(raw).unwrap(), expected, "{raw}");
+        }
+    }
+
+    #[test]
+    fn parse_rejects_bad_links_and_paths() {
+        let cases = [
+            "",
+            "\"\"",
+            "https://osu.ppy.sh/beatmapsets/39804#osu/abc",
+            "https://osu.ppy.sh/beatmapsets/abc",
+            "https://osu.ppy.sh/b/abc",
+            "https://osu.ppy.sh/s/",
+            "https://osu.ppy.sh/users/2",
+            "https://osu.ppy.sh/",
+            "osu://chan/#osu",
+            "missing.osu",
+            "missing.osz",
+            "songs/missing",
+        ];
+
+        for raw in cases {
+            assert!(
+                matches!(MapSource::parse(raw), Err(PpifyError::BadInput(_))),
+                "{raw}"
+            );
+        }
+    }
+
+    #[test]
+    fn parse_reads_existing_files() {
+        let path = std::env::temp_dir().join(format!("ppify-input-{}.osu", std::process::id()));
+        std::fs::write(&path, "osu file format v14").unwrap();
+
+        let quoted = format!("'{}'", path.display());
+        let parsed = [
+            MapSource::parse(&path.display().to_string()),
+            MapSource::parse(&quoted),
+        ];
+        std::fs::remove_file(&path).unwrap();
+
+        for source in parsed {
+            assert_eq!(source.unwrap(), MapSource::File(path.clone()));
+        }
+    }
+
+    #[test]
+    fn parse_score_ref_reads_ids_and_links() {
+        let cases = [
+            ("4234567890", (4234567890, None)),
+            (" 4234567890 ", (4234567890, None)),
+            ("https://osu.ppy.sh/scores/4234567890", (4234567890, None)),
+            ("osu.ppy.sh/scores/4234567890", (4234567890, None)),
+            (
+                "https://osu.ppy.sh/scores/4234567890?foo=bar",
+                (4234567890, None),
+            ),
+            (
+                "https://osu.ppy.sh/scores/osu/123456",
+                (123456, Some(GameMode::Osu)),
+            ),
+            (
+                "http://osu.ppy.sh/scores/taiko/123456#top",
+                (123456, Some(GameMode::Taiko)),
+            ),
+            (
+                "https://osu.ppy.sh/scores/fruits/123456",
+                (123456, Some(GameMode::Catch)),
+            ),
+            (
+                "https://osu.ppy.sh/scores/mania/123456",
+                (123456, Some(GameMode::Mania)),
+            ),
+        ];
+
+        for (raw, expected) in cases {
+            assert_eq!(parse_score_ref(raw).unwrap(), expected, "{raw}");
+        }
+    }
+
+    #[test]
+    fn parse_score_ref_rejects_other_input() {
+        let cases = [
+            "",
+            "-1",
+            "abc",
+            "https://osu.ppy.sh/scores/",
+            "https://osu.ppy.sh/scores/abc",
+            "https://osu.ppy.sh/scores/lazer/123456",
+            "https://osu.ppy.sh/scores/osu/abc",
+            "https://osu.ppy.sh/scores/osu/123456/extra",
+            "https://osu.ppy.sh/beatmaps/129891",
+            "https://example.com/scores/123456",
+        ];
+
+        for raw in cases {
+            assert!(
+                matches!(parse_score_ref(raw), Err(PpifyError::BadInput(_))),
+                "{raw}"
+            );
+        }
+    }
+}
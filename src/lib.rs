@@ -0,0 +1,23 @@
+//! Core of ppify: beatmap downloads, pp calculation and top-play weighting,
+//! usable without the interactive prompts of the binary.
+
+pub mod api;
+pub mod batch_file;
+pub mod cache;
+pub mod calc;
+pub mod config;
+pub mod credentials;
+pub mod error;
+pub mod export;
+pub mod history;
+pub mod input;
+pub mod library;
+pub mod mods;
+pub mod osu_db;
+pub mod ranking;
+pub mod ratelimit;
+pub mod report;
+pub mod retry;
+pub mod snapshot;
+
+pub use error::PpifyError;
@@ -0,0 +1,209 @@
+//! Beatmaps of a local osu! installation, for calculating without downloads.
+
+use {
+    crate::{
+        cache::md5_hex,
+        calc::BeatmapMetadata,
+        osu_db::{self, DbBeatmap},
+    },
+    rosu_v2::prelude::GameMode,
+    std::{
+        fs::{self, File},
+        io::{self, Read},
+        path::{Path, PathBuf},
+    },
+    zip::ZipArchive,
+};
+
+#[derive(Clone, Debug)]
+pub struct LocalMap {
+    pub path: PathBuf,
+    pub metadata: BeatmapMetadata,
+    pub mode: GameMode,
+    /// MD5 hash of the `.osu` file, how osu!'s other databases refer to beatmaps.
+    pub md5: String,
+}
+
+impl LocalMap {
+    pub fn from_file(path: PathBuf) -> io::Result<Self> {
+        let bytes = fs::read(&path)?;
+
+        Ok(Self {
+            metadata: BeatmapMetadata::from_osu_bytes(&bytes),
+            mode: osu_file_mode(&bytes),
+            md5: md5_hex(&bytes),
+            path,
+        })
+    }
+
+    fn from_db(songs_dir: &Path, beatmap: DbBeatmap) -> Self {
+        Self {
+            path: songs_dir.join(&beatmap.folder).join(&beatmap.file_name),
+            metadata: BeatmapMetadata {
+                beatmap_id: (beatmap.beatmap_id != 0).then_some(beatmap.beatmap_id),
+                artist: beatmap.artist,
+                title: beatmap.title,
+                version: beatmap.version,
+                creator: beatmap.creator,
+            },
+            mode: GameMode::from(beatmap.mode),
+            md5: beatmap.md5,
+        }
+    }
+}
+
+/// A difficulty in a `.osz` beatmapset archive.
+#[derive(Clone, Debug)]
+pub struct OszDifficulty {
+    /// Name of the `.osu` file in the archive.
+    pub file_name: String,
+    pub metadata: BeatmapMetadata,
+    pub mode: GameMode,
+}
+
+/// The difficulties of the `.osz` archive at `path`, sorted by mode and name.
+pub fn osz_difficulties(path: &Path) -> io::Result<Vec<OszDifficulty>> {
+    let mut archive = ZipArchive::new(File::open(path)?)?;
+    let mut difficulties = Vec::new();
+
+    for i in 0..archive.len() {
+        let mut entry = archive.by_index(i)?;
+
+        if !entry.is_file() || !entry.name().to_ascii_lowercase().ends_with(".osu") {
+            continue;
+        }
+
+        let mut bytes = Vec::new();
+        entry.read_to_end(&mut bytes)?;
+
+        difficulties.push(OszDifficulty {
+            file_name: entry.name().to_owned(),
+            metadata: BeatmapMetadata::from_osu_bytes(&bytes),
+            mode: osu_file_mode(&bytes),
+        });
+    }
+
+    difficulties.sort_by(|a, b| {
+        (a.mode as u8).cmp(&(b.mode as u8)).then_with(|| {
+            a.metadata
+                .version
+                .to_lowercase()
+                .cmp(&b.metadata.version.to_lowercase())
+        })
+    });
+
+    Ok(difficulties)
+}
+
+/// The contents of the `.osu` file `file_name` in the `.osz` archive at `path`.
+pub fn read_osz_file(path: &Path, file_name: &str) -> io::Result<Vec<u8>> {
+    let mut archive = ZipArchive::new(File::open(path)?)?;
+    let mut bytes = Vec::new();
+    archive.by_name(file_name)?.read_to_end(&mut bytes)?;
+
+    Ok(bytes)
+}
+
+/// `%LOCALAPPDATA%\osu!\Songs`, where osu! stable keeps its beatmaps on Windows.
+pub fn default_songs_dir() -> Option<PathBuf> {
+    dirs::data_local_dir().map(|dir| dir.join("osu!").join("Songs"))
+}
+
+/// `osu!.db` in the osu! folder that contains `songs_dir`.
+pub fn osu_db_path(songs_dir: &Path) -> Option<PathBuf> {
+    game_file(songs_dir, "osu!.db")
+}
+
+/// `scores.db` in the osu! folder that contains `songs_dir`.
+pub fn scores_db_path(songs_dir: &Path) -> Option<PathBuf> {
+    game_file(songs_dir, "scores.db")
+}
+
+/// `collection.db` in the osu! folder that contains `songs_dir`.
+pub fn collection_db_path(songs_dir: &Path) -> Option<PathBuf> {
+    game_file(songs_dir, "collection.db")
+}
+
+fn game_file(songs_dir: &Path, name: &str) -> Option<PathBuf> {
+    songs_dir
+        .parent()
+        .map(|osu_dir| osu_dir.join(name))
+        .filter(|path| path.is_file())
+}
+
+/// The beatmaps of `songs_dir`, from `osu!.db` when there is one and by reading every `.osu`
+/// file otherwise. Both are sorted by artist and title.
+pub fn load_songs(songs_dir: &Path) -> io::Result<Vec<LocalMap>> {
+    let Some(db_path) = osu_db_path(songs_dir) else {
+        return scan_songs(songs_dir);
+    };
+
+    let mut maps: Vec<_> = osu_db::read_osu_db(&db_path)?
+        .into_iter()
+        .map(|beatmap| LocalMap::from_db(songs_dir, beatmap))
+        .collect();
+
+    sort_maps(&mut maps);
+
+    Ok(maps)
+}
+
+/// Reads every `.osu` file in the beatmapset folders of `songs_dir`, sorted by artist and
+/// title. Unreadable files are skipped.
+pub fn scan_songs(songs_dir: &Path) -> io::Result<Vec<LocalMap>> {
+    let mut maps = Vec::new();
+
+    for set_dir in fs::read_dir(songs_dir)? {
+        let set_dir = set_dir?.path();
+
+        if !set_dir.is_dir() {
+            continue;
+        }
+
+        // a single broken set folder shouldn't hide all the others
+        let Ok(entries) = fs::read_dir(&set_dir) else {
+            continue;
+        };
+
+        for entry in entries.flatten() {
+            let path = entry.path();
+
+            if path
+                .extension()
+                .is_some_and(|ext| ext.eq_ignore_ascii_case("osu"))
+                && let Ok(map) = LocalMap::from_file(path)
+            {
+                maps.push(map);
+            }
+        }
+    }
+
+    sort_maps(&mut maps);
+
+    Ok(maps)
+}
+
+fn sort_maps(maps: &mut [LocalMap]) {
+    maps.sort_by(|a, b| {
+        let key = |map: &LocalMap| {
+            (
+                map.metadata.artist.to_lowercase(),
+                map.metadata.title.to_lowercase(),
+            )
+        };
+
+        key(a).cmp(&key(b))
+    });
+}
+
+// `Mode:` of the `[General]` section, osu!standard when it's missing
+fn osu_file_mode(bytes: &[u8]) -> GameMode {
+    let content = String::from_utf8_lossy(bytes);
+
+    content
+        .lines()
+        .take_while(|line| !line.starts_with("[Metadata]"))
+        .find_map(|line| line.strip_prefix("Mode:"))
+        .and_then(|mode| mode.trim().parse::<u8>().ok())
+        .map_or(GameMode::Osu, GameMode::from)
+}
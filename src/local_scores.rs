@@ -0,0 +1,233 @@
+use {
+    crate::{cli::Cli, markdown, modes_for, songs_dir},
+    color_eyre::{
+        Result,
+        eyre::{self, Context},
+    },
+    demand::{DemandOption, Select},
+    ppify::{
+        PpifyError,
+        calc::{self, PlayInputs, Rules},
+        export,
+        library::{self, LocalMap},
+        mods,
+        osu_db::{self, DbScore},
+        report::{LocalScore, LocalScoresReport},
+    },
+    rosu_v2::prelude::{GameMode, GameModsIntermode},
+    serde::Serialize,
+    std::{collections::HashMap, fs},
+};
+
+pub fn run(cli: &Cli, all: bool) -> Result<()> {
+    let songs_dir = songs_dir(cli)?;
+    let db_path = library::scores_db_path(&songs_dir).ok_or_else(|| {
+        eyre::eyre!(
+            "no scores.db next to {}, set `songs_dir` in the config",
+            songs_dir.display()
+        )
+    })?;
+
+    let mut scores = osu_db::read_scores_db(&db_path)
+        .with_context(|| format!("failed to read {}", db_path.display()))?;
+
+    // scores.db also has the scores of everyone else who played on this install
+    if let Some(user) = &cli.user {
+        scores.retain(|score| score.player.eq_ignore_ascii_case(user.trim()));
+
+        if scores.is_empty() {
+            return Err(PpifyError::BadInput(format!("no local scores of `{user}`")).into());
+        }
+    }
+
+    let library: HashMap<_, _> = library::load_songs(&songs_dir)
+        .with_context(|| format!("failed to read {}", songs_dir.display()))?
+        .into_iter()
+        .map(|map| (map.md5.clone(), map))
+        .collect();
+
+    if !all {
+        let md5 = read_beatmap(&scores, &library)?;
+        scores.retain(|score| score.beatmap_md5 == md5);
+    }
+
+    let mut local_scores = Vec::with_capacity(scores.len());
+    let mut missing = 0;
+
+    for score in &scores {
+        let Some(local) = library.get(&score.beatmap_md5) else {
+            missing += 1;
+            continue;
+        };
+
+        let api_mode = GameMode::from(score.mode);
+        let play = PlayInputs::from_db_score(score);
+
+        let (pp, error) = match calculate_score(score, local, &play) {
+            Ok(pp) => (Some(pp), None),
+            Err(err) => (None, Some(err.to_string())),
+        };
+
+        local_scores.push(LocalScore {
+            beatmap: local.metadata.clone(),
+            player: score.player.clone(),
+            mode: api_mode.as_str(),
+            mods: GameModsIntermode::from_bits(score.mods).to_string(),
+            play,
+            score: score.score,
+            online_id: (score.online_id != 0).then_some(score.online_id),
+            pp,
+            error,
+        });
+    }
+
+    // failed scores last
+    local_scores.sort_by(|a, b| {
+        b.pp.unwrap_or(f64::NEG_INFINITY)
+            .total_cmp(&a.pp.unwrap_or(f64::NEG_INFINITY))
+    });
+
+    let report = LocalScoresReport {
+        scores: local_scores,
+        missing,
+    };
+
+    if let Some(path) = &cli.out {
+        let rows: Vec<_> = report.scores.iter().map(CsvRow::from).collect();
+
+        export::write_csv(path, &rows)
+            .with_context(|| format!("failed to write {}", path.display()))?;
+    }
+
+    if cli.json {
+        let json = serde_json::to_string_pretty(&report).context("failed to serialize report")?;
+        println!("{json}");
+    } else if cli.markdown() {
+        print!("{}", markdown::local_scores(&report));
+    } else {
+        print_report(&report);
+    }
+
+    Ok(())
+}
+
+// `LocalScore` flattened, CSV has no nested columns
+#[derive(Serialize)]
+struct CsvRow<'a> {
+    beatmap_id: Option<u32>,
+    artist: &'a str,
+    title: &'a str,
+    version: &'a str,
+    player: &'a str,
+    mode: &'a str,
+    mods: &'a str,
+    score: u32,
+    combo: Option<u32>,
+    misses: u32,
+    online_id: Option<u64>,
+    pp: Option<f64>,
+    error: Option<&'a str>,
+}
+
+impl<'a> From<&'a LocalScore> for CsvRow<'a> {
+    fn from(score: &'a LocalScore) -> Self {
+        Self {
+            beatmap_id: score.beatmap.beatmap_id,
+            artist: &score.beatmap.artist,
+            title: &score.beatmap.title,
+            version: &score.beatmap.version,
+            player: &score.player,
+            mode: score.mode,
+            mods: &score.mods,
+            score: score.score,
+            combo: score.play.combo,
+            misses: score.play.misses(),
+            online_id: score.online_id,
+            pp: score.pp,
+            error: score.error.as_deref(),
+        }
+    }
+}
+
+/// Picks one of the local beatmaps that have scores and returns its md5.
+fn read_beatmap(scores: &[DbScore], library: &HashMap<String, LocalMap>) -> Result<String> {
+    let mut counts: HashMap<&str, usize> = HashMap::new();
+
+    for score in scores {
+        *counts.entry(&score.beatmap_md5).or_default() += 1;
+    }
+
+    let mut maps: Vec<_> = counts
+        .into_iter()
+        .filter_map(|(md5, count)| library.get(md5).map(|local| (local, count)))
+        .collect();
+
+    if maps.is_empty() {
+        return Err(PpifyError::BadInput(
+            "no local scores on beatmaps in the Songs folder".to_owned(),
+        )
+        .into());
+    }
+
+    maps.sort_by_key(|(local, _)| local.metadata.to_string().to_lowercase());
+
+    let labels: Vec<_> = maps
+        .iter()
+        .map(|(local, count)| format!("{} ({count} scores)", local.metadata))
+        .collect();
+
+    let mut select = Select::new("Beatmap").filterable(true);
+
+    for (i, label) in labels.iter().enumerate() {
+        select = select.option(DemandOption::new(i).label(label));
+    }
+
+    let picked = select.run().context("failed to read beatmap")?;
+
+    Ok(maps[picked].0.md5.clone())
+}
+
+fn calculate_score(score: &DbScore, local: &LocalMap, play: &PlayInputs) -> Result<f64> {
+    let (api_mode, pp_mode) = modes_for(GameMode::from(score.mode));
+
+    let bytes = fs::read(&local.path)
+        .with_context(|| format!("failed to read {}", local.path.display()))?;
+    let map = calc::parse_beatmap(&bytes, pp_mode)?;
+    let game_mods = mods::stable_score_mods(score.mods, api_mode);
+
+    Ok(calc::calculate_play(&map, pp_mode, &game_mods, None, Rules::Stable, play).pp())
+}
+
+fn print_report(report: &LocalScoresReport) {
+    println!();
+    println!("        pp       score  combo   mods      player           beatmap");
+
+    for score in &report.scores {
+        let combo = score
+            .play
+            .combo
+            .map_or_else(String::new, |combo| format!("{combo}x"));
+
+        let pp = score
+            .pp
+            .map_or_else(|| "-".to_owned(), |pp| format!("{pp:.2}"));
+
+        print!(
+            "  {pp:>8}  {:>10}  {combo:>5}   {:<8}  {:<15}  {}",
+            score.score, score.mods, score.player, score.beatmap
+        );
+
+        match &score.error {
+            Some(err) => println!(": {err}"),
+            None => println!(),
+        }
+    }
+
+    if report.missing > 0 {
+        println!();
+        println!(
+            "{} scores are on beatmaps that aren't in the Songs folder.",
+            report.missing
+        );
+    }
+}
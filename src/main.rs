@@ -1,44 +1,82 @@
+mod batch;
+mod card;
+mod cli;
+mod clipboard;
+mod collection;
+mod compare;
+#[cfg(feature = "discord")]
+mod discord;
+mod if_fc;
+mod local_scores;
+mod markdown;
+mod matrix;
+mod now_playing;
+mod pipe;
+mod plot;
+#[cfg(feature = "rich-presence")]
+mod presence;
+mod progress;
+mod recent;
+mod serve;
+mod session;
+mod style;
+mod target;
+mod tracker;
+mod tui;
+mod watch;
+
 use {
+    crate::{
+        cli::{
+            AuthAction, Cli, Command, ConfigAction, HistoryAction, ModeArg, ProfileAction,
+            ScoreArgs,
+        },
+        target::Goal,
+    },
+    clap::{CommandFactory, Parser},
     color_eyre::{
         Result,
         eyre::{self, Context},
     },
     demand::{DemandOption, Input, MultiSelect, Select},
     dotenvy::dotenv,
-    rosu_pp::{Beatmap as PpBeatmap, Performance, model::mode::GameMode as PpGameMode},
-    rosu_v2::prelude::*,
-    std::{env, fmt::Display},
-};
-
-#[derive(Clone, Copy, Debug)]
-enum DetailedJudgements {
-    Osu {
-        n300: u32,
-        n100: u32,
-        n50: u32,
-        misses: u32,
-    },
-    Taiko {
-        n300: u32,
-        n100: u32,
-        misses: u32,
-    },
-    Catch {
-        fruits: u32,
-        droplets: u32,
-        tiny_droplets: u32,
-        tiny_droplet_misses: u32,
-        misses: u32,
+    futures_util::TryFutureExt,
+    ppify::{
+        PpifyError,
+        api::{self, Client},
+        cache::{MapCache, RankingsCache, ScoreCache, TokenCache, UserCache},
+        calc::{
+            self, AccuracyRow, BeatmapMetadata, BonusPp, BuiltinWeighting, DetailedJudgements,
+            DifficultySummary, FailAt, GainProjection, Judgements, MapSettings, MissRow,
+            ModComboRow, ModdedSettings, PROGRESSION_PERCENTS, PlayInputs, PpBreakdown,
+            ProgressionPoint, RoundTrip, Rules, SkillDifficulty, StrainGraph, StrainSpike,
+            TOP_PLAYS_CAP, TopPlay,
+        },
+        config::{Config, Profile},
+        credentials, export,
+        history::{History, HistoryEntry},
+        input::{self, MapSource},
+        library::{self, OszDifficulty},
+        mods::{self, ModOptionDef, ModSettings},
+        ranking::{RankChange, RankEstimator},
+        ratelimit,
+        report::{BeatReport, CalculationReport, ModEffect, ScoreRecomputation},
+        retry,
     },
-    Mania {
-        n320: u32,
-        n300: u32,
-        n200: u32,
-        n100: u32,
-        n50: u32,
-        misses: u32,
+    rosu_pp::{Beatmap as PpBeatmap, model::mode::GameMode as PpGameMode},
+    rosu_v2::prelude::*,
+    std::{
+        env,
+        fmt::Display,
+        fs,
+        io::{self, IsTerminal},
+        iter,
+        path::{Path, PathBuf},
+        process::ExitCode,
+        time::Duration,
     },
-}
+    tracing_subscriber::EnvFilter,
+};
 
 #[derive(Clone, Copy, Debug)]
 enum ScoreInputMode {
@@ -56,114 +94,2477 @@ impl Display for ScoreInputMode {
 }
 
 #[tokio::main]
-async fn main() -> Result<()> {
+async fn main() -> ExitCode {
+    match run().await {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(report) => {
+            let code = report
+                .chain()
+                .find_map(|err| err.downcast_ref::<PpifyError>())
+                .map_or(ExitCode::FAILURE, PpifyError::exit_code);
+
+            eprintln!("Error: {report:?}");
+
+            code
+        }
+    }
+}
+
+async fn run() -> Result<()> {
+    let mut cli = Cli::parse();
+
     dotenv().ok();
+    init_logging(&cli);
+
+    let config_path = Config::default_path();
+
+    match &cli.command {
+        Some(Command::Completions { shell }) => {
+            clap_complete::generate(*shell, &mut Cli::command(), "ppify", &mut io::stdout());
+            return Ok(());
+        }
+        Some(Command::Config { action }) => {
+            return config(action.as_ref(), config_path.as_deref());
+        }
+        Some(Command::Profile { action }) => {
+            return profile(action, cli.mode, config_path.as_deref());
+        }
+        _ => {}
+    }
+
+    if let Some(path) = &config_path {
+        let config = Config::load(path)?;
+
+        cli.apply_config(config)?;
+    }
+
+    // a past calculation runs like any other with the flags it was recorded with
+    if let Some(Command::History {
+        action: Some(HistoryAction::Rerun { id }),
+    }) = &cli.command
+    {
+        let id = *id;
+        rerun(&mut cli, id)?;
+    }
+
+    style::init(&cli);
+
+    if let Some(path) = &cli.watch {
+        return watch::run(&cli, path);
+    }
+
+    // none of these need the osu! api
+    match &cli.command {
+        Some(Command::Auth { action }) => return auth(action, &cli.config).await,
+        Some(Command::Collection { name }) => return collection::run(&cli, name.as_deref()),
+        Some(Command::LocalScores { all }) => return local_scores::run(&cli, *all),
+        Some(Command::History { action }) => return history(action.as_ref(), &cli),
+        Some(Command::Diff { user }) => return tracker::diff(&cli, user.as_deref()),
+        Some(Command::NowPlaying { source, url }) => {
+            let source = source.or(cli.config.now_playing).unwrap_or_default();
+            let url = url.as_deref().unwrap_or(source.default_url());
+
+            // beatmap downloads need no token
+            let osu = client(api::connect_offline().await?, &cli);
+
+            return now_playing::run(&osu, &cli, source, url).await;
+        }
+        _ => {}
+    }
+
+    let osu = if cli.offline {
+        api::connect_offline().await?
+    } else {
+        let (client_id, _) = read_client_id(&cli.config)?;
+        let (client_secret, _) = read_client_secret(&cli.config)?;
+
+        api::connect(client_id, client_secret, token_cache(&cli).as_ref()).await?
+    };
+    let osu = client(osu, &cli);
+
+    if let Some(path) = &cli.batch_json {
+        return batch::run_json(&osu, &cli, path).await;
+    }
+
+    if let Some(path) = &cli.matrix {
+        return matrix::run(&osu, &cli, path).await;
+    }
+
+    match &cli.command {
+        Some(Command::IfFc) => return if_fc::run(&osu, &cli).await,
+        Some(Command::Recent) => return recent::run(&osu, &cli).await,
+        Some(Command::Session) => return session::run(&osu, &cli).await,
+        Some(Command::Batch { input, output }) => {
+            let output = output.as_deref().or(cli.out.as_deref());
+
+            return batch::run(&osu, &cli, input, output).await;
+        }
+        Some(Command::Target {
+            total,
+            target_rank,
+            position,
+        }) => {
+            let goal = match (total, target_rank, position) {
+                (_, Some(rank), _) => Goal::Rank(*rank),
+                (_, _, Some(position)) => Goal::Position(*position),
+                (Some(total), None, None) => Goal::Total(*total),
+                (None, None, None) => {
+                    unreachable!("clap requires a total without --target-rank or --position")
+                }
+            };
+
+            return target::run(&osu, &cli, goal).await;
+        }
+        Some(Command::Overtake { rival }) => {
+            return target::run(&osu, &cli, Goal::Overtake(rival)).await;
+        }
+        Some(Command::Compare { user_a, user_b }) => {
+            return compare::run(&osu, &cli, user_a, user_b).await;
+        }
+        Some(Command::Snapshot { user }) => {
+            return tracker::snapshot(&osu, &cli, user.as_deref()).await;
+        }
+        Some(Command::Serve { port }) => return serve::run(osu, &cli, *port).await,
+        #[cfg(feature = "discord")]
+        Some(Command::Discord) => return discord::run(osu, &cli).await,
+        Some(
+            Command::Auth { .. }
+            | Command::Collection { .. }
+            | Command::Completions { .. }
+            | Command::Config { .. }
+            | Command::Diff { .. }
+            | Command::History { .. }
+            | Command::LocalScores { .. }
+            | Command::Profile { .. }
+            | Command::NowPlaying { .. },
+        )
+        | None => {}
+    }
+
+    if cli.stdin {
+        return pipe::run(osu, &cli).await;
+    }
+
+    if let Some(score_ref) = cli.score_ref.as_deref() {
+        return recompute_score(&osu, score_ref, &cli).await;
+    }
+
+    if cli.out.is_some() {
+        check_out_table(&cli)?;
+    }
+
+    // --beat compares with a known value, the profile isn't needed
+    let username = match cli.beat {
+        Some(_) => String::new(),
+        None => read_username(cli.user.as_deref())?,
+    };
+
+    let map_source = resolve_map_source(&osu, &cli).await?;
+
+    let known_modes = match (cli.mode, map_source.mode()) {
+        (Some(mode), _) => Some(mode.modes()),
+        (None, Some(mode)) => {
+            eprintln!("Using {} from the beatmap", GM::from(modes_for(mode)));
+
+            Some(modes_for(mode))
+        }
+        (None, None) => None,
+    };
+
+    // the profile only needs the mode, so it downloads alongside the map when that is known
+    let (map_bytes, profile) = match known_modes {
+        Some((api_mode, _)) if cli.beat.is_none() => {
+            let (map_bytes, profile) = tokio::try_join!(
+                read_map_bytes(&osu, &map_source, &cli),
+                fetch_profile(&osu, &cli, &username, api_mode)
+            )?;
+
+            (map_bytes, Some(profile))
+        }
+        _ => (read_map_bytes(&osu, &map_source, &cli).await?, None),
+    };
+
+    let (api_mode, pp_mode) = match known_modes {
+        Some(modes) => modes,
+        None => read_mode_for_map(&calc::parse_native_beatmap(&map_bytes)?)?,
+    };
+
+    let map = calc::parse_beatmap(&map_bytes, pp_mode)?;
+
+    // the tui has its own inputs for mods and the play, so it starts from NoMod and an SS
+    let selected_mods = match cli.mods.as_deref() {
+        Some(acronyms) => mods::parse_acronyms(acronyms, api_mode)?,
+        None if cli.tui => Vec::new(),
+        None => read_mods_for_map(api_mode, &map)?,
+    };
+
+    if selected_mods.is_empty() && !cli.tui {
+        eprintln!("No mods selected — computing NoMod");
+    }
+
+    let mod_settings = if cli.mods.is_some() || cli.tui {
+        cli.mod_settings()
+    } else {
+        read_mod_settings(&selected_mods, api_mode, cli.mod_settings())?
+    };
+
+    let clock_rate = checked_clock_rate(&cli)?;
+
+    let ignored_mods = mods::parse_acronyms(cli.ignore_mod.as_deref().unwrap_or(""), api_mode)?;
+
+    for ignored in &ignored_mods {
+        if !selected_mods.iter().any(|m| m.acronym == ignored.acronym) {
+            eprintln!(
+                "Warning: {} isn't selected, ignoring it changes nothing",
+                ignored.acronym
+            );
+        }
+    }
+
+    // settings are checked against the selection, ignoring DT drops its speed change too
+    let game_mods = mods::without(
+        mods::apply_settings(
+            mods::lazer_mods(selected_mods.iter().copied(), api_mode),
+            api_mode,
+            &mod_settings,
+        )?,
+        &ignored_mods,
+    );
+
+    let (judgements, combo_input) = if cli.score.is_specified() {
+        score_inputs_from_args(&cli.score, api_mode)?
+    } else if cli.tui {
+        let ss = Judgements::Accuracy {
+            accuracy: 100.0,
+            misses: 0,
+        };
+
+        (ss, None)
+    } else {
+        read_score_inputs(api_mode, &cli.score)?
+    };
+
+    if let Some(beat) = cli.beat {
+        let rules = Rules::new(cli.stable);
+        let play = PlayInputs {
+            judgements,
+            combo: resolve_combo(combo_input, &map, pp_mode, &game_mods, rules),
+        };
+        let play = if cli.score.assume_fc {
+            play.full_combo()
+        } else {
+            play
+        };
+
+        let pp = calc::calculate_play(&map, pp_mode, &game_mods, clock_rate, rules, &play).pp();
+        let report = BeatReport {
+            pp,
+            beat,
+            difference: pp - beat,
+            beats: pp > beat,
+        };
+
+        if cli.json {
+            let json =
+                serde_json::to_string_pretty(&report).context("failed to serialize report")?;
+            println!("{json}");
+        } else {
+            print_beat_report(&report);
+        }
+
+        return Ok(());
+    }
+
+    let (scores, user) = match profile {
+        Some(profile) => profile,
+        None => fetch_profile(&osu, &cli, &username, api_mode).await?,
+    };
+
+    let mut calculation = Calculation {
+        api_mode,
+        pp_mode,
+        map_source,
+        map_bytes,
+        map,
+        selected_mods,
+        ignored_mods,
+        mod_settings,
+        game_mods,
+        clock_rate,
+        rules: Rules::new(cli.stable),
+        weighting: cli.weighting(),
+        judgements,
+        combo_input,
+        scores,
+        user,
+    };
+
+    if cli.tui {
+        return tui::run(calculation, &cli);
+    }
+
+    #[cfg(feature = "rich-presence")]
+    let mut presence = rich_presence(&cli);
+
+    loop {
+        let mut report = calculation.report(&cli)?;
+
+        if !cli.no_rank {
+            report.global_rank = calculation
+                .estimate_rank(&osu, &cli, &report.profile, false)
+                .await;
+            report.country_rank = calculation
+                .estimate_rank(&osu, &cli, &report.profile, true)
+                .await;
+        }
+
+        if cli.json {
+            let json =
+                serde_json::to_string_pretty(&report).context("failed to serialize report")?;
+
+            if cli.clipboard {
+                clipboard::copy(&format!("{json}\n"));
+            } else {
+                println!("{json}");
+            }
+        } else if cli.markdown() {
+            let markdown = markdown::calculation(&report);
+
+            if cli.clipboard {
+                clipboard::copy(&markdown);
+            } else {
+                print!("{markdown}");
+            }
+        } else {
+            print_report(&report, cli.top_n_diff.unwrap_or(TOP_N_DIFF));
+
+            if cli.clipboard {
+                clipboard::copy(&clipboard::summary(&report));
+            }
+        }
+
+        #[cfg(feature = "rich-presence")]
+        if let Some(presence) = &mut presence {
+            let mods = if report.mods.is_empty() {
+                String::new()
+            } else {
+                format!("+{} ", report.mods.concat())
+            };
+
+            presence.show(
+                &report.beatmap.to_string(),
+                &format!(
+                    "{mods}{:.0}pp, {:+.1}pp to the profile",
+                    report.pp, report.profile.gain
+                ),
+            );
+        }
+
+        if let Some(path) = &cli.plot {
+            calculation.plot(path, &report)?;
+            eprintln!("Plot written to {}", path.display());
+        }
+
+        if let Some(path) = &cli.card {
+            card::write(path, &calculation.user.username, &report)?;
+            eprintln!("Card written to {}", path.display());
+        }
+
+        if let Some(path) = &cli.out {
+            write_report_table(path, &report)?;
+            eprintln!("Table written to {}", path.display());
+        }
+
+        if cli.config.history != Some(false) {
+            calculation.record(&report);
+        }
+
+        // scripts and pipes get a single result
+        if cli.json || cli.markdown() || !io::stdin().is_terminal() {
+            if cli.print_command {
+                calculation.print_command(&report);
+            }
+
+            return Ok(());
+        }
+
+        loop {
+            let step = read_next_step();
+
+            if let NextStep::Quit = step {
+                if cli.print_command {
+                    calculation.print_command(&report);
+                }
+
+                return Ok(());
+            }
+
+            // a bad value only discards that change, everything else is kept
+            match calculation.change(step, &osu, &cli).await {
+                Ok(()) => break,
+                Err(err) => eprintln!("Error: {err:?}"),
+            }
+        }
+    }
+}
+
+/// The inputs of a single calculation, kept in memory so one of them can be changed
+/// without downloading and parsing everything again.
+struct Calculation {
+    api_mode: GameMode,
+    pp_mode: PpGameMode,
+    map_source: MapSource,
+    map_bytes: Vec<u8>,
+    map: PpBeatmap,
+    selected_mods: Vec<&'static ModOptionDef>,
+    /// Left out of `game_mods`, see `--ignore-mod`.
+    ignored_mods: Vec<&'static ModOptionDef>,
+    mod_settings: ModSettings,
+    game_mods: GameMods,
+    clock_rate: Option<f64>,
+    rules: Rules,
+    weighting: BuiltinWeighting,
+    judgements: Judgements,
+    combo_input: Option<ComboInput>,
+    scores: Vec<Score>,
+    user: UserExtended,
+}
+
+impl Calculation {
+    fn report(&self, cli: &Cli) -> Result<CalculationReport> {
+        let (map, pp_mode, game_mods, clock_rate, rules) = (
+            &self.map,
+            self.pp_mode,
+            &self.game_mods,
+            self.clock_rate,
+            self.rules,
+        );
+
+        let metadata = BeatmapMetadata::from_osu_bytes(&self.map_bytes);
+        let map_id = match self.map_source {
+            MapSource::Id { map_id, .. } => Some(map_id),
+            MapSource::Set(_)
+            | MapSource::File(_)
+            | MapSource::Archive { .. }
+            | MapSource::Search(_) => metadata.beatmap_id,
+        };
+
+        let entered_play = PlayInputs {
+            judgements: self.judgements,
+            combo: resolve_combo(self.combo_input, map, pp_mode, game_mods, rules),
+        };
+        let play = if cli.score.assume_fc {
+            entered_play.full_combo()
+        } else {
+            entered_play
+        };
+
+        let perf_attrs = calc::calculate_play(map, pp_mode, game_mods, clock_rate, rules, &play);
+        let new_play_pp = perf_attrs.pp();
+
+        let mod_matrix = match &cli.compare_mods {
+            Some(combos) => {
+                let combos = mod_combinations(combos, self.api_mode)?;
+
+                Some(calc::mod_matrix(
+                    map, pp_mode, &combos, clock_rate, rules, &play,
+                ))
+            }
+            None => None,
+        };
+
+        let accuracy_table = if cli.acc_table {
+            let accuracies = match &cli.sweep_points {
+                Some(points) => calc::check_sweep_points(points)?,
+                None => calc::sweep_points(pp_mode).to_vec(),
+            };
+
+            Some(calc::accuracy_table(
+                &perf_attrs,
+                game_mods,
+                clock_rate,
+                rules,
+                &play,
+                &accuracies,
+            ))
+        } else {
+            None
+        };
+
+        let progression = match &cli.progression {
+            Some(percents) => {
+                let percents = if percents.is_empty() {
+                    &PROGRESSION_PERCENTS[..]
+                } else {
+                    percents
+                };
+
+                Some(calc::pp_progression(
+                    map, pp_mode, game_mods, clock_rate, rules, &play, percents,
+                )?)
+            }
+            None => None,
+        };
+
+        let failed_at = match cli.fail_at.as_deref() {
+            Some(raw) => Some(calc::pp_at_fail(
+                map,
+                pp_mode,
+                game_mods,
+                clock_rate,
+                rules,
+                &play,
+                parse_fail_at(raw)?,
+            )?),
+            None => None,
+        };
+
+        let strain_graph = (cli.strain_graph || cli.spikes.is_some())
+            .then(|| calc::strain_graph(map, pp_mode, game_mods, clock_rate, rules))
+            .transpose()?;
+        let spikes = cli
+            .spikes
+            .zip(strain_graph.as_ref())
+            .map(|(count, graph)| graph.spikes(count));
+        let strain_graph = strain_graph.filter(|_| cli.strain_graph);
+
+        let bonus = BonusPp::for_user(&self.user, &self.scores, &[map_id]);
+        let settings = ModdedSettings::new(map, pp_mode, game_mods, clock_rate)?;
+
+        Ok(CalculationReport {
+            map_id,
+            local_file: matches!(
+                self.map_source,
+                MapSource::File(_) | MapSource::Archive { .. }
+            ),
+            beatmap: metadata,
+            settings,
+            mode: self.api_mode.as_str(),
+            mods: self.selected_mods.iter().map(|m| m.acronym).collect(),
+            ignored_mods: self.ignored_mods.iter().map(|m| m.acronym).collect(),
+            mod_effects: cli.explain_mods.then(|| {
+                // a custom rate replaces the one of the mods
+                let rate = clock_rate.unwrap_or(if settings.raw.bpm > 0.0 {
+                    settings.modded.bpm / settings.raw.bpm
+                } else {
+                    1.0
+                });
+
+                self.selected_mods
+                    .iter()
+                    .map(|m| ModEffect {
+                        acronym: m.acronym,
+                        effect: m.pp_effect.describe(rate),
+                    })
+                    .collect()
+            }),
+            mod_settings: self.mod_settings,
+            clock_rate,
+            play,
+            entered_play: cli.score.assume_fc.then_some(entered_play),
+            rules: rules.for_mods(game_mods).name(),
+            pp: new_play_pp,
+            pp_breakdown: PpBreakdown::from_performance(&perf_attrs),
+            difficulty: DifficultySummary::from_performance(&perf_attrs),
+            flashlight: cli
+                .fl_breakdown
+                .then(|| {
+                    calc::flashlight_contribution(&perf_attrs, game_mods, clock_rate, rules, &play)
+                })
+                .flatten(),
+            accuracy_formula: match play.judgements {
+                Judgements::Detailed(detailed) if cli.explain => Some(detailed.accuracy_formula()),
+                Judgements::Detailed(_) | Judgements::Accuracy { .. } => None,
+            },
+            accuracy_table,
+            miss_table: cli
+                .miss_table
+                .then(|| calc::miss_table(&perf_attrs, game_mods, clock_rate, rules, &play)),
+            mod_matrix,
+            progression,
+            failed_at,
+            strain_graph,
+            spikes,
+            profile: calc::project_gain(
+                &self.scores,
+                new_play_pp,
+                "Hypothetical play",
+                map_id,
+                bonus,
+                &self.weighting,
+            ),
+            global_rank: None,
+            country_code: Some(self.user.country_code.to_string()),
+            country_rank: None,
+            round_trip: map_id.filter(|_| cli.round_trip).and_then(|map_id| {
+                calc::round_trip(&play, &self.scores, map_id, game_mods, new_play_pp)
+            }),
+        })
+    }
+
+    fn plot(&self, path: &Path, report: &CalculationReport) -> Result<()> {
+        let graph = match &report.strain_graph {
+            Some(graph) => graph.clone(),
+            None => calc::strain_graph(
+                &self.map,
+                self.pp_mode,
+                &self.game_mods,
+                self.clock_rate,
+                self.rules,
+            )?,
+        };
+
+        let attrs = calc::calculate_play(
+            &self.map,
+            self.pp_mode,
+            &self.game_mods,
+            self.clock_rate,
+            self.rules,
+            &report.play,
+        );
+        let curve = calc::accuracy_curve(
+            &attrs,
+            &self.game_mods,
+            self.clock_rate,
+            self.rules,
+            &report.play,
+            90.0,
+            0.25,
+        );
+
+        plot::write(path, &report.beatmap.to_string(), &graph, &curve)
+    }
+
+    /// Saves the calculation for `ppify history`, a failure only costs the entry.
+    fn record(&self, report: &CalculationReport) {
+        let args = self.history_args(report);
+        let recorded = open_history().and_then(|history| {
+            history
+                .record(&self.user.username, report, &args)
+                .context("failed to write the history")
+        });
+
+        if let Err(err) = recorded {
+            eprintln!("Warning: calculation not saved to the history: {err:#}");
+        }
+    }
+
+    /// Prints the `ppify` command of `report` to stderr, which keeps the output of scripts
+    /// parseable.
+    fn print_command(&self, report: &CalculationReport) {
+        let args: Vec<String> = self
+            .history_args(report)
+            .iter()
+            .map(String::as_str)
+            .map(shell_quote)
+            .collect();
+
+        eprintln!();
+        eprintln!("{}", style::heading("Reproduce with:"));
+        eprintln!("ppify {}", args.join(" "));
+    }
+
+    /// The flags that calculate the play of `report` again, see `ppify history rerun`.
+    fn history_args(&self, report: &CalculationReport) -> Vec<String> {
+        let map = match &self.map_source {
+            MapSource::Id { map_id, .. } => map_id.to_string(),
+            // reruns may start in another directory
+            MapSource::File(path) | MapSource::Archive { path, .. } => fs::canonicalize(path)
+                .unwrap_or_else(|_| path.clone())
+                .display()
+                .to_string(),
+            MapSource::Set(_) | MapSource::Search(_) => {
+                unreachable!("beatmapsets are resolved to a difficulty by resolve_map_source")
+            }
+        };
+
+        let mods = if report.mods.is_empty() {
+            "NM".to_owned()
+        } else {
+            report.mods.concat()
+        };
+
+        let mut args = Vec::new();
+        let mut flag = |name: &str, value: String| {
+            args.push(format!("--{name}"));
+            args.push(value);
+        };
+
+        flag("user", self.user.username.to_string());
+        flag("mode", report.mode.to_owned());
+        flag("map", map);
+
+        if let MapSource::Archive { .. } = self.map_source {
+            flag("diff-name", report.beatmap.version.clone());
+        }
+
+        flag("mods", mods);
+
+        if !report.ignored_mods.is_empty() {
+            flag("ignore-mod", report.ignored_mods.concat());
+        }
+
+        let settings = &report.mod_settings;
+        let mod_flags = [
+            ("speed", settings.speed_change),
+            ("da-ar", settings.ar),
+            ("da-od", settings.od),
+            ("da-cs", settings.cs),
+            ("da-hp", settings.hp),
+        ];
+
+        for (name, value) in mod_flags {
+            if let Some(value) = value {
+                flag(name, value.to_string());
+            }
+        }
+
+        if let Some(rate) = report.clock_rate {
+            flag("rate", rate.to_string());
+        }
+
+        let counts: &[(&str, u32)] = match report.play.judgements {
+            Judgements::Accuracy { accuracy, misses } => {
+                flag("acc", accuracy.to_string());
+
+                &[("misses", misses)]
+            }
+            Judgements::Detailed(DetailedJudgements::Osu {
+                n300,
+                n100,
+                n50,
+                misses,
+                slider_end_hits,
+                large_tick_hits,
+            }) => {
+                if let Some(hits) = slider_end_hits {
+                    flag("slider-ends", hits.to_string());
+                }
+
+                if let Some(hits) = large_tick_hits {
+                    flag("large-ticks", hits.to_string());
+                }
+
+                &[
+                    ("n300", n300),
+                    ("n100", n100),
+                    ("n50", n50),
+                    ("misses", misses),
+                ]
+            }
+            Judgements::Detailed(DetailedJudgements::Taiko { n300, n100, misses }) => {
+                &[("n300", n300), ("n100", n100), ("misses", misses)]
+            }
+            Judgements::Detailed(DetailedJudgements::Catch {
+                fruits,
+                droplets,
+                tiny_droplets,
+                tiny_droplet_misses,
+                misses,
+            }) => &[
+                ("fruits", fruits),
+                ("droplets", droplets),
+                ("tiny-droplets", tiny_droplets),
+                ("tiny-droplet-misses", tiny_droplet_misses),
+                ("misses", misses),
+            ],
+            Judgements::Detailed(DetailedJudgements::Mania {
+                n320,
+                n300,
+                n200,
+                n100,
+                n50,
+                misses,
+            }) => &[
+                ("n320", n320),
+                ("n300", n300),
+                ("n200", n200),
+                ("n100", n100),
+                ("n50", n50),
+                ("misses", misses),
+            ],
+        };
+
+        for (name, count) in counts {
+            flag(name, count.to_string());
+        }
+
+        if let Some(combo) = report.play.combo {
+            flag("combo", combo.to_string());
+        }
+
+        if report.rules == "stable" {
+            args.push("--stable".to_owned());
+        }
+
+        args
+    }
+
+    /// The global or country rank change of `profile`.
+    ///
+    /// `None` if the rankings can't be fetched, the rank is only an extra.
+    async fn estimate_rank(
+        &self,
+        osu: &Client,
+        cli: &Cli,
+        profile: &GainProjection,
+        country: bool,
+    ) -> Option<RankChange> {
+        let stats = self.user.statistics.as_ref()?;
+        let cache = rankings_cache(cli);
+        let (mut estimator, rank, what) = if country {
+            let estimator = RankEstimator::new(osu, self.api_mode)
+                .country(self.user.country_code.to_string())
+                .cache(cache.as_ref());
+
+            (estimator, stats.country_rank, "country")
+        } else {
+            (
+                RankEstimator::new(osu, self.api_mode).cache(cache.as_ref()),
+                stats.global_rank,
+                "global",
+            )
+        };
+
+        let anchor = rank.map(|rank| (f64::from(stats.pp), rank));
+        let change = estimator
+            .rank_change(profile.old_total_pp, profile.new_total_pp, anchor)
+            .await;
+
+        change.unwrap_or_else(|err| {
+            // offline the rank is only there when the rankings were cached before
+            if !osu.offline {
+                eprintln!("Warning: couldn't estimate the {what} rank: {err}");
+            }
+
+            None
+        })
+    }
+
+    /// Prompts for the new value of `step`; nothing changes if that fails.
+    async fn change(&mut self, step: NextStep, osu: &Client, cli: &Cli) -> Result<()> {
+        match step {
+            NextStep::Accuracy => {
+                (self.judgements, self.combo_input) =
+                    read_score_inputs(self.api_mode, &ScoreArgs::default())?;
+            }
+            NextStep::Mods => {
+                let selected_mods = read_mods_for_map(self.api_mode, &self.map)?;
+
+                if selected_mods.is_empty() {
+                    eprintln!("No mods selected — computing NoMod");
+                }
+
+                let mod_settings =
+                    read_mod_settings(&selected_mods, self.api_mode, ModSettings::default())?;
+                let game_mods = mods::without(
+                    mods::apply_settings(
+                        mods::lazer_mods(selected_mods.iter().copied(), self.api_mode),
+                        self.api_mode,
+                        &mod_settings,
+                    )?,
+                    &self.ignored_mods,
+                );
+
+                self.selected_mods = selected_mods;
+                self.mod_settings = mod_settings;
+                self.game_mods = game_mods;
+            }
+            NextStep::Map => {
+                let source = if cli.local {
+                    read_local_map(cli)?
+                } else {
+                    let source =
+                        pick_difficulty(osu, read_map_source()?, Some(self.api_mode)).await?;
+
+                    // --diff-name named a difficulty of the first archive, not of this one
+                    read_archive_difficulty(source, None, Some(self.api_mode))?
+                };
+                let bytes = read_map_bytes(osu, &source, cli).await?;
+
+                self.map = calc::parse_beatmap(&bytes, self.pp_mode)?;
+                self.map_bytes = bytes;
+                self.map_source = source;
+            }
+            NextStep::User => {
+                let username = read_username(None)?;
+
+                (self.scores, self.user) =
+                    fetch_profile(osu, cli, &username, self.api_mode).await?;
+            }
+            NextStep::Quit => {}
+        }
+
+        Ok(())
+    }
+}
+
+#[derive(Clone, Copy, Debug)]
+enum NextStep {
+    Accuracy,
+    Mods,
+    Map,
+    User,
+    Quit,
+}
+
+fn read_next_step() -> NextStep {
+    let select = Select::new("What next?")
+        .description("Change one input and recalculate, everything else is kept")
+        .option(DemandOption::new(NextStep::Accuracy).label("Change accuracy"))
+        .option(DemandOption::new(NextStep::Mods).label("Change mods"))
+        .option(DemandOption::new(NextStep::Map).label("Change map"))
+        .option(DemandOption::new(NextStep::User).label("Change user"))
+        .option(DemandOption::new(NextStep::Quit).label("Quit"));
+
+    select.run().unwrap_or(NextStep::Quit)
+}
+
+/// Turns `--map`/`--set` or the beatmap prompt into a single difficulty or local file.
+async fn resolve_map_source(osu: &Client, cli: &Cli) -> Result<MapSource> {
+    let map_source = match (cli.map.as_deref(), cli.set) {
+        (Some(raw), _) => MapSource::parse(raw)?,
+        (None, Some(set_id)) => MapSource::Set(set_id),
+        (None, None) if cli.local => read_local_map(cli)?,
+        (None, None) => read_map_source()?,
+    };
+
+    let mode = cli.mode.map(|mode| mode.modes().0);
+    let source = pick_difficulty(osu, map_source, mode).await?;
+
+    read_archive_difficulty(source, cli.diff_name.as_deref(), mode)
+}
+
+/// Turns a `.osz` file into one of its difficulties, other sources pass through.
+fn read_archive_difficulty(
+    source: MapSource,
+    diff_name: Option<&str>,
+    mode: Option<GameMode>,
+) -> Result<MapSource> {
+    match source {
+        MapSource::File(path)
+            if path
+                .extension()
+                .is_some_and(|ext| ext.eq_ignore_ascii_case("osz")) =>
+        {
+            read_osz_difficulty(path, diff_name, mode)
+        }
+        source => Ok(source),
+    }
+}
+
+/// Lets the user pick a difficulty for beatmapsets and searches, other sources pass through.
+async fn pick_difficulty(
+    osu: &Client,
+    source: MapSource,
+    mode: Option<GameMode>,
+) -> Result<MapSource> {
+    let map_source = match source {
+        MapSource::Set(set_id) => {
+            let set = api::fetch_beatmapset(osu, set_id).await?;
+
+            read_set_difficulty(set, mode)?
+        }
+        MapSource::Search(query) => {
+            let sets = api::search_beatmapsets(osu, &query, mode).await?;
+
+            read_set_difficulty(read_search_result(&query, sets)?, mode)?
+        }
+        source => source,
+    };
+
+    Ok(map_source)
+}
+
+async fn read_map_bytes(osu: &Client, source: &MapSource, cli: &Cli) -> Result<Vec<u8>> {
+    let bytes = match source {
+        MapSource::Id { map_id, .. } => {
+            let spinner = progress::spinner(format!("Loading beatmap {map_id}"));
+            let bytes =
+                api::fetch_osu_file(osu, *map_id, None, map_cache(cli).as_ref(), cli.refresh).await;
+            spinner.finish_and_clear();
+
+            bytes?
+        }
+        MapSource::Set(_) | MapSource::Search(_) => {
+            unreachable!("beatmapsets are resolved to a difficulty by resolve_map_source")
+        }
+        MapSource::File(path) => fs::read(path)
+            .with_context(|| format!("failed to read beatmap file {}", path.display()))?,
+        MapSource::Archive { path, file_name } => library::read_osz_file(path, file_name)
+            .with_context(|| format!("failed to read {file_name} from {}", path.display()))?,
+    };
+
+    Ok(bytes)
+}
 
-    let client_id = read_client_id()?;
-    let client_secret = read_client_secret()?;
+/// `--out` writes exactly one of the tables of the report.
+fn check_out_table(cli: &Cli) -> Result<()> {
+    let tables = [cli.acc_table, cli.miss_table, cli.compare_mods.is_some()];
 
-    let osu = Osu::new(client_id, client_secret)
-        .await
-        .context("failed to create osu! api v2 client")?;
+    match tables.iter().filter(|&&table| table).count() {
+        1 => Ok(()),
+        0 => Err(PpifyError::BadInput(
+            "--out needs a table, add --acc-table, --miss-table or --compare-mods".to_owned(),
+        )
+        .into()),
+        _ => Err(PpifyError::BadInput(
+            "--out writes a single table, pick one of --acc-table, --miss-table and --compare-mods"
+                .to_owned(),
+        )
+        .into()),
+    }
+}
+
+fn write_report_table(path: &Path, report: &CalculationReport) -> Result<()> {
+    let written = match (
+        &report.accuracy_table,
+        &report.miss_table,
+        &report.mod_matrix,
+    ) {
+        (Some(rows), ..) => export::write_csv(path, rows),
+        (_, Some(rows), _) => export::write_csv(path, rows),
+        (.., Some(rows)) => export::write_csv(path, rows),
+        (None, None, None) => unreachable!("checked by check_out_table"),
+    };
+
+    written.with_context(|| format!("failed to write {}", path.display()))
+}
+
+fn checked_clock_rate(cli: &Cli) -> Result<Option<f64>> {
+    if let Some(rate) = cli.rate
+        && !(0.01..=100.0).contains(&rate)
+    {
+        return Err(PpifyError::BadInput(format!(
+            "clock rate must be between 0.01 and 100, got {rate}"
+        ))
+        .into());
+    }
+
+    Ok(cli.rate)
+}
+
+// `combos` empty means the common combinations of the mode
+fn mod_combinations(combos: &[String], mode: GameMode) -> Result<Vec<(String, GameMods)>> {
+    let combos: Vec<&str> = if combos.is_empty() {
+        mods::common_combinations(mode).to_vec()
+    } else {
+        combos.iter().map(String::as_str).collect()
+    };
+
+    combos
+        .into_iter()
+        .map(|combo| -> Result<(String, GameMods)> {
+            let selected = mods::parse_acronyms(combo, mode)?;
+            let label = if selected.is_empty() {
+                "NM".to_owned()
+            } else {
+                selected.iter().map(|m| m.acronym).collect()
+            };
+
+            Ok((label, mods::lazer_mods(selected, mode)))
+        })
+        .collect()
+}
+
+fn read_username(arg: Option<&str>) -> Result<String> {
+    if let Some(user) = arg {
+        return Ok(user.to_owned());
+    }
 
-    let username = Input::new("osu! username or user id")
+    Input::new("osu! username or user id")
         .placeholder("e.g. peppy or 33138610")
         .prompt("User: ")
         .run()
-        .context("failed to read username")?;
+        .context("failed to read username")
+}
 
-    let (api_mode, pp_mode) = read_mode()?;
+/// `None` without `--rich-presence` or when Discord isn't reachable.
+#[cfg(feature = "rich-presence")]
+fn rich_presence(cli: &Cli) -> Option<presence::Presence> {
+    if !cli.rich_presence {
+        return None;
+    }
 
-    let map_id_raw = Input::new("Beatmap ID")
-        .placeholder("numeric id, e.g. 3897329")
-        .prompt("Beatmap ID: ")
-        .run()
-        .context("failed to read beatmap id")?;
+    presence::Presence::connect()
+        .inspect_err(|err| eprintln!("Warning: no Discord presence: {err:#}"))
+        .ok()
+}
 
-    let map_id: u32 = map_id_raw
-        .trim()
-        .parse()
-        .context("beatmap id must be an integer")?;
+/// `osu` with the request settings of the config and `--offline`.
+fn client(osu: Osu, cli: &Cli) -> Client {
+    Client {
+        max_retries: cli.config.retries.unwrap_or(retry::DEFAULT_MAX_RETRIES),
+        offline: cli.offline,
+        rate_limiter: ratelimit::limiter(cli.config.rate_limit, cli.config.rate_limit_burst),
+        ..Client::new(osu)
+    }
+}
 
-    let mod_bits = read_mods_for_mode(api_mode)?;
+// `cache_dir` from the config replaces the platform cache directory, here and in the
+// other caches below
+fn map_cache(cli: &Cli) -> Option<MapCache> {
+    if cli.no_cache {
+        return None;
+    }
 
-    let score_input_mode = read_score_input_mode();
+    match &cli.config.cache_dir {
+        Some(dir) => Some(MapCache::new(dir.join("maps"))),
+        None => MapCache::default_dir().map(MapCache::new),
+    }
+}
 
-    let (accuracy, combo_opt, counts_opt) = match score_input_mode {
-        ScoreInputMode::Detailed => read_detailed_judgements(api_mode)?,
-        ScoreInputMode::Simple => read_simple_score()?,
-    };
+fn token_cache(cli: &Cli) -> Option<TokenCache> {
+    if cli.no_cache {
+        return None;
+    }
 
-    let map_bytes = download_osu_file(map_id)
-        .await
-        .with_context(|| format!("failed to download .osu for beatmap {map_id}"))?;
+    match &cli.config.cache_dir {
+        Some(dir) => Some(TokenCache::new(dir.join("token.json"))),
+        None => TokenCache::default_path().map(TokenCache::new),
+    }
+}
+
+fn score_cache(cli: &Cli) -> Option<ScoreCache> {
+    if cli.no_cache {
+        return None;
+    }
+
+    let ttl = Duration::from_secs(cli.scores_ttl * 60);
+
+    match &cli.config.cache_dir {
+        Some(dir) => Some(ScoreCache::new(dir.join("scores"), ttl)),
+        None => ScoreCache::default_dir().map(|dir| ScoreCache::new(dir, ttl)),
+    }
+}
+
+// the rankings move slower than a profile, the same age is still fresh enough
+fn rankings_cache(cli: &Cli) -> Option<RankingsCache> {
+    if cli.no_cache {
+        return None;
+    }
+
+    let ttl = Duration::from_secs(cli.scores_ttl * 60);
+
+    match &cli.config.cache_dir {
+        Some(dir) => Some(RankingsCache::new(dir.join("rankings"), ttl)),
+        None => RankingsCache::default_dir().map(|dir| RankingsCache::new(dir, ttl)),
+    }
+}
+
+// profiles go stale with the top scores they're shown next to
+fn user_cache(cli: &Cli) -> Option<UserCache> {
+    if cli.no_cache {
+        return None;
+    }
 
-    let map = PpBeatmap::from_bytes(&map_bytes).context("failed to parse .osu file")?;
+    let ttl = Duration::from_secs(cli.scores_ttl * 60);
 
-    if let Err(suspicion) = map.check_suspicion() {
-        eyre::bail!("beatmap is suspicious: {suspicion:?}");
+    match &cli.config.cache_dir {
+        Some(dir) => Some(UserCache::new(dir.join("users"), ttl)),
+        None => UserCache::default_dir().map(|dir| UserCache::new(dir, ttl)),
+    }
+}
+
+fn config(action: Option<&ConfigAction>, path: Option<&Path>) -> Result<()> {
+    let path = path.ok_or_else(|| eyre::eyre!("no config directory on this platform"))?;
+
+    match action {
+        None | Some(ConfigAction::Show) => {
+            let config = Config::load(path)?;
+            let content = toml::to_string_pretty(&config).context("failed to format config")?;
+
+            println!("# {}", path.display());
+            print!("{content}");
+        }
+        Some(ConfigAction::Path) => println!("{}", path.display()),
+        Some(ConfigAction::Set { key, value }) => {
+            let mut config = Config::load(path)?;
+            config.set(key, value)?;
+            config
+                .save(path)
+                .with_context(|| format!("failed to write {}", path.display()))?;
+        }
+        Some(ConfigAction::Unset { key }) => {
+            let mut config = Config::load(path)?;
+            config.unset(key)?;
+            config
+                .save(path)
+                .with_context(|| format!("failed to write {}", path.display()))?;
+        }
     }
 
-    let mut perf = Performance::new(&map)
-        .mods(mod_bits)
-        .mode_or_ignore(pp_mode);
+    Ok(())
+}
 
-    if let Some(c) = combo_opt {
-        perf = perf.combo(c);
+async fn fetch_best_scores(
+    osu: &Client,
+    cli: &Cli,
+    user: &str,
+    mode: GameMode,
+) -> Result<Vec<Score>> {
+    let cache = score_cache(cli);
+    let spinner = progress::spinner(format!("Fetching the top scores of {}", user.trim()));
+    let scores = api::fetch_user_best_scores_cached(
+        osu,
+        user.trim(),
+        mode,
+        cache.as_ref(),
+        cli.refresh_scores,
+    )
+    .await;
+    spinner.finish_and_clear();
+
+    let mut scores = scores?;
+
+    if let Some(baseline) = cli.baseline_mods.as_deref() {
+        let baseline_mods = mods::parse_acronyms(baseline, mode)?;
+        let total = scores.len();
+
+        scores.retain(|score| {
+            mods::matches_baseline(&score.mods, &baseline_mods, cli.baseline_exact)
+        });
+
+        let acronyms: String = baseline_mods.iter().map(|m| m.acronym).collect();
+        let matching = if cli.baseline_exact {
+            "exactly"
+        } else {
+            "at least"
+        };
+
+        eprintln!(
+            "Counting {} of {total} top plays, the ones with {matching} {}",
+            scores.len(),
+            if acronyms.is_empty() { "NM" } else { &acronyms }
+        );
     }
 
-    if let Some(detailed) = counts_opt {
-        perf = apply_detailed_judgements(perf, detailed);
-    } else if let Some((acc, misses)) = accuracy {
-        perf = perf.accuracy(acc).misses(misses);
+    if cli.no_converts {
+        let total = scores.len();
+        let mut unknown = 0;
+
+        // without the map it can't be told, the play stays in
+        scores.retain(|score| match calc::is_convert(score) {
+            Some(convert) => !convert,
+            None => {
+                unknown += 1;
+                true
+            }
+        });
+
+        eprintln!(
+            "Counting {} of {total} top plays, leaving out converts",
+            scores.len()
+        );
+
+        if unknown > 0 {
+            eprintln!(
+                "Warning: {unknown} top plays don't say their map's mode and are counted anyway"
+            );
+        }
     }
 
-    let perf_attrs = perf.calculate();
-    let new_play_pp = perf_attrs.pp();
+    Ok(scores)
+}
+
+/// A user's top scores and profile, requested at the same time.
+async fn fetch_profile(
+    osu: &Client,
+    cli: &Cli,
+    user: &str,
+    mode: GameMode,
+) -> Result<(Vec<Score>, UserExtended)> {
+    let cache = user_cache(cli);
+    let user_fut =
+        api::fetch_user_cached(osu, user.trim(), mode, cache.as_ref(), cli.refresh_scores)
+            .map_err(eyre::Report::from);
+
+    tokio::try_join!(fetch_best_scores(osu, cli, user, mode), user_fut)
+}
+
+async fn recompute_score(osu: &Client, score_ref: &str, cli: &Cli) -> Result<()> {
+    let (score_id, mode) = input::parse_score_ref(score_ref)?;
+    let score = api::fetch_score(osu, score_id, mode).await?;
+
+    let (api_mode, pp_mode) = modes_for(score.mode);
+    let map_bytes = api::fetch_osu_file(
+        osu,
+        score.map_id,
+        api::map_checksum(&score),
+        map_cache(cli).as_ref(),
+        cli.refresh,
+    )
+    .await?;
+    let map = calc::parse_beatmap(&map_bytes, pp_mode)?;
+
+    let play = PlayInputs::from_score(&score);
+    let perf_attrs = calc::calculate_play(
+        &map,
+        pp_mode,
+        &score.mods,
+        None,
+        Rules::new(cli.stable),
+        &play,
+    );
+    let pp = perf_attrs.pp();
+
+    let report = ScoreRecomputation {
+        score_id,
+        map_id: score.map_id,
+        beatmap: BeatmapMetadata::from_osu_bytes(&map_bytes),
+        mode: api_mode.as_str(),
+        mods: score.mods.iter().map(|m| m.acronym().to_string()).collect(),
+        play,
+        pp,
+        pp_breakdown: PpBreakdown::from_performance(&perf_attrs),
+        difficulty: DifficultySummary::from_performance(&perf_attrs),
+        round_trip: score.pp.map(|api_pp| RoundTrip {
+            api_pp: f64::from(api_pp),
+            recomputed_pp: pp,
+            matches: 1,
+        }),
+    };
+
+    if cli.json {
+        let json = serde_json::to_string_pretty(&report).context("failed to serialize report")?;
+        println!("{json}");
+    } else {
+        print_score_recomputation(&report);
+    }
+
+    Ok(())
+}
+
+fn print_score_recomputation(report: &ScoreRecomputation) {
+    println!();
+    println!("Score {} on {}", report.score_id, report.beatmap);
+
+    if !report.mods.is_empty() {
+        println!("Mods: +{}", report.mods.concat());
+    }
+
+    println!();
+    println!("Recomputed pp: {:.2}pp", report.pp);
+    print_pp_breakdown(&report.pp_breakdown);
+
+    match &report.round_trip {
+        Some(round_trip) => {
+            let delta = round_trip.delta();
+
+            println!("API pp:        {:.2}pp", round_trip.api_pp);
+            println!(
+                "Difference:    {:+.2}pp ({:+.2}%)",
+                delta,
+                delta / round_trip.api_pp * 100.0
+            );
+        }
+        None => println!("API pp:        none (the score awards no pp)"),
+    }
+
+    println!();
+    println!("Difficulty:");
+    print_difficulty(&report.difficulty);
+}
+
+fn print_report(report: &CalculationReport, top_n: usize) {
+    println!();
+    println!("{} {}", style::heading("Beatmap:"), report.beatmap);
+    print_map_settings(&report.settings);
+
+    if !report.ignored_mods.is_empty() {
+        println!(
+            "{}",
+            style::dim(format!(
+                "(calculated without the pp effect of {}, which stay selected)",
+                report.ignored_mods.concat()
+            ))
+        );
+    }
+
+    if let Some(entered) = &report.entered_play {
+        let combo = entered
+            .combo
+            .map_or_else(|| "max combo".to_owned(), |combo| format!("{combo}x"));
+
+        println!();
+        println!(
+            "Entered play: {:.2}%, {} misses, {combo}",
+            entered.accuracy(),
+            entered.misses()
+        );
+        println!(
+            "{}",
+            style::dim("(calculated as a full combo with --assume-fc)")
+        );
+    }
+
+    if let Some(effects) = &report.mod_effects {
+        print_mod_effects(effects, &report.ignored_mods);
+    }
+
+    println!();
+    match report.clock_rate {
+        Some(rate) => println!(
+            "Hypothetical play PP: {} (at {rate}x, {} rules)",
+            style::value(format!("{:.2}pp", report.pp)),
+            report.rules
+        ),
+        None => println!(
+            "Hypothetical play PP: {} ({} rules)",
+            style::value(format!("{:.2}pp", report.pp)),
+            report.rules
+        ),
+    }
+
+    print_pp_breakdown(&report.pp_breakdown);
+    print_difficulty(&report.difficulty);
+
+    if let Some(formula) = &report.accuracy_formula {
+        println!();
+        println!("{}", style::heading("Accuracy:"));
+        println!("  {}", formula.formula);
+        println!("  = {}", formula.substituted);
+        println!("  = {}", style::value(format!("{:.2}%", formula.accuracy)));
+        println!(
+            "{}",
+            style::dim("(osu!stable's formula, which ppify uses for the accuracy of counts)")
+        );
+    }
+
+    if let Some(fl) = &report.flashlight {
+        println!();
+        println!("{}", style::heading("Flashlight contribution:"));
+        println!("  FL pp component:     {:.2}pp", fl.pp_flashlight);
+        println!("  PP without FL:       {:.2}pp", fl.pp_without_fl);
+        println!("  FL-attributable pp:  {:+.2}pp", fl.fl_attributable_pp);
+    }
+
+    if let Some(rows) = &report.accuracy_table {
+        print_accuracy_table(rows);
+    }
+
+    if let Some(rows) = &report.miss_table {
+        print_miss_table(rows, report.pp);
+    }
+
+    if let Some(rows) = &report.mod_matrix {
+        print_mod_matrix(rows);
+    }
+
+    if let Some(points) = &report.progression {
+        print_progression(points, report.pp);
+    }
+
+    if let Some(point) = report.failed_at {
+        print_failed_at(point);
+    }
+
+    if let Some(graph) = &report.strain_graph {
+        print_strain_graph(graph);
+    }
+
+    if let Some(spikes) = &report.spikes {
+        print_spikes(spikes);
+    }
+
+    let profile = &report.profile;
+
+    println!();
+    println!(
+        "Approx. old total PP (recomputed): {:.2}pp",
+        profile.old_total_pp
+    );
+    println!(
+        "Approx. new total PP:             {:.2}pp",
+        profile.new_total_pp
+    );
+    println!(
+        "Approx. PP gain from this play:   {}",
+        style::delta(profile.gain, format!("{:+.2}pp", profile.gain))
+    );
+
+    if let Some(rank) = report.global_rank {
+        println!(
+            "Approx. global rank:              ~#{} -> ~#{}",
+            thousands(rank.old),
+            thousands(rank.new)
+        );
+    }
+
+    if let (Some(rank), Some(country)) = (report.country_rank, &report.country_code) {
+        println!(
+            "Approx. country rank ({country}):        ~#{} -> ~#{}",
+            thousands(rank.old),
+            thousands(rank.new)
+        );
+    }
+
+    if let Some(bonus) = profile.bonus {
+        println!(
+            "{}",
+            style::dim(format!(
+                "(totals include {:.2}pp bonus PP, {:.2}pp after the play, estimated from the \
+                 A-and-above grade counts)",
+                bonus.old, bonus.new
+            ))
+        );
+    }
+
+    if report.local_file {
+        println!(
+            "{}",
+            style::dim("(local beatmap: the gain assumes the map were ranked and awarded pp)")
+        );
+    }
+
+    if let (Some(rank), Some(weighted)) = (profile.new_play_rank, profile.new_play_weighted_pp) {
+        println!(
+            "This would be your new #{rank} top play, worth {weighted:.2} weighted pp ({:.1}% weight).",
+            profile.play_weight(rank - 1) * 100.0
+        );
+    }
+
+    if let Some(existing) = &profile.outscored_by {
+        println!(
+            "Your #{} on this map ({:.2}pp) is worth more, so this play changes nothing.",
+            existing.old_rank.unwrap_or_default(),
+            existing.pp
+        );
+    }
+
+    if let Some(replaced) = &profile.replaced {
+        println!(
+            "It replaces your #{} on this map ({:.2}pp).",
+            replaced.old_rank.unwrap_or_default(),
+            replaced.pp
+        );
+    }
+
+    if let (Some(dropped), Some(lost)) = (&profile.dropped, profile.dropped_weighted_pp()) {
+        match dropped.old_rank {
+            Some(old) => println!(
+                "You lose {lost:.2} weighted pp from your old #{old} ({:.2}pp, {}), which falls out of the top {TOP_PLAYS_CAP}.",
+                dropped.pp, dropped.label
+            ),
+            None => {
+                println!("The hypothetical play doesn't make it into your top {TOP_PLAYS_CAP}.")
+            }
+        }
+    }
+
+    print_shifted_plays(profile, top_n);
+    print_top_plays_diff(&profile.top_plays, top_n);
+
+    if let Some(round_trip) = &report.round_trip {
+        let delta = round_trip.delta();
+
+        println!();
+        println!(
+            "{}",
+            style::heading("Round-trip check against your matching top score:")
+        );
+        println!("  API pp:        {:.2}pp", round_trip.api_pp);
+        println!("  Recomputed pp: {:.2}pp", round_trip.recomputed_pp);
+        println!(
+            "  Difference:    {:+.2}pp ({:+.2}%)",
+            delta,
+            delta / round_trip.api_pp * 100.0
+        );
+
+        if round_trip.matches > 1 {
+            println!(
+                "  Warning: {} top scores match this play, compared against the highest one.",
+                round_trip.matches
+            );
+        }
+    }
+
+    println!();
+    println!("{}", style::heading("Notes:"));
+    let notes = [
+        "- Supported modes: osu, taiko, catch, mania.".to_owned(),
+        "- Mods list mirrors osu!lazer's modifiers per mode.".to_owned(),
+        "- Mods are calculated as lazer mods; ones without a PP effect are ignored.".to_owned(),
+        format!(
+            "- Uses {} weighting on your top {TOP_PLAYS_CAP} plays.",
+            profile.weighting
+        ),
+        "- Bonus PP is estimated from the profile's SS/S/A counts.".to_owned(),
+    ];
+
+    for note in notes {
+        println!("{}", style::dim(note));
+    }
+}
+
+// 45000 -> 45,000
+/// `arg` as one word for POSIX shells, quoted only when needed.
+fn shell_quote(arg: &str) -> String {
+    let plain = !arg.is_empty()
+        && arg
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || "-_./:=+,@%".contains(c));
+
+    if plain {
+        arg.to_owned()
+    } else {
+        format!("'{}'", arg.replace('\'', "'\\''"))
+    }
+}
+
+fn thousands(n: u32) -> String {
+    let digits = n.to_string();
+    let mut out = String::with_capacity(digits.len() + digits.len() / 3);
+
+    for (i, digit) in digits.chars().enumerate() {
+        if i > 0 && (digits.len() - i) % 3 == 0 {
+            out.push(',');
+        }
+
+        out.push(digit);
+    }
+
+    out
+}
+
+// the parts are combined non-linearly, so they don't add up to the total
+fn print_pp_breakdown(breakdown: &PpBreakdown) {
+    match *breakdown {
+        PpBreakdown::Osu {
+            aim,
+            speed,
+            accuracy,
+            flashlight,
+        } => println!(
+            "  Aim {aim:.2}pp  Speed {speed:.2}pp  Accuracy {accuracy:.2}pp  Flashlight {flashlight:.2}pp"
+        ),
+        PpBreakdown::Taiko {
+            difficulty,
+            accuracy,
+        } => println!("  Difficulty {difficulty:.2}pp  Accuracy {accuracy:.2}pp"),
+        PpBreakdown::Catch => {}
+        PpBreakdown::Mania { difficulty } => println!("  Difficulty {difficulty:.2}pp"),
+    }
+}
+
+fn print_difficulty(difficulty: &DifficultySummary) {
+    println!(
+        "  {:.2}★, max combo {}x",
+        difficulty.stars, difficulty.max_combo
+    );
+
+    match difficulty.skills {
+        SkillDifficulty::Osu {
+            aim,
+            speed,
+            flashlight,
+            slider_factor,
+            speed_note_count,
+            n_circles,
+            n_sliders,
+            n_spinners,
+            ..
+        } => {
+            println!("  Aim {aim:.2}★  Speed {speed:.2}★  Flashlight {flashlight:.2}★");
+            println!("  Slider factor {slider_factor:.3}  Speed notes {speed_note_count:.1}");
+            println!("  {n_circles} circles, {n_sliders} sliders, {n_spinners} spinners");
+        }
+        SkillDifficulty::Taiko {
+            stamina,
+            rhythm,
+            color,
+        } => println!("  Stamina {stamina:.2}★  Rhythm {rhythm:.2}★  Color {color:.2}★"),
+        SkillDifficulty::Catch {
+            n_fruits,
+            n_droplets,
+            n_tiny_droplets,
+            ..
+        } => {
+            println!("  {n_fruits} fruits, {n_droplets} droplets, {n_tiny_droplets} tiny droplets")
+        }
+        SkillDifficulty::Mania { n_objects } => println!("  {n_objects} objects"),
+    }
+}
+
+fn print_accuracy_table(rows: &[AccuracyRow]) {
+    let Some((ss, rows)) = rows.split_last() else {
+        return;
+    };
+
+    println!();
+    println!("{}", style::heading("PP by accuracy:"));
+
+    for row in rows {
+        let misses = match row.misses {
+            0 => String::new(),
+            misses => style::dim(format!("  ({misses} misses)")).to_string(),
+        };
+
+        println!("  {:>6.2}%  {:>8.2}pp{misses}", row.accuracy, row.pp);
+    }
+
+    println!("  {:>7}  {:>8.2}pp", "SS", ss.pp);
+}
+
+fn print_miss_table(rows: &[MissRow], play_pp: f64) {
+    println!();
+    println!("{}", style::heading("PP by misses (same accuracy):"));
+
+    for row in rows {
+        let delta = row.pp - play_pp;
+
+        println!(
+            "  {:>2} misses  {:>8.2}pp  {}",
+            row.misses,
+            row.pp,
+            style::delta(delta, format!("{delta:>+8.2}pp"))
+        );
+    }
+}
+
+fn print_mod_matrix(rows: &[ModComboRow]) {
+    println!();
+    println!("{}", style::heading("PP by mods (same play):"));
+
+    for row in rows {
+        println!("  {:<8} {:>6.2}★  {:>8.2}pp", row.mods, row.stars, row.pp);
+    }
+}
+
+fn print_progression(points: &[ProgressionPoint], play_pp: f64) {
+    println!();
+    println!("{}", style::heading("PP if the play ended at:"));
+
+    for point in points {
+        let share = if play_pp > 0.0 {
+            point.pp / play_pp * 100.0
+        } else {
+            0.0
+        };
+
+        println!(
+            "  {:>5.1}%  {:>6} objects  {:>8.2}pp  ({share:>5.1}% of the play)",
+            point.percent, point.objects, point.pp
+        );
+    }
+}
+
+fn print_mod_effects(effects: &[ModEffect], ignored: &[&str]) {
+    println!();
+    println!("{}", style::heading("Mods:"));
+
+    if effects.is_empty() {
+        println!("  NM   no mods, nothing to explain");
+    }
+
+    for effect in effects {
+        let ignored = if ignored.contains(&effect.acronym) {
+            style::dim("  (ignored with --ignore-mod)").to_string()
+        } else {
+            String::new()
+        };
+
+        println!("  {:<4} {}{ignored}", effect.acronym, effect.effect);
+    }
+}
+
+fn print_beat_report(report: &BeatReport) {
+    println!();
+    println!(
+        "Hypothetical play PP: {}",
+        style::value(format!("{:.2}pp", report.pp))
+    );
+
+    let difference = style::delta(report.difference, format!("{:+.2}pp", report.difference));
+
+    if report.beats {
+        println!("It beats {:.2}pp by {difference}.", report.beat);
+    } else {
+        println!("It doesn't beat {:.2}pp ({difference}).", report.beat);
+    }
+}
+
+fn print_failed_at(point: ProgressionPoint) {
+    println!();
+    println!(
+        "{} {}pp  (object {}, {:.1}% of the map)",
+        style::heading("PP if the play failed:"),
+        style::value(format!("{:.2}", point.pp)),
+        thousands(point.objects as u32),
+        point.percent
+    );
+    println!(
+        "{}",
+        style::dim("Failed plays give no ranked pp, this is only what the played part is worth.")
+    );
+}
+
+const GRAPH_WIDTH: usize = 60;
+const GRAPH_HEIGHT: usize = 5;
+const GRAPH_BARS: [char; 9] = [' ', '▁', '▂', '▃', '▄', '▅', '▆', '▇', '█'];
+
+fn print_strain_graph(graph: &StrainGraph) {
+    let Some(peak) = graph.peak() else {
+        return;
+    };
+
+    // each column shows the highest section it covers
+    let per_column = graph.strains.len().div_ceil(GRAPH_WIDTH);
+    let columns: Vec<f64> = graph
+        .strains
+        .chunks(per_column)
+        .map(|chunk| chunk.iter().copied().fold(0.0, f64::max))
+        .collect();
+
+    let max = graph.strains[peak];
+    let eighths: Vec<usize> = columns
+        .iter()
+        .map(|strain| {
+            if max > 0.0 {
+                (strain / max * (GRAPH_HEIGHT * 8) as f64).round() as usize
+            } else {
+                0
+            }
+        })
+        .collect();
+
+    println!();
+    println!("{}", style::heading("Strain over time:"));
+
+    for row in (0..GRAPH_HEIGHT).rev() {
+        let line: String = eighths
+            .iter()
+            .map(|&e| GRAPH_BARS[e.saturating_sub(row * 8).min(8)])
+            .collect();
+
+        println!("  │{line}");
+    }
+
+    let peak_column = peak / per_column;
+    println!("  └{}", "─".repeat(columns.len()));
+    println!("   {}^", " ".repeat(peak_column));
+
+    let end_ms = graph.section_start_ms(graph.strains.len());
+    let (start, end) = (timestamp(graph.start_ms), timestamp(end_ms));
+    println!(
+        "   {start}{}{end}",
+        " ".repeat(columns.len().saturating_sub(start.len() + end.len()))
+    );
+
+    println!(
+        "  Hardest section at {} {}",
+        style::value(timestamp(graph.section_start_ms(peak))),
+        style::dim("(map time)")
+    );
+}
+
+fn print_spikes(spikes: &[StrainSpike]) {
+    println!();
+    println!(
+        "{} {}",
+        style::heading("Difficulty spikes:"),
+        style::dim("(map time)")
+    );
+
+    if spikes.is_empty() {
+        println!("  None, the strain never rises.");
+    }
+
+    for spike in spikes {
+        let rise = if spike.from > 0.0 {
+            format!("+{:.0}%", (spike.to / spike.from - 1.0) * 100.0)
+        } else {
+            "from nothing".to_owned()
+        };
+
+        println!(
+            "  {:>6}  strain {:>6.2} -> {}  ({rise})",
+            timestamp(spike.start_ms),
+            spike.from,
+            style::value(format!("{:.2}", spike.to))
+        );
+    }
+}
+
+fn timestamp(ms: f64) -> String {
+    let secs = (ms / 1000.0).max(0.0) as u32;
+
+    format!("{}:{:02}", secs / 60, secs % 60)
+}
+
+fn print_map_settings(settings: &ModdedSettings) {
+    let row = |name: &str, s: &MapSettings| {
+        let length = s.drain_secs.round() as u32;
+
+        println!(
+            "  {name:<6} AR {:>5.2}  OD {:>5.2}  CS {:>5.2}  HP {:>5.2}  {:>6.1} BPM  {}:{:02}",
+            s.ar,
+            s.od,
+            s.cs,
+            s.hp,
+            s.bpm,
+            length / 60,
+            length % 60
+        );
+    };
+
+    row("Raw", &settings.raw);
+    row("Modded", &settings.modded);
+
+    if let Some(keys) = settings.keys {
+        println!("  Keys   {keys}K");
+    }
+}
+
+/// `RUST_LOG` replaces the filter of `-v`, even without it.
+fn init_logging(cli: &Cli) {
+    let default = match cli.verbose {
+        0 => "off",
+        1 => "ppify=debug",
+        _ => "debug,ppify=trace",
+    };
+
+    let filter = EnvFilter::try_from_default_env().unwrap_or_else(|_| EnvFilter::new(default));
+
+    let ansi = io::stderr().is_terminal() && !cli.no_color && env::var_os("NO_COLOR").is_none();
+
+    tracing_subscriber::fmt()
+        .with_env_filter(filter)
+        .with_writer(io::stderr)
+        .with_ansi(ansi)
+        .init();
+}
+
+fn read_map_source() -> Result<MapSource> {
+    let raw = Input::new("Beatmap ID, link, .osu file or search")
+        .placeholder("e.g. 3897329, https://osu.ppy.sh/beatmapsets/1234567#osu/3897329 or a path")
+        .prompt("Beatmap: ")
+        .run()
+        .context("failed to read beatmap")?;
+
+    Ok(MapSource::parse(&raw)?)
+}
+
+fn songs_dir(cli: &Cli) -> Result<PathBuf> {
+    cli.config
+        .songs_dir
+        .clone()
+        .or_else(library::default_songs_dir)
+        .ok_or_else(|| eyre::eyre!("no Songs folder, set `songs_dir` in the config"))
+}
+
+/// Picks a `.osu` file from the Songs folder, see [`library::load_songs`].
+fn read_local_map(cli: &Cli) -> Result<MapSource> {
+    let songs_dir = songs_dir(cli)?;
+
+    let maps = library::load_songs(&songs_dir).with_context(|| {
+        format!(
+            "failed to read {}, set `songs_dir` in the config",
+            songs_dir.display()
+        )
+    })?;
+
+    // osu!standard maps convert to every other mode
+    let mode = cli.mode.map(|mode| mode.modes().0);
+    let maps: Vec<_> = maps
+        .into_iter()
+        .filter(|map| mode.is_none_or(|mode| map.mode == mode || map.mode == GameMode::Osu))
+        .collect();
+
+    if maps.is_empty() {
+        eyre::bail!("no beatmaps in {}", songs_dir.display());
+    }
+
+    let labels: Vec<_> = maps
+        .iter()
+        .map(|map| {
+            format!(
+                "{} ({})",
+                map.metadata,
+                calc::pp_mode_name(modes_for(map.mode).1)
+            )
+        })
+        .collect();
+
+    let description = format!("{} beatmaps in {}", maps.len(), songs_dir.display());
+
+    let mut select = Select::new("Beatmap")
+        .description(&description)
+        .filterable(true);
+
+    for (i, label) in labels.iter().enumerate() {
+        select = select.option(DemandOption::new(i).label(label));
+    }
+
+    let picked = select.run().context("failed to read beatmap")?;
+
+    Ok(MapSource::File(maps[picked].path.clone()))
+}
+
+fn read_search_result(query: &str, sets: Vec<BeatmapsetExtended>) -> Result<BeatmapsetExtended> {
+    if sets.is_empty() {
+        return Err(PpifyError::BadInput(format!("no beatmaps found for `{query}`")).into());
+    }
+
+    let labels: Vec<_> = sets
+        .iter()
+        .map(|set| {
+            let stars = set.maps.iter().flatten().map(|map| map.stars).fold(
+                None,
+                |range: Option<(f32, f32)>, stars| {
+                    Some(range.map_or((stars, stars), |(lo, hi)| (lo.min(stars), hi.max(stars))))
+                },
+            );
+
+            let stars = match stars {
+                Some((lo, hi)) if (hi - lo).abs() < 0.005 => format!("{lo:.2}★"),
+                Some((lo, hi)) => format!("{lo:.2}–{hi:.2}★"),
+                None => "?★".to_owned(),
+            };
+
+            format!(
+                "{} - {} (mapped by {}) [{:?}, {stars}]",
+                set.artist, set.title, set.creator_name, set.status
+            )
+        })
+        .collect();
+
+    let description = format!("Results for `{query}`");
+
+    let mut select = Select::new("Beatmap")
+        .description(&description)
+        .filterable(true);
+
+    for (i, label) in labels.iter().enumerate() {
+        select = select.option(DemandOption::new(i).label(label));
+    }
+
+    let picked = select.run().context("failed to read search result")?;
+
+    Ok(sets
+        .into_iter()
+        .nth(picked)
+        .expect("picked index comes from the list"))
+}
+
+/// Picks the difficulty of a `.osz` file by `--diff-name`, or from a list without it.
+fn read_osz_difficulty(
+    path: PathBuf,
+    diff_name: Option<&str>,
+    mode: Option<GameMode>,
+) -> Result<MapSource> {
+    let difficulties = library::osz_difficulties(&path)
+        .with_context(|| format!("failed to read beatmap archive {}", path.display()))?;
+
+    // osu!standard maps convert to every other mode
+    let fits = |difficulty: &&OszDifficulty| {
+        mode.is_none_or(|mode| difficulty.mode == mode || difficulty.mode == GameMode::Osu)
+    };
+
+    let picked = match diff_name {
+        Some(name) => {
+            let name = name.trim();
+            let Some(difficulty) = difficulties
+                .iter()
+                .find(|difficulty| difficulty.metadata.version.eq_ignore_ascii_case(name))
+            else {
+                let names: Vec<_> = difficulties
+                    .iter()
+                    .map(|difficulty| format!("[{}]", difficulty.metadata.version))
+                    .collect();
+
+                return Err(PpifyError::BadInput(format!(
+                    "{} has no difficulty named `{name}`, it has {}",
+                    path.display(),
+                    names.join(", ")
+                ))
+                .into());
+            };
+
+            if let Some(mode) = mode
+                && !fits(&difficulty)
+            {
+                return Err(PpifyError::BadInput(format!(
+                    "[{}] is an {} map and cannot be converted to {} (only osu!standard maps have converts)",
+                    difficulty.metadata.version,
+                    calc::pp_mode_name(modes_for(difficulty.mode).1),
+                    calc::pp_mode_name(modes_for(mode).1)
+                ))
+                .into());
+            }
+
+            difficulty
+        }
+        None => {
+            let playable: Vec<_> = difficulties.iter().filter(fits).collect();
+
+            match playable.as_slice() {
+                [] => {
+                    return Err(PpifyError::BadInput(format!(
+                        "{} has no playable difficulties",
+                        path.display()
+                    ))
+                    .into());
+                }
+                [difficulty] => difficulty,
+                _ => {
+                    let labels: Vec<_> = playable
+                        .iter()
+                        .map(|difficulty| {
+                            format!(
+                                "{} [{}]",
+                                GM::from(modes_for(difficulty.mode)),
+                                difficulty.metadata.version
+                            )
+                        })
+                        .collect();
+
+                    let file_name = path.display().to_string();
+                    let mut select = Select::new("Difficulty")
+                        .description(&file_name)
+                        .filterable(true);
+
+                    for (i, label) in labels.iter().enumerate() {
+                        select = select.option(DemandOption::new(i).label(label));
+                    }
+
+                    playable[select.run().context("failed to read difficulty")?]
+                }
+            }
+        }
+    };
+
+    Ok(MapSource::Archive {
+        file_name: picked.file_name.clone(),
+        path,
+    })
+}
+
+fn read_set_difficulty(set: BeatmapsetExtended, mode: Option<GameMode>) -> Result<MapSource> {
+    let mut maps: Vec<_> = set
+        .maps
+        .unwrap_or_default()
+        .into_iter()
+        // osu!standard maps convert to every other mode
+        .filter(|map| mode.is_none_or(|mode| map.mode == mode || map.mode == GameMode::Osu))
+        .collect();
+
+    if maps.is_empty() {
+        return Err(PpifyError::BadInput(format!(
+            "beatmapset {} has no playable difficulties",
+            set.mapset_id
+        ))
+        .into());
+    }
+
+    maps.sort_by(|a, b| {
+        (a.mode as u8)
+            .cmp(&(b.mode as u8))
+            .then(a.stars.total_cmp(&b.stars))
+    });
+
+    let labels: Vec<_> = maps
+        .iter()
+        .map(|map| {
+            format!(
+                "{} [{}] ({:.2}★)",
+                GM::from(modes_for(map.mode)),
+                map.version,
+                map.stars
+            )
+        })
+        .collect();
+
+    let set_name = format!("{} - {}", set.artist, set.title);
+
+    let mut select = Select::new("Difficulty")
+        .description(&set_name)
+        .filterable(true);
+
+    for (i, label) in labels.iter().enumerate() {
+        select = select.option(DemandOption::new(i).label(label));
+    }
+
+    let picked = select.run().context("failed to read difficulty")?;
+    let map = &maps[picked];
+
+    Ok(MapSource::Id {
+        map_id: map.map_id,
+        mode: Some(map.mode),
+    })
+}
+
+async fn auth(action: &AuthAction, config: &Config) -> Result<()> {
+    match action {
+        AuthAction::Login => {
+            let client_id = prompt_client_id()?;
+            let client_secret = prompt_client_secret()?;
+
+            // only store credentials that work
+            api::connect(client_id, client_secret.as_str(), None).await?;
+            credentials::store(client_id, &client_secret)?;
+
+            eprintln!("Credentials stored in the system keyring.");
+        }
+        AuthAction::Logout => {
+            if credentials::delete()? {
+                eprintln!("Credentials removed from the system keyring.");
+            } else {
+                eprintln!("No credentials were stored.");
+            }
+        }
+        AuthAction::Check => {
+            let (client_id, id_source) = read_client_id(config)?;
+            let (client_secret, secret_source) = read_client_secret(config)?;
+
+            // a cached token would pass without the secret being checked
+            api::connect(client_id, client_secret, None).await?;
+
+            println!("The credentials work with the osu! api.");
+            println!("Client id:      {client_id} (from {id_source})");
+            println!("Client secret:  from {secret_source}");
+        }
+    }
+
+    Ok(())
+}
+
+fn profile(action: &ProfileAction, mode: Option<ModeArg>, path: Option<&Path>) -> Result<()> {
+    let path = path.ok_or_else(|| eyre::eyre!("no config directory on this platform"))?;
+    let mut config = Config::load(path)?;
+
+    match action {
+        ProfileAction::Add { name, user } => {
+            let profile = Profile {
+                user: user.clone(),
+                mode: mode.map(|mode| mode.name().to_owned()),
+            };
+
+            config.add_profile(name, profile)?;
+        }
+        ProfileAction::Remove { name } => {
+            if !config.remove_profile(name) {
+                return Err(PpifyError::BadInput(format!("no profile named `{name}`")).into());
+            }
+        }
+        ProfileAction::List => {
+            if config.profiles.is_empty() {
+                println!("No profiles yet, add one with `ppify profile add <name> <user>`.");
+            }
+
+            for (name, profile) in &config.profiles {
+                match &profile.mode {
+                    Some(mode) => println!("{name}: {} ({mode})", profile.user),
+                    None => println!("{name}: {}", profile.user),
+                }
+            }
+
+            return Ok(());
+        }
+    }
+
+    config
+        .save(path)
+        .with_context(|| format!("failed to write {}", path.display()))
+}
+
+/// `path` in the platform data directory, which is created if needed.
+fn data_path(path: Option<PathBuf>) -> Result<PathBuf> {
+    let path = path.ok_or_else(|| eyre::eyre!("no data directory on this platform"))?;
+
+    if let Some(dir) = path.parent() {
+        fs::create_dir_all(dir).with_context(|| format!("failed to create {}", dir.display()))?;
+    }
+
+    Ok(path)
+}
+
+fn open_history() -> Result<History> {
+    let path = data_path(History::default_path())?;
+
+    History::open(&path).with_context(|| format!("failed to open {}", path.display()))
+}
+
+fn history(action: Option<&HistoryAction>, cli: &Cli) -> Result<()> {
+    let mut history = open_history()?;
+
+    let entries = match action {
+        // like `history list`
+        None => history.list(20),
+        Some(HistoryAction::List { limit }) => history.list(*limit),
+        Some(HistoryAction::Search { text, limit }) => history.search(text, *limit),
+        Some(HistoryAction::Export { file }) => {
+            let entries = history.all().context("failed to read the history")?;
+            let json =
+                serde_json::to_string_pretty(&entries).context("failed to serialize history")?;
+
+            match file {
+                Some(path) => {
+                    fs::write(path, json)
+                        .with_context(|| format!("failed to write {}", path.display()))?;
+                    eprintln!(
+                        "{} calculations written to {}",
+                        entries.len(),
+                        path.display()
+                    );
+                }
+                None => println!("{json}"),
+            }
+
+            return Ok(());
+        }
+        Some(HistoryAction::Import { file }) => {
+            let json =
+                fs::read(file).with_context(|| format!("failed to read {}", file.display()))?;
+            let entries: Vec<HistoryEntry> = serde_json::from_slice(&json)
+                .with_context(|| format!("{} isn't a ppify history export", file.display()))?;
+
+            let added = history
+                .import(&entries)
+                .context("failed to write the history")?;
+
+            eprintln!(
+                "Imported {added} calculations, {} were already in the history",
+                entries.len() - added
+            );
+
+            return Ok(());
+        }
+        Some(HistoryAction::Rerun { .. }) => {
+            unreachable!("reruns replace the command before the history is listed")
+        }
+    }
+    .context("failed to read the history")?;
+
+    if cli.json {
+        let json = serde_json::to_string_pretty(&entries).context("failed to serialize history")?;
+        println!("{json}");
+    } else if cli.markdown() {
+        print!("{}", markdown::history(&entries));
+    } else if entries.is_empty() {
+        println!("No calculations found.");
+    } else {
+        print_history(&entries);
+    }
+
+    Ok(())
+}
+
+fn print_history(entries: &[HistoryEntry]) {
+    for entry in entries {
+        let mods = if entry.mods.is_empty() {
+            String::new()
+        } else {
+            format!(" +{}", entry.mods)
+        };
+
+        println!();
+        println!(
+            "{}  {}  {} ({}){mods}",
+            style::heading(format!("#{}", entry.id)),
+            entry.time,
+            entry.user,
+            entry.mode
+        );
+        println!("  {}", entry.beatmap);
+        println!(
+            "  {}  {}pp  {}",
+            entry.play,
+            style::value(format!("{:.2}", entry.pp)),
+            style::delta(entry.gain, format!("{:+.2}pp", entry.gain))
+        );
+        println!(
+            "  {}",
+            style::dim(format!("ppify {}", entry.args.join(" ")))
+        );
+    }
+}
 
-    println!();
-    println!("Hypothetical play PP: {:.2}pp", new_play_pp);
+/// Replaces the play in `cli` with the one of history entry `id`, the rest stays, e.g.
+/// `--json`.
+fn rerun(cli: &mut Cli, id: i64) -> Result<()> {
+    let entry = open_history()?
+        .get(id)
+        .context("failed to read the history")?
+        .ok_or_else(|| PpifyError::BadInput(format!("no calculation #{id} in the history")))?;
 
-    let current_scores = fetch_user_best_scores(&osu, username.trim(), api_mode).await?;
+    apply_history_args(cli, &entry.args)
+        .with_context(|| format!("calculation #{id} has invalid flags"))?;
 
-    let mut current_pps: Vec<f64> = current_scores
-        .iter()
-        .filter_map(|s| s.pp)
-        .map(|pp| pp as f64)
-        .collect();
+    eprintln!("Calculating #{id} again: {}", entry.beatmap);
 
-    current_pps.sort_by(|a, b| b.partial_cmp(a).unwrap());
-    let old_total_pp = weighted_total_pp(&current_pps);
+    Ok(())
+}
 
-    current_pps.push(new_play_pp);
-    current_pps.sort_by(|a, b| b.partial_cmp(a).unwrap());
-    let new_total_pp = weighted_total_pp(&current_pps);
-    let gain = new_total_pp - old_total_pp;
+/// Replaces the play in `cli` with the one of the stored flags `args`.
+fn apply_history_args(cli: &mut Cli, args: &[String]) -> Result<()> {
+    let past = Cli::try_parse_from(iter::once("ppify").chain(args.iter().map(String::as_str)))?;
+
+    cli.command = None;
+    cli.user = past.user;
+    cli.mode = past.mode;
+    cli.map = past.map;
+    cli.diff_name = past.diff_name;
+    // the stored map is always a difficulty or a file
+    cli.set = past.set;
+    cli.local = past.local;
+    cli.mods = past.mods;
+    cli.ignore_mod = past.ignore_mod;
+    cli.speed = past.speed;
+    cli.rate = past.rate;
+    cli.da_ar = past.da_ar;
+    cli.da_od = past.da_od;
+    cli.da_cs = past.da_cs;
+    cli.da_hp = past.da_hp;
+    cli.score = past.score;
+    cli.stable |= past.stable;
 
-    println!();
-    println!("Approx. old total PP (recomputed): {:.2}pp", old_total_pp);
-    println!("Approx. new total PP:             {:.2}pp", new_total_pp);
-    println!("Approx. PP gain from this play:   {:+.2}pp", gain);
+    Ok(())
+}
 
-    println!();
-    println!("Notes:");
-    println!("- Supported modes: osu, taiko, catch, mania.");
-    println!("- Mods list mirrors osu!lazer's modifiers per mode.");
-    println!("- Lazer‑only / fun mods are shown but do not affect PP here.");
-    println!("- Uses classic 0.95^i weighting on your top 100 plays.");
-    println!("- Ignores bonus‑PP components.");
+/// Where a credential came from, for `ppify auth check`. The environment (or `.env`) wins
+/// over the config, then the keyring, then the prompt.
+enum CredentialSource {
+    Env(String),
+    Config,
+    Keyring,
+    Prompt,
+}
 
-    Ok(())
+impl Display for CredentialSource {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Env(var) => write!(f, "the {var} environment variable"),
+            Self::Config => write!(f, "the config file"),
+            Self::Keyring => write!(f, "the system keyring"),
+            Self::Prompt => write!(f, "the prompt"),
+        }
+    }
 }
 
-fn read_client_id() -> Result<u64> {
+fn read_client_id(config: &Config) -> Result<(u64, CredentialSource)> {
     if let Ok(id) = env::var("OSU_CLIENT_ID") {
-        return id
+        let id = id
             .trim()
             .parse()
-            .context("OSU_CLIENT_ID must be an integer client id");
+            .context("OSU_CLIENT_ID must be an integer client id")?;
+
+        return Ok((id, CredentialSource::Env("OSU_CLIENT_ID".to_owned())));
+    }
+
+    if let Some(id) = config.client_id {
+        return Ok((id, CredentialSource::Config));
+    }
+
+    // an unusable keyring just means asking
+    if let Ok(Some(id)) = credentials::stored_client_id() {
+        return Ok((id, CredentialSource::Keyring));
     }
 
+    Ok((prompt_client_id()?, CredentialSource::Prompt))
+}
+
+fn prompt_client_id() -> Result<u64> {
     let raw = Input::new("osu! OAuth client id")
         .placeholder("numeric client id")
         .prompt("Client ID: ")
@@ -173,11 +2574,28 @@ fn read_client_id() -> Result<u64> {
     raw.trim().parse().context("client id must be an integer")
 }
 
-fn read_client_secret() -> Result<String> {
+fn read_client_secret(config: &Config) -> Result<(String, CredentialSource)> {
     if let Ok(secret) = env::var("OSU_CLIENT_SECRET") {
-        return Ok(secret);
+        return Ok((
+            secret,
+            CredentialSource::Env("OSU_CLIENT_SECRET".to_owned()),
+        ));
+    }
+
+    if let Some(var) = &config.client_secret_env {
+        let secret = env::var(var).with_context(|| format!("{var} from the config is not set"))?;
+
+        return Ok((secret, CredentialSource::Env(var.clone())));
+    }
+
+    if let Ok(Some(secret)) = credentials::stored_client_secret() {
+        return Ok((secret, CredentialSource::Keyring));
     }
 
+    Ok((prompt_client_secret()?, CredentialSource::Prompt))
+}
+
+fn prompt_client_secret() -> Result<String> {
     let secret = Input::new("osu! OAuth client secret")
         .placeholder("will not be echoed")
         .prompt("Client secret: ")
@@ -207,6 +2625,29 @@ impl Display for GM {
     }
 }
 
+fn modes_for(mode: GameMode) -> (GameMode, PpGameMode) {
+    match mode {
+        GameMode::Osu => (GameMode::Osu, PpGameMode::Osu),
+        GameMode::Taiko => (GameMode::Taiko, PpGameMode::Taiko),
+        GameMode::Catch => (GameMode::Catch, PpGameMode::Catch),
+        GameMode::Mania => (GameMode::Mania, PpGameMode::Mania),
+    }
+}
+
+/// Uses the mode of `map`, except for osu!standard maps which can be played as converts.
+fn read_mode_for_map(map: &PpBeatmap) -> Result<(GameMode, PpGameMode)> {
+    let modes = match map.mode {
+        PpGameMode::Osu => return read_mode(),
+        PpGameMode::Taiko => modes_for(GameMode::Taiko),
+        PpGameMode::Catch => modes_for(GameMode::Catch),
+        PpGameMode::Mania => modes_for(GameMode::Mania),
+    };
+
+    eprintln!("Using {} from the beatmap", GM::from(modes));
+
+    Ok(modes)
+}
+
 fn read_mode() -> Result<(GameMode, PpGameMode)> {
     let select = Select::new("Game mode")
         .description("Use ↑/↓ and Enter. ESC to cancel.")
@@ -239,6 +2680,13 @@ fn read_mode() -> Result<(GameMode, PpGameMode)> {
     Ok((api_mode, pp_mode))
 }
 
+fn read_score_inputs(mode: GameMode, args: &ScoreArgs) -> Result<ScoreInputs> {
+    match read_score_input_mode() {
+        ScoreInputMode::Detailed => read_detailed_judgements(mode, args),
+        ScoreInputMode::Simple => read_simple_score(args),
+    }
+}
+
 fn read_score_input_mode() -> ScoreInputMode {
     let select = Select::new("Score input mode")
         .description("Choose how to describe the play")
@@ -268,6 +2716,99 @@ fn read_u32(label: &str, placeholder: &str) -> Result<u32> {
         .with_context(|| format!("{label} must be an unsigned integer"))
 }
 
+type ScoreInputs = (Judgements, Option<ComboInput>);
+
+#[derive(Clone, Copy, Debug)]
+enum ComboInput {
+    Absolute(u32),
+    PercentOfMax(f64),
+}
+
+fn read_combo(arg: Option<&str>) -> Result<Option<ComboInput>> {
+    if let Some(raw) = arg {
+        return parse_combo(raw);
+    }
+
+    let raw = Input::new("Combo (optional)")
+        .placeholder("e.g. 1200 or 85%, leave empty for full combo assumed by rosu-pp")
+        .prompt("Combo (optional): ")
+        .run()
+        .context("failed to read combo")?;
+
+    parse_combo(&raw)
+}
+
+fn parse_combo(raw: &str) -> Result<Option<ComboInput>> {
+    let trimmed = raw.trim();
+
+    if trimmed.is_empty() {
+        return Ok(None);
+    }
+
+    if let Some(pct) = trimmed.strip_suffix('%') {
+        let pct: f64 = pct
+            .trim()
+            .parse()
+            .context("combo percentage must be a number like 85%")?;
+
+        if !(pct > 0.0 && pct <= 100.0) {
+            eyre::bail!("combo percentage must be in (0, 100], got {pct}%");
+        }
+
+        return Ok(Some(ComboInput::PercentOfMax(pct)));
+    }
+
+    let combo = trimmed
+        .parse()
+        .context("combo must be an unsigned integer or a percentage like 85%")?;
+
+    Ok(Some(ComboInput::Absolute(combo)))
+}
+
+fn parse_fail_at(raw: &str) -> Result<FailAt> {
+    let trimmed = raw.trim();
+
+    if let Some(pct) = trimmed.strip_suffix('%') {
+        let pct: f64 = pct
+            .trim()
+            .parse()
+            .context("--fail-at percentage must be a number like 40%")?;
+
+        if !(pct > 0.0 && pct <= 100.0) {
+            eyre::bail!("--fail-at percentage must be in (0, 100], got {pct}%");
+        }
+
+        return Ok(FailAt::Percent(pct));
+    }
+
+    let object: usize = trimmed
+        .parse()
+        .context("--fail-at must be a hit object number or a percentage like 40%")?;
+
+    if object == 0 {
+        eyre::bail!("--fail-at hit objects count from 1");
+    }
+
+    Ok(FailAt::Object(object))
+}
+
+fn resolve_combo(
+    input: Option<ComboInput>,
+    map: &PpBeatmap,
+    mode: PpGameMode,
+    mods: &GameMods,
+    rules: Rules,
+) -> Option<u32> {
+    match input? {
+        ComboInput::Absolute(combo) => Some(combo),
+        ComboInput::PercentOfMax(pct) => {
+            let max_combo = calc::max_combo(map, mode, mods, rules);
+
+            Some((f64::from(max_combo) * pct / 100.0).round() as u32)
+        }
+    }
+}
+
 fn read_optional_u32(label: &str, placeholder: &str) -> Result<Option<u32>> {
     let raw = Input::new(label)
         .placeholder(placeholder)
@@ -275,20 +2816,122 @@ fn read_optional_u32(label: &str, placeholder: &str) -> Result<Option<u32>> {
         .run()
         .with_context(|| format!("failed to read {label}"))?;
 
-    let trimmed = raw.trim();
-    if trimmed.is_empty() {
-        Ok(None)
-    } else {
-        let v = trimmed
+    match raw.trim() {
+        "" => Ok(None),
+        raw => raw
             .parse()
-            .with_context(|| format!("{label} must be an unsigned integer"))?;
-        Ok(Some(v))
+            .map(Some)
+            .with_context(|| format!("{label} must be an unsigned integer")),
+    }
+}
+
+fn read_u32_or(arg: Option<u32>, label: &str, placeholder: &str) -> Result<u32> {
+    match arg {
+        Some(value) => Ok(value),
+        None => read_u32(label, placeholder),
     }
 }
 
-type AccuracyAndMisses = Option<(f64, u32)>;
+fn score_inputs_from_args(args: &ScoreArgs, mode: GameMode) -> Result<ScoreInputs> {
+    let combo = args
+        .combo
+        .as_deref()
+        .map(parse_combo)
+        .transpose()?
+        .flatten();
+    let misses = args.misses.unwrap_or(0);
+
+    if let Some(acc) = args.acc {
+        return Ok((
+            Judgements::Accuracy {
+                accuracy: acc,
+                misses,
+            },
+            combo,
+        ));
+    }
+
+    let c = &args.counts;
+    let n = |count: Option<u32>| count.unwrap_or(0);
+
+    let unused: &[(&str, Option<u32>)] = match mode {
+        GameMode::Osu => &[
+            ("--n320", c.n320),
+            ("--n200", c.n200),
+            ("--fruits", c.fruits),
+            ("--droplets", c.droplets),
+            ("--tiny-droplets", c.tiny_droplets),
+            ("--tiny-droplet-misses", c.tiny_droplet_misses),
+        ],
+        GameMode::Taiko => &[
+            ("--slider-ends", c.slider_ends),
+            ("--large-ticks", c.large_ticks),
+            ("--n320", c.n320),
+            ("--n200", c.n200),
+            ("--n50", c.n50),
+            ("--fruits", c.fruits),
+            ("--droplets", c.droplets),
+            ("--tiny-droplets", c.tiny_droplets),
+            ("--tiny-droplet-misses", c.tiny_droplet_misses),
+        ],
+        GameMode::Catch => &[
+            ("--slider-ends", c.slider_ends),
+            ("--large-ticks", c.large_ticks),
+            ("--n320", c.n320),
+            ("--n300", c.n300),
+            ("--n200", c.n200),
+            ("--n100", c.n100),
+            ("--n50", c.n50),
+        ],
+        GameMode::Mania => &[
+            ("--slider-ends", c.slider_ends),
+            ("--large-ticks", c.large_ticks),
+            ("--fruits", c.fruits),
+            ("--droplets", c.droplets),
+            ("--tiny-droplets", c.tiny_droplets),
+            ("--tiny-droplet-misses", c.tiny_droplet_misses),
+        ],
+    };
+
+    if let Some((flag, _)) = unused.iter().find(|(_, count)| count.is_some()) {
+        eyre::bail!("{flag} is not a judgement of mode `{}`", mode.as_str());
+    }
+
+    let counts = match mode {
+        GameMode::Osu => DetailedJudgements::Osu {
+            n300: n(c.n300),
+            n100: n(c.n100),
+            n50: n(c.n50),
+            misses,
+            slider_end_hits: c.slider_ends,
+            large_tick_hits: c.large_ticks,
+        },
+        GameMode::Taiko => DetailedJudgements::Taiko {
+            n300: n(c.n300),
+            n100: n(c.n100),
+            misses,
+        },
+        GameMode::Catch => DetailedJudgements::Catch {
+            fruits: n(c.fruits),
+            droplets: n(c.droplets),
+            tiny_droplets: n(c.tiny_droplets),
+            tiny_droplet_misses: n(c.tiny_droplet_misses),
+            misses,
+        },
+        GameMode::Mania => DetailedJudgements::Mania {
+            n320: n(c.n320),
+            n300: n(c.n300),
+            n200: n(c.n200),
+            n100: n(c.n100),
+            n50: n(c.n50),
+            misses,
+        },
+    };
+
+    Ok((Judgements::Detailed(counts), combo))
+}
 
-fn read_simple_score() -> Result<(AccuracyAndMisses, Option<u32>, Option<DetailedJudgements>)> {
+fn read_simple_score(args: &ScoreArgs) -> Result<ScoreInputs> {
     let acc_raw = Input::new("Accuracy in %")
         .placeholder("e.g. 98.75")
         .prompt("Accuracy: ")
@@ -300,53 +2943,50 @@ fn read_simple_score() -> Result<(AccuracyAndMisses, Option<u32>, Option<Detaile
         .parse::<f64>()
         .context("accuracy must be a floating number like 98.5")?;
 
-    let misses = read_u32("Number of misses", "usually 0 for FC")?;
-    let combo = read_optional_u32(
-        "Combo (optional)",
-        "leave empty for full combo assumed by rosu-pp",
-    )?;
+    let misses = read_u32_or(args.misses, "Number of misses", "usually 0 for FC")?;
+    let combo = read_combo(args.combo.as_deref())?;
 
-    Ok((Some((accuracy, misses)), combo, None))
+    Ok((Judgements::Accuracy { accuracy, misses }, combo))
 }
 
-fn read_detailed_judgements(
-    mode: GameMode,
-) -> Result<(AccuracyAndMisses, Option<u32>, Option<DetailedJudgements>)> {
+fn read_detailed_judgements(mode: GameMode, args: &ScoreArgs) -> Result<ScoreInputs> {
     match mode {
         GameMode::Osu => {
             let n300 = read_u32("Number of 300s", "e.g. 1000")?;
             let n100 = read_u32("Number of 100s", "e.g. 10")?;
             let n50 = read_u32("Number of 50s", "e.g. 0")?;
-            let misses = read_u32("Number of misses", "e.g. 1")?;
-            let combo = read_optional_u32(
-                "Combo (optional)",
-                "leave empty for full combo assumed by rosu-pp",
+            let misses = read_u32_or(args.misses, "Number of misses", "e.g. 1")?;
+            let slider_end_hits = read_optional_u32(
+                "Slider ends hit (optional)",
+                "lazer only, leave empty if all were hit",
+            )?;
+            let large_tick_hits = read_optional_u32(
+                "Slider ticks and repeats hit (optional)",
+                "lazer only, leave empty if all were hit",
             )?;
+            let combo = read_combo(args.combo.as_deref())?;
 
             Ok((
-                None,
-                combo,
-                Some(DetailedJudgements::Osu {
+                Judgements::Detailed(DetailedJudgements::Osu {
                     n300,
                     n100,
                     n50,
                     misses,
+                    slider_end_hits,
+                    large_tick_hits,
                 }),
+                combo,
             ))
         }
         GameMode::Taiko => {
             let n300 = read_u32("Number of GREATs (300)", "e.g. 1000")?;
             let n100 = read_u32("Number of GOODs (100)", "e.g. 10")?;
-            let misses = read_u32("Number of misses", "e.g. 1")?;
-            let combo = read_optional_u32(
-                "Combo (optional)",
-                "leave empty for full combo assumed by rosu-pp",
-            )?;
+            let misses = read_u32_or(args.misses, "Number of misses", "e.g. 1")?;
+            let combo = read_combo(args.combo.as_deref())?;
 
             Ok((
-                None,
+                Judgements::Detailed(DetailedJudgements::Taiko { n300, n100, misses }),
                 combo,
-                Some(DetailedJudgements::Taiko { n300, n100, misses }),
             ))
         }
         GameMode::Catch => {
@@ -361,22 +3001,18 @@ fn read_detailed_judgements(
             let droplets = read_u32("Droplets caught", "e.g. 100")?;
             let tiny_droplets = read_u32("Tiny droplets caught", "e.g. 50")?;
             let tiny_droplet_misses = read_u32("Tiny droplet misses", "e.g. 0 (usually small)")?;
-            let misses = read_u32("Fruit+droplet misses", "e.g. 0")?;
-            let combo = read_optional_u32(
-                "Combo (optional)",
-                "leave empty for full combo assumed by rosu-pp",
-            )?;
+            let misses = read_u32_or(args.misses, "Fruit+droplet misses", "e.g. 0")?;
+            let combo = read_combo(args.combo.as_deref())?;
 
             Ok((
-                None,
-                combo,
-                Some(DetailedJudgements::Catch {
+                Judgements::Detailed(DetailedJudgements::Catch {
                     fruits,
                     droplets,
                     tiny_droplets,
                     tiny_droplet_misses,
                     misses,
                 }),
+                combo,
             ))
         }
         GameMode::Mania => {
@@ -392,16 +3028,11 @@ fn read_detailed_judgements(
             let n200 = read_u32("Number of 200s", "e.g. 10")?;
             let n100 = read_u32("Number of 100s", "e.g. 0")?;
             let n50 = read_u32("Number of 50s", "e.g. 0")?;
-            let misses = read_u32("Number of misses", "e.g. 1")?;
-            let combo = read_optional_u32(
-                "Combo (optional)",
-                "leave empty for full combo assumed by rosu-pp",
-            )?;
+            let misses = read_u32_or(args.misses, "Number of misses", "e.g. 1")?;
+            let combo = read_combo(args.combo.as_deref())?;
 
             Ok((
-                None,
-                combo,
-                Some(DetailedJudgements::Mania {
+                Judgements::Detailed(DetailedJudgements::Mania {
                     n320,
                     n300,
                     n200,
@@ -409,372 +3040,22 @@ fn read_detailed_judgements(
                     n50,
                     misses,
                 }),
+                combo,
             ))
         }
     }
 }
 
-struct ModOptionDef {
-    acronym: &'static str,
-    bits: u32,
-    description: &'static str,
-    modes: &'static [GameMode],
-}
-
-impl Display for ModOptionDef {
-    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        let out_str = format!(
-            "Acronym: {}\n
-Bits: {}\n
-Description: {}\n
-Modes: {}
-            ",
-            self.acronym,
-            self.bits,
-            self.description,
-            self.modes
-                .iter()
-                .map(|a| a.as_str())
-                .collect::<Vec<_>>()
-                .join(",")
-        );
-
-        write!(f, "{}", out_str)
-    }
-}
-
-const fn b(bit: u32) -> u32 {
-    1 << bit
-}
-
-const MODS_LAZER: &[ModOptionDef] = &[
-    ModOptionDef {
-        acronym: "EZ",
-        bits: b(1),
-        description: "Easy",
-        modes: &[
-            GameMode::Osu,
-            GameMode::Taiko,
-            GameMode::Catch,
-            GameMode::Mania,
-        ],
-    },
-    ModOptionDef {
-        acronym: "NF",
-        bits: b(0),
-        description: "No Fail",
-        modes: &[
-            GameMode::Osu,
-            GameMode::Taiko,
-            GameMode::Catch,
-            GameMode::Mania,
-        ],
-    },
-    ModOptionDef {
-        acronym: "HT",
-        bits: b(8),
-        description: "Half Time",
-        modes: &[
-            GameMode::Osu,
-            GameMode::Taiko,
-            GameMode::Catch,
-            GameMode::Mania,
-        ],
-    },
-    ModOptionDef {
-        acronym: "DC",
-        bits: 0,
-        description: "Daycore (lazer only, no PP effect here)",
-        modes: &[
-            GameMode::Osu,
-            GameMode::Taiko,
-            GameMode::Catch,
-            GameMode::Mania,
-        ],
-    },
-    ModOptionDef {
-        acronym: "NR",
-        bits: 0,
-        description: "No Release (mania only, no PP effect here)",
-        modes: &[GameMode::Mania],
-    },
-    ModOptionDef {
-        acronym: "HR",
-        bits: b(4),
-        description: "Hard Rock",
-        modes: &[
-            GameMode::Osu,
-            GameMode::Taiko,
-            GameMode::Catch,
-            GameMode::Mania,
-        ],
-    },
-    ModOptionDef {
-        acronym: "SD",
-        bits: b(5),
-        description: "Sudden Death",
-        modes: &[
-            GameMode::Osu,
-            GameMode::Taiko,
-            GameMode::Catch,
-            GameMode::Mania,
-        ],
-    },
-    ModOptionDef {
-        acronym: "PF",
-        bits: b(5) | b(14),
-        description: "Perfect (full combo SD)",
-        modes: &[
-            GameMode::Osu,
-            GameMode::Taiko,
-            GameMode::Catch,
-            GameMode::Mania,
-        ],
-    },
-    ModOptionDef {
-        acronym: "DT",
-        bits: b(6),
-        description: "Double Time",
-        modes: &[
-            GameMode::Osu,
-            GameMode::Taiko,
-            GameMode::Catch,
-            GameMode::Mania,
-        ],
-    },
-    ModOptionDef {
-        acronym: "NC",
-        bits: b(6) | b(9),
-        description: "Nightcore (DT variant)",
-        modes: &[
-            GameMode::Osu,
-            GameMode::Taiko,
-            GameMode::Catch,
-            GameMode::Mania,
-        ],
-    },
-    ModOptionDef {
-        acronym: "HD",
-        bits: b(3),
-        description: "Hidden",
-        modes: &[
-            GameMode::Osu,
-            GameMode::Taiko,
-            GameMode::Catch,
-            GameMode::Mania,
-        ],
-    },
-    ModOptionDef {
-        acronym: "FI",
-        bits: 0,
-        description: "Fade In (mania only in stable)",
-        modes: &[GameMode::Mania],
-    },
-    ModOptionDef {
-        acronym: "CO",
-        bits: 0,
-        description: "Cover (lazer only, no PP effect here)",
-        modes: &[
-            GameMode::Osu,
-            GameMode::Taiko,
-            GameMode::Catch,
-            GameMode::Mania,
-        ],
-    },
-    ModOptionDef {
-        acronym: "FL",
-        bits: b(10),
-        description: "Flashlight",
-        modes: &[GameMode::Osu, GameMode::Catch, GameMode::Mania],
-    },
-    ModOptionDef {
-        acronym: "BL",
-        bits: 0,
-        description: "Blinds (lazer fun mod, no PP effect here)",
-        modes: &[GameMode::Osu, GameMode::Catch, GameMode::Mania],
-    },
-    ModOptionDef {
-        acronym: "ST",
-        bits: 0,
-        description: "Strict Tracking (taiko only, no PP effect here)",
-        modes: &[GameMode::Taiko],
-    },
-    ModOptionDef {
-        acronym: "AC",
-        bits: 0,
-        description: "Accuracy Challenge (lazer only, no PP effect here)",
-        modes: &[
-            GameMode::Osu,
-            GameMode::Taiko,
-            GameMode::Catch,
-            GameMode::Mania,
-        ],
-    },
-    ModOptionDef {
-        acronym: "AT",
-        bits: b(7),
-        description: "Autoplay (no PP)",
-        modes: &[
-            GameMode::Osu,
-            GameMode::Taiko,
-            GameMode::Catch,
-            GameMode::Mania,
-        ],
-    },
-    ModOptionDef {
-        acronym: "AP",
-        bits: b(9),
-        description: "AutoPilot (osu!, no PP)",
-        modes: &[GameMode::Osu],
-    },
-    ModOptionDef {
-        acronym: "CN",
-        bits: 0,
-        description: "Cinema (no PP)",
-        modes: &[GameMode::Osu, GameMode::Catch],
-    },
-    ModOptionDef {
-        acronym: "RL",
-        bits: 0,
-        description: "Relax (no PP)",
-        modes: &[GameMode::Osu, GameMode::Catch],
-    },
-    ModOptionDef {
-        acronym: "RX",
-        bits: 0,
-        description: "Classic Relax acronym (no PP)",
-        modes: &[GameMode::Osu, GameMode::Catch],
-    },
-    ModOptionDef {
-        acronym: "TD",
-        bits: 0,
-        description: "Target Practice / Touch Device (no PP)",
-        modes: &[GameMode::Osu],
-    },
-    ModOptionDef {
-        acronym: "SO",
-        bits: b(12),
-        description: "Spun Out (osu! only)",
-        modes: &[GameMode::Osu],
-    },
-    ModOptionDef {
-        acronym: "DA",
-        bits: 0,
-        description: "Difficulty Adjust (lazer only, no PP here)",
-        modes: &[
-            GameMode::Osu,
-            GameMode::Taiko,
-            GameMode::Catch,
-            GameMode::Mania,
-        ],
-    },
-    ModOptionDef {
-        acronym: "TC",
-        bits: 0,
-        description: "Traceable (lazer only)",
-        modes: &[GameMode::Osu],
-    },
-    ModOptionDef {
-        acronym: "WI",
-        bits: 0,
-        description: "Wiggle (lazer only)",
-        modes: &[GameMode::Osu],
-    },
-    ModOptionDef {
-        acronym: "CL",
-        bits: 0,
-        description: "Classic (lazer: emulate stable quirks)",
-        modes: &[GameMode::Osu, GameMode::Taiko],
-    },
-    ModOptionDef {
-        acronym: "RD",
-        bits: 0,
-        description: "Random (mania only, no PP)",
-        modes: &[GameMode::Mania],
-    },
-    ModOptionDef {
-        acronym: "MR",
-        bits: 0,
-        description: "Mirror (mania only, no PP)",
-        modes: &[GameMode::Mania],
-    },
-    ModOptionDef {
-        acronym: "ATC",
-        bits: 0,
-        description: "Adaptive Speed / Challenge (lazer system, no PP)",
-        modes: &[
-            GameMode::Osu,
-            GameMode::Taiko,
-            GameMode::Catch,
-            GameMode::Mania,
-        ],
-    },
-    ModOptionDef {
-        acronym: "1K",
-        bits: 0,
-        description: "1 key (mania only, no legacy bit)",
-        modes: &[GameMode::Mania],
-    },
-    ModOptionDef {
-        acronym: "2K",
-        bits: 0,
-        description: "2 keys",
-        modes: &[GameMode::Mania],
-    },
-    ModOptionDef {
-        acronym: "3K",
-        bits: 0,
-        description: "3 keys",
-        modes: &[GameMode::Mania],
-    },
-    ModOptionDef {
-        acronym: "4K",
-        bits: b(15),
-        description: "4 keys",
-        modes: &[GameMode::Mania],
-    },
-    ModOptionDef {
-        acronym: "5K",
-        bits: b(16),
-        description: "5 keys",
-        modes: &[GameMode::Mania],
-    },
-    ModOptionDef {
-        acronym: "6K",
-        bits: b(17),
-        description: "6 keys",
-        modes: &[GameMode::Mania],
-    },
-    ModOptionDef {
-        acronym: "7K",
-        bits: b(18),
-        description: "7 keys",
-        modes: &[GameMode::Mania],
-    },
-    ModOptionDef {
-        acronym: "8K",
-        bits: b(19),
-        description: "8 keys",
-        modes: &[GameMode::Mania],
-    },
-    ModOptionDef {
-        acronym: "9K",
-        bits: b(24),
-        description: "9 keys",
-        modes: &[GameMode::Mania],
-    },
-];
-
-fn read_mods_for_mode(mode: GameMode) -> Result<u32> {
+fn read_mods_for_mode(mode: GameMode) -> Result<Vec<&'static ModOptionDef>> {
     let mut ms = MultiSelect::new("Mods")
         .description(
             "Space = toggle, Enter = confirm. Empty = NoMod.\n\
-                      Some lazer‑only mods are shown but will not affect PP.",
+                      Mods that don't affect PP are ignored by the calculation.",
         )
         .min(0)
         .filterable(true);
 
-    for m in MODS_LAZER.iter().filter(|m| m.modes.contains(&mode)) {
+    for m in mods::available_for_mode(mode) {
         ms = ms.option(
             DemandOption::new(m)
                 .label(m.acronym)
@@ -782,100 +3063,257 @@ fn read_mods_for_mode(mode: GameMode) -> Result<u32> {
         );
     }
 
-    let selected = ms.run().context("failed to run mods multiselect")?;
+    ms.run().context("failed to run mods multiselect")
+}
+
+/// Like [`read_mods_for_mode`], but also asks for the key count of osu!mania converts
+/// unless a key count mod was picked.
+fn read_mods_for_map(mode: GameMode, map: &PpBeatmap) -> Result<Vec<&'static ModOptionDef>> {
+    let mut selected = read_mods_for_mode(mode)?;
 
-    let mut bits = 0u32;
-    for m in selected {
-        bits |= m.bits;
+    let is_convert = mode == GameMode::Mania && map.mode == PpGameMode::Osu;
+
+    if is_convert
+        && !selected.iter().any(|m| m.is_key_count())
+        && let Some(key_mod) = read_key_count(map)?
+    {
+        selected.push(key_mod);
     }
 
-    Ok(bits)
+    Ok(selected)
 }
 
-fn apply_detailed_judgements(
-    perf: Performance<'_>,
-    detailed: DetailedJudgements,
-) -> Performance<'_> {
-    match detailed {
-        DetailedJudgements::Osu {
-            n300,
-            n100,
-            n50,
-            misses,
-        } => perf.n300(n300).n100(n100).n50(n50).misses(misses),
+fn read_key_count(map: &PpBeatmap) -> Result<Option<&'static ModOptionDef>> {
+    let default = match calc::mania_key_count(map, &GameMods::new()) {
+        Some(keys) => format!("Map default ({keys}K)"),
+        None => "Map default".to_owned(),
+    };
+
+    let mut select = Select::new("Key count")
+        .description("osu!standard maps are converted with this many keys")
+        .option(DemandOption::new(None).label(&default));
+
+    for m in mods::available_for_mode(GameMode::Mania).filter(|m| m.is_key_count()) {
+        select = select.option(DemandOption::new(Some(m)).label(m.acronym));
+    }
 
-        DetailedJudgements::Taiko { n300, n100, misses } => {
-            perf.n300(n300).n100(n100).misses(misses)
+    select.run().context("failed to read key count")
+}
+
+/// Asks for the settings of the picked mods that weren't given as flags.
+fn read_mod_settings(
+    selected: &[&ModOptionDef],
+    mode: GameMode,
+    mut settings: ModSettings,
+) -> Result<ModSettings> {
+    let picked = |acronyms: &[&str]| selected.iter().any(|m| acronyms.contains(&m.acronym));
+
+    if picked(&["DT", "NC", "HT", "DC"]) && settings.speed_change.is_none() {
+        settings.speed_change = read_optional_f32("Speed change", "e.g. 1.3, empty for default")?;
+    }
+
+    if picked(&["DA"]) {
+        let has_ar_cs = matches!(mode, GameMode::Osu | GameMode::Catch);
+
+        if has_ar_cs && settings.ar.is_none() {
+            settings.ar = read_optional_f32("Approach rate", "empty to keep the map's")?;
         }
 
-        DetailedJudgements::Catch {
-            fruits,
-            droplets,
-            tiny_droplets,
-            tiny_droplet_misses,
-            misses,
-        } => perf
-            .n300(fruits)
-            .large_tick_hits(droplets)
-            .small_tick_hits(tiny_droplets)
-            .n_katu(tiny_droplet_misses)
-            .misses(misses),
-
-        DetailedJudgements::Mania {
-            n320,
-            n300,
-            n200,
-            n100,
-            n50,
-            misses,
-        } => perf
-            .n_geki(n320)
-            .n300(n300)
-            .n_katu(n200)
-            .n100(n100)
-            .n50(n50)
-            .misses(misses),
+        if settings.od.is_none() {
+            settings.od = read_optional_f32("Overall difficulty", "empty to keep the map's")?;
+        }
+
+        if has_ar_cs && settings.cs.is_none() {
+            settings.cs = read_optional_f32("Circle size", "empty to keep the map's")?;
+        }
+
+        if settings.hp.is_none() {
+            settings.hp = read_optional_f32("HP drain", "empty to keep the map's")?;
+        }
     }
+
+    Ok(settings)
 }
 
-async fn fetch_user_best_scores(osu: &Osu, user_input: &str, mode: GameMode) -> Result<Vec<Score>> {
-    let trimmed = user_input.trim();
+fn read_optional_f32(label: &str, placeholder: &str) -> Result<Option<f32>> {
+    let raw = Input::new(label)
+        .placeholder(placeholder)
+        .prompt(&format!("{label}: "))
+        .run()
+        .with_context(|| format!("failed to read {label}"))?;
 
-    let builder = if let Ok(id) = trimmed.parse::<u32>() {
-        osu.user_scores(id)
-    } else {
-        osu.user_scores(trimmed)
-    };
+    let trimmed = raw.trim();
 
-    let scores = builder
-        .mode(mode)
-        .best()
-        .limit(100)
-        .await
-        .context("failed to fetch user top scores")?;
+    if trimmed.is_empty() {
+        return Ok(None);
+    }
 
-    Ok(scores)
+    trimmed
+        .parse()
+        .map(Some)
+        .with_context(|| format!("{label} must be a number"))
 }
 
-async fn download_osu_file(map_id: u32) -> Result<Vec<u8>> {
-    let url = format!("https://osu.ppy.sh/osu/{map_id}");
+/// Plays listed by the diffs of the top plays without `--top-n-diff`.
+const TOP_N_DIFF: usize = 10;
+
+fn print_shifted_plays(profile: &GainProjection, n: usize) {
+    let shifted: Vec<_> = profile.shifted_plays().collect();
+
+    if shifted.is_empty() {
+        return;
+    }
+
+    let total_lost: f64 = shifted.iter().map(|(_, _, lost)| lost).sum();
+
+    println!();
+    println!(
+        "{}",
+        style::heading(format!(
+            "{} plays move down a spot and lose {total_lost:.2} weighted pp:",
+            shifted.len()
+        ))
+    );
+
+    for (rank, play, lost) in shifted.iter().take(n) {
+        println!(
+            "  #{:<3} -> #{rank:<3} {:>8.2}pp  {}  {}",
+            play.old_rank.unwrap_or_default(),
+            play.pp,
+            style::delta(-lost, format!("{:>7.2}pp", -lost)),
+            play.label
+        );
+    }
+
+    if shifted.len() > n {
+        println!(
+            "{}",
+            style::dim(format!("  ... and {} more", shifted.len() - n))
+        );
+    }
 
-    let bytes = reqwest::get(&url)
-        .await
-        .with_context(|| format!("GET {url} failed"))?
-        .error_for_status()
-        .with_context(|| format!("{url} returned non-success status"))?
-        .bytes()
-        .await
-        .context("failed to read response body")?;
+    if let Some(weighted) = profile.new_play_weighted_pp {
+        let dropped = profile.dropped_weighted_pp().unwrap_or(0.0);
+
+        println!(
+            "Net: {weighted:.2} weighted pp from the new play - {total_lost:.2} shifted - {dropped:.2} dropped"
+        );
+    }
+}
 
-    Ok(bytes.to_vec())
+fn print_top_plays_diff(plays: &[TopPlay], n: usize) {
+    println!();
+    println!("{}", style::heading(format!("Top {n} plays afterwards:")));
+
+    for (i, play) in plays.iter().take(n).enumerate() {
+        let rank = i + 1;
+        let change = match play.old_rank {
+            None => style::delta(1.0, "NEW".to_owned()),
+            Some(old) if old != rank => style::dim(format!("was #{old}")),
+            Some(_) => style::dim(String::new()),
+        };
+
+        println!(
+            "  #{rank:<3} {:>8.2}pp  {:<9} {}",
+            play.pp, change, play.label
+        );
+    }
 }
 
-fn weighted_total_pp(pps: &[f64]) -> f64 {
-    pps.iter()
-        .take(100)
-        .enumerate()
-        .map(|(i, pp)| pp * 0.95_f64.powi(i as i32))
-        .sum()
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_combo_reads_absolute_combos() {
+        assert!(matches!(
+            parse_combo("1200"),
+            Ok(Some(ComboInput::Absolute(1200)))
+        ));
+        assert!(matches!(
+            parse_combo(" 0 "),
+            Ok(Some(ComboInput::Absolute(0)))
+        ));
+        assert!(matches!(parse_combo("  "), Ok(None)));
+    }
+
+    #[test]
+    fn parse_combo_reads_percentages() {
+        assert!(matches!(
+            parse_combo("85%"),
+            Ok(Some(ComboInput::PercentOfMax(pct))) if pct == 85.0
+        ));
+        assert!(matches!(
+            parse_combo("100 %"),
+            Ok(Some(ComboInput::PercentOfMax(pct))) if pct == 100.0
+        ));
+    }
+
+    #[test]
+    fn parse_combo_rejects_invalid_combos() {
+        for raw in ["-5", "1.5", "max", "0%", "101%", "NaN%", "%"] {
+            assert!(parse_combo(raw).is_err(), "{raw}");
+        }
+    }
+
+    #[test]
+    fn apply_history_args_replaces_the_play() {
+        let mut cli = Cli::try_parse_from([
+            "ppify",
+            "--json",
+            "--local",
+            "--diff-name",
+            "Easy",
+            "--mods",
+            "HR",
+            "--combo",
+            "500",
+        ])
+        .unwrap();
+        // as `history_args` stores a play on a difficulty of a .osz
+        let args = [
+            "--user",
+            "peppy",
+            "--mode",
+            "taiko",
+            "--map",
+            "/songs/set.osz",
+            "--diff-name",
+            "Insane",
+            "--mods",
+            "HDDT",
+            "--speed",
+            "1.25",
+            "--acc",
+            "98.5",
+            "--misses",
+            "1",
+        ]
+        .map(String::from);
+
+        apply_history_args(&mut cli, &args).unwrap();
+
+        assert_eq!(cli.user.as_deref(), Some("peppy"));
+        assert!(matches!(cli.mode, Some(ModeArg::Taiko)));
+        assert_eq!(cli.map.as_deref(), Some("/songs/set.osz"));
+        assert_eq!(cli.diff_name.as_deref(), Some("Insane"));
+        assert!(!cli.local);
+        assert_eq!(cli.set, None);
+        assert_eq!(cli.mods.as_deref(), Some("HDDT"));
+        assert_eq!(cli.speed, Some(1.25));
+        assert_eq!(cli.score.acc, Some(98.5));
+        assert_eq!(cli.score.misses, Some(1));
+        // the entered combo was part of the old play
+        assert_eq!(cli.score.combo, None);
+        // output flags aren't part of the play
+        assert!(cli.json);
+    }
+
+    #[test]
+    fn apply_history_args_rejects_unknown_flags() {
+        let mut cli = Cli::try_parse_from(["ppify"]).unwrap();
+        let args = ["--no-such-flag".to_owned()];
+
+        assert!(apply_history_args(&mut cli, &args).is_err());
+    }
 }
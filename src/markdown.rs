@@ -0,0 +1,433 @@
+//! `--format markdown`: results as Markdown tables for Discord, GitHub and spreadsheets.
+
+use {
+    ppify::{
+        batch_file::BatchResult,
+        calc::{AccuracyRow, MissRow, ModComboRow},
+        history::HistoryEntry,
+        report::{
+            CalculationReport, CollectionReport, IfFcReport, LocalScoresReport, MatrixReport,
+            SnapshotDiff,
+        },
+    },
+    std::{fmt::Write, iter},
+};
+
+pub fn calculation(report: &CalculationReport) -> String {
+    let mut out = format!(
+        "**{}**{}\n\n",
+        escape(&report.beatmap.to_string()),
+        mods(&report.mods.concat())
+    );
+    let profile = &report.profile;
+
+    out += &table(
+        &[
+            ("pp", Align::Right),
+            ("Stars", Align::Right),
+            ("Rules", Align::Left),
+            ("Old total", Align::Right),
+            ("New total", Align::Right),
+            ("Gain", Align::Right),
+        ],
+        [vec![
+            format!("{:.2}", report.pp),
+            format!("{:.2}", report.difficulty.stars),
+            report.rules.to_owned(),
+            format!("{:.2}", profile.old_total_pp),
+            format!("{:.2}", profile.new_total_pp),
+            format!("{:+.2}", profile.gain),
+        ]],
+    );
+
+    if let Some(rows) = &report.accuracy_table {
+        out += "\n";
+        out += &accuracy_table(rows);
+    }
+
+    if let Some(rows) = &report.miss_table {
+        out += "\n";
+        out += &miss_table(rows);
+    }
+
+    if let Some(rows) = &report.mod_matrix {
+        out += "\n";
+        out += &mod_matrix(rows);
+    }
+
+    out
+}
+
+fn accuracy_table(rows: &[AccuracyRow]) -> String {
+    table(
+        &[
+            ("Accuracy", Align::Right),
+            ("Misses", Align::Right),
+            ("pp", Align::Right),
+        ],
+        rows.iter().map(|row| {
+            vec![
+                format!("{:.2}%", row.accuracy),
+                row.misses.to_string(),
+                format!("{:.2}", row.pp),
+            ]
+        }),
+    )
+}
+
+fn miss_table(rows: &[MissRow]) -> String {
+    table(
+        &[("Misses", Align::Right), ("pp", Align::Right)],
+        rows.iter()
+            .map(|row| vec![row.misses.to_string(), format!("{:.2}", row.pp)]),
+    )
+}
+
+fn mod_matrix(rows: &[ModComboRow]) -> String {
+    table(
+        &[
+            ("Mods", Align::Left),
+            ("Stars", Align::Right),
+            ("pp", Align::Right),
+        ],
+        rows.iter().map(|row| {
+            vec![
+                row.mods.clone(),
+                format!("{:.2}", row.stars),
+                format!("{:.2}", row.pp),
+            ]
+        }),
+    )
+}
+
+pub fn batch(results: &[BatchResult]) -> String {
+    table(
+        &[
+            ("Map", Align::Right),
+            ("Mods", Align::Left),
+            ("Accuracy", Align::Right),
+            ("Misses", Align::Right),
+            ("Combo", Align::Right),
+            ("Stars", Align::Right),
+            ("pp", Align::Right),
+            ("Beatmap", Align::Left),
+        ],
+        results.iter().map(|result| {
+            vec![
+                result.map_id.to_string(),
+                none_as_nm(&result.mods),
+                format!("{:.2}%", result.accuracy),
+                result.misses.to_string(),
+                result
+                    .combo
+                    .map_or_else(|| "FC".to_owned(), |combo| format!("{combo}x")),
+                optional(result.stars),
+                optional(result.pp),
+                result
+                    .error
+                    .as_deref()
+                    .or(result.beatmap.as_deref())
+                    .unwrap_or_default()
+                    .to_owned(),
+            ]
+        }),
+    )
+}
+
+pub fn if_fc(report: &IfFcReport) -> String {
+    let mut out = table(
+        &[
+            ("#", Align::Right),
+            ("pp", Align::Right),
+            ("FC pp", Align::Right),
+            ("Choked", Align::Right),
+            ("Play", Align::Left),
+        ],
+        report.plays.iter().enumerate().map(|(i, play)| {
+            vec![
+                (i + 1).to_string(),
+                format!("{:.2}", play.pp),
+                format!("{:.2}", play.fc_pp),
+                format!("{:+.2}", play.choked_pp()),
+                play.label.clone(),
+            ]
+        }),
+    );
+
+    let _ = writeln!(
+        out,
+        "\nTotal: {:.2}pp, {:.2}pp if every play were an FC",
+        report.total_pp, report.fc_total_pp
+    );
+
+    out
+}
+
+pub fn collection(report: &CollectionReport) -> String {
+    let mut out = format!(
+        "**{}**: {:.2}% with {} misses{}\n\n",
+        escape(&report.collection),
+        report.accuracy,
+        report.misses,
+        mods(&report.mods)
+    );
+
+    out += &table(
+        &[
+            ("#", Align::Right),
+            ("pp", Align::Right),
+            ("Stars", Align::Right),
+            ("Mode", Align::Left),
+            ("Beatmap", Align::Left),
+        ],
+        report.maps.iter().enumerate().map(|(i, map)| {
+            let beatmap = match &map.error {
+                Some(err) => format!("{}: {err}", map.beatmap),
+                None => map.beatmap.to_string(),
+            };
+
+            vec![
+                (i + 1).to_string(),
+                optional(map.pp),
+                optional(map.stars),
+                map.mode.to_owned(),
+                beatmap,
+            ]
+        }),
+    );
+
+    out
+}
+
+pub fn local_scores(report: &LocalScoresReport) -> String {
+    table(
+        &[
+            ("pp", Align::Right),
+            ("Score", Align::Right),
+            ("Combo", Align::Right),
+            ("Mods", Align::Left),
+            ("Player", Align::Left),
+            ("Beatmap", Align::Left),
+        ],
+        report.scores.iter().map(|score| {
+            vec![
+                optional(score.pp),
+                score.score.to_string(),
+                score
+                    .play
+                    .combo
+                    .map_or_else(String::new, |combo| format!("{combo}x")),
+                score.mods.clone(),
+                score.player.clone(),
+                score.beatmap.to_string(),
+            ]
+        }),
+    )
+}
+
+pub fn snapshot_diff(report: &SnapshotDiff) -> String {
+    let mut rows = vec![vec![
+        "pp".to_owned(),
+        format!("{:.2}", report.old_pp),
+        format!("{:.2}", report.new_pp),
+        format!("{:+.2}", report.pp_gained),
+    ]];
+
+    for (name, rank) in [
+        ("Global rank", report.global_rank),
+        ("Country rank", report.country_rank),
+    ] {
+        if let Some(rank) = rank {
+            rows.push(vec![
+                name.to_owned(),
+                format!("#{}", rank.old),
+                format!("#{}", rank.new),
+                format!("{:+}", i64::from(rank.old) - i64::from(rank.new)),
+            ]);
+        }
+    }
+
+    rows.push(vec![
+        "Accuracy".to_owned(),
+        format!("{:.2}%", report.old_accuracy),
+        format!("{:.2}%", report.new_accuracy),
+        format!("{:+.2}%", report.new_accuracy - report.old_accuracy),
+    ]);
+
+    let mut out = format!(
+        "**{}** ({}), {} to {}\n\n",
+        escape(&report.username),
+        report.mode,
+        report.from,
+        report.to
+    );
+
+    out += &table(
+        &[
+            ("", Align::Left),
+            ("Before", Align::Right),
+            ("After", Align::Right),
+            ("Change", Align::Right),
+        ],
+        rows,
+    );
+
+    if !report.new_plays.is_empty() {
+        out += "\n";
+        out += &table(
+            &[
+                ("#", Align::Right),
+                ("pp", Align::Right),
+                ("Accuracy", Align::Right),
+                ("New top play", Align::Left),
+            ],
+            report.new_plays.iter().map(|new_play| {
+                vec![
+                    new_play.rank.to_string(),
+                    format!("{:.2}", new_play.play.pp),
+                    format!("{:.2}%", new_play.play.accuracy),
+                    new_play.play.label.clone(),
+                ]
+            }),
+        );
+    }
+
+    out
+}
+
+pub fn history(entries: &[HistoryEntry]) -> String {
+    table(
+        &[
+            ("#", Align::Right),
+            ("Time", Align::Left),
+            ("User", Align::Left),
+            ("Beatmap", Align::Left),
+            ("Mods", Align::Left),
+            ("Play", Align::Left),
+            ("pp", Align::Right),
+            ("Gain", Align::Right),
+        ],
+        entries.iter().map(|entry| {
+            vec![
+                entry.id.to_string(),
+                entry.time.clone(),
+                entry.user.clone(),
+                entry.beatmap.clone(),
+                none_as_nm(&entry.mods),
+                entry.play.clone(),
+                format!("{:.2}", entry.pp),
+                format!("{:+.2}", entry.gain),
+            ]
+        }),
+    )
+}
+
+#[derive(Clone, Copy)]
+enum Align {
+    Left,
+    Right,
+}
+
+fn table(columns: &[(&str, Align)], rows: impl IntoIterator<Item = Vec<String>>) -> String {
+    let headers: Vec<_> = columns.iter().map(|(header, _)| *header).collect();
+    let mut out = format!("| {} |\n|", headers.join(" | "));
+
+    for (_, align) in columns {
+        out += match align {
+            Align::Left => "---|",
+            Align::Right => "---:|",
+        };
+    }
+
+    out += "\n";
+
+    for row in rows {
+        let cells: Vec<_> = row.iter().map(|cell| escape(cell)).collect();
+        let _ = writeln!(out, "| {} |", cells.join(" | "));
+    }
+
+    out
+}
+
+// pipes end a cell and asterisks or underscores in titles would turn into emphasis
+fn escape(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+
+    for c in text.chars() {
+        if matches!(c, '|' | '*' | '_' | '`' | '\\') {
+            escaped.push('\\');
+        }
+
+        escaped.push(c);
+    }
+
+    escaped
+}
+
+fn mods(mods: &str) -> String {
+    if mods.is_empty() {
+        String::new()
+    } else {
+        format!(" +{mods}")
+    }
+}
+
+fn none_as_nm(mods: &str) -> String {
+    if mods.is_empty() {
+        "NM".to_owned()
+    } else {
+        mods.to_owned()
+    }
+}
+
+pub fn matrix(report: &MatrixReport) -> String {
+    let headers: Vec<String> = report
+        .maps
+        .iter()
+        .map(|map| map.map_id.to_string())
+        .collect();
+    let columns: Vec<_> = iter::once(("Player", Align::Left))
+        .chain(iter::once(("Total", Align::Right)))
+        .chain(headers.iter().map(|header| (header.as_str(), Align::Right)))
+        .collect();
+
+    let mut out = table(
+        &columns,
+        report.players.iter().map(|player| {
+            let mut row = vec![player.user.clone(), optional(player.total_pp)];
+
+            match &player.error {
+                Some(err) => row.push(err.clone()),
+                None => row.extend(player.projected_pp.iter().copied().map(optional)),
+            }
+
+            row
+        }),
+    );
+
+    out += "\n";
+    out += &table(
+        &[
+            ("Map", Align::Right),
+            ("pp", Align::Right),
+            ("Beatmap", Align::Left),
+        ],
+        report.maps.iter().map(|map| {
+            vec![
+                map.map_id.to_string(),
+                optional(map.pp),
+                map.error
+                    .as_deref()
+                    .or(map.beatmap.as_deref())
+                    .unwrap_or_default()
+                    .to_owned(),
+            ]
+        }),
+    );
+
+    out
+}
+
+fn optional(value: Option<f64>) -> String {
+    value.map_or_else(|| "-".to_owned(), |value| format!("{value:.2}"))
+}
@@ -0,0 +1,217 @@
+//! `--matrix`: the same play on several beatmaps for several players, as a grid.
+
+use {
+    crate::{batch::download_maps, cli::Cli, fetch_profile, markdown, read_mode},
+    color_eyre::{Result, eyre::Context},
+    ppify::{
+        api::Client,
+        batch_file,
+        calc::{self, BeatmapMetadata, BonusPp, Judgements, PlayInputs, Rules},
+        export, mods,
+        report::{MatrixMap, MatrixPlayer, MatrixReport},
+    },
+    std::{collections::HashSet, fs::File, path::Path},
+};
+
+pub async fn run(osu: &Client, cli: &Cli, input: &Path) -> Result<()> {
+    let file = File::open(input).with_context(|| format!("failed to open {}", input.display()))?;
+    let matrix = batch_file::read_matrix(file)?;
+
+    let (api_mode, pp_mode) = match cli.mode {
+        Some(mode) => mode.modes(),
+        None => read_mode()?,
+    };
+
+    let selected_mods = mods::parse_acronyms(cli.mods.as_deref().unwrap_or(""), api_mode)?;
+    let game_mods = mods::lazer_mods(selected_mods.iter().copied(), api_mode);
+    let rules = Rules::new(cli.stable);
+
+    // every cell is the same play, an SS unless the flags say otherwise
+    let accuracy = cli.score.acc.unwrap_or(100.0);
+    let misses = cli.score.misses.unwrap_or(0);
+    let play = PlayInputs {
+        judgements: Judgements::Accuracy { accuracy, misses },
+        combo: None,
+    };
+
+    let map_ids: HashSet<u32> = matrix.map_ids.iter().copied().collect();
+    let bytes = download_maps(osu, cli, map_ids).await;
+
+    let maps: Vec<MatrixMap> = matrix
+        .map_ids
+        .iter()
+        .map(|&map_id| {
+            let calculated = bytes[&map_id]
+                .as_ref()
+                .map_err(ToString::to_string)
+                .and_then(|bytes| {
+                    let map = calc::parse_beatmap(bytes, pp_mode).map_err(|err| err.to_string())?;
+                    let pp =
+                        calc::calculate_play(&map, pp_mode, &game_mods, None, rules, &play).pp();
+
+                    Ok((BeatmapMetadata::from_osu_bytes(bytes).to_string(), pp))
+                });
+
+            match calculated {
+                Ok((beatmap, pp)) => MatrixMap {
+                    map_id,
+                    beatmap: Some(beatmap),
+                    pp: Some(pp),
+                    error: None,
+                },
+                Err(err) => MatrixMap {
+                    map_id,
+                    beatmap: None,
+                    pp: None,
+                    error: Some(err),
+                },
+            }
+        })
+        .collect();
+
+    let weighting = cli.weighting();
+    let mut players = Vec::with_capacity(matrix.players.len());
+
+    for user in &matrix.players {
+        // one player that doesn't exist shouldn't end the whole grid
+        let player = match fetch_profile(osu, cli, user, api_mode).await {
+            Ok((scores, profile)) => {
+                let projected_pp = maps
+                    .iter()
+                    .map(|map| {
+                        let map_id = Some(map.map_id);
+                        let bonus = BonusPp::for_user(&profile, &scores, &[map_id]);
+
+                        map.pp.map(|pp| {
+                            calc::project_gain(&scores, pp, "", map_id, bonus, &weighting)
+                                .new_total_pp
+                        })
+                    })
+                    .collect();
+
+                MatrixPlayer {
+                    user: profile.username.to_string(),
+                    total_pp: Some(calc::total_pp(
+                        &scores,
+                        BonusPp::for_user(&profile, &scores, &[]),
+                        &weighting,
+                    )),
+                    projected_pp,
+                    error: None,
+                }
+            }
+            Err(err) => MatrixPlayer {
+                user: user.clone(),
+                total_pp: None,
+                projected_pp: Vec::new(),
+                error: Some(format!("{err:#}")),
+            },
+        };
+
+        players.push(player);
+    }
+
+    let report = MatrixReport {
+        mode: api_mode.as_str(),
+        mods: selected_mods.iter().map(|m| m.acronym).collect(),
+        accuracy,
+        misses,
+        maps,
+        players,
+    };
+
+    if let Some(path) = &cli.out {
+        export::write_csv(path, &csv_rows(&report))
+            .with_context(|| format!("failed to write {}", path.display()))?;
+    }
+
+    if cli.json {
+        let json = serde_json::to_string_pretty(&report).context("failed to serialize report")?;
+        println!("{json}");
+    } else if cli.markdown() {
+        print!("{}", markdown::matrix(&report));
+    } else {
+        print_report(&report);
+    }
+
+    Ok(())
+}
+
+// the header of the input file with the projected totals filled in
+fn csv_rows(report: &MatrixReport) -> Vec<Vec<String>> {
+    let header = ["player".to_owned(), "total_pp".to_owned()]
+        .into_iter()
+        .chain(report.maps.iter().map(|map| map.map_id.to_string()))
+        .collect();
+
+    let cell = |pp: Option<f64>| pp.map_or_else(String::new, |pp| format!("{pp:.2}"));
+    let rows = report.players.iter().map(|player| {
+        [player.user.clone(), cell(player.total_pp)]
+            .into_iter()
+            .chain(player.projected_pp.iter().copied().map(cell))
+            .collect()
+    });
+
+    std::iter::once(header).chain(rows).collect()
+}
+
+fn print_report(report: &MatrixReport) {
+    let mods = if report.mods.is_empty() {
+        "NM"
+    } else {
+        &report.mods
+    };
+
+    println!();
+    println!(
+        "Projected totals after a {:.2}% {mods} play with {} misses ({})",
+        report.accuracy, report.misses, report.mode
+    );
+    println!();
+
+    print!("  {:<16} {:>9}", "player", "total");
+
+    for map in &report.maps {
+        print!(" {:>10}", map.map_id);
+    }
+
+    println!();
+
+    for player in &report.players {
+        print!(
+            "  {:<16} {:>9}",
+            player.user,
+            player
+                .total_pp
+                .map_or_else(|| "-".to_owned(), |pp| format!("{pp:.2}"))
+        );
+
+        match &player.error {
+            Some(err) => print!("  {err}"),
+            None => {
+                for pp in &player.projected_pp {
+                    print!(
+                        " {:>10}",
+                        pp.map_or_else(|| "-".to_owned(), |pp| format!("{pp:.2}"))
+                    );
+                }
+            }
+        }
+
+        println!();
+    }
+
+    println!();
+
+    for map in &report.maps {
+        match (&map.error, map.pp) {
+            (Some(err), _) => println!("  {:>10}  {err}", map.map_id),
+            (None, Some(pp)) => println!(
+                "  {:>10}  {pp:>8.2}pp  {}",
+                map.map_id,
+                map.beatmap.as_deref().unwrap_or_default()
+            ),
+            (None, None) => println!("  {:>10}", map.map_id),
+        }
+    }
+}
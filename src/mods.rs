@@ -0,0 +1,748 @@
+use {
+    crate::error::PpifyError,
+    rosu_v2::prelude::{Acronym, GameMod, GameModIntermode, GameMode, GameMods, GameModsIntermode},
+    serde::Serialize,
+    std::fmt::Display,
+};
+
+pub struct ModOptionDef {
+    pub acronym: &'static str,
+    pub bits: u32,
+    pub description: &'static str,
+    pub modes: &'static [GameMode],
+    pub pp_effect: PpEffect,
+}
+
+/// What a mod does to the pp of a play, for `--explain-mods`.
+#[derive(Clone, Copy, Debug)]
+pub enum PpEffect {
+    /// Changes the speed, and with it the difficulty.
+    Rate,
+    /// Changes the difficulty settings of the map.
+    Difficulty(&'static str),
+    /// Adjusts the pp of the play, not the map.
+    Adjusts(&'static str),
+    /// Not part of the pp calculation.
+    None,
+    /// Plays with it give no pp at all.
+    NoPp,
+}
+
+impl PpEffect {
+    /// A short line for the mod, `clock_rate` being the rate the play is calculated at.
+    pub fn describe(self, clock_rate: f64) -> String {
+        match self {
+            Self::Rate => format!("rate {clock_rate:.2}x"),
+            Self::Difficulty(effect) | Self::Adjusts(effect) => effect.to_owned(),
+            Self::None => "no pp effect".to_owned(),
+            Self::NoPp => "no pp, plays with it aren't ranked".to_owned(),
+        }
+    }
+}
+
+impl Display for ModOptionDef {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let out_str = format!(
+            "Acronym: {}\n
+Bits: {}\n
+Description: {}\n
+Modes: {}
+            ",
+            self.acronym,
+            self.bits,
+            self.description,
+            self.modes
+                .iter()
+                .map(|a| a.as_str())
+                .collect::<Vec<_>>()
+                .join(",")
+        );
+
+        write!(f, "{}", out_str)
+    }
+}
+
+const fn b(bit: u32) -> u32 {
+    1 << bit
+}
+
+pub const MODS_LAZER: &[ModOptionDef] = &[
+    ModOptionDef {
+        acronym: "EZ",
+        bits: b(1),
+        description: "Easy",
+        modes: &[
+            GameMode::Osu,
+            GameMode::Taiko,
+            GameMode::Catch,
+            GameMode::Mania,
+        ],
+        pp_effect: PpEffect::Difficulty("lower CS, AR, OD and HP"),
+    },
+    ModOptionDef {
+        acronym: "NF",
+        bits: b(0),
+        description: "No Fail",
+        modes: &[
+            GameMode::Osu,
+            GameMode::Taiko,
+            GameMode::Catch,
+            GameMode::Mania,
+        ],
+        pp_effect: PpEffect::Adjusts("slightly less pp"),
+    },
+    ModOptionDef {
+        acronym: "HT",
+        bits: b(8),
+        description: "Half Time",
+        modes: &[
+            GameMode::Osu,
+            GameMode::Taiko,
+            GameMode::Catch,
+            GameMode::Mania,
+        ],
+        pp_effect: PpEffect::Rate,
+    },
+    ModOptionDef {
+        acronym: "DC",
+        bits: 0,
+        description: "Daycore (lazer only)",
+        modes: &[
+            GameMode::Osu,
+            GameMode::Taiko,
+            GameMode::Catch,
+            GameMode::Mania,
+        ],
+        pp_effect: PpEffect::Rate,
+    },
+    ModOptionDef {
+        acronym: "NR",
+        bits: 0,
+        description: "No Release (mania only, no PP effect here)",
+        modes: &[GameMode::Mania],
+        pp_effect: PpEffect::None,
+    },
+    ModOptionDef {
+        acronym: "HR",
+        bits: b(4),
+        description: "Hard Rock",
+        modes: &[
+            GameMode::Osu,
+            GameMode::Taiko,
+            GameMode::Catch,
+            GameMode::Mania,
+        ],
+        pp_effect: PpEffect::Difficulty("higher CS, AR, OD and HP"),
+    },
+    ModOptionDef {
+        acronym: "SD",
+        bits: b(5),
+        description: "Sudden Death",
+        modes: &[
+            GameMode::Osu,
+            GameMode::Taiko,
+            GameMode::Catch,
+            GameMode::Mania,
+        ],
+        pp_effect: PpEffect::None,
+    },
+    ModOptionDef {
+        acronym: "PF",
+        bits: b(5) | b(14),
+        description: "Perfect (full combo SD)",
+        modes: &[
+            GameMode::Osu,
+            GameMode::Taiko,
+            GameMode::Catch,
+            GameMode::Mania,
+        ],
+        pp_effect: PpEffect::None,
+    },
+    ModOptionDef {
+        acronym: "DT",
+        bits: b(6),
+        description: "Double Time",
+        modes: &[
+            GameMode::Osu,
+            GameMode::Taiko,
+            GameMode::Catch,
+            GameMode::Mania,
+        ],
+        pp_effect: PpEffect::Rate,
+    },
+    ModOptionDef {
+        acronym: "NC",
+        bits: b(6) | b(9),
+        description: "Nightcore (DT variant)",
+        modes: &[
+            GameMode::Osu,
+            GameMode::Taiko,
+            GameMode::Catch,
+            GameMode::Mania,
+        ],
+        pp_effect: PpEffect::Rate,
+    },
+    ModOptionDef {
+        acronym: "HD",
+        bits: b(3),
+        description: "Hidden",
+        modes: &[
+            GameMode::Osu,
+            GameMode::Taiko,
+            GameMode::Catch,
+            GameMode::Mania,
+        ],
+        pp_effect: PpEffect::Adjusts("more pp, except in osu!mania"),
+    },
+    ModOptionDef {
+        acronym: "FI",
+        bits: 0,
+        description: "Fade In (mania only in stable)",
+        modes: &[GameMode::Mania],
+        pp_effect: PpEffect::None,
+    },
+    ModOptionDef {
+        acronym: "CO",
+        bits: 0,
+        description: "Cover (lazer only, no PP effect here)",
+        modes: &[
+            GameMode::Osu,
+            GameMode::Taiko,
+            GameMode::Catch,
+            GameMode::Mania,
+        ],
+        pp_effect: PpEffect::None,
+    },
+    ModOptionDef {
+        acronym: "FL",
+        bits: b(10),
+        description: "Flashlight",
+        modes: &[GameMode::Osu, GameMode::Catch, GameMode::Mania],
+        pp_effect: PpEffect::Adjusts("more pp in osu!standard and osu!catch"),
+    },
+    ModOptionDef {
+        acronym: "BL",
+        bits: 0,
+        description: "Blinds (lazer only)",
+        modes: &[GameMode::Osu, GameMode::Catch, GameMode::Mania],
+        pp_effect: PpEffect::Adjusts("more pp in osu!standard"),
+    },
+    ModOptionDef {
+        acronym: "ST",
+        bits: 0,
+        description: "Strict Tracking (taiko only, no PP effect here)",
+        modes: &[GameMode::Taiko],
+        pp_effect: PpEffect::None,
+    },
+    ModOptionDef {
+        acronym: "AC",
+        bits: 0,
+        description: "Accuracy Challenge (lazer only, no PP effect here)",
+        modes: &[
+            GameMode::Osu,
+            GameMode::Taiko,
+            GameMode::Catch,
+            GameMode::Mania,
+        ],
+        pp_effect: PpEffect::None,
+    },
+    ModOptionDef {
+        acronym: "AT",
+        bits: b(7),
+        description: "Autoplay (no PP)",
+        modes: &[
+            GameMode::Osu,
+            GameMode::Taiko,
+            GameMode::Catch,
+            GameMode::Mania,
+        ],
+        pp_effect: PpEffect::NoPp,
+    },
+    ModOptionDef {
+        acronym: "AP",
+        bits: b(9),
+        description: "AutoPilot (osu!, no PP)",
+        modes: &[GameMode::Osu],
+        pp_effect: PpEffect::NoPp,
+    },
+    ModOptionDef {
+        acronym: "CN",
+        bits: 0,
+        description: "Cinema (no PP)",
+        modes: &[GameMode::Osu, GameMode::Catch],
+        pp_effect: PpEffect::NoPp,
+    },
+    ModOptionDef {
+        acronym: "RL",
+        bits: 0,
+        description: "Relax (no PP)",
+        modes: &[GameMode::Osu, GameMode::Catch],
+        pp_effect: PpEffect::NoPp,
+    },
+    ModOptionDef {
+        acronym: "RX",
+        bits: 0,
+        description: "Classic Relax acronym (no PP)",
+        modes: &[GameMode::Osu, GameMode::Catch],
+        pp_effect: PpEffect::NoPp,
+    },
+    ModOptionDef {
+        acronym: "TD",
+        bits: 0,
+        description: "Target Practice / Touch Device (no PP)",
+        modes: &[GameMode::Osu],
+        pp_effect: PpEffect::NoPp,
+    },
+    ModOptionDef {
+        acronym: "SO",
+        bits: b(12),
+        description: "Spun Out (osu! only)",
+        modes: &[GameMode::Osu],
+        pp_effect: PpEffect::Adjusts("less pp, the more spinners the map has"),
+    },
+    ModOptionDef {
+        acronym: "DA",
+        bits: 0,
+        description: "Difficulty Adjust (lazer only)",
+        modes: &[
+            GameMode::Osu,
+            GameMode::Taiko,
+            GameMode::Catch,
+            GameMode::Mania,
+        ],
+        pp_effect: PpEffect::Difficulty("the set CS, AR, OD and HP"),
+    },
+    ModOptionDef {
+        acronym: "TC",
+        bits: 0,
+        description: "Traceable (lazer only)",
+        modes: &[GameMode::Osu],
+        pp_effect: PpEffect::None,
+    },
+    ModOptionDef {
+        acronym: "WI",
+        bits: 0,
+        description: "Wiggle (lazer only)",
+        modes: &[GameMode::Osu],
+        pp_effect: PpEffect::None,
+    },
+    ModOptionDef {
+        acronym: "CL",
+        bits: 0,
+        description: "Classic (lazer: emulate stable quirks)",
+        modes: &[GameMode::Osu, GameMode::Taiko],
+        pp_effect: PpEffect::Adjusts(
+            "osu!stable's rules, e.g. slider ends don't count towards accuracy",
+        ),
+    },
+    ModOptionDef {
+        acronym: "RD",
+        bits: 0,
+        description: "Random (mania only, no PP)",
+        modes: &[GameMode::Mania],
+        pp_effect: PpEffect::None,
+    },
+    ModOptionDef {
+        acronym: "MR",
+        bits: 0,
+        description: "Mirror (mania only, no PP)",
+        modes: &[GameMode::Mania],
+        pp_effect: PpEffect::None,
+    },
+    ModOptionDef {
+        acronym: "ATC",
+        bits: 0,
+        description: "Adaptive Speed / Challenge (lazer system, no PP)",
+        modes: &[
+            GameMode::Osu,
+            GameMode::Taiko,
+            GameMode::Catch,
+            GameMode::Mania,
+        ],
+        pp_effect: PpEffect::NoPp,
+    },
+    ModOptionDef {
+        acronym: "1K",
+        bits: 0,
+        description: "1 key (mania converts)",
+        modes: &[GameMode::Mania],
+        pp_effect: PpEffect::Difficulty("the key count of the convert"),
+    },
+    ModOptionDef {
+        acronym: "2K",
+        bits: 0,
+        description: "2 keys (converts)",
+        modes: &[GameMode::Mania],
+        pp_effect: PpEffect::Difficulty("the key count of the convert"),
+    },
+    ModOptionDef {
+        acronym: "3K",
+        bits: 0,
+        description: "3 keys (converts)",
+        modes: &[GameMode::Mania],
+        pp_effect: PpEffect::Difficulty("the key count of the convert"),
+    },
+    ModOptionDef {
+        acronym: "4K",
+        bits: b(15),
+        description: "4 keys (converts)",
+        modes: &[GameMode::Mania],
+        pp_effect: PpEffect::Difficulty("the key count of the convert"),
+    },
+    ModOptionDef {
+        acronym: "5K",
+        bits: b(16),
+        description: "5 keys (converts)",
+        modes: &[GameMode::Mania],
+        pp_effect: PpEffect::Difficulty("the key count of the convert"),
+    },
+    ModOptionDef {
+        acronym: "6K",
+        bits: b(17),
+        description: "6 keys (converts)",
+        modes: &[GameMode::Mania],
+        pp_effect: PpEffect::Difficulty("the key count of the convert"),
+    },
+    ModOptionDef {
+        acronym: "7K",
+        bits: b(18),
+        description: "7 keys (converts)",
+        modes: &[GameMode::Mania],
+        pp_effect: PpEffect::Difficulty("the key count of the convert"),
+    },
+    ModOptionDef {
+        acronym: "8K",
+        bits: b(19),
+        description: "8 keys (converts)",
+        modes: &[GameMode::Mania],
+        pp_effect: PpEffect::Difficulty("the key count of the convert"),
+    },
+    ModOptionDef {
+        acronym: "9K",
+        bits: b(24),
+        description: "9 keys (converts)",
+        modes: &[GameMode::Mania],
+        pp_effect: PpEffect::Difficulty("the key count of the convert"),
+    },
+];
+
+pub fn available_for_mode(mode: GameMode) -> impl Iterator<Item = &'static ModOptionDef> {
+    MODS_LAZER.iter().filter(move |m| m.modes.contains(&mode))
+}
+
+impl ModOptionDef {
+    /// Whether this is one of the osu!mania key count mods 1K to 9K.
+    pub fn is_key_count(&self) -> bool {
+        matches!(self.acronym.as_bytes(), [b'1'..=b'9', b'K'])
+    }
+}
+
+/// Builds lazer mods for `mode`, so mods without a legacy bit still reach rosu-pp.
+pub fn lazer_mods<'a>(
+    mods: impl IntoIterator<Item = &'a ModOptionDef>,
+    mode: GameMode,
+) -> GameMods {
+    mods.into_iter()
+        .filter_map(|m| m.acronym.parse::<Acronym>().ok())
+        .map(GameModIntermode::from_acronym)
+        .collect::<GameModsIntermode>()
+        .with_mode(mode)
+}
+
+/// Lazer mods of an osu!stable score's mod bits. CL is added, so the score is calculated
+/// with stable's rules.
+pub fn stable_score_mods(bits: u32, mode: GameMode) -> GameMods {
+    let mut mods = GameModsIntermode::from_bits(bits);
+    mods.insert(GameModIntermode::Classic);
+
+    mods.with_mode(mode)
+}
+
+/// Parses an acronym string such as `HDDT`, `HD,HR` or `+hdhr`.
+///
+/// An empty string, `NM` and `nomod` all mean NoMod and yield no mods.
+pub fn parse_acronyms(
+    acronyms: &str,
+    mode: GameMode,
+) -> Result<Vec<&'static ModOptionDef>, PpifyError> {
+    let normalized: String = acronyms
+        .chars()
+        .filter(char::is_ascii_alphanumeric)
+        .collect::<String>()
+        .to_ascii_uppercase();
+
+    if normalized.is_empty() || normalized == "NM" || normalized == "NOMOD" {
+        return Ok(Vec::new());
+    }
+
+    let mut rest = normalized.as_str();
+    let mut mods = Vec::new();
+
+    while !rest.is_empty() {
+        let m = MODS_LAZER
+            .iter()
+            .filter(|m| rest.starts_with(m.acronym))
+            .max_by_key(|m| m.acronym.len())
+            .ok_or_else(|| {
+                let unknown = rest.get(..2).unwrap_or(rest);
+
+                PpifyError::BadInput(format!("unknown mod acronym `{unknown}` in `{acronyms}`"))
+            })?;
+
+        if !m.modes.contains(&mode) {
+            return Err(PpifyError::BadInput(format!(
+                "{} is not available in mode `{}`",
+                m.acronym,
+                mode.as_str()
+            )));
+        }
+
+        mods.push(m);
+        rest = &rest[m.acronym.len()..];
+    }
+
+    Ok(mods)
+}
+
+const KEY_COUNTS: [GameModIntermode; 9] = [
+    GameModIntermode::OneKey,
+    GameModIntermode::TwoKeys,
+    GameModIntermode::ThreeKeys,
+    GameModIntermode::FourKeys,
+    GameModIntermode::FiveKeys,
+    GameModIntermode::SixKeys,
+    GameModIntermode::SevenKeys,
+    GameModIntermode::EightKeys,
+    GameModIntermode::NineKeys,
+];
+
+/// Settings of lazer mods; `None` keeps the mod's default.
+#[derive(Clone, Copy, Debug, Default, Serialize)]
+pub struct ModSettings {
+    /// Rate of DT/NC/HT/DC.
+    pub speed_change: Option<f32>,
+    /// Difficulty Adjust overrides.
+    pub ar: Option<f32>,
+    pub od: Option<f32>,
+    pub cs: Option<f32>,
+    pub hp: Option<f32>,
+}
+
+/// Writes `settings` into the matching mods of `mods`.
+///
+/// Fails for values that are out of range or have no mod to go into, e.g. a
+/// speed change without DT/NC/HT/DC.
+pub fn apply_settings(
+    mods: GameMods,
+    mode: GameMode,
+    settings: &ModSettings,
+) -> Result<GameMods, PpifyError> {
+    let has =
+        |intermodes: &[GameModIntermode]| mods.iter().any(|m| intermodes.contains(&m.intermode()));
+
+    if let Some(speed) = settings.speed_change {
+        if !has(&[
+            GameModIntermode::DoubleTime,
+            GameModIntermode::Nightcore,
+            GameModIntermode::HalfTime,
+            GameModIntermode::Daycore,
+        ]) {
+            return Err(PpifyError::BadInput(
+                "a speed change needs DT, NC, HT or DC".to_owned(),
+            ));
+        }
+
+        if !(0.5..=2.0).contains(&speed) {
+            return Err(PpifyError::BadInput(format!(
+                "speed change must be between 0.5 and 2.0, got {speed}"
+            )));
+        }
+    }
+
+    let da_values = [
+        ("AR", settings.ar),
+        ("OD", settings.od),
+        ("CS", settings.cs),
+        ("HP", settings.hp),
+    ];
+
+    if da_values.iter().any(|(_, value)| value.is_some())
+        && !has(&[GameModIntermode::DifficultyAdjust])
+    {
+        return Err(PpifyError::BadInput(
+            "AR/OD/CS/HP overrides need Difficulty Adjust (DA)".to_owned(),
+        ));
+    }
+
+    for (name, value) in da_values {
+        if let Some(value) = value
+            && !(0.0..=10.0).contains(&value)
+        {
+            return Err(PpifyError::BadInput(format!(
+                "{name} must be between 0 and 10, got {value}"
+            )));
+        }
+    }
+
+    // taiko and mania have neither approach rate nor circle size
+    if matches!(mode, GameMode::Taiko | GameMode::Mania)
+        && (settings.ar.is_some() || settings.cs.is_some())
+    {
+        return Err(PpifyError::BadInput(format!(
+            "AR and CS can't be adjusted in mode `{}`",
+            mode.as_str()
+        )));
+    }
+
+    let key_counts = mods
+        .iter()
+        .filter(|m| KEY_COUNTS.contains(&m.intermode()))
+        .count();
+
+    if key_counts > 1 {
+        return Err(PpifyError::BadInput(
+            "only one key count mod (1K to 9K) can be used".to_owned(),
+        ));
+    }
+
+    let speed = settings.speed_change;
+
+    let mods = mods
+        .into_iter()
+        .map(|mut m| {
+            match &mut m {
+                GameMod::DoubleTimeOsu(m) => m.speed_change = speed,
+                GameMod::DoubleTimeTaiko(m) => m.speed_change = speed,
+                GameMod::DoubleTimeCatch(m) => m.speed_change = speed,
+                GameMod::DoubleTimeMania(m) => m.speed_change = speed,
+                GameMod::NightcoreOsu(m) => m.speed_change = speed,
+                GameMod::NightcoreTaiko(m) => m.speed_change = speed,
+                GameMod::NightcoreCatch(m) => m.speed_change = speed,
+                GameMod::NightcoreMania(m) => m.speed_change = speed,
+                GameMod::HalfTimeOsu(m) => m.speed_change = speed,
+                GameMod::HalfTimeTaiko(m) => m.speed_change = speed,
+                GameMod::HalfTimeCatch(m) => m.speed_change = speed,
+                GameMod::HalfTimeMania(m) => m.speed_change = speed,
+                GameMod::DaycoreOsu(m) => m.speed_change = speed,
+                GameMod::DaycoreTaiko(m) => m.speed_change = speed,
+                GameMod::DaycoreCatch(m) => m.speed_change = speed,
+                GameMod::DaycoreMania(m) => m.speed_change = speed,
+                GameMod::DifficultyAdjustOsu(m) => {
+                    m.approach_rate = settings.ar;
+                    m.overall_difficulty = settings.od;
+                    m.circle_size = settings.cs;
+                    m.drain_rate = settings.hp;
+                }
+                GameMod::DifficultyAdjustTaiko(m) => {
+                    m.overall_difficulty = settings.od;
+                    m.drain_rate = settings.hp;
+                }
+                GameMod::DifficultyAdjustCatch(m) => {
+                    m.approach_rate = settings.ar;
+                    m.overall_difficulty = settings.od;
+                    m.circle_size = settings.cs;
+                    m.drain_rate = settings.hp;
+                }
+                GameMod::DifficultyAdjustMania(m) => {
+                    m.overall_difficulty = settings.od;
+                    m.drain_rate = settings.hp;
+                }
+                _ => {}
+            }
+
+            m
+        })
+        .collect();
+
+    Ok(mods)
+}
+
+/// `mods` without the ones in `ignored`, to calculate as if those had no pp effect.
+pub fn without(mods: GameMods, ignored: &[&ModOptionDef]) -> GameMods {
+    mods.into_iter()
+        .filter(|m| {
+            !ignored
+                .iter()
+                .any(|ignored| ignored.acronym == m.acronym().as_str())
+        })
+        .collect()
+}
+
+/// Whether a score with `mods` belongs to the profile of `--baseline-mods`: with exactly
+/// the `baseline` mods when `exact`, otherwise with at least them. CL only marks scores
+/// set on osu!stable, so it counts for neither unless it is part of `baseline`.
+pub fn matches_baseline(mods: &GameMods, baseline: &[&ModOptionDef], exact: bool) -> bool {
+    let in_baseline = |acronym: &str| baseline.iter().any(|m| m.acronym == acronym);
+    let has_all = baseline
+        .iter()
+        .all(|def| mods.iter().any(|m| m.acronym().as_str() == def.acronym));
+
+    has_all
+        && (!exact
+            || mods.iter().all(|m| {
+                let acronym = m.acronym();
+
+                acronym.as_str() == "CL" || in_baseline(acronym.as_str())
+            }))
+}
+
+/// Mod combinations compared by default, per mode.
+pub fn common_combinations(mode: GameMode) -> &'static [&'static str] {
+    match mode {
+        GameMode::Osu => &[
+            "NM", "HD", "HR", "DT", "EZ", "FL", "HDHR", "HDDT", "HRDT", "HDFL", "HDHRDT",
+        ],
+        GameMode::Taiko | GameMode::Catch => &["NM", "HD", "HR", "DT", "EZ", "HDHR", "HDDT"],
+        GameMode::Mania => &["NM", "DT", "HT", "EZ"],
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn acronyms(input: &str, mode: GameMode) -> Vec<&'static str> {
+        parse_acronyms(input, mode)
+            .unwrap()
+            .iter()
+            .map(|m| m.acronym)
+            .collect()
+    }
+
+    #[test]
+    fn parse_acronyms_accepts_the_usual_spellings() {
+        assert_eq!(acronyms("HDDT", GameMode::Osu), ["HD", "DT"]);
+        assert_eq!(acronyms("hd,hr", GameMode::Osu), ["HD", "HR"]);
+        assert_eq!(acronyms("+NC HD", GameMode::Taiko), ["NC", "HD"]);
+        assert_eq!(acronyms("4kDT", GameMode::Mania), ["4K", "DT"]);
+        // the longest acronym wins over its prefix
+        assert_eq!(acronyms("ATC", GameMode::Osu), ["ATC"]);
+    }
+
+    #[test]
+    fn parse_acronyms_reads_nomod_as_no_mods() {
+        for nomod in ["", "NM", "nm", "nomod", " + "] {
+            assert!(acronyms(nomod, GameMode::Osu).is_empty(), "{nomod:?}");
+        }
+    }
+
+    #[test]
+    fn parse_acronyms_rejects_unknown_and_unavailable_mods() {
+        let err = parse_acronyms("HDXX", GameMode::Osu).unwrap_err();
+        assert_eq!(err.to_string(), "unknown mod acronym `XX` in `HDXX`");
+
+        let err = parse_acronyms("4K", GameMode::Osu).unwrap_err();
+        assert_eq!(err.to_string(), "4K is not available in mode `osu`");
+    }
+
+    #[test]
+    fn lazer_mods_keeps_mods_without_a_legacy_bit() {
+        // Difficulty Adjust has no legacy bit to carry it
+        let selected = parse_acronyms("HDDA", GameMode::Osu).unwrap();
+        let mods = lazer_mods(selected, GameMode::Osu);
+
+        let mut acronyms: Vec<_> = mods.iter().map(|m| m.acronym().to_string()).collect();
+        acronyms.sort();
+        assert_eq!(acronyms, ["DA", "HD"]);
+    }
+}
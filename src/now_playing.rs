@@ -0,0 +1,347 @@
+use {
+    crate::{cli::Cli, modes_for, read_map_bytes},
+    color_eyre::{
+        Result,
+        eyre::{self, Context},
+    },
+    futures_util::{SinkExt, StreamExt},
+    ppify::{
+        api::Client,
+        calc::{self, BeatmapMetadata, Judgements, PlayInputs, Rules},
+        config::NowPlayingSource,
+        input::MapSource,
+        mods,
+    },
+    rosu_pp::{Beatmap as PpBeatmap, model::mode::GameMode as PpGameMode},
+    rosu_v2::prelude::{GameMode, GameMods},
+    serde::Deserialize,
+    serde_json::Value,
+    std::path::PathBuf,
+    tokio::io::{AsyncBufReadExt, BufReader},
+    tokio_tungstenite::{connect_async, tungstenite::Message},
+};
+
+/// The parts of gosumemory's (and tosu's) websocket state ppify needs.
+#[derive(Debug, Deserialize)]
+struct GosuState {
+    settings: GosuSettings,
+    menu: GosuMenu,
+}
+
+#[derive(Debug, Deserialize)]
+struct GosuSettings {
+    folders: GosuFolders,
+}
+
+#[derive(Debug, Deserialize)]
+struct GosuFolders {
+    songs: PathBuf,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct GosuMenu {
+    game_mode: u8,
+    bm: GosuBeatmap,
+    mods: GosuMods,
+}
+
+#[derive(Debug, Deserialize)]
+struct GosuBeatmap {
+    id: u32,
+    path: GosuPath,
+}
+
+#[derive(Debug, Deserialize)]
+struct GosuPath {
+    folder: PathBuf,
+    file: PathBuf,
+}
+
+#[derive(Debug, Deserialize)]
+struct GosuMods {
+    #[serde(rename = "str")]
+    acronyms: String,
+}
+
+/// The beatmap, mods and mode selected in the osu! client.
+#[derive(Clone, Debug, PartialEq)]
+struct NowPlaying {
+    /// `None` for unsubmitted maps.
+    map_id: Option<u32>,
+    file: PathBuf,
+    mods: String,
+    mode: GameMode,
+}
+
+impl From<GosuState> for NowPlaying {
+    fn from(state: GosuState) -> Self {
+        let path = state.menu.bm.path;
+
+        Self {
+            map_id: (state.menu.bm.id > 0).then_some(state.menu.bm.id),
+            file: state
+                .settings
+                .folders
+                .songs
+                .join(path.folder)
+                .join(path.file),
+            mods: state.menu.mods.acronyms,
+            mode: GameMode::from(state.menu.game_mode),
+        }
+    }
+}
+
+// the tokens ppify subscribes to
+const STREAM_COMPANION_TOKENS: [&str; 4] = ["mapid", "osuFileLocation", "mods", "gameMode"];
+
+/// StreamCompanion only sends the tokens that changed, so they are collected here.
+#[derive(Debug, Default, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct StreamCompanionTokens {
+    mapid: Option<u32>,
+    osu_file_location: Option<PathBuf>,
+    /// e.g. `HD,DT` or `None`.
+    mods: Option<String>,
+    /// A name such as `OsuMania`, or the mode's number.
+    game_mode: Option<Value>,
+}
+
+impl StreamCompanionTokens {
+    fn update(&mut self, changed: Self) {
+        self.mapid = changed.mapid.or(self.mapid);
+        self.osu_file_location = changed.osu_file_location.or(self.osu_file_location.take());
+        self.mods = changed.mods.or(self.mods.take());
+        self.game_mode = changed.game_mode.or(self.game_mode.take());
+    }
+
+    /// `None` until every token was received once.
+    fn now_playing(&self) -> Option<NowPlaying> {
+        let mode = match self.game_mode.as_ref()? {
+            Value::Number(n) => GameMode::from(u8::try_from(n.as_u64()?).ok()?),
+            Value::String(name) => match name.as_str() {
+                "Osu" => GameMode::Osu,
+                "Taiko" => GameMode::Taiko,
+                "CatchTheBeat" => GameMode::Catch,
+                "OsuMania" => GameMode::Mania,
+                _ => return None,
+            },
+            _ => return None,
+        };
+
+        let mods = match self.mods.as_deref()? {
+            "None" => String::new(),
+            mods => mods.to_owned(),
+        };
+
+        Some(NowPlaying {
+            map_id: self.mapid.filter(|&id| id > 0),
+            file: self.osu_file_location.clone()?,
+            mods,
+            mode,
+        })
+    }
+}
+
+/// Turns the messages of a source into what is selected in the client.
+enum Feed {
+    Gosumemory,
+    StreamCompanion(StreamCompanionTokens),
+}
+
+impl Feed {
+    // other messages, e.g. while the client starts up, are skipped
+    fn update(&mut self, text: &str) -> Option<NowPlaying> {
+        match self {
+            Self::Gosumemory => serde_json::from_str::<GosuState>(text)
+                .ok()
+                .map(NowPlaying::from),
+            Self::StreamCompanion(tokens) => {
+                tokens.update(serde_json::from_str(text).ok()?);
+                tokens.now_playing()
+            }
+        }
+    }
+}
+
+struct LoadedMap {
+    beatmap: BeatmapMetadata,
+    map: PpBeatmap,
+    pp_mode: PpGameMode,
+    mods: GameMods,
+    rules: Rules,
+    label: String,
+}
+
+impl LoadedMap {
+    async fn load(osu: &Client, playing: &NowPlaying, cli: &Cli) -> Result<Self> {
+        // the local file also covers unsubmitted maps and needs no download
+        let source = match playing.map_id {
+            Some(map_id) if !playing.file.is_file() => MapSource::Id { map_id, mode: None },
+            _ => MapSource::File(playing.file.clone()),
+        };
+
+        let bytes = read_map_bytes(osu, &source, cli).await?;
+        let (api_mode, pp_mode) = modes_for(playing.mode);
+        let map = calc::parse_beatmap(&bytes, pp_mode)?;
+        let selected = mods::parse_acronyms(&playing.mods, api_mode)?;
+
+        let label = if selected.is_empty() {
+            String::new()
+        } else {
+            format!(
+                " +{}",
+                selected.iter().map(|m| m.acronym).collect::<String>()
+            )
+        };
+
+        Ok(Self {
+            beatmap: BeatmapMetadata::from_osu_bytes(&bytes),
+            map,
+            pp_mode,
+            mods: mods::lazer_mods(selected, api_mode),
+            rules: Rules::new(cli.stable),
+            label,
+        })
+    }
+
+    fn pp(&self, accuracy: f64, misses: u32) -> f64 {
+        let play = PlayInputs {
+            judgements: Judgements::Accuracy { accuracy, misses },
+            combo: None,
+        };
+
+        calc::calculate_play(&self.map, self.pp_mode, &self.mods, None, self.rules, &play).pp()
+    }
+}
+
+pub async fn run(osu: &Client, cli: &Cli, source: NowPlayingSource, url: &str) -> Result<()> {
+    let (mut socket, _) = connect_async(url).await.with_context(|| {
+        format!(
+            "failed to connect to {url}, is {} running?",
+            source_name(source)
+        )
+    })?;
+
+    let mut feed = match source {
+        NowPlayingSource::Gosumemory => Feed::Gosumemory,
+        NowPlayingSource::StreamCompanion => {
+            let tokens = serde_json::to_string(&STREAM_COMPANION_TOKENS)
+                .context("failed to serialize tokens")?;
+
+            socket
+                .send(Message::text(tokens))
+                .await
+                .context("failed to subscribe to StreamCompanion's tokens")?;
+
+            Feed::StreamCompanion(StreamCompanionTokens::default())
+        }
+    };
+
+    let mut lines = BufReader::new(tokio::io::stdin()).lines();
+
+    let mut playing: Option<NowPlaying> = None;
+    let mut loaded: Option<LoadedMap> = None;
+
+    #[cfg(feature = "rich-presence")]
+    let mut presence = crate::rich_presence(cli);
+
+    eprintln!("Connected to {url}");
+    eprintln!("Type an accuracy, optionally with misses (`98.5 1`), or nothing for 95-100%.");
+
+    loop {
+        tokio::select! {
+            message = socket.next() => {
+                let Some(message) = message else {
+                    eyre::bail!("{url} closed the connection");
+                };
+
+                let Message::Text(text) = message.context("failed to read the websocket")? else {
+                    continue;
+                };
+
+                let Some(current) = feed.update(&text) else {
+                    continue;
+                };
+
+                if playing.as_ref() == Some(&current) {
+                    continue;
+                }
+
+                loaded = match LoadedMap::load(osu, &current, cli).await {
+                    Ok(map) => {
+                        println!();
+                        println!("Now playing: {}{}", map.beatmap, map.label);
+
+                        #[cfg(feature = "rich-presence")]
+                        if let Some(presence) = &mut presence {
+                            let state = format!("{:.0}pp for an SS{}", map.pp(100.0, 0), map.label);
+                            presence.show(&map.beatmap.to_string(), &state);
+                        }
+
+                        Some(map)
+                    }
+                    Err(err) => {
+                        eprintln!("Error: {err:?}");
+
+                        None
+                    }
+                };
+                playing = Some(current);
+            }
+            line = lines.next_line() => {
+                let Some(line) = line.context("failed to read stdin")? else {
+                    return Ok(());
+                };
+
+                let Some(map) = &loaded else {
+                    eprintln!("No beatmap selected in osu! yet");
+                    continue;
+                };
+
+                if let Err(err) = print_pp(map, &line) {
+                    eprintln!("Error: {err:?}");
+                }
+            }
+        }
+    }
+}
+
+fn source_name(source: NowPlayingSource) -> &'static str {
+    match source {
+        NowPlayingSource::Gosumemory => "gosumemory or tosu",
+        NowPlayingSource::StreamCompanion => "StreamCompanion",
+    }
+}
+
+fn print_pp(map: &LoadedMap, line: &str) -> Result<()> {
+    let mut parts = line.split_whitespace();
+
+    let Some(accuracy) = parts.next() else {
+        for accuracy in 95..=100 {
+            let accuracy = f64::from(accuracy);
+            println!("  {accuracy:>6.2}%  {:>8.2}pp", map.pp(accuracy, 0));
+        }
+
+        return Ok(());
+    };
+
+    let accuracy: f64 = accuracy
+        .trim_end_matches('%')
+        .parse()
+        .context("accuracy must be a number like 98.5")?;
+
+    let misses: u32 = match parts.next() {
+        Some(misses) => misses
+            .parse()
+            .context("misses must be an unsigned integer")?,
+        None => 0,
+    };
+
+    println!(
+        "  {accuracy:>6.2}%  {misses} misses  {:>8.2}pp",
+        map.pp(accuracy, misses)
+    );
+
+    Ok(())
+}
@@ -0,0 +1,373 @@
+//! Readers for osu! stable's database files in the game folder.
+
+use std::{
+    fs,
+    io::{self, ErrorKind},
+    path::Path,
+};
+
+/// `osu!.db` versions from here on store no entry size before each beatmap.
+const NO_ENTRY_SIZE_VERSION: u32 = 20191106;
+/// `osu!.db` versions from here on store float difficulty values and star ratings.
+const FLOAT_DIFFICULTY_VERSION: u32 = 20140609;
+/// `osu!.db` versions from here on store star ratings as singles instead of doubles.
+const SINGLE_STARS_VERSION: u32 = 20250107;
+
+/// A beatmap as listed in `osu!.db`.
+#[derive(Clone, Debug)]
+pub struct DbBeatmap {
+    pub artist: String,
+    pub title: String,
+    pub creator: String,
+    pub version: String,
+    pub md5: String,
+    /// File name of the `.osu` file inside `folder`.
+    pub file_name: String,
+    /// Beatmapset folder relative to the Songs folder.
+    pub folder: String,
+    /// 0 to 3 for osu!standard, taiko, catch and mania.
+    pub mode: u8,
+    /// 0 for unsubmitted beatmaps.
+    pub beatmap_id: u32,
+    pub beatmapset_id: u32,
+}
+
+/// The beatmaps of `osu!.db`.
+pub fn read_osu_db(path: &Path) -> io::Result<Vec<DbBeatmap>> {
+    let bytes = fs::read(path)?;
+    let mut reader = DbReader::new(&bytes);
+
+    let version = reader.u32()?;
+    reader.skip(4 + 1 + 8)?; // folder count, account unlocked, unlock date
+    reader.string()?; // player name
+
+    let count = reader.u32()?;
+    let mut beatmaps = Vec::with_capacity(count.min(1 << 20) as usize);
+
+    for _ in 0..count {
+        beatmaps.push(reader.db_beatmap(version)?);
+    }
+
+    Ok(beatmaps)
+}
+
+/// A collection of `collection.db`.
+#[derive(Clone, Debug)]
+pub struct Collection {
+    pub name: String,
+    /// MD5 hashes of the beatmaps' `.osu` files.
+    pub md5s: Vec<String>,
+}
+
+/// The collections of `collection.db`.
+pub fn read_collection_db(path: &Path) -> io::Result<Vec<Collection>> {
+    let bytes = fs::read(path)?;
+    let mut reader = DbReader::new(&bytes);
+
+    reader.u32()?; // version
+    let count = reader.u32()?;
+    let mut collections = Vec::new();
+
+    for _ in 0..count {
+        let name = reader.string()?;
+        let len = reader.u32()?;
+        let md5s = (0..len)
+            .map(|_| reader.string())
+            .collect::<io::Result<_>>()?;
+
+        collections.push(Collection { name, md5s });
+    }
+
+    Ok(collections)
+}
+
+/// Mod bit of Target Practice, whose scores store one more value.
+const TARGET_PRACTICE: u32 = 1 << 23;
+
+/// A score of `scores.db`.
+#[derive(Clone, Debug)]
+pub struct DbScore {
+    /// 0 to 3 for osu!standard, taiko, catch and mania.
+    pub mode: u8,
+    pub beatmap_md5: String,
+    pub player: String,
+    pub n300: u32,
+    pub n100: u32,
+    pub n50: u32,
+    pub n_geki: u32,
+    pub n_katu: u32,
+    pub misses: u32,
+    pub score: u32,
+    pub max_combo: u32,
+    /// Legacy mod bits.
+    pub mods: u32,
+    /// 0 for scores that weren't submitted.
+    pub online_id: u64,
+}
+
+/// Every score of `scores.db`.
+pub fn read_scores_db(path: &Path) -> io::Result<Vec<DbScore>> {
+    let bytes = fs::read(path)?;
+    let mut reader = DbReader::new(&bytes);
+
+    reader.u32()?; // version
+    let beatmaps = reader.u32()?;
+    let mut scores = Vec::new();
+
+    for _ in 0..beatmaps {
+        reader.string()?; // beatmap md5, repeated in every score
+        let count = reader.u32()?;
+
+        for _ in 0..count {
+            scores.push(reader.db_score()?);
+        }
+    }
+
+    Ok(scores)
+}
+
+/// Sequential reader for the primitive types of osu!'s database files.
+pub(crate) struct DbReader<'a> {
+    bytes: &'a [u8],
+}
+
+impl<'a> DbReader<'a> {
+    pub(crate) fn new(bytes: &'a [u8]) -> Self {
+        Self { bytes }
+    }
+
+    fn take(&mut self, len: usize) -> io::Result<&'a [u8]> {
+        if self.bytes.len() < len {
+            return Err(io::Error::new(
+                ErrorKind::UnexpectedEof,
+                "osu! database ends early",
+            ));
+        }
+
+        let (head, rest) = self.bytes.split_at(len);
+        self.bytes = rest;
+
+        Ok(head)
+    }
+
+    pub(crate) fn skip(&mut self, len: usize) -> io::Result<()> {
+        self.take(len).map(|_| ())
+    }
+
+    pub(crate) fn u8(&mut self) -> io::Result<u8> {
+        Ok(self.take(1)?[0])
+    }
+
+    pub(crate) fn u16(&mut self) -> io::Result<u16> {
+        Ok(u16::from_le_bytes(self.take(2)?.try_into().unwrap()))
+    }
+
+    pub(crate) fn u32(&mut self) -> io::Result<u32> {
+        Ok(u32::from_le_bytes(self.take(4)?.try_into().unwrap()))
+    }
+
+    pub(crate) fn u64(&mut self) -> io::Result<u64> {
+        Ok(u64::from_le_bytes(self.take(8)?.try_into().unwrap()))
+    }
+
+    /// `0x00` for no string, or `0x0b`, a ULEB128 length and UTF-8 bytes.
+    pub(crate) fn string(&mut self) -> io::Result<String> {
+        match self.u8()? {
+            0x00 => Ok(String::new()),
+            0x0b => {
+                let len = self.uleb128()?;
+                let bytes = self.take(len)?;
+
+                Ok(String::from_utf8_lossy(bytes).into_owned())
+            }
+            tag => Err(io::Error::new(
+                ErrorKind::InvalidData,
+                format!("unexpected string tag {tag:#04x} in osu! database"),
+            )),
+        }
+    }
+
+    fn uleb128(&mut self) -> io::Result<usize> {
+        let mut value = 0;
+
+        for shift in (0..64).step_by(7) {
+            let byte = self.u8()?;
+            value |= usize::from(byte & 0x7f) << shift;
+
+            if byte & 0x80 == 0 {
+                return Ok(value);
+            }
+        }
+
+        Err(io::Error::new(
+            ErrorKind::InvalidData,
+            "string length too long in osu! database",
+        ))
+    }
+
+    fn db_score(&mut self) -> io::Result<DbScore> {
+        let mode = self.u8()?;
+        self.u32()?; // version
+        let beatmap_md5 = self.string()?;
+        let player = self.string()?;
+        self.string()?; // replay md5
+
+        let n300 = self.u16()?.into();
+        let n100 = self.u16()?.into();
+        let n50 = self.u16()?.into();
+        let n_geki = self.u16()?.into();
+        let n_katu = self.u16()?.into();
+        let misses = self.u16()?.into();
+        let score = self.u32()?;
+        let max_combo = self.u16()?.into();
+        self.u8()?; // perfect combo
+        let mods = self.u32()?;
+
+        self.string()?; // life bar graph
+        self.skip(8 + 4)?; // timestamp, always -1
+        let online_id = self.u64()?;
+
+        if mods & TARGET_PRACTICE != 0 {
+            self.skip(8)?; // target practice accuracy
+        }
+
+        Ok(DbScore {
+            mode,
+            beatmap_md5,
+            player,
+            n300,
+            n100,
+            n50,
+            n_geki,
+            n_katu,
+            misses,
+            score,
+            max_combo,
+            mods,
+            online_id,
+        })
+    }
+
+    fn db_beatmap(&mut self, version: u32) -> io::Result<DbBeatmap> {
+        if version < NO_ENTRY_SIZE_VERSION {
+            self.u32()?;
+        }
+
+        let artist = self.string()?;
+        self.string()?; // artist unicode
+        let title = self.string()?;
+        self.string()?; // title unicode
+        let creator = self.string()?;
+        let diff_name = self.string()?;
+        self.string()?; // audio file
+        let md5 = self.string()?;
+        let file_name = self.string()?;
+
+        // ranked status, circle/slider/spinner counts, last modification
+        self.skip(1 + 3 * 2 + 8)?;
+
+        // AR, CS, HP and OD, then the slider velocity
+        let difficulty_size = if version < FLOAT_DIFFICULTY_VERSION {
+            1
+        } else {
+            4
+        };
+        self.skip(4 * difficulty_size + 8)?;
+
+        if version >= FLOAT_DIFFICULTY_VERSION {
+            let star_size = if version < SINGLE_STARS_VERSION { 8 } else { 4 };
+
+            // star ratings per mod combination for each of the four modes
+            for _ in 0..4 {
+                let pairs = self.u32()? as usize;
+                self.skip(pairs * (1 + 4 + 1 + star_size))?;
+            }
+        }
+
+        self.skip(3 * 4)?; // drain time, total time, preview time
+
+        let timing_points = self.u32()? as usize;
+        self.skip(timing_points * (8 + 8 + 1))?;
+
+        let beatmap_id = self.u32()?;
+        let beatmapset_id = self.u32()?;
+
+        // thread id, grades, local offset, stack leniency
+        self.skip(4 + 4 + 2 + 4)?;
+        let mode = self.u8()?;
+
+        self.string()?; // source
+        self.string()?; // tags
+        self.u16()?; // online offset
+        self.string()?; // title font
+        self.skip(1 + 8 + 1)?; // unplayed, last played, osz2
+        let folder = self.string()?;
+
+        // last online check, ignore sound/skin, disable storyboard/video, visual override
+        self.skip(8 + 5)?;
+
+        if version < FLOAT_DIFFICULTY_VERSION {
+            self.u16()?;
+        }
+
+        self.skip(4 + 1)?; // last modification, mania scroll speed
+
+        Ok(DbBeatmap {
+            artist,
+            title,
+            creator,
+            version: diff_name,
+            md5,
+            file_name,
+            folder,
+            mode,
+            beatmap_id,
+            beatmapset_id,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn reader_reads_little_endian_integers() {
+        let bytes = [
+            0x2a, 0x34, 0x12, 0x78, 0x56, 0x34, 0x12, 1, 0, 0, 0, 0, 0, 0, 0x80,
+        ];
+        let mut reader = DbReader::new(&bytes);
+
+        assert_eq!(reader.u8().unwrap(), 0x2a);
+        assert_eq!(reader.u16().unwrap(), 0x1234);
+        assert_eq!(reader.u32().unwrap(), 0x1234_5678);
+        assert_eq!(reader.u64().unwrap(), 0x8000_0000_0000_0001);
+        assert_eq!(reader.u8().unwrap_err().kind(), ErrorKind::UnexpectedEof);
+    }
+
+    #[test]
+    fn reader_reads_strings() {
+        let long = "a".repeat(200);
+        // 200 as ULEB128 takes two bytes
+        let mut bytes = vec![0x00, 0x0b, 0x04, b'p', b'e', b'p', b'p', 0x0b, 0xc8, 0x01];
+        bytes.extend_from_slice(long.as_bytes());
+        let mut reader = DbReader::new(&bytes);
+
+        assert_eq!(reader.string().unwrap(), "");
+        assert_eq!(reader.string().unwrap(), "pepp");
+        assert_eq!(reader.string().unwrap(), long);
+    }
+
+    #[test]
+    fn reader_rejects_broken_strings() {
+        let err = DbReader::new(&[0x05]).string().unwrap_err();
+        assert_eq!(err.kind(), ErrorKind::InvalidData);
+
+        // a length past the end of the file
+        let err = DbReader::new(&[0x0b, 0x10, b'a']).string().unwrap_err();
+        assert_eq!(err.kind(), ErrorKind::UnexpectedEof);
+
+        let mut reader = DbReader::new(&[0x01, 0x02]);
+        assert!(reader.skip(3).is_err());
+    }
+}
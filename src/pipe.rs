@@ -0,0 +1,287 @@
+use {
+    crate::{
+        cli::{Cli, ModeArg},
+        map_cache, modes_for, score_cache, user_cache,
+    },
+    color_eyre::{Result, eyre::Context},
+    ppify::{
+        api::{self, Client},
+        cache::{MapCache, ScoreCache, UserCache},
+        calc::{
+            self, AccuracyRow, BeatmapMetadata, BonusPp, BuiltinWeighting, DifficultySummary,
+            GainProjection, Judgements, PlayInputs, Rules,
+        },
+        mods::{self, ModSettings},
+    },
+    rosu_pp::{Beatmap as PpBeatmap, model::mode::GameMode as PpGameMode},
+    rosu_v2::prelude::{GameMode, Score, UserExtended},
+    serde::{Deserialize, Serialize},
+    serde_json::Value,
+    std::{
+        collections::{HashMap, hash_map::Entry},
+        sync::Arc,
+    },
+    tokio::io::{AsyncBufReadExt, BufReader},
+};
+
+/// One line of input, e.g. `{"map_id": 3897329, "mods": "HDDT", "accuracy": 98.5}`.
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct PipeRequest {
+    /// Echoed back so callers can match answers to requests.
+    pub id: Option<Value>,
+    map_id: u32,
+    /// Defaults to `--mode`, then to the map's own mode.
+    mode: Option<ModeArg>,
+    #[serde(default)]
+    mods: String,
+    speed: Option<f32>,
+    rate: Option<f64>,
+    /// Defaults to 100.
+    accuracy: Option<f64>,
+    #[serde(default)]
+    misses: u32,
+    /// Defaults to a full combo.
+    combo: Option<u32>,
+    /// Also project the play onto this user's top plays.
+    user: Option<String>,
+    /// osu!stable's rules instead of lazer's, defaults to `--stable`.
+    stable: Option<bool>,
+}
+
+#[derive(Debug, Default, Serialize)]
+pub struct PipeResponse {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub id: Option<Value>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pp: Option<f64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    difficulty: Option<DifficultySummary>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    profile: Option<GainProjection>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
+
+/// The latest answered request, for the overlay of `ppify serve`.
+#[derive(Clone, Debug, Serialize)]
+pub struct LastCalculation {
+    pub map_id: u32,
+    pub beatmap: BeatmapMetadata,
+    pub mode: &'static str,
+    pub mods: Vec<&'static str>,
+    pub stars: f64,
+    pub pp: f64,
+    pub accuracy_table: Vec<AccuracyRow>,
+}
+
+/// Everything worth keeping between requests.
+pub struct PipeState {
+    osu: Arc<Client>,
+    cache: Option<MapCache>,
+    refresh: bool,
+    score_cache: Option<ScoreCache>,
+    user_cache: Option<UserCache>,
+    refresh_scores: bool,
+    pub default_mode: Option<ModeArg>,
+    /// For requests without `stable`.
+    rules: Rules,
+    pub weighting: BuiltinWeighting,
+    maps: HashMap<u32, (PpBeatmap, BeatmapMetadata)>,
+    // keyed by the trimmed user input and the mode
+    users: HashMap<(String, u8), (Vec<Score>, UserExtended)>,
+    /// Whether to fill `last`, which costs an accuracy table per request.
+    pub keep_last: bool,
+    pub last: Option<LastCalculation>,
+}
+
+pub async fn run(osu: Client, cli: &Cli) -> Result<()> {
+    let mut state = PipeState::new(Arc::new(osu), cli);
+    let mut lines = BufReader::new(tokio::io::stdin()).lines();
+
+    while let Some(line) = lines.next_line().await.context("failed to read stdin")? {
+        if line.trim().is_empty() {
+            continue;
+        }
+
+        let response = match serde_json::from_str::<PipeRequest>(&line) {
+            Ok(request) => {
+                let id = request.id.clone();
+
+                state
+                    .answer(request)
+                    .await
+                    .unwrap_or_else(|err| PipeResponse {
+                        id,
+                        error: Some(format!("{err:#}")),
+                        ..PipeResponse::default()
+                    })
+            }
+            Err(err) => PipeResponse {
+                error: Some(format!("invalid request: {err}")),
+                ..PipeResponse::default()
+            },
+        };
+
+        let json = serde_json::to_string(&response).context("failed to serialize response")?;
+        println!("{json}");
+    }
+
+    Ok(())
+}
+
+impl PipeState {
+    pub fn new(osu: Arc<Client>, cli: &Cli) -> Self {
+        Self {
+            osu,
+            cache: map_cache(cli),
+            refresh: cli.refresh,
+            score_cache: score_cache(cli),
+            user_cache: user_cache(cli),
+            refresh_scores: cli.refresh_scores,
+            default_mode: cli.mode,
+            rules: Rules::new(cli.stable),
+            weighting: cli.weighting(),
+            maps: HashMap::new(),
+            users: HashMap::new(),
+            keep_last: false,
+            last: None,
+        }
+    }
+
+    pub async fn answer(&mut self, request: PipeRequest) -> Result<PipeResponse> {
+        let (map, beatmap) = match self.maps.entry(request.map_id) {
+            Entry::Occupied(entry) => entry.into_mut(),
+            Entry::Vacant(entry) => {
+                let bytes = api::fetch_osu_file(
+                    &self.osu,
+                    request.map_id,
+                    None,
+                    self.cache.as_ref(),
+                    self.refresh,
+                )
+                .await?;
+                let map = calc::parse_native_beatmap(&bytes)?;
+
+                entry.insert((map, BeatmapMetadata::from_osu_bytes(&bytes)))
+            }
+        };
+
+        let (api_mode, pp_mode) = match request.mode.or(self.default_mode) {
+            Some(mode) => mode.modes(),
+            None => modes_for(native_mode(map.mode)),
+        };
+
+        calc::check_map_mode(map, pp_mode)?;
+
+        let selected_mods = mods::parse_acronyms(&request.mods, api_mode)?;
+        let game_mods = mods::apply_settings(
+            mods::lazer_mods(selected_mods.iter().copied(), api_mode),
+            api_mode,
+            &ModSettings {
+                speed_change: request.speed,
+                ..ModSettings::default()
+            },
+        )?;
+
+        let play = PlayInputs {
+            judgements: Judgements::Accuracy {
+                accuracy: request.accuracy.unwrap_or(100.0),
+                misses: request.misses,
+            },
+            combo: request.combo,
+        };
+        let rules = request.stable.map_or(self.rules, Rules::new);
+
+        let perf_attrs = calc::calculate_play(map, pp_mode, &game_mods, request.rate, rules, &play);
+        let pp = perf_attrs.pp();
+
+        if self.keep_last {
+            self.last = Some(LastCalculation {
+                map_id: request.map_id,
+                beatmap: beatmap.clone(),
+                mode: api_mode.as_str(),
+                mods: selected_mods.iter().map(|m| m.acronym).collect(),
+                stars: perf_attrs.stars(),
+                pp,
+                accuracy_table: calc::accuracy_table(
+                    &perf_attrs,
+                    &game_mods,
+                    request.rate,
+                    rules,
+                    &play,
+                    calc::sweep_points(pp_mode),
+                ),
+            });
+        }
+
+        let profile = match request.user.as_deref() {
+            Some(user) => {
+                let weighting = self.weighting;
+                let (scores, user) = self.user(user, api_mode).await?;
+                let bonus = BonusPp::for_user(user, scores, &[Some(request.map_id)]);
+
+                Some(calc::project_gain(
+                    scores,
+                    pp,
+                    "Hypothetical play",
+                    Some(request.map_id),
+                    bonus,
+                    &weighting,
+                ))
+            }
+            None => None,
+        };
+
+        Ok(PipeResponse {
+            id: request.id,
+            pp: Some(pp),
+            difficulty: Some(DifficultySummary::from_performance(&perf_attrs)),
+            profile,
+            error: None,
+        })
+    }
+
+    pub async fn user(
+        &mut self,
+        user: &str,
+        mode: GameMode,
+    ) -> Result<&(Vec<Score>, UserExtended)> {
+        let user = user.trim();
+
+        let cached = match self.users.entry((user.to_owned(), mode as u8)) {
+            Entry::Occupied(entry) => entry.into_mut(),
+            Entry::Vacant(entry) => {
+                let scores = api::fetch_user_best_scores_cached(
+                    &self.osu,
+                    user,
+                    mode,
+                    self.score_cache.as_ref(),
+                    self.refresh_scores,
+                )
+                .await?;
+                let profile = api::fetch_user_cached(
+                    &self.osu,
+                    user,
+                    mode,
+                    self.user_cache.as_ref(),
+                    self.refresh_scores,
+                )
+                .await?;
+
+                entry.insert((scores, profile))
+            }
+        };
+
+        Ok(cached)
+    }
+}
+
+pub fn native_mode(mode: PpGameMode) -> GameMode {
+    match mode {
+        PpGameMode::Osu => GameMode::Osu,
+        PpGameMode::Taiko => GameMode::Taiko,
+        PpGameMode::Catch => GameMode::Catch,
+        PpGameMode::Mania => GameMode::Mania,
+    }
+}
@@ -0,0 +1,107 @@
+use {
+    crate::timestamp,
+    color_eyre::{Result, eyre::eyre},
+    plotters::{coord::Shift, prelude::*},
+    ppify::calc::{AccuracyRow, StrainGraph},
+    std::{ffi::OsStr, path::Path},
+};
+
+const SIZE: (u32, u32) = (1000, 800);
+
+/// Draws the strain over time and the pp by accuracy to `path`, as an SVG for `.svg`
+/// paths and a PNG otherwise.
+pub fn write(path: &Path, title: &str, graph: &StrainGraph, curve: &[AccuracyRow]) -> Result<()> {
+    let svg = path
+        .extension()
+        .and_then(OsStr::to_str)
+        .is_some_and(|ext| ext.eq_ignore_ascii_case("svg"));
+
+    let drawn = if svg {
+        draw(
+            SVGBackend::new(path, SIZE).into_drawing_area(),
+            title,
+            graph,
+            curve,
+        )
+        .map_err(|err| err.to_string())
+    } else {
+        draw(
+            BitMapBackend::new(path, SIZE).into_drawing_area(),
+            title,
+            graph,
+            curve,
+        )
+        .map_err(|err| err.to_string())
+    };
+
+    drawn.map_err(|err| eyre!("failed to write {}: {err}", path.display()))
+}
+
+fn draw<DB: DrawingBackend>(
+    root: DrawingArea<DB, Shift>,
+    title: &str,
+    graph: &StrainGraph,
+    curve: &[AccuracyRow],
+) -> Result<(), DrawingAreaErrorKind<DB::ErrorType>> {
+    root.fill(&WHITE)?;
+
+    let root = root.titled(title, ("sans-serif", 22))?;
+    let (strain_area, accuracy_area) = root.split_vertically(SIZE.1 / 2);
+
+    // strain over time, in seconds of map time
+    let points: Vec<(f64, f64)> = graph
+        .strains
+        .iter()
+        .enumerate()
+        .map(|(i, &strain)| (graph.section_start_ms(i) / 1000.0, strain))
+        .collect();
+
+    let end_secs = graph.section_start_ms(graph.strains.len()) / 1000.0;
+    let max_strain = graph.strains.iter().copied().fold(0.0, f64::max);
+
+    let mut chart = ChartBuilder::on(&strain_area)
+        .caption("Strain over time", ("sans-serif", 18))
+        .margin(15)
+        .x_label_area_size(35)
+        .y_label_area_size(60)
+        .build_cartesian_2d(
+            graph.start_ms / 1000.0..end_secs.max(1.0),
+            0.0..max_strain * 1.1,
+        )?;
+
+    chart
+        .configure_mesh()
+        .x_desc("Map time")
+        .y_desc("Strain")
+        .x_label_formatter(&|secs: &f64| timestamp(secs * 1000.0))
+        .draw()?;
+
+    chart.draw_series(AreaSeries::new(points, 0.0, BLUE.mix(0.3)).border_style(BLUE))?;
+
+    // pp by accuracy
+    let (Some(first), Some(last)) = (curve.first(), curve.last()) else {
+        return root.present();
+    };
+
+    let max_pp = curve.iter().map(|row| row.pp).fold(0.0, f64::max);
+
+    let mut chart = ChartBuilder::on(&accuracy_area)
+        .caption("PP by accuracy", ("sans-serif", 18))
+        .margin(15)
+        .x_label_area_size(35)
+        .y_label_area_size(60)
+        .build_cartesian_2d(first.accuracy..last.accuracy, 0.0..max_pp * 1.1)?;
+
+    chart
+        .configure_mesh()
+        .x_desc("Accuracy (%)")
+        .y_desc("PP")
+        .draw()?;
+
+    chart.draw_series(LineSeries::new(
+        curve.iter().map(|row| (row.accuracy, row.pp)),
+        RED.stroke_width(2),
+    ))?;
+
+    root.present()
+}
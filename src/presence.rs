@@ -0,0 +1,53 @@
+use {
+    color_eyre::{
+        Result,
+        eyre::{Context, eyre},
+    },
+    discord_rich_presence::{DiscordIpc, DiscordIpcClient, activity::Activity},
+    std::env,
+};
+
+/// Shows what ppify is calculating as the Discord activity of the user.
+pub struct Presence {
+    client: DiscordIpcClient,
+}
+
+impl Presence {
+    /// Connects to the running Discord client as the application in `DISCORD_APP_ID`.
+    pub fn connect() -> Result<Self> {
+        let app_id =
+            env::var("DISCORD_APP_ID").context("DISCORD_APP_ID must be set for --rich-presence")?;
+
+        let mut client = DiscordIpcClient::new(&app_id).map_err(|err| eyre!("{err}"))?;
+        client
+            .connect()
+            .map_err(|err| eyre!("failed to connect to Discord: {err}"))?;
+
+        Ok(Self { client })
+    }
+
+    /// Only warns on errors, the presence is an extra.
+    pub fn show(&mut self, details: &str, state: &str) {
+        let activity = Activity::new()
+            .details(truncate(details))
+            .state(truncate(state));
+
+        if let Err(err) = self.client.set_activity(activity) {
+            eprintln!("Warning: couldn't update the Discord presence: {err}");
+        }
+    }
+}
+
+impl Drop for Presence {
+    fn drop(&mut self) {
+        let _ = self.client.close();
+    }
+}
+
+// Discord rejects longer texts
+fn truncate(text: &str) -> &str {
+    match text.char_indices().nth(128) {
+        Some((end, _)) => &text[..end],
+        None => text,
+    }
+}
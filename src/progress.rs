@@ -0,0 +1,30 @@
+//! Spinners and progress bars on stderr, hidden when it isn't a terminal.
+
+use {
+    indicatif::{MultiProgress, ProgressBar, ProgressStyle},
+    std::{borrow::Cow, sync::LazyLock, time::Duration},
+};
+
+// requests run concurrently, so their spinners share one set of lines
+static BARS: LazyLock<MultiProgress> = LazyLock::new(MultiProgress::new);
+
+/// A spinner for a request of unknown length, remove it with `finish_and_clear`.
+pub fn spinner(message: impl Into<Cow<'static, str>>) -> ProgressBar {
+    let spinner = BARS.add(ProgressBar::new_spinner().with_message(message));
+    spinner.enable_steady_tick(Duration::from_millis(100));
+
+    spinner
+}
+
+/// A bar over `len` items, whose message tells the last finished one.
+pub fn bar(len: usize, prefix: &'static str) -> ProgressBar {
+    let style = ProgressStyle::with_template("{prefix} [{bar:30}] {pos}/{len} {wide_msg}")
+        .expect("valid progress template")
+        .progress_chars("=> ");
+
+    BARS.add(
+        ProgressBar::new(len as u64)
+            .with_style(style)
+            .with_prefix(prefix),
+    )
+}
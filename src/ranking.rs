@@ -0,0 +1,364 @@
+//! Global and country ranks for arbitrary totals and the totals of ranks, looked up in
+//! the performance rankings.
+
+use {
+    crate::{
+        api::Client,
+        cache::{RankingsCache, RankingsPage},
+        error::PpifyError,
+        retry,
+    },
+    rosu_v2::prelude::GameMode,
+    serde::Serialize,
+    std::collections::HashMap,
+};
+
+// what the rankings endpoint serves
+const PAGE_SIZE: u32 = 50;
+const MAX_PAGE: u32 = 200;
+
+/// A total before and after a play, as ranks.
+#[derive(Clone, Copy, Debug, Serialize)]
+pub struct RankChange {
+    pub old: u32,
+    pub new: u32,
+}
+
+/// Finds the rank a total would have, and the total of a rank, by bisecting the pages of
+/// the performance rankings. Pages are fetched once per estimator, with retries, and kept
+/// in a [`RankingsCache`] if given one.
+///
+/// The rankings only list the top 10,000 (per country: fewer); below that the rank is
+/// interpolated between the last listed user and a known (pp, rank) anchor, usually the
+/// user's own profile.
+pub struct RankEstimator<'a> {
+    /// `None` for the fixed pages of [`RankEstimator::from_pages`].
+    client: Option<&'a Client>,
+    mode: GameMode,
+    country: Option<String>,
+    cache: Option<&'a RankingsCache>,
+    // pp of every user on a page, fetched pages only
+    pages: HashMap<u32, Vec<f64>>,
+    last_page: Option<u32>,
+}
+
+impl<'a> RankEstimator<'a> {
+    pub fn new(client: &'a Client, mode: GameMode) -> Self {
+        Self {
+            client: Some(client),
+            mode,
+            country: None,
+            cache: None,
+            pages: HashMap::new(),
+            last_page: None,
+        }
+    }
+
+    /// Estimates from `pages` of the rankings instead of fetching them, each with the
+    /// totals of 50 users, best first. The last page is the last listed one.
+    pub fn from_pages(mode: GameMode, pages: Vec<Vec<f64>>) -> Self {
+        Self {
+            client: None,
+            mode,
+            country: None,
+            cache: None,
+            last_page: Some(pages.len().max(1) as u32),
+            pages: (1..).zip(pages).collect(),
+        }
+    }
+
+    /// Looks in the rankings of a country, e.g. `DE`, instead of the global ones.
+    pub fn country(mut self, country_code: impl Into<String>) -> Self {
+        self.country = Some(country_code.into());
+
+        self
+    }
+
+    /// Keeps fetched pages in `cache` for later runs.
+    pub fn cache(mut self, cache: Option<&'a RankingsCache>) -> Self {
+        self.cache = cache;
+
+        self
+    }
+
+    /// The approximate rank of `total_pp`, `None` if it is below the listed ranks and
+    /// there is no usable `anchor`.
+    pub async fn rank_for_pp(
+        &mut self,
+        total_pp: f64,
+        anchor: Option<(f64, u32)>,
+    ) -> Result<Option<u32>, PpifyError> {
+        let first = self.page(1).await?;
+
+        if first.first().is_none_or(|&top| total_pp >= top) {
+            return Ok(Some(1));
+        }
+
+        let last_page = self.last_page.unwrap_or(1);
+        let last = self.page(last_page).await?;
+        let (last_rank, last_pp) = (
+            (last_page - 1) * PAGE_SIZE + last.len() as u32,
+            last[last.len() - 1],
+        );
+
+        if total_pp < last_pp {
+            return Ok(
+                anchor.and_then(|anchor| interpolate(total_pp, (last_pp, last_rank), anchor))
+            );
+        }
+
+        // the first page whose last user has less pp than the total
+        let (mut lo, mut hi) = (1, last_page);
+
+        while lo < hi {
+            let mid = (lo + hi) / 2;
+            let page = self.page(mid).await?;
+
+            if page.last().is_some_and(|&pp| pp >= total_pp) {
+                lo = mid + 1;
+            } else {
+                hi = mid;
+            }
+        }
+
+        let page = self.page(lo).await?;
+        let above = page.iter().filter(|&&pp| pp > total_pp).count() as u32;
+
+        Ok(Some((lo - 1) * PAGE_SIZE + above + 1))
+    }
+
+    /// The total of the user at `rank`. Below the listed ranks it is interpolated towards
+    /// `anchor` like in [`RankEstimator::rank_for_pp`], `None` without a usable one.
+    pub async fn pp_for_rank(
+        &mut self,
+        rank: u32,
+        anchor: Option<(f64, u32)>,
+    ) -> Result<Option<f64>, PpifyError> {
+        let rank = rank.max(1);
+        let listed = self.listed_ranks().await?;
+        let page = self.page((rank.min(listed) - 1) / PAGE_SIZE + 1).await?;
+
+        if rank <= listed {
+            let index = ((rank - 1) % PAGE_SIZE) as usize;
+
+            return Ok(page.get(index).or(page.last()).copied());
+        }
+
+        let last_pp = page[page.len() - 1];
+
+        Ok(anchor.and_then(|anchor| interpolate_pp(rank, (last_pp, listed), anchor)))
+    }
+
+    /// How many ranks the rankings list, at most 10,000.
+    pub async fn listed_ranks(&mut self) -> Result<u32, PpifyError> {
+        self.page(1).await?;
+
+        let last_page = self.last_page.unwrap_or(1);
+        let last = self.page(last_page).await?;
+
+        Ok((last_page - 1) * PAGE_SIZE + last.len() as u32)
+    }
+
+    /// The old and new rank of a total, see [`RankEstimator::rank_for_pp`].
+    pub async fn rank_change(
+        &mut self,
+        old_total_pp: f64,
+        new_total_pp: f64,
+        anchor: Option<(f64, u32)>,
+    ) -> Result<Option<RankChange>, PpifyError> {
+        let old = self.rank_for_pp(old_total_pp, anchor).await?;
+        let new = self.rank_for_pp(new_total_pp, anchor).await?;
+
+        Ok(old.zip(new).map(|(old, new)| RankChange { old, new }))
+    }
+
+    async fn page(&mut self, page: u32) -> Result<Vec<f64>, PpifyError> {
+        if let Some(pps) = self.pages.get(&page) {
+            return Ok(pps.clone());
+        }
+
+        let country = self.country.as_deref();
+
+        if let Some(cached) = self
+            .cache
+            .and_then(|cache| cache.get(self.mode, country, page))
+            .filter(|cached| !cached.pps.is_empty())
+        {
+            self.last_page
+                .get_or_insert_with(|| last_page(cached.total));
+            self.pages.insert(page, cached.pps.clone());
+
+            return Ok(cached.pps);
+        }
+
+        let Some(client) = self.client else {
+            return Err(PpifyError::Calculation(format!(
+                "page {page} of the performance rankings is missing"
+            )));
+        };
+
+        client.ensure_online(|| "the performance rankings can't be fetched".to_owned())?;
+
+        let request = || {
+            let request = client.osu.performance_rankings(self.mode).page(page);
+
+            match &self.country {
+                Some(country) => request.country(country.as_str()),
+                None => request,
+            }
+            .into_future()
+        };
+
+        let rankings = retry::retry(
+            client.max_retries,
+            &client.rate_limiter,
+            request,
+            retry::osu_failure,
+        )
+        .await
+        .map_err(|err| PpifyError::network("failed to fetch the performance rankings", err))?;
+
+        self.last_page
+            .get_or_insert_with(|| last_page(rankings.total));
+
+        let pps: Vec<f64> = rankings
+            .ranking
+            .iter()
+            .filter_map(|user| user.statistics.as_ref())
+            .map(|stats| f64::from(stats.pp))
+            .collect();
+
+        if pps.is_empty() {
+            return Err(PpifyError::Calculation(format!(
+                "page {page} of the performance rankings is empty"
+            )));
+        }
+
+        if let Some(cache) = self.cache {
+            let cached = RankingsPage {
+                total: rankings.total,
+                pps: pps.clone(),
+            };
+
+            // a failed cache write only costs a refetch next time
+            let _ = cache.put(self.mode, country, page, &cached);
+        }
+
+        self.pages.insert(page, pps.clone());
+
+        Ok(pps)
+    }
+}
+
+fn last_page(total: u32) -> u32 {
+    total.max(1).div_ceil(PAGE_SIZE).min(MAX_PAGE)
+}
+
+// log(rank) is roughly linear in pp below the listed ranks
+fn interpolate(
+    total_pp: f64,
+    (last_pp, last_rank): (f64, u32),
+    (anchor_pp, anchor_rank): (f64, u32),
+) -> Option<u32> {
+    if anchor_rank <= last_rank || anchor_pp >= last_pp {
+        return None;
+    }
+
+    let (last_log, anchor_log) = (f64::from(last_rank).ln(), f64::from(anchor_rank).ln());
+    let slope = (anchor_log - last_log) / (anchor_pp - last_pp);
+    let rank = (last_log + (total_pp - last_pp) * slope).exp().round();
+
+    Some((rank as u32).max(last_rank + 1))
+}
+
+// the inverse of `interpolate`
+fn interpolate_pp(
+    rank: u32,
+    (last_pp, last_rank): (f64, u32),
+    (anchor_pp, anchor_rank): (f64, u32),
+) -> Option<f64> {
+    if anchor_rank <= last_rank || anchor_pp >= last_pp {
+        return None;
+    }
+
+    let (last_log, anchor_log) = (f64::from(last_rank).ln(), f64::from(anchor_rank).ln());
+    let slope = (anchor_log - last_log) / (anchor_pp - last_pp);
+    let pp = last_pp + (f64::from(rank).ln() - last_log) / slope;
+
+    Some(pp.clamp(0.0, last_pp))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // ranks 1 to 150 on three pages, 10pp apart from 9990pp down to 8500pp
+    fn estimator() -> RankEstimator<'static> {
+        let pps: Vec<f64> = (1..=150)
+            .map(|rank| 10_000.0 - f64::from(rank) * 10.0)
+            .collect();
+
+        RankEstimator::from_pages(GameMode::Osu, pps.chunks(50).map(<[f64]>::to_vec).collect())
+    }
+
+    #[tokio::test]
+    async fn rank_for_pp_counts_the_listed_users_above() {
+        let mut estimator = estimator();
+
+        assert_eq!(
+            estimator.rank_for_pp(20_000.0, None).await.unwrap(),
+            Some(1)
+        );
+        assert_eq!(estimator.rank_for_pp(9575.0, None).await.unwrap(), Some(43));
+        // a tie shares the rank
+        assert_eq!(estimator.rank_for_pp(9580.0, None).await.unwrap(), Some(42));
+        assert_eq!(
+            estimator.rank_for_pp(8500.0, None).await.unwrap(),
+            Some(150)
+        );
+    }
+
+    #[tokio::test]
+    async fn pp_for_rank_reads_the_listed_users() {
+        let mut estimator = estimator();
+
+        assert_eq!(estimator.listed_ranks().await.unwrap(), 150);
+        assert_eq!(estimator.pp_for_rank(1, None).await.unwrap(), Some(9990.0));
+        assert_eq!(
+            estimator.pp_for_rank(120, None).await.unwrap(),
+            Some(8800.0)
+        );
+    }
+
+    #[tokio::test]
+    async fn past_the_listed_ranks_needs_an_anchor() {
+        let mut estimator = estimator();
+
+        assert_eq!(estimator.rank_for_pp(8000.0, None).await.unwrap(), None);
+        assert_eq!(estimator.pp_for_rank(500, None).await.unwrap(), None);
+        // an anchor above the last listed user says nothing about the ranks below
+        assert_eq!(
+            estimator
+                .rank_for_pp(8000.0, Some((9000.0, 100)))
+                .await
+                .unwrap(),
+            None
+        );
+    }
+
+    #[tokio::test]
+    async fn past_the_listed_ranks_interpolates_towards_the_anchor() {
+        let mut estimator = estimator();
+        let anchor = Some((7000.0, 1000));
+
+        // log(rank) is linear in pp between #150 at 8500pp and #1000 at 7000pp
+        assert_eq!(
+            estimator.rank_for_pp(8000.0, anchor).await.unwrap(),
+            Some(282)
+        );
+
+        let pp = estimator.pp_for_rank(500, anchor).await.unwrap().unwrap();
+        assert!(pp < 8500.0 && pp > 7000.0);
+        assert_eq!(estimator.rank_for_pp(pp, anchor).await.unwrap(), Some(500));
+    }
+}
@@ -0,0 +1,23 @@
+//! A token bucket shared by every osu! api request and beatmap download of a client, so
+//! batch runs stay within the api's limits.
+
+use {
+    governor::{DefaultDirectRateLimiter, Quota, RateLimiter},
+    std::num::NonZeroU32,
+};
+
+/// Requests per minute, well below the api's 1200.
+const DEFAULT_PER_MINUTE: NonZeroU32 = NonZeroU32::new(300).unwrap();
+const DEFAULT_BURST: NonZeroU32 = NonZeroU32::new(20).unwrap();
+
+/// `per_minute` requests in bursts of up to `burst`, 300 per minute in bursts of up to 20
+/// by default.
+pub fn limiter(
+    per_minute: Option<NonZeroU32>,
+    burst: Option<NonZeroU32>,
+) -> DefaultDirectRateLimiter {
+    let quota = Quota::per_minute(per_minute.unwrap_or(DEFAULT_PER_MINUTE))
+        .allow_burst(burst.unwrap_or(DEFAULT_BURST));
+
+    RateLimiter::direct(quota)
+}
@@ -0,0 +1,151 @@
+use {
+    crate::{
+        TOP_N_DIFF, cli::Cli, fetch_best_scores, map_cache, print_top_plays_diff, read_mode,
+        read_username,
+    },
+    color_eyre::{Result, eyre::Context},
+    demand::{DemandOption, Select},
+    ppify::{
+        PpifyError,
+        api::{self, Client},
+        calc::{self, GainProjection, PlayInputs, Rules},
+        report::RecentWhatIf,
+    },
+    rosu_v2::prelude::Score,
+};
+
+pub async fn run(osu: &Client, cli: &Cli) -> Result<()> {
+    let username = read_username(cli.user.as_deref())?;
+
+    let (api_mode, pp_mode) = match cli.mode {
+        Some(mode) => mode.modes(),
+        None => read_mode()?,
+    };
+
+    let recent = api::fetch_user_recent_scores(osu, &username, api_mode).await?;
+    let score = read_recent_score(&username, &recent)?;
+
+    let bytes = api::fetch_osu_file(
+        osu,
+        score.map_id,
+        api::map_checksum(score),
+        map_cache(cli).as_ref(),
+        cli.refresh,
+    )
+    .await?;
+    let map = calc::parse_beatmap(&bytes, pp_mode)?;
+    let play = PlayInputs::from_score(score);
+    let label = calc::score_label(score);
+    let rules = Rules::new(cli.stable);
+    let weighting = cli.weighting();
+
+    let best_scores = fetch_best_scores(osu, cli, &username, api_mode).await?;
+
+    // rosu-pp fills the objects a failed score never reached with perfect hits
+    let fc_pp =
+        calc::calculate_play(&map, pp_mode, &score.mods, None, rules, &play.full_combo()).pp();
+    let if_fc = calc::project_gain(
+        &best_scores,
+        fc_pp,
+        &label,
+        Some(score.map_id),
+        None,
+        &weighting,
+    );
+
+    let played_pp = score
+        .passed
+        .then(|| calc::calculate_play(&map, pp_mode, &score.mods, None, rules, &play).pp());
+    let as_played = played_pp.map(|pp| {
+        calc::project_gain(
+            &best_scores,
+            pp,
+            &label,
+            Some(score.map_id),
+            None,
+            &weighting,
+        )
+    });
+
+    let report = RecentWhatIf {
+        label,
+        passed: score.passed,
+        as_played,
+        played_pp,
+        fc_pp,
+        if_fc,
+    };
+
+    if cli.json {
+        let json = serde_json::to_string_pretty(&report).context("failed to serialize report")?;
+        println!("{json}");
+    } else {
+        print_report(&report, cli.top_n_diff.unwrap_or(TOP_N_DIFF));
+    }
+
+    Ok(())
+}
+
+fn read_recent_score<'a>(username: &str, scores: &'a [Score]) -> Result<&'a Score> {
+    if scores.is_empty() {
+        return Err(PpifyError::BadInput(format!("`{username}` has no recent scores")).into());
+    }
+
+    let labels: Vec<_> = scores
+        .iter()
+        .map(|score| {
+            format!(
+                "{}  {:.2}%  {}x  {}❌{}",
+                calc::score_label(score),
+                score.accuracy,
+                score.max_combo,
+                score.statistics.miss,
+                if score.passed { "" } else { "  (failed)" }
+            )
+        })
+        .collect();
+
+    let mut select = Select::new("Recent score").filterable(true);
+
+    for (i, label) in labels.iter().enumerate() {
+        select = select.option(DemandOption::new(i).label(label));
+    }
+
+    let picked = select.run().context("failed to read recent score")?;
+
+    Ok(&scores[picked])
+}
+
+fn print_projection(title: &str, pp: f64, projection: &GainProjection) {
+    println!();
+    println!("{title}: {pp:.2}pp");
+
+    match (&projection.outscored_by, projection.new_play_rank) {
+        (Some(existing), _) => println!(
+            "  Your #{} on this map ({:.2}pp) is worth more, no change.",
+            existing.old_rank.unwrap_or_default(),
+            existing.pp
+        ),
+        (None, Some(rank)) => println!(
+            "  Would be your #{rank} top play: {:.2}pp -> {:.2}pp ({:+.2}pp)",
+            projection.old_total_pp, projection.new_total_pp, projection.gain
+        ),
+        (None, None) => println!("  Doesn't make it into your top plays."),
+    }
+}
+
+fn print_report(report: &RecentWhatIf, top_n: usize) {
+    println!();
+    println!("{}", report.label);
+
+    match (&report.as_played, report.played_pp) {
+        (Some(projection), Some(pp)) => print_projection("As played", pp, projection),
+        _ => {
+            println!();
+            println!("As played: failed, awards no pp");
+        }
+    }
+
+    print_projection("If FC'd", report.fc_pp, &report.if_fc);
+    print_top_plays_diff(&report.if_fc.top_plays, top_n);
+}
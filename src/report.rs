@@ -0,0 +1,375 @@
+use {
+    crate::{
+        calc::{
+            AccuracyFormula, AccuracyRow, BeatmapMetadata, DifficultySummary,
+            FlashlightContribution, GainProjection, MissRow, ModComboRow, ModdedSettings, NewPlay,
+            PlayInputs, PpBreakdown, ProgressionPoint, RoundTrip, SessionProjection, StrainGraph,
+            StrainSpike, Weighting,
+        },
+        mods::ModSettings,
+        ranking::RankChange,
+        snapshot::{Snapshot, SnapshotPlay},
+    },
+    serde::Serialize,
+};
+
+/// Everything ppify computes for a single hypothetical play.
+#[derive(Clone, Debug, Serialize)]
+pub struct CalculationReport {
+    /// `None` for local files without a (submitted) beatmap id.
+    pub map_id: Option<u32>,
+    pub local_file: bool,
+    pub beatmap: BeatmapMetadata,
+    pub settings: ModdedSettings,
+    pub mode: &'static str,
+    pub mods: Vec<&'static str>,
+    /// Selected, but left out of the calculation with `--ignore-mod`.
+    pub ignored_mods: Vec<&'static str>,
+    /// With `--explain-mods`, one per selected mod.
+    pub mod_effects: Option<Vec<ModEffect>>,
+    pub mod_settings: ModSettings,
+    pub clock_rate: Option<f64>,
+    pub play: PlayInputs,
+    /// With `--assume-fc`, the play as entered; `play` is then its full combo.
+    pub entered_play: Option<PlayInputs>,
+    /// `lazer` or `stable`, the scoring rules the pp follow.
+    pub rules: &'static str,
+    pub pp: f64,
+    pub pp_breakdown: PpBreakdown,
+    pub difficulty: DifficultySummary,
+    pub flashlight: Option<FlashlightContribution>,
+    /// With `--explain` when the play has counts instead of an accuracy.
+    pub accuracy_formula: Option<AccuracyFormula>,
+    pub accuracy_table: Option<Vec<AccuracyRow>>,
+    pub miss_table: Option<Vec<MissRow>>,
+    pub mod_matrix: Option<Vec<ModComboRow>>,
+    pub progression: Option<Vec<ProgressionPoint>>,
+    /// With `--fail-at`, never ranked pp.
+    pub failed_at: Option<ProgressionPoint>,
+    pub strain_graph: Option<StrainGraph>,
+    /// With `--spikes`, the biggest rise first.
+    pub spikes: Option<Vec<StrainSpike>>,
+    pub profile: GainProjection,
+    /// Estimated global rank before and after the play.
+    pub global_rank: Option<RankChange>,
+    pub country_code: Option<String>,
+    /// Estimated rank in `country_code` before and after the play.
+    pub country_rank: Option<RankChange>,
+    pub round_trip: Option<RoundTrip>,
+}
+
+#[derive(Clone, Debug, Serialize)]
+pub struct ModEffect {
+    pub acronym: &'static str,
+    /// e.g. `rate 1.50x` or `no pp effect`.
+    pub effect: String,
+}
+
+/// The play of `--beat` against a known pp value.
+#[derive(Clone, Copy, Debug, Serialize)]
+pub struct BeatReport {
+    pub pp: f64,
+    pub beat: f64,
+    /// `pp - beat`, negative when the play falls short.
+    pub difference: f64,
+    pub beats: bool,
+}
+
+/// A submitted score recomputed locally.
+#[derive(Clone, Debug, Serialize)]
+pub struct ScoreRecomputation {
+    pub score_id: u64,
+    pub map_id: u32,
+    pub beatmap: BeatmapMetadata,
+    pub mode: &'static str,
+    pub mods: Vec<String>,
+    pub play: PlayInputs,
+    pub pp: f64,
+    pub pp_breakdown: PpBreakdown,
+    pub difficulty: DifficultySummary,
+    /// `None` when the api stores no pp for the score, e.g. on loved maps.
+    pub round_trip: Option<RoundTrip>,
+}
+
+#[derive(Clone, Debug, Serialize)]
+pub struct IfFcPlay {
+    pub label: String,
+    pub pp: f64,
+    /// Equal to `pp` for plays that already are full combos.
+    pub fc_pp: f64,
+}
+
+impl IfFcPlay {
+    pub fn choked_pp(&self) -> f64 {
+        self.fc_pp - self.pp
+    }
+}
+
+/// A user's top plays recomputed as full combos.
+#[derive(Clone, Debug, Serialize)]
+pub struct IfFcReport {
+    pub mode: &'static str,
+    pub plays: Vec<IfFcPlay>,
+    pub total_pp: f64,
+    pub fc_total_pp: f64,
+}
+
+impl IfFcReport {
+    pub fn new(mode: &'static str, plays: Vec<IfFcPlay>, weighting: &dyn Weighting) -> Self {
+        let weighted_total = |pp: fn(&IfFcPlay) -> f64| {
+            let mut pps: Vec<f64> = plays.iter().map(pp).collect();
+            pps.sort_by(|a, b| b.total_cmp(a));
+
+            weighting.total(&pps)
+        };
+
+        Self {
+            mode,
+            total_pp: weighted_total(|play| play.pp),
+            fc_total_pp: weighted_total(|play| play.fc_pp),
+            plays,
+        }
+    }
+}
+
+/// A recent score, recomputed as played and as a full combo.
+#[derive(Clone, Debug, Serialize)]
+pub struct RecentWhatIf {
+    pub label: String,
+    pub passed: bool,
+    /// `None` for failed scores.
+    pub as_played: Option<GainProjection>,
+    pub played_pp: Option<f64>,
+    pub fc_pp: f64,
+    pub if_fc: GainProjection,
+}
+
+/// What it takes to reach a total pp value, or pass another user, with one more play.
+#[derive(Clone, Debug, Serialize)]
+pub struct TargetReport {
+    pub mode: &'static str,
+    /// Set when the target is another user's total.
+    pub rival: Option<String>,
+    /// Set when the target is the total of a global rank.
+    pub target_rank: Option<TargetRank>,
+    /// Set when the target is a position in the top plays.
+    pub position: Option<TargetPosition>,
+    pub target_total_pp: f64,
+    pub current_total_pp: f64,
+    /// `None` if no single play reaches the target.
+    pub required_play_pp: Option<f64>,
+    pub on_map: Option<TargetOnMap>,
+}
+
+#[derive(Clone, Copy, Debug, Serialize)]
+pub struct TargetRank {
+    pub rank: u32,
+    /// Total of the user at the rank.
+    pub pp: f64,
+    /// Past the ranks the rankings list, estimated from the user's own rank.
+    pub interpolated: bool,
+}
+
+#[derive(Clone, Copy, Debug, Serialize)]
+pub struct TargetPosition {
+    /// 1-based, e.g. 20 for a new #20.
+    pub position: usize,
+    /// pp of the play there now, `None` if there are fewer top plays.
+    pub current_pp: Option<f64>,
+    /// Where the new play lands after the top plays are sorted again, which is higher when
+    /// it replaces a play above on the same map. `None` if that play is worth more.
+    pub new_rank: Option<usize>,
+}
+
+#[derive(Clone, Debug, Serialize)]
+pub struct TargetOnMap {
+    pub beatmap: BeatmapMetadata,
+    pub mods: Vec<&'static str>,
+    pub misses: u32,
+    pub max_pp: f64,
+    /// `None` if even 100% falls short.
+    pub accuracy: Option<f64>,
+}
+
+/// Several hypothetical plays inserted into the top plays at once.
+#[derive(Clone, Debug, Serialize)]
+pub struct SessionReport {
+    pub mode: &'static str,
+    pub plays: Vec<NewPlay>,
+    pub profile: SessionProjection,
+}
+
+/// The same play on every beatmap of an osu! collection.
+#[derive(Clone, Debug, Serialize)]
+pub struct CollectionReport {
+    pub collection: String,
+    pub mods: String,
+    pub accuracy: f64,
+    pub misses: u32,
+    /// Sorted by pp, highest first.
+    pub maps: Vec<CollectionMap>,
+    /// Beatmaps of the collection that aren't in the Songs folder.
+    pub missing: usize,
+}
+
+#[derive(Clone, Debug, Serialize)]
+pub struct CollectionMap {
+    pub beatmap: BeatmapMetadata,
+    pub mode: &'static str,
+    pub stars: Option<f64>,
+    pub pp: Option<f64>,
+    pub error: Option<String>,
+}
+
+/// The same play on several beatmaps, projected onto the totals of several players.
+#[derive(Clone, Debug, Serialize)]
+pub struct MatrixReport {
+    pub mode: &'static str,
+    pub mods: String,
+    pub accuracy: f64,
+    pub misses: u32,
+    /// In the order of the file's header.
+    pub maps: Vec<MatrixMap>,
+    /// In the order of the file.
+    pub players: Vec<MatrixPlayer>,
+}
+
+#[derive(Clone, Debug, Serialize)]
+pub struct MatrixMap {
+    pub map_id: u32,
+    pub beatmap: Option<String>,
+    pub pp: Option<f64>,
+    /// Set instead of `beatmap` and `pp` when the map couldn't be calculated.
+    pub error: Option<String>,
+}
+
+#[derive(Clone, Debug, Serialize)]
+pub struct MatrixPlayer {
+    pub user: String,
+    pub total_pp: Option<f64>,
+    /// The total after the play on each map of [`MatrixReport::maps`].
+    pub projected_pp: Vec<Option<f64>>,
+    /// Set when the player's profile couldn't be fetched.
+    pub error: Option<String>,
+}
+
+/// Scores of osu! stable's `scores.db` recomputed locally.
+#[derive(Clone, Debug, Serialize)]
+pub struct LocalScoresReport {
+    /// Sorted by recomputed pp, highest first.
+    pub scores: Vec<LocalScore>,
+    /// Scores on beatmaps that aren't in the Songs folder anymore.
+    pub missing: usize,
+}
+
+#[derive(Clone, Debug, Serialize)]
+pub struct LocalScore {
+    pub beatmap: BeatmapMetadata,
+    pub player: String,
+    pub mode: &'static str,
+    pub mods: String,
+    pub play: PlayInputs,
+    /// The in-game score.
+    pub score: u32,
+    /// `None` for scores that weren't submitted.
+    pub online_id: Option<u64>,
+    pub pp: Option<f64>,
+    pub error: Option<String>,
+}
+
+/// Two users' totals side by side.
+#[derive(Clone, Debug, Serialize)]
+pub struct CompareReport {
+    pub mode: &'static str,
+    pub a: ComparedUser,
+    pub b: ComparedUser,
+    /// `a` minus `b`, from the recomputed totals.
+    pub difference: f64,
+    /// The raw pp of one new play that puts `a` above `b`, `None` if no single play does.
+    pub required_play_pp: Option<f64>,
+}
+
+#[derive(Clone, Debug, Serialize)]
+pub struct ComparedUser {
+    pub username: String,
+    /// The total shown on the profile.
+    pub profile_pp: Option<f64>,
+    /// Weighted top plays plus estimated bonus pp.
+    pub total_pp: f64,
+    pub top_play_pp: Option<f64>,
+}
+
+/// What changed on a profile between two snapshots.
+#[derive(Clone, Debug, Serialize)]
+pub struct SnapshotDiff {
+    pub username: String,
+    pub mode: String,
+    /// Local times of the older and the newer snapshot.
+    pub from: String,
+    pub to: String,
+    pub old_pp: f64,
+    pub new_pp: f64,
+    pub pp_gained: f64,
+    /// `None` if the user was unranked in either snapshot.
+    pub global_rank: Option<RankChange>,
+    pub country_rank: Option<RankChange>,
+    pub old_accuracy: f64,
+    pub new_accuracy: f64,
+    pub play_count_gained: i64,
+    /// Top plays of the newer snapshot that the older one doesn't have, best first.
+    pub new_plays: Vec<NewTopPlay>,
+    /// Plays of the older snapshot that are no longer in the top plays.
+    pub dropped_plays: usize,
+}
+
+#[derive(Clone, Debug, Serialize)]
+pub struct NewTopPlay {
+    /// Position in the newer top plays, 1 for the best.
+    pub rank: usize,
+    #[serde(flatten)]
+    pub play: SnapshotPlay,
+}
+
+impl SnapshotDiff {
+    pub fn new(old: &Snapshot, new: &Snapshot) -> Self {
+        let in_old = |score_id: u64| old.top_plays.iter().any(|play| play.score_id == score_id);
+        let in_new = |score_id: u64| new.top_plays.iter().any(|play| play.score_id == score_id);
+
+        let rank_change = |old: Option<u32>, new: Option<u32>| {
+            Some(RankChange {
+                old: old?,
+                new: new?,
+            })
+        };
+
+        Self {
+            username: new.username.clone(),
+            mode: new.mode.clone(),
+            from: old.time.clone(),
+            to: new.time.clone(),
+            old_pp: old.pp,
+            new_pp: new.pp,
+            pp_gained: new.pp - old.pp,
+            global_rank: rank_change(old.global_rank, new.global_rank),
+            country_rank: rank_change(old.country_rank, new.country_rank),
+            old_accuracy: old.accuracy,
+            new_accuracy: new.accuracy,
+            play_count_gained: i64::from(new.play_count) - i64::from(old.play_count),
+            new_plays: new
+                .top_plays
+                .iter()
+                .enumerate()
+                .filter(|(_, play)| !in_old(play.score_id))
+                .map(|(i, play)| NewTopPlay {
+                    rank: i + 1,
+                    play: play.clone(),
+                })
+                .collect(),
+            dropped_plays: old
+                .top_plays
+                .iter()
+                .filter(|play| !in_new(play.score_id))
+                .count(),
+        }
+    }
+}
@@ -0,0 +1,188 @@
+//! Retries of osu! api requests and beatmap downloads that failed for transient reasons,
+//! such as rate limits, server errors or timeouts.
+
+use {
+    governor::DefaultDirectRateLimiter,
+    reqwest::header::{HeaderMap, RETRY_AFTER},
+    rosu_v2::error::OsuError,
+    std::{
+        collections::hash_map::RandomState,
+        hash::{BuildHasher, Hasher},
+        time::{Duration, Instant},
+    },
+    tracing::debug,
+};
+
+const BASE_DELAY: Duration = Duration::from_millis(500);
+const MAX_DELAY: Duration = Duration::from_secs(30);
+/// Longer `Retry-After`s are capped, an interactive session shouldn't stall for minutes.
+const MAX_RETRY_AFTER: Duration = Duration::from_secs(60);
+
+/// Retries per request unless the config says otherwise.
+pub const DEFAULT_MAX_RETRIES: u32 = 3;
+
+/// Whether a failed request is worth another attempt.
+pub(crate) enum Failure {
+    Permanent,
+    /// With the delay the server asked for, if any.
+    Transient(Option<Duration>),
+}
+
+/// Runs `request` until it succeeds, fails permanently or was retried `max_retries` times,
+/// waiting a jittered exponential backoff or the server's `Retry-After` in between. Every
+/// attempt waits for `limiter` first.
+pub(crate) async fn retry<T, E, Fut>(
+    max_retries: u32,
+    limiter: &DefaultDirectRateLimiter,
+    mut request: impl FnMut() -> Fut,
+    failure: impl Fn(&E) -> Failure,
+) -> Result<T, E>
+where
+    Fut: Future<Output = Result<T, E>>,
+{
+    let mut attempt = 0;
+
+    loop {
+        // retries count against the rate limit like any other request
+        limiter.until_ready().await;
+
+        let started = Instant::now();
+        let result = request().await;
+        debug!(elapsed = ?started.elapsed(), attempt, ok = result.is_ok(), "request finished");
+
+        let err = match result {
+            Ok(value) => return Ok(value),
+            Err(err) => err,
+        };
+
+        let delay = match failure(&err) {
+            Failure::Transient(_) if attempt >= max_retries => return Err(err),
+            Failure::Permanent => return Err(err),
+            Failure::Transient(Some(retry_after)) => retry_after.min(MAX_RETRY_AFTER),
+            Failure::Transient(None) => backoff(attempt),
+        };
+
+        debug!(?delay, "retrying");
+        tokio::time::sleep(delay).await;
+        attempt += 1;
+    }
+}
+
+/// Classifies errors of rosu-v2 requests. Their `Retry-After` isn't exposed, so rate
+/// limits back off like server errors.
+pub(crate) fn osu_failure(err: &OsuError) -> Failure {
+    match err {
+        OsuError::Response { status, .. } if status.as_u16() == 429 || status.is_server_error() => {
+            Failure::Transient(None)
+        }
+        OsuError::ServiceUnavailable { .. }
+        | OsuError::RequestTimeout
+        | OsuError::Request { .. }
+        | OsuError::ChunkingResponse { .. } => Failure::Transient(None),
+        _ => Failure::Permanent,
+    }
+}
+
+/// A failed plain HTTP request, with the `Retry-After` of its response if there was one.
+pub(crate) struct HttpFailure {
+    pub source: reqwest::Error,
+    pub retry_after: Option<Duration>,
+}
+
+impl HttpFailure {
+    pub(crate) fn failure(&self) -> Failure {
+        let transient = match self.source.status() {
+            Some(status) => {
+                status == reqwest::StatusCode::TOO_MANY_REQUESTS || status.is_server_error()
+            }
+            None => self.source.is_connect() || self.source.is_timeout(),
+        };
+
+        if transient {
+            Failure::Transient(self.retry_after)
+        } else {
+            Failure::Permanent
+        }
+    }
+}
+
+impl From<reqwest::Error> for HttpFailure {
+    fn from(source: reqwest::Error) -> Self {
+        Self {
+            source,
+            retry_after: None,
+        }
+    }
+}
+
+/// `Retry-After` in seconds; the HTTP date form is ignored.
+pub(crate) fn retry_after(headers: &HeaderMap) -> Option<Duration> {
+    headers
+        .get(RETRY_AFTER)?
+        .to_str()
+        .ok()?
+        .trim()
+        .parse()
+        .ok()
+        .map(Duration::from_secs)
+}
+
+// somewhere between half and all of the exponential delay, so clients don't retry in lockstep
+fn backoff(attempt: u32) -> Duration {
+    let delay = BASE_DELAY
+        .saturating_mul(2u32.saturating_pow(attempt))
+        .min(MAX_DELAY);
+
+    let random = RandomState::new().build_hasher().finish();
+    let jitter = (random % 1000) as f64 / 1000.0;
+
+    delay.mul_f64(0.5 + jitter / 2.0)
+}
+
+#[cfg(test)]
+mod tests {
+    use {super::*, reqwest::header::HeaderValue};
+
+    fn headers(retry_after: &'static str) -> HeaderMap {
+        let mut headers = HeaderMap::new();
+        headers.insert(RETRY_AFTER, HeaderValue::from_static(retry_after));
+
+        headers
+    }
+
+    #[test]
+    fn retry_after_reads_seconds() {
+        assert_eq!(retry_after(&headers("120")), Some(Duration::from_secs(120)));
+        assert_eq!(retry_after(&headers(" 5 ")), Some(Duration::from_secs(5)));
+        assert_eq!(retry_after(&HeaderMap::new()), None);
+    }
+
+    #[test]
+    fn retry_after_ignores_dates_and_garbage() {
+        assert_eq!(retry_after(&headers("Wed, 21 Oct 2015 07:28:00 GMT")), None);
+        assert_eq!(retry_after(&headers("-1")), None);
+        assert_eq!(retry_after(&headers("soon")), None);
+    }
+
+    #[test]
+    fn backoff_stays_between_half_and_all_of_the_delay() {
+        for attempt in 0..5 {
+            let full = BASE_DELAY * 2u32.pow(attempt);
+            let delay = backoff(attempt);
+
+            assert!(delay >= full / 2 && delay <= full, "{attempt}: {delay:?}");
+        }
+    }
+
+    #[test]
+    fn backoff_is_capped() {
+        for attempt in [10, 31, 32, u32::MAX] {
+            let delay = backoff(attempt);
+
+            assert!(
+                delay >= MAX_DELAY / 2 && delay <= MAX_DELAY,
+                "{attempt}: {delay:?}"
+            );
+        }
+    }
+}
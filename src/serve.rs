@@ -0,0 +1,185 @@
+use {
+    crate::{
+        cli::{Cli, ModeArg},
+        pipe::{LastCalculation, PipeRequest, PipeResponse, PipeState},
+    },
+    axum::{
+        Json, Router,
+        extract::{Path, Query, State},
+        http::StatusCode,
+        response::Html,
+        routing::{get, post},
+    },
+    color_eyre::{Result, eyre::Context},
+    ppify::{
+        PpifyError,
+        api::Client,
+        calc::{self, BonusPp, TopPlay},
+    },
+    rosu_v2::prelude::GameMode,
+    serde::{Deserialize, Serialize},
+    std::{
+        net::{Ipv4Addr, SocketAddr},
+        sync::Arc,
+    },
+    tokio::{net::TcpListener, sync::Mutex},
+};
+
+// requests are answered one at a time, which keeps the caches and api usage simple
+type SharedState = Arc<Mutex<PipeState>>;
+
+#[derive(Debug, Deserialize)]
+struct TopPlaysQuery {
+    mode: Option<ModeArg>,
+}
+
+#[derive(Debug, Serialize)]
+struct TopPlaysResponse {
+    username: String,
+    mode: &'static str,
+    /// Weighted top plays plus estimated bonus pp.
+    total_pp: f64,
+    plays: Vec<TopPlay>,
+}
+
+#[derive(Debug, Serialize)]
+struct ErrorResponse {
+    error: String,
+}
+
+pub async fn run(osu: Client, cli: &Cli, port: u16) -> Result<()> {
+    let mut state = PipeState::new(Arc::new(osu), cli);
+    state.keep_last = true;
+
+    let app = Router::new()
+        .route("/calculate", post(calculate))
+        .route("/user/{name}/top", get(top_plays))
+        .route("/overlay", get(overlay))
+        .route("/overlay.json", get(overlay_json))
+        .with_state(Arc::new(Mutex::new(state)));
+
+    let addr = SocketAddr::from((Ipv4Addr::LOCALHOST, port));
+    let listener = TcpListener::bind(addr)
+        .await
+        .with_context(|| format!("failed to listen on {addr}"))?;
+
+    eprintln!("Listening on http://{addr}");
+
+    axum::serve(listener, app).await.context("server failed")
+}
+
+/// Takes the same requests as `--stdin`, one per call.
+async fn calculate(
+    State(state): State<SharedState>,
+    Json(request): Json<PipeRequest>,
+) -> (StatusCode, Json<PipeResponse>) {
+    let id = request.id.clone();
+
+    match state.lock().await.answer(request).await {
+        Ok(response) => (StatusCode::OK, Json(response)),
+        Err(err) => (
+            StatusCode::UNPROCESSABLE_ENTITY,
+            Json(PipeResponse {
+                id,
+                error: Some(format!("{err:#}")),
+                ..PipeResponse::default()
+            }),
+        ),
+    }
+}
+
+async fn top_plays(
+    State(state): State<SharedState>,
+    Path(name): Path<String>,
+    Query(query): Query<TopPlaysQuery>,
+) -> Result<Json<TopPlaysResponse>, (StatusCode, Json<ErrorResponse>)> {
+    let mut state = state.lock().await;
+
+    let mode = match query.mode.or(state.default_mode) {
+        Some(mode) => mode.modes().0,
+        None => GameMode::Osu,
+    };
+
+    let weighting = state.weighting;
+    let (scores, user) = state.user(&name, mode).await.map_err(|err| {
+        let status = match err.downcast_ref::<PpifyError>() {
+            Some(PpifyError::UserNotFound(_) | PpifyError::MapNotFound(_)) => StatusCode::NOT_FOUND,
+            Some(PpifyError::BadInput(_)) => StatusCode::BAD_REQUEST,
+            Some(PpifyError::Network { .. } | PpifyError::AuthFailed(_)) => StatusCode::BAD_GATEWAY,
+            // only cached profiles can be answered
+            Some(PpifyError::Offline(_)) => StatusCode::SERVICE_UNAVAILABLE,
+            _ => StatusCode::INTERNAL_SERVER_ERROR,
+        };
+        let error = format!("{err:#}");
+
+        (status, Json(ErrorResponse { error }))
+    })?;
+
+    let bonus = BonusPp::for_user(user, scores, &[]);
+
+    Ok(Json(TopPlaysResponse {
+        username: user.username.to_string(),
+        mode: mode.as_str(),
+        total_pp: calc::total_pp(scores, bonus, &weighting),
+        plays: scores.iter().filter_map(TopPlay::from_score).collect(),
+    }))
+}
+
+/// The last calculation as JSON, `null` before the first one.
+async fn overlay_json(State(state): State<SharedState>) -> Json<Option<LastCalculation>> {
+    Json(state.lock().await.last.clone())
+}
+
+/// The last calculation as a page for OBS browser sources, reloading itself every 2 seconds.
+async fn overlay(State(state): State<SharedState>) -> Html<String> {
+    let body = match &state.lock().await.last {
+        Some(last) => {
+            let mods = if last.mods.is_empty() {
+                String::new()
+            } else {
+                format!(" +{}", last.mods.concat())
+            };
+
+            // the table ends with an SS
+            let rows: String = last
+                .accuracy_table
+                .iter()
+                .enumerate()
+                .map(|(i, row)| {
+                    let accuracy = if i + 1 == last.accuracy_table.len() {
+                        "SS".to_owned()
+                    } else {
+                        format!("{:.0}%", row.accuracy)
+                    };
+
+                    format!("<tr><td>{accuracy}</td><td>{:.0}pp</td></tr>", row.pp)
+                })
+                .collect();
+
+            format!(
+                "<h1>{}{mods}</h1><p>{:.2}★</p><table>{rows}</table>",
+                escape_html(&last.beatmap.to_string()),
+                last.stars
+            )
+        }
+        None => "<p>No calculation yet</p>".to_owned(),
+    };
+
+    Html(format!(
+        "<!DOCTYPE html><html><head><meta charset=\"utf-8\">\
+         <meta http-equiv=\"refresh\" content=\"2\"><style>{OVERLAY_STYLE}</style>\
+         </head><body>{body}</body></html>"
+    ))
+}
+
+// transparent, so only the text shows on stream
+const OVERLAY_STYLE: &str = "body { background: transparent; color: white; \
+    font-family: sans-serif; text-shadow: 0 0 4px black; } \
+    h1 { font-size: 1.4em; } td { padding-right: 1em; }";
+
+fn escape_html(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
@@ -0,0 +1,162 @@
+use {
+    crate::{
+        TOP_N_DIFF,
+        cli::{Cli, ScoreArgs},
+        fetch_profile, pick_difficulty, print_top_plays_diff, read_map_bytes, read_map_source,
+        read_mod_settings, read_mode, read_mods_for_mode, read_score_inputs, read_username,
+        resolve_combo,
+    },
+    color_eyre::{Result, eyre::Context},
+    demand::Confirm,
+    ppify::{
+        api::Client,
+        calc::{self, BeatmapMetadata, BonusPp, NewPlay, PlayInputs, Rules, TOP_PLAYS_CAP},
+        input::MapSource,
+        mods::{self, ModSettings},
+        report::SessionReport,
+    },
+};
+
+pub async fn run(osu: &Client, cli: &Cli) -> Result<()> {
+    let username = read_username(cli.user.as_deref())?;
+
+    let (api_mode, pp_mode) = match cli.mode {
+        Some(mode) => mode.modes(),
+        None => read_mode()?,
+    };
+
+    let rules = Rules::new(cli.stable);
+    let mut plays = Vec::new();
+
+    loop {
+        let source = pick_difficulty(osu, read_map_source()?, Some(api_mode)).await?;
+        let selected_mods = read_mods_for_mode(api_mode)?;
+        let mod_settings = read_mod_settings(&selected_mods, api_mode, ModSettings::default())?;
+        let game_mods = mods::apply_settings(
+            mods::lazer_mods(selected_mods.iter().copied(), api_mode),
+            api_mode,
+            &mod_settings,
+        )?;
+
+        // every play is prompted from scratch, the score flags only describe a single play
+        let (judgements, combo_input) = read_score_inputs(api_mode, &ScoreArgs::default())?;
+
+        let map_bytes = read_map_bytes(osu, &source, cli).await?;
+        let metadata = BeatmapMetadata::from_osu_bytes(&map_bytes);
+        let map_id = match source {
+            MapSource::Id { map_id, .. } => Some(map_id),
+            _ => metadata.beatmap_id,
+        };
+
+        let map = calc::parse_beatmap(&map_bytes, pp_mode)?;
+        let play = PlayInputs {
+            judgements,
+            combo: resolve_combo(combo_input, &map, pp_mode, &game_mods, rules),
+        };
+        let pp = calc::calculate_play(&map, pp_mode, &game_mods, None, rules, &play).pp();
+
+        let label = if selected_mods.is_empty() {
+            metadata.to_string()
+        } else {
+            let acronyms: String = selected_mods.iter().map(|m| m.acronym).collect();
+
+            format!("{metadata} +{acronyms}")
+        };
+
+        eprintln!("{label}: {pp:.2}pp");
+        plays.push(NewPlay { pp, label, map_id });
+
+        let another = Confirm::new("Add another play?")
+            .affirmative("Yes")
+            .negative("No")
+            .run()
+            .context("failed to read confirmation")?;
+
+        if !another {
+            break;
+        }
+    }
+
+    let (scores, user) = fetch_profile(osu, cli, &username, api_mode).await?;
+
+    let map_ids: Vec<Option<u32>> = plays.iter().map(|play| play.map_id).collect();
+    let bonus = BonusPp::for_user(&user, &scores, &map_ids);
+
+    let report = SessionReport {
+        mode: api_mode.as_str(),
+        profile: calc::project_session(&scores, &plays, bonus, &cli.weighting()),
+        plays,
+    };
+
+    if cli.json {
+        let json = serde_json::to_string_pretty(&report).context("failed to serialize report")?;
+        println!("{json}");
+    } else {
+        print_report(&report, cli.top_n_diff.unwrap_or(TOP_N_DIFF));
+    }
+
+    Ok(())
+}
+
+fn print_report(report: &SessionReport, top_n: usize) {
+    let profile = &report.profile;
+
+    println!();
+    println!("{} queued plays:", report.plays.len());
+
+    for play in &report.plays {
+        let rank = profile
+            .top_plays
+            .iter()
+            .position(|top| top.old_rank.is_none() && top.label == play.label && top.pp == play.pp);
+
+        match rank {
+            Some(i) => println!("  #{:<3} {:>8.2}pp  {}", i + 1, play.pp, play.label),
+            None => println!(
+                "  {:<4} {:>8.2}pp  {} (doesn't count)",
+                "-", play.pp, play.label
+            ),
+        }
+    }
+
+    println!();
+    println!(
+        "Approx. old total PP (recomputed): {:.2}pp",
+        profile.old_total_pp
+    );
+    println!(
+        "Approx. new total PP:             {:.2}pp",
+        profile.new_total_pp
+    );
+    println!("Approx. PP gain from the session: {:+.2}pp", profile.gain);
+
+    if let Some(bonus) = profile.bonus {
+        println!(
+            "(totals include {:.2}pp bonus PP, {:.2}pp after the session, estimated from the \
+             A-and-above grade counts)",
+            bonus.old, bonus.new
+        );
+    }
+
+    let dropped: Vec<_> = profile
+        .dropped
+        .iter()
+        .filter(|play| play.old_rank.is_some())
+        .collect();
+
+    if !dropped.is_empty() {
+        println!();
+        println!("Falling out of the top {TOP_PLAYS_CAP}:");
+
+        for play in dropped {
+            println!(
+                "  was #{:<3} {:>8.2}pp  {}",
+                play.old_rank.unwrap_or_default(),
+                play.pp,
+                play.label
+            );
+        }
+    }
+
+    print_top_plays_diff(&profile.top_plays, top_n);
+}
@@ -0,0 +1,183 @@
+//! Saved states of a profile, for `ppify snapshot` and `ppify diff`.
+
+use {
+    crate::calc::score_label,
+    rosu_v2::prelude::{GameMode, Score, UserExtended},
+    rusqlite::{Connection, Row, params},
+    serde::{Deserialize, Serialize},
+    std::{
+        path::{Path, PathBuf},
+        time::{SystemTime, UNIX_EPOCH},
+    },
+};
+
+const SCHEMA: &str = "
+    CREATE TABLE IF NOT EXISTS snapshots (
+        id INTEGER PRIMARY KEY,
+        created_at INTEGER NOT NULL,
+        user_id INTEGER NOT NULL,
+        username TEXT NOT NULL,
+        mode TEXT NOT NULL,
+        pp REAL NOT NULL,
+        global_rank INTEGER,
+        country_rank INTEGER,
+        accuracy REAL NOT NULL,
+        play_count INTEGER NOT NULL,
+        top_plays TEXT NOT NULL
+    );
+";
+
+const COLUMNS: &str = "strftime('%Y-%m-%d %H:%M', created_at, 'unixepoch', 'localtime'), \
+    user_id, username, mode, pp, global_rank, country_rank, accuracy, play_count, top_plays";
+
+/// A user's totals and top plays at one point in time.
+#[derive(Clone, Debug, Serialize)]
+pub struct Snapshot {
+    /// Local time, e.g. `2025-01-31 18:04`.
+    pub time: String,
+    pub user_id: u32,
+    pub username: String,
+    pub mode: String,
+    /// The total shown on the profile.
+    pub pp: f64,
+    pub global_rank: Option<u32>,
+    pub country_rank: Option<u32>,
+    pub accuracy: f64,
+    pub play_count: u32,
+    /// Best first.
+    pub top_plays: Vec<SnapshotPlay>,
+}
+
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct SnapshotPlay {
+    pub score_id: u64,
+    pub map_id: u32,
+    /// "Artist - Title [Diff] +MODS"
+    pub label: String,
+    pub pp: f64,
+    pub accuracy: f64,
+}
+
+impl Snapshot {
+    /// `user`'s profile and top `scores` in `mode` as of now.
+    pub fn new(user: &UserExtended, scores: &[Score], mode: GameMode) -> Self {
+        let stats = user.statistics.as_ref();
+
+        Self {
+            time: String::new(),
+            user_id: user.user_id,
+            username: user.username.to_string(),
+            mode: mode.as_str().to_owned(),
+            pp: stats.map_or(0.0, |stats| f64::from(stats.pp)),
+            global_rank: stats.and_then(|stats| stats.global_rank),
+            country_rank: stats.and_then(|stats| stats.country_rank),
+            accuracy: stats.map_or(0.0, |stats| f64::from(stats.accuracy)),
+            play_count: stats.map_or(0, |stats| stats.playcount),
+            top_plays: scores
+                .iter()
+                .map(|score| SnapshotPlay {
+                    score_id: score.id,
+                    map_id: score.map_id,
+                    label: score_label(score),
+                    pp: score.pp.map_or(0.0, f64::from),
+                    accuracy: f64::from(score.accuracy),
+                })
+                .collect(),
+        }
+    }
+
+    fn from_row(row: &Row<'_>) -> rusqlite::Result<Self> {
+        let top_plays: String = row.get(9)?;
+
+        Ok(Self {
+            time: row.get(0)?,
+            user_id: row.get(1)?,
+            username: row.get(2)?,
+            mode: row.get(3)?,
+            pp: row.get(4)?,
+            global_rank: row.get(5)?,
+            country_rank: row.get(6)?,
+            accuracy: row.get(7)?,
+            play_count: row.get(8)?,
+            top_plays: serde_json::from_str(&top_plays).map_err(|err| {
+                rusqlite::Error::FromSqlConversionFailure(
+                    9,
+                    rusqlite::types::Type::Text,
+                    err.into(),
+                )
+            })?,
+        })
+    }
+}
+
+pub struct Snapshots {
+    conn: Connection,
+}
+
+impl Snapshots {
+    /// `~/.local/share/ppify/snapshots.db` or the platform equivalent.
+    pub fn default_path() -> Option<PathBuf> {
+        dirs::data_dir().map(|dir| dir.join("ppify").join("snapshots.db"))
+    }
+
+    /// Opens the database at `path`, creating it if needed. The directory has to exist.
+    pub fn open(path: &Path) -> rusqlite::Result<Self> {
+        let conn = Connection::open(path)?;
+        conn.execute_batch(SCHEMA)?;
+
+        Ok(Self { conn })
+    }
+
+    pub fn save(&self, snapshot: &Snapshot) -> rusqlite::Result<()> {
+        let created_at = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map_or(0, |since| since.as_secs() as i64);
+
+        // a list of plain structs always serializes
+        let top_plays = serde_json::to_string(&snapshot.top_plays).unwrap_or_default();
+
+        self.conn.execute(
+            "INSERT INTO snapshots (created_at, user_id, username, mode, pp, global_rank,
+                 country_rank, accuracy, play_count, top_plays)
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10)",
+            params![
+                created_at,
+                snapshot.user_id,
+                snapshot.username,
+                snapshot.mode,
+                snapshot.pp,
+                snapshot.global_rank,
+                snapshot.country_rank,
+                snapshot.accuracy,
+                snapshot.play_count,
+                top_plays,
+            ],
+        )?;
+
+        Ok(())
+    }
+
+    /// Up to `limit` snapshots of `user` in `mode`, newest first. `user` is a user id or a
+    /// username as of the snapshot, ignoring ASCII case.
+    pub fn latest(
+        &self,
+        user: &str,
+        mode: GameMode,
+        limit: u32,
+    ) -> rusqlite::Result<Vec<Snapshot>> {
+        let user = user.trim();
+        let user_id = user.parse::<u32>().ok();
+
+        let mut statement = self.conn.prepare(&format!(
+            "SELECT {COLUMNS} FROM snapshots
+             WHERE mode = ?1 AND (user_id = ?2 OR username = ?3 COLLATE NOCASE)
+             ORDER BY created_at DESC, id DESC LIMIT ?4"
+        ))?;
+        let snapshots = statement.query_map(
+            params![mode.as_str(), user_id, user, limit],
+            Snapshot::from_row,
+        )?;
+
+        snapshots.collect()
+    }
+}
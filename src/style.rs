@@ -0,0 +1,86 @@
+//! Colors of the text report, see `--no-color` and the `theme` config key.
+
+use {
+    crate::cli::Cli,
+    console::{Style, StyledObject},
+    ppify::config::Theme,
+    std::sync::OnceLock,
+};
+
+struct Palette {
+    heading: Style,
+    value: Style,
+    gain: Style,
+    loss: Style,
+    dim: Style,
+}
+
+impl Palette {
+    fn new(theme: Theme) -> Self {
+        match theme {
+            Theme::Dark => Self {
+                heading: Style::new().cyan().bright().bold(),
+                value: Style::new().bold(),
+                gain: Style::new().green().bright(),
+                loss: Style::new().red().bright(),
+                dim: Style::new().dim(),
+            },
+            Theme::Light => Self {
+                heading: Style::new().blue().bold(),
+                value: Style::new().bold(),
+                gain: Style::new().green(),
+                loss: Style::new().red(),
+                dim: Style::new().dim(),
+            },
+            Theme::Mono => Self {
+                heading: Style::new().bold(),
+                value: Style::new().bold(),
+                gain: Style::new().bold(),
+                loss: Style::new().underlined(),
+                dim: Style::new().dim(),
+            },
+        }
+    }
+}
+
+static PALETTE: OnceLock<Palette> = OnceLock::new();
+
+/// Picks the palette of the configured theme and turns colors off for `--no-color` or
+/// `color = false`. `NO_COLOR` and pipes already turn them off in `console`.
+pub fn init(cli: &Cli) {
+    if cli.no_color || cli.config.color == Some(false) {
+        console::set_colors_enabled(false);
+        console::set_colors_enabled_stderr(false);
+    }
+
+    let _ = PALETTE.set(Palette::new(cli.config.theme.unwrap_or_default()));
+}
+
+fn palette() -> &'static Palette {
+    PALETTE.get_or_init(|| Palette::new(Theme::default()))
+}
+
+pub fn heading<D>(text: D) -> StyledObject<D> {
+    palette().heading.apply_to(text)
+}
+
+/// The numbers the report is about, e.g. the play's pp.
+pub fn value<D>(text: D) -> StyledObject<D> {
+    palette().value.apply_to(text)
+}
+
+/// `text` in the gain color for positive `delta`s and the loss color for negative ones.
+pub fn delta<D>(delta: f64, text: D) -> StyledObject<D> {
+    if delta > 0.0 {
+        palette().gain.apply_to(text)
+    } else if delta < 0.0 {
+        palette().loss.apply_to(text)
+    } else {
+        Style::new().apply_to(text)
+    }
+}
+
+/// Notes and other text that explains rather than reports.
+pub fn dim<D>(text: D) -> StyledObject<D> {
+    palette().dim.apply_to(text)
+}
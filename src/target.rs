@@ -0,0 +1,307 @@
+use {
+    crate::{
+        checked_clock_rate, cli::Cli, fetch_profile, modes_for, rankings_cache, read_map_bytes,
+        read_mode, read_username, resolve_map_source, style, thousands,
+    },
+    color_eyre::{Result, eyre::Context},
+    ppify::{
+        PpifyError,
+        api::Client,
+        calc::{
+            self, BeatmapMetadata, BonusPp, Judgements, PASS_MARGIN, PlayInputs, Rules,
+            TOP_PLAYS_CAP,
+        },
+        input::MapSource,
+        mods,
+        ranking::RankEstimator,
+        report::{TargetOnMap, TargetPosition, TargetRank, TargetReport},
+    },
+    rosu_v2::prelude::{GameMode, UserExtended},
+};
+
+pub enum Goal<'a> {
+    Total(f64),
+    /// Pass the total of another user.
+    Overtake(&'a str),
+    /// Pass the total of the user at a global rank.
+    Rank(u32),
+    /// A new top play at a 1-based position.
+    Position(usize),
+}
+
+pub async fn run(osu: &Client, cli: &Cli, goal: Goal<'_>) -> Result<()> {
+    let username = read_username(cli.user.as_deref())?;
+
+    // only look at a map when one was asked for
+    let map_source = if cli.map.is_some() || cli.set.is_some() {
+        Some(resolve_map_source(osu, cli).await?)
+    } else {
+        None
+    };
+
+    let (api_mode, pp_mode) = match (cli.mode, map_source.as_ref().and_then(MapSource::mode)) {
+        (Some(mode), _) => mode.modes(),
+        (None, Some(mode)) => modes_for(mode),
+        (None, None) => read_mode()?,
+    };
+
+    let map_fut = async {
+        match &map_source {
+            Some(source) => read_map_bytes(osu, source, cli).await.map(Some),
+            None => Ok(None),
+        }
+    };
+
+    let ((scores, user), map_bytes) =
+        tokio::try_join!(fetch_profile(osu, cli, &username, api_mode), map_fut)?;
+
+    let map_id = match &map_source {
+        Some(MapSource::Id { map_id, .. }) => Some(*map_id),
+        _ => map_bytes
+            .as_deref()
+            .and_then(|bytes| BeatmapMetadata::from_osu_bytes(bytes).beatmap_id),
+    };
+
+    let bonus = BonusPp::for_user(&user, &scores, &[map_id]);
+    let weighting = cli.weighting();
+
+    let (target_total_pp, rival, target_rank, position) = match goal {
+        Goal::Total(total) => (total, None, None, None),
+        Goal::Overtake(rival) => {
+            let (rival_scores, rival_user) = fetch_profile(osu, cli, rival, api_mode).await?;
+            let rival_bonus = BonusPp::for_user(&rival_user, &rival_scores, &[]);
+            let rival_total = calc::total_pp(&rival_scores, rival_bonus, &cli.weighting());
+
+            (
+                rival_total + PASS_MARGIN,
+                Some(rival_user.username.to_string()),
+                None,
+                None,
+            )
+        }
+        Goal::Rank(rank) => {
+            let target = rank_total(osu, cli, rank, &user, api_mode).await?;
+
+            (target.pp + PASS_MARGIN, None, Some(target), None)
+        }
+        Goal::Position(position) => {
+            if !(1..=TOP_PLAYS_CAP).contains(&position) {
+                return Err(PpifyError::BadInput(format!(
+                    "the position must be between 1 and {TOP_PLAYS_CAP}, got {position}"
+                ))
+                .into());
+            }
+
+            // ties go to the existing play, so the new one has to be worth a bit more
+            let current_pp = calc::pp_at_position(&scores, position);
+            let play_pp = current_pp.unwrap_or(0.0) + PASS_MARGIN;
+            let projection = calc::project_gain(&scores, play_pp, "", map_id, bonus, &weighting);
+
+            let target = TargetPosition {
+                position,
+                current_pp,
+                new_rank: projection.new_play_rank,
+            };
+
+            (projection.new_total_pp, None, None, Some((target, play_pp)))
+        }
+    };
+
+    let current_total_pp = calc::total_pp(&scores, bonus, &weighting);
+    let required_play_pp = match position {
+        Some((target, play_pp)) => target.new_rank.map(|_| play_pp),
+        None => calc::required_play_pp(&scores, target_total_pp, map_id, bonus, &weighting),
+    };
+    let position = position.map(|(target, _)| target);
+
+    let on_map = match map_bytes {
+        Some(bytes) => {
+            let map = calc::parse_beatmap(&bytes, pp_mode)?;
+            let selected_mods = mods::parse_acronyms(cli.mods.as_deref().unwrap_or(""), api_mode)?;
+            let game_mods = mods::apply_settings(
+                mods::lazer_mods(selected_mods.iter().copied(), api_mode),
+                api_mode,
+                &cli.mod_settings(),
+            )?;
+            let clock_rate = checked_clock_rate(cli)?;
+            let misses = cli.score.misses.unwrap_or(0);
+
+            let max_pp = calc::calculate_play(
+                &map,
+                pp_mode,
+                &game_mods,
+                clock_rate,
+                Rules::new(cli.stable),
+                &PlayInputs {
+                    judgements: Judgements::Accuracy {
+                        accuracy: 100.0,
+                        misses,
+                    },
+                    combo: None,
+                },
+            )
+            .pp();
+
+            let accuracy = required_play_pp.and_then(|pp| {
+                calc::accuracy_for_pp(
+                    &map,
+                    pp_mode,
+                    &game_mods,
+                    clock_rate,
+                    Rules::new(cli.stable),
+                    misses,
+                    pp,
+                )
+            });
+
+            Some(TargetOnMap {
+                beatmap: BeatmapMetadata::from_osu_bytes(&bytes),
+                mods: selected_mods.iter().map(|m| m.acronym).collect(),
+                misses,
+                max_pp,
+                accuracy,
+            })
+        }
+        None => None,
+    };
+
+    let report = TargetReport {
+        mode: api_mode.as_str(),
+        rival,
+        target_rank,
+        position,
+        target_total_pp,
+        current_total_pp,
+        required_play_pp,
+        on_map,
+    };
+
+    if cli.json {
+        let json = serde_json::to_string_pretty(&report).context("failed to serialize report")?;
+        println!("{json}");
+    } else {
+        print_report(&report);
+    }
+
+    Ok(())
+}
+
+/// The total of global `rank`, interpolated towards `user`'s own rank past the listed ones.
+async fn rank_total(
+    osu: &Client,
+    cli: &Cli,
+    rank: u32,
+    user: &UserExtended,
+    mode: GameMode,
+) -> Result<TargetRank> {
+    let cache = rankings_cache(cli);
+    let mut estimator = RankEstimator::new(osu, mode).cache(cache.as_ref());
+
+    let anchor = user
+        .statistics
+        .as_ref()
+        .and_then(|stats| Some((f64::from(stats.pp), stats.global_rank?)));
+    let listed = estimator.listed_ranks().await?;
+
+    let pp = estimator.pp_for_rank(rank, anchor).await?.ok_or_else(|| {
+        PpifyError::BadInput(format!(
+            "the rankings only list the top {}, rank #{} past that can only be estimated \
+             from a profile ranked below it",
+            thousands(listed),
+            thousands(rank)
+        ))
+    })?;
+
+    Ok(TargetRank {
+        rank,
+        pp,
+        interpolated: rank > listed,
+    })
+}
+
+fn print_report(report: &TargetReport) {
+    println!();
+    println!("Current total: {:.2}pp", report.current_total_pp);
+    match (&report.rival, report.target_rank) {
+        (Some(rival), _) => println!(
+            "{rival}'s total: {:.2}pp",
+            report.target_total_pp - PASS_MARGIN
+        ),
+        (None, Some(target)) => {
+            println!("Total of #{}: {:.2}pp", thousands(target.rank), target.pp);
+
+            if target.interpolated {
+                println!(
+                    "{}",
+                    style::dim(
+                        "(past the listed ranks, estimated between the last listed user and \
+                         your own rank)"
+                    )
+                );
+            }
+        }
+        (None, None) => match report.position {
+            Some(target) => match target.current_pp {
+                Some(pp) => println!("Your #{} now: {pp:.2}pp", target.position),
+                None => println!("You have fewer than {} top plays.", target.position),
+            },
+            None => println!("Target total:  {:.2}pp", report.target_total_pp),
+        },
+    }
+    println!();
+
+    let Some(required) = report.required_play_pp else {
+        match report.position {
+            Some(_) => {
+                println!("Your play on this map is worth more, a new one there changes nothing.")
+            }
+            None => println!("No single play can reach that total."),
+        }
+        return;
+    };
+
+    if required == 0.0 {
+        match &report.rival {
+            Some(rival) => println!("You are already ahead of {rival}."),
+            None => println!("You are already there."),
+        }
+
+        return;
+    }
+
+    match (&report.rival, report.position) {
+        (Some(rival), _) => println!("You need one play worth {required:.2}pp to pass {rival}."),
+        (None, Some(target)) => {
+            println!(
+                "You need one play worth {required:.2}pp for a new #{}.",
+                target.position
+            );
+
+            if let Some(rank) = target.new_rank.filter(|&rank| rank != target.position) {
+                println!(
+                    "{}",
+                    style::dim(format!(
+                        "(it lands at #{rank}, as it replaces your better-placed play on this map)"
+                    ))
+                );
+            }
+        }
+        (None, None) => println!("You need one play worth {required:.2}pp."),
+    }
+
+    if let Some(on_map) = &report.on_map {
+        let mods = if on_map.mods.is_empty() {
+            String::new()
+        } else {
+            format!(" +{}", on_map.mods.concat())
+        };
+
+        println!();
+        println!("On {}{mods} with {} misses:", on_map.beatmap, on_map.misses);
+
+        match on_map.accuracy {
+            Some(accuracy) => println!("  {accuracy:.2}% accuracy gets you there."),
+            None => println!("  Not possible, 100% is only worth {:.2}pp.", on_map.max_pp),
+        }
+    }
+}
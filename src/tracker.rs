@@ -0,0 +1,192 @@
+//! `ppify snapshot` and `ppify diff`: a user's progress between saved profile states.
+
+use {
+    crate::{
+        cli::Cli, data_path, markdown, progress, read_mode, read_username, score_cache, style,
+        thousands, user_cache,
+    },
+    color_eyre::{Result, eyre::Context},
+    ppify::{
+        PpifyError,
+        api::{self, Client},
+        ranking::RankChange,
+        report::SnapshotDiff,
+        snapshot::{Snapshot, Snapshots},
+    },
+};
+
+pub async fn snapshot(osu: &Client, cli: &Cli, user: Option<&str>) -> Result<()> {
+    let username = read_username(user.or(cli.user.as_deref()))?;
+
+    let (api_mode, _) = match cli.mode {
+        Some(mode) => mode.modes(),
+        None => read_mode()?,
+    };
+
+    let (score_cache, user_cache) = (score_cache(cli), user_cache(cli));
+
+    // a snapshot is of the profile now, not of what the caches still hold
+    let spinner = progress::spinner(format!("Fetching the profile of {}", username.trim()));
+    let fetched = tokio::try_join!(
+        api::fetch_user_best_scores_cached(
+            osu,
+            username.trim(),
+            api_mode,
+            score_cache.as_ref(),
+            true
+        ),
+        api::fetch_user_cached(osu, username.trim(), api_mode, user_cache.as_ref(), true)
+    );
+    spinner.finish_and_clear();
+
+    let (scores, profile) = fetched?;
+    let snapshot = Snapshot::new(&profile, &scores, api_mode);
+
+    let snapshots = open_snapshots()?;
+    snapshots
+        .save(&snapshot)
+        .context("failed to save the snapshot")?;
+
+    println!(
+        "Saved a snapshot of {} ({}): {:.2}pp, {} top plays",
+        snapshot.username,
+        snapshot.mode,
+        snapshot.pp,
+        snapshot.top_plays.len()
+    );
+
+    let saved = snapshots
+        .latest(&snapshot.user_id.to_string(), api_mode, 2)
+        .context("failed to read the snapshots")?;
+
+    if saved.len() > 1 {
+        println!(
+            "{}",
+            style::dim(format!(
+                "Compare it with the previous one with `ppify diff {}`",
+                snapshot.username
+            ))
+        );
+    }
+
+    Ok(())
+}
+
+pub fn diff(cli: &Cli, user: Option<&str>) -> Result<()> {
+    let username = read_username(user.or(cli.user.as_deref()))?;
+
+    let (api_mode, _) = match cli.mode {
+        Some(mode) => mode.modes(),
+        None => read_mode()?,
+    };
+
+    let snapshots = open_snapshots()?
+        .latest(&username, api_mode, 2)
+        .context("failed to read the snapshots")?;
+
+    let [new, old] = snapshots.as_slice() else {
+        return Err(PpifyError::BadInput(format!(
+            "`{}` has {} {} snapshot(s), `ppify snapshot` saves one and diff needs two",
+            username.trim(),
+            snapshots.len(),
+            api_mode.as_str()
+        ))
+        .into());
+    };
+
+    let report = SnapshotDiff::new(old, new);
+
+    if cli.json {
+        let json = serde_json::to_string_pretty(&report).context("failed to serialize report")?;
+        println!("{json}");
+    } else if cli.markdown() {
+        print!("{}", markdown::snapshot_diff(&report));
+    } else {
+        print_report(&report);
+    }
+
+    Ok(())
+}
+
+fn open_snapshots() -> Result<Snapshots> {
+    let path = data_path(Snapshots::default_path())?;
+
+    Snapshots::open(&path).with_context(|| format!("failed to open {}", path.display()))
+}
+
+fn print_report(report: &SnapshotDiff) {
+    println!();
+    println!(
+        "{}",
+        style::heading(format!(
+            "{} ({}), {} -> {}",
+            report.username, report.mode, report.from, report.to
+        ))
+    );
+    println!();
+    println!(
+        "PP:            {:.2}pp -> {}pp  {}",
+        report.old_pp,
+        style::value(format!("{:.2}", report.new_pp)),
+        style::delta(report.pp_gained, format!("({:+.2}pp)", report.pp_gained))
+    );
+
+    if let Some(rank) = report.global_rank {
+        println!("Global rank:   {}", rank_movement(rank));
+    }
+
+    if let Some(rank) = report.country_rank {
+        println!("Country rank:  {}", rank_movement(rank));
+    }
+
+    let accuracy = report.new_accuracy - report.old_accuracy;
+    println!(
+        "Accuracy:      {:.2}% -> {:.2}%  {}",
+        report.old_accuracy,
+        report.new_accuracy,
+        style::delta(accuracy, format!("({accuracy:+.2}%)"))
+    );
+    println!("Plays:         {:+}", report.play_count_gained);
+
+    println!();
+
+    if report.new_plays.is_empty() {
+        println!("No new top plays.");
+    } else {
+        println!("{}", style::heading("New top plays:"));
+
+        for new_play in &report.new_plays {
+            let play = &new_play.play;
+
+            println!(
+                "  #{:<3} {}pp  {:>6.2}%  {}",
+                new_play.rank,
+                style::value(format!("{:>7.2}", play.pp)),
+                play.accuracy,
+                play.label
+            );
+        }
+    }
+
+    if report.dropped_plays > 0 {
+        println!(
+            "{}",
+            style::dim(format!(
+                "{} older plays dropped out of the top plays.",
+                report.dropped_plays
+            ))
+        );
+    }
+}
+
+// climbing means a smaller rank
+fn rank_movement(rank: RankChange) -> String {
+    let climbed = i64::from(rank.old) - i64::from(rank.new);
+
+    format!(
+        "#{} -> #{}  {}",
+        thousands(rank.old),
+        thousands(rank.new),
+        style::delta(climbed as f64, format!("({climbed:+})"))
+    )
+}
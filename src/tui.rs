@@ -0,0 +1,457 @@
+//! `--tui`: a full-screen view of one calculation that recalculates as the play changes.
+
+use {
+    crate::{Calculation, ComboInput, cli::Cli},
+    color_eyre::Result,
+    ppify::{
+        calc::{self, Judgements, PlayInputs, PpBreakdown, TOP_PLAYS_CAP},
+        mods::{self, ModOptionDef},
+        report::CalculationReport,
+    },
+    ratatui::{
+        Frame,
+        crossterm::event::{self, Event, KeyCode, KeyEvent, KeyEventKind, KeyModifiers},
+        layout::{Constraint, Layout, Rect},
+        style::{Color, Modifier, Style, Stylize},
+        text::{Line, Span},
+        widgets::{Block, Paragraph, Row, Table, Wrap},
+    },
+};
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum Field {
+    Accuracy,
+    Misses,
+    Combo,
+    Mods,
+}
+
+const FIELDS: [Field; 4] = [Field::Accuracy, Field::Misses, Field::Combo, Field::Mods];
+
+struct App<'a> {
+    cli: &'a Cli,
+    calculation: Calculation,
+    available_mods: Vec<&'static ModOptionDef>,
+    field: Field,
+    mod_cursor: usize,
+    top_scroll: usize,
+    report: Option<CalculationReport>,
+    /// The last change that couldn't be applied.
+    error: Option<String>,
+}
+
+/// Shows `calculation` until `q` or Esc, recalculating on every key.
+pub fn run(calculation: Calculation, cli: &Cli) -> Result<()> {
+    let mut app = App {
+        cli,
+        available_mods: mods::available_for_mode(calculation.api_mode).collect(),
+        calculation,
+        field: Field::Accuracy,
+        mod_cursor: 0,
+        top_scroll: 0,
+        report: None,
+        error: None,
+    };
+
+    app.recalculate();
+
+    let mut terminal = ratatui::init();
+    let result = app.event_loop(&mut terminal);
+    ratatui::restore();
+
+    result
+}
+
+impl App<'_> {
+    fn event_loop(&mut self, terminal: &mut ratatui::DefaultTerminal) -> Result<()> {
+        loop {
+            terminal.draw(|frame| self.draw(frame))?;
+
+            let Event::Key(key) = event::read()? else {
+                continue;
+            };
+
+            if key.kind != KeyEventKind::Press {
+                continue;
+            }
+
+            match key.code {
+                KeyCode::Char('q') | KeyCode::Esc => return Ok(()),
+                KeyCode::Char('c') if key.modifiers.contains(KeyModifiers::CONTROL) => {
+                    return Ok(());
+                }
+                _ => self.handle_key(key),
+            }
+        }
+    }
+
+    fn handle_key(&mut self, key: KeyEvent) {
+        let big = key.modifiers.contains(KeyModifiers::SHIFT);
+        let field_index = FIELDS.iter().position(|&f| f == self.field).unwrap_or(0);
+        self.error = None;
+
+        match key.code {
+            KeyCode::Up | KeyCode::Char('k') | KeyCode::BackTab => {
+                self.field = FIELDS[(field_index + FIELDS.len() - 1) % FIELDS.len()];
+            }
+            KeyCode::Down | KeyCode::Char('j') | KeyCode::Tab => {
+                self.field = FIELDS[(field_index + 1) % FIELDS.len()];
+            }
+            KeyCode::Left | KeyCode::Char('h') => self.adjust(-1, big),
+            KeyCode::Right | KeyCode::Char('l') => self.adjust(1, big),
+            KeyCode::Char(' ') | KeyCode::Enter if self.field == Field::Mods => self.toggle_mod(),
+            KeyCode::PageUp => self.top_scroll = self.top_scroll.saturating_sub(10),
+            KeyCode::PageDown => {
+                self.top_scroll = (self.top_scroll + 10).min(TOP_PLAYS_CAP.saturating_sub(1))
+            }
+            _ => return,
+        }
+
+        self.recalculate();
+    }
+
+    /// Steps the selected field by `direction`, further for `big` steps.
+    fn adjust(&mut self, direction: i32, big: bool) {
+        let calculation = &mut self.calculation;
+
+        match self.field {
+            Field::Accuracy | Field::Misses => {
+                // detailed judgements from the flags become accuracy and misses on the first change
+                let play = PlayInputs {
+                    judgements: calculation.judgements,
+                    combo: None,
+                };
+                let (mut accuracy, mut misses) = (play.accuracy(), play.misses());
+
+                if self.field == Field::Accuracy {
+                    let step = if big { 1.0 } else { 0.1 };
+                    accuracy = (accuracy + f64::from(direction) * step).clamp(0.0, 100.0);
+                } else {
+                    let step = if big { 10 } else { 1 };
+                    misses = misses.saturating_add_signed(direction * step);
+                }
+
+                calculation.judgements = Judgements::Accuracy { accuracy, misses };
+            }
+            Field::Combo => {
+                let max_combo = calc::max_combo(
+                    &calculation.map,
+                    calculation.pp_mode,
+                    &calculation.game_mods,
+                    calculation.rules,
+                );
+                let current = match calculation.combo_input {
+                    Some(ComboInput::Absolute(combo)) => combo,
+                    Some(ComboInput::PercentOfMax(pct)) => {
+                        (f64::from(max_combo) * pct / 100.0).round() as u32
+                    }
+                    None => max_combo,
+                };
+
+                let step = if big { 100 } else { 10 };
+                let combo = current
+                    .saturating_add_signed(direction * step)
+                    .min(max_combo);
+
+                // the max combo is the same as no combo, a full combo
+                calculation.combo_input =
+                    (combo < max_combo).then_some(ComboInput::Absolute(combo));
+            }
+            Field::Mods => {
+                let len = self.available_mods.len() as i32;
+                let step = if big { 5 } else { 1 };
+
+                self.mod_cursor =
+                    (self.mod_cursor as i32 + direction * step).rem_euclid(len) as usize;
+            }
+        }
+    }
+
+    fn toggle_mod(&mut self) {
+        let calculation = &mut self.calculation;
+        let m = self.available_mods[self.mod_cursor];
+        let mut selected = calculation.selected_mods.clone();
+
+        match selected.iter().position(|s| s.acronym == m.acronym) {
+            Some(i) => {
+                selected.remove(i);
+            }
+            None => selected.push(m),
+        }
+
+        let game_mods = mods::apply_settings(
+            mods::lazer_mods(selected.iter().copied(), calculation.api_mode),
+            calculation.api_mode,
+            &calculation.mod_settings,
+        )
+        .map(|game_mods| mods::without(game_mods, &calculation.ignored_mods));
+
+        // e.g. a --speed-change without DT/NC/HT/DC left
+        match game_mods {
+            Ok(game_mods) => {
+                calculation.selected_mods = selected;
+                calculation.game_mods = game_mods;
+            }
+            Err(err) => self.error = Some(err.to_string()),
+        }
+    }
+
+    fn recalculate(&mut self) {
+        match self.calculation.report(self.cli) {
+            Ok(report) => self.report = Some(report),
+            Err(err) => self.error = Some(err.to_string()),
+        }
+    }
+
+    fn draw(&self, frame: &mut Frame) {
+        let [main, status] =
+            Layout::vertical([Constraint::Min(0), Constraint::Length(1)]).areas(frame.area());
+        let [left, right] =
+            Layout::horizontal([Constraint::Percentage(50), Constraint::Percentage(50)])
+                .areas(main);
+        let [map_area, play_area] =
+            Layout::vertical([Constraint::Length(7), Constraint::Min(0)]).areas(left);
+        let [result_area, top_area] =
+            Layout::vertical([Constraint::Length(9), Constraint::Min(0)]).areas(right);
+
+        if let Some(report) = &self.report {
+            self.draw_map(frame, map_area, report);
+            self.draw_result(frame, result_area, report);
+            self.draw_top_plays(frame, top_area, report);
+        }
+
+        self.draw_play(frame, play_area);
+
+        let status_line = match &self.error {
+            Some(err) => Line::from(format!(" {err}")).fg(Color::Red),
+            None => Line::from(
+                " ↑↓ field  ←→ change (shift: more)  space toggle mod  PgUp/PgDn top plays  q quit",
+            )
+            .dim(),
+        };
+
+        frame.render_widget(status_line, status);
+    }
+
+    fn draw_map(&self, frame: &mut Frame, area: Rect, report: &CalculationReport) {
+        let modded = &report.settings.modded;
+        let length = modded.drain_secs.round() as u32;
+
+        let lines = vec![
+            Line::from(report.beatmap.to_string()).bold(),
+            Line::from(format!(
+                "{:.2}★  max combo {}x  {} rules",
+                report.difficulty.stars, report.difficulty.max_combo, report.rules
+            )),
+            Line::from(format!(
+                "AR {:.2}  OD {:.2}  CS {:.2}  HP {:.2}",
+                modded.ar, modded.od, modded.cs, modded.hp
+            )),
+            Line::from(format!(
+                "{:.1} BPM  {}:{:02}",
+                modded.bpm,
+                length / 60,
+                length % 60
+            )),
+        ];
+
+        frame.render_widget(
+            Paragraph::new(lines)
+                .wrap(Wrap { trim: true })
+                .block(Block::bordered().title(" Map ")),
+            area,
+        );
+    }
+
+    fn draw_play(&self, frame: &mut Frame, area: Rect) {
+        let calculation = &self.calculation;
+        let play = PlayInputs {
+            judgements: calculation.judgements,
+            combo: None,
+        };
+
+        let combo = match calculation.combo_input {
+            Some(ComboInput::Absolute(combo)) => format!("{combo}x"),
+            Some(ComboInput::PercentOfMax(pct)) => format!("{pct}% of max"),
+            None => "full combo".to_owned(),
+        };
+
+        let label = |field: Field, name: &str| {
+            if self.field == field {
+                Span::from(format!("> {name:<10}")).bold().fg(Color::Cyan)
+            } else {
+                Span::from(format!("  {name:<10}"))
+            }
+        };
+
+        let mut mod_spans = vec![label(Field::Mods, "Mods")];
+
+        for (i, m) in self.available_mods.iter().enumerate() {
+            let mut style = Style::new();
+
+            if calculation
+                .selected_mods
+                .iter()
+                .any(|s| s.acronym == m.acronym)
+            {
+                style = style.fg(Color::Green).add_modifier(Modifier::BOLD);
+            } else {
+                style = style.add_modifier(Modifier::DIM);
+            }
+
+            if self.field == Field::Mods && i == self.mod_cursor {
+                style = style.add_modifier(Modifier::REVERSED);
+            }
+
+            mod_spans.push(Span::styled(m.acronym, style));
+            mod_spans.push(Span::from(" "));
+        }
+
+        let lines = vec![
+            Line::from(vec![
+                label(Field::Accuracy, "Accuracy"),
+                Span::from(format!("{:.2}%", play.accuracy())),
+            ]),
+            Line::from(vec![
+                label(Field::Misses, "Misses"),
+                Span::from(play.misses().to_string()),
+            ]),
+            Line::from(vec![label(Field::Combo, "Combo"), Span::from(combo)]),
+            Line::from(""),
+            Line::from(mod_spans),
+        ];
+
+        let description = match self.field {
+            Field::Mods => self.available_mods[self.mod_cursor].description,
+            _ => "",
+        };
+
+        frame.render_widget(
+            Paragraph::new(lines).wrap(Wrap { trim: false }).block(
+                Block::bordered()
+                    .title(" Play ")
+                    .title_bottom(Line::from(description).dim()),
+            ),
+            area,
+        );
+    }
+
+    fn draw_result(&self, frame: &mut Frame, area: Rect, report: &CalculationReport) {
+        let profile = &report.profile;
+        let gain_color = if profile.gain < 0.0 {
+            Color::Red
+        } else {
+            Color::Green
+        };
+
+        let mut lines = vec![
+            Line::from(format!("{:.2}pp", report.pp)).bold(),
+            Line::from(breakdown(&report.pp_breakdown)).dim(),
+            Line::from(vec![
+                Span::from("Gain  "),
+                Span::from(format!("{:+.2}pp", profile.gain))
+                    .fg(gain_color)
+                    .bold(),
+            ]),
+            Line::from(format!(
+                "Total {:.2}pp -> {:.2}pp",
+                profile.old_total_pp, profile.new_total_pp
+            )),
+        ];
+
+        if let Some(existing) = &profile.outscored_by {
+            lines.push(Line::from(format!(
+                "Your #{} on this map ({:.2}pp) is worth more",
+                existing.old_rank.unwrap_or_default(),
+                existing.pp
+            )));
+        } else if let (Some(rank), Some(weighted)) =
+            (profile.new_play_rank, profile.new_play_weighted_pp)
+        {
+            lines.push(Line::from(format!(
+                "New #{rank} top play, {weighted:.2} weighted pp"
+            )));
+        } else {
+            lines.push(Line::from(format!("Not in the top {TOP_PLAYS_CAP}")));
+        }
+
+        if let Some(replaced) = &profile.replaced {
+            lines.push(
+                Line::from(format!(
+                    "Replaces your #{} on this map ({:.2}pp)",
+                    replaced.old_rank.unwrap_or_default(),
+                    replaced.pp
+                ))
+                .dim(),
+            );
+        }
+
+        let title = format!(" {} ", self.calculation.user.username);
+
+        frame.render_widget(
+            Paragraph::new(lines).block(Block::bordered().title(title)),
+            area,
+        );
+    }
+
+    fn draw_top_plays(&self, frame: &mut Frame, area: Rect, report: &CalculationReport) {
+        let rows = report
+            .profile
+            .top_plays
+            .iter()
+            .enumerate()
+            .skip(self.top_scroll)
+            .map(|(i, play)| {
+                let rank = i + 1;
+                let change = match play.old_rank {
+                    None => "NEW".to_owned(),
+                    Some(old) if old != rank => format!("was #{old}"),
+                    Some(_) => String::new(),
+                };
+
+                let row = Row::new(vec![
+                    format!("#{rank}"),
+                    format!("{:.2}pp", play.pp),
+                    change,
+                    play.label.clone(),
+                ]);
+
+                if play.old_rank.is_none() {
+                    row.fg(Color::Green).bold()
+                } else {
+                    row
+                }
+            });
+
+        let table = Table::new(
+            rows,
+            [
+                Constraint::Length(5),
+                Constraint::Length(10),
+                Constraint::Length(8),
+                Constraint::Min(0),
+            ],
+        )
+        .header(Row::new(vec!["", "pp", "", "play"]).dim())
+        .block(Block::bordered().title(format!(" Top {TOP_PLAYS_CAP} afterwards ")));
+
+        frame.render_widget(table, area);
+    }
+}
+
+fn breakdown(breakdown: &PpBreakdown) -> String {
+    match *breakdown {
+        PpBreakdown::Osu {
+            aim,
+            speed,
+            accuracy,
+            flashlight,
+        } => format!("Aim {aim:.0}  Speed {speed:.0}  Acc {accuracy:.0}  FL {flashlight:.0}"),
+        PpBreakdown::Taiko {
+            difficulty,
+            accuracy,
+        } => format!("Difficulty {difficulty:.0}  Acc {accuracy:.0}"),
+        PpBreakdown::Catch => String::new(),
+        PpBreakdown::Mania { difficulty } => format!("Difficulty {difficulty:.0}"),
+    }
+}
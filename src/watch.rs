@@ -0,0 +1,185 @@
+//! `--watch`: recalculates a local `.osu` file whenever it is saved.
+
+use {
+    crate::{
+        checked_clock_rate, cli::Cli, modes_for, parse_combo, pipe::native_mode, resolve_combo,
+        score_inputs_from_args, style,
+    },
+    color_eyre::{
+        Result,
+        eyre::{self, Context},
+    },
+    notify::{Event, RecursiveMode, Watcher},
+    ppify::{
+        PpifyError,
+        calc::{self, BeatmapMetadata, Judgements, PlayInputs, Rules},
+        mods,
+    },
+    rosu_v2::prelude::GameMods,
+    std::{
+        fs,
+        path::Path,
+        sync::mpsc::{self, RecvTimeoutError},
+        time::{Duration, SystemTime, UNIX_EPOCH},
+    },
+};
+
+// editors often write a file in several steps, the last one counts
+const DEBOUNCE: Duration = Duration::from_millis(300);
+
+pub fn run(cli: &Cli, path: &Path) -> Result<()> {
+    if !path.is_file() {
+        return Err(PpifyError::BadInput(format!("{} is not a file", path.display())).into());
+    }
+
+    // editors that save by replacing the file would end a watch on the file itself
+    let dir = match path.parent() {
+        Some(dir) if !dir.as_os_str().is_empty() => dir,
+        _ => Path::new("."),
+    };
+    let name = path.file_name();
+
+    let (sender, events) = mpsc::channel();
+    let mut watcher =
+        notify::recommended_watcher(sender).context("failed to start watching files")?;
+    watcher
+        .watch(dir, RecursiveMode::NonRecursive)
+        .with_context(|| format!("failed to watch {}", dir.display()))?;
+
+    let is_change = |event: notify::Result<Event>| {
+        event.is_ok_and(|event| {
+            !event.kind.is_access() && event.paths.iter().any(|path| path.file_name() == name)
+        })
+    };
+
+    eprintln!(
+        "Watching {}, recalculating on every save. Ctrl+C stops.",
+        path.display()
+    );
+
+    let mut last_pp = None;
+
+    loop {
+        match calculate(cli, path) {
+            Ok((beatmap, stars, pp)) => {
+                let change = last_pp.map_or_else(String::new, |last: f64| {
+                    let delta = pp - last;
+
+                    format!("  {}", style::delta(delta, format!("({delta:+.2}pp)")))
+                });
+
+                println!(
+                    "[{}] {}  {:.2}★  {}{change}",
+                    timestamp(),
+                    beatmap,
+                    stars,
+                    style::value(format!("{pp:.2}pp"))
+                );
+                last_pp = Some(pp);
+            }
+            // a half-written file parses again after the next save
+            Err(err) => eprintln!("[{}] Error: {err:#}", timestamp()),
+        }
+
+        // wait for a change, then for the saves to settle
+        loop {
+            let event = events.recv().context("stopped watching files")?;
+
+            if is_change(event) {
+                break;
+            }
+        }
+
+        loop {
+            match events.recv_timeout(DEBOUNCE) {
+                Ok(_) => {}
+                Err(RecvTimeoutError::Timeout) => break,
+                Err(RecvTimeoutError::Disconnected) => {
+                    eyre::bail!("stopped watching files");
+                }
+            }
+        }
+    }
+}
+
+/// The beatmap, star rating and pp of the play of the flags on the file as it is now.
+fn calculate(cli: &Cli, path: &Path) -> Result<(BeatmapMetadata, f64, f64)> {
+    let bytes = fs::read(path)
+        .with_context(|| format!("failed to read beatmap file {}", path.display()))?;
+    let native = calc::parse_native_beatmap(&bytes)?;
+
+    // the file's own mode unless --mode asks for a convert
+    let (api_mode, pp_mode) = match cli.mode {
+        Some(mode) => mode.modes(),
+        None => modes_for(native_mode(native.mode)),
+    };
+    calc::check_map_mode(&native, pp_mode)?;
+
+    let selected_mods = mods::parse_acronyms(cli.mods.as_deref().unwrap_or(""), api_mode)?;
+    let ignored_mods = mods::parse_acronyms(cli.ignore_mod.as_deref().unwrap_or(""), api_mode)?;
+    let game_mods: GameMods = mods::without(
+        mods::apply_settings(
+            mods::lazer_mods(selected_mods.iter().copied(), api_mode),
+            api_mode,
+            &cli.mod_settings(),
+        )?,
+        &ignored_mods,
+    );
+
+    let rules = Rules::new(cli.stable);
+    // judgement counts are checked against the mode, which can change with the file
+    let (judgements, combo_input) = if cli.score.is_specified() {
+        score_inputs_from_args(&cli.score, api_mode)?
+    } else {
+        let ss = Judgements::Accuracy {
+            accuracy: 100.0,
+            misses: cli.score.misses.unwrap_or(0),
+        };
+        let combo = match cli.score.combo.as_deref() {
+            Some(raw) => parse_combo(raw)?,
+            None => None,
+        };
+
+        (ss, combo)
+    };
+
+    let play = PlayInputs {
+        judgements,
+        // the max combo changes with the map, so a percentage is resolved on every save
+        combo: resolve_combo(combo_input, &native, pp_mode, &game_mods, rules),
+    };
+    let play = if cli.score.assume_fc {
+        play.full_combo()
+    } else {
+        play
+    };
+
+    let attrs = calc::calculate_play(
+        &native,
+        pp_mode,
+        &game_mods,
+        checked_clock_rate(cli)?,
+        rules,
+        &play,
+    );
+
+    Ok((
+        BeatmapMetadata::from_osu_bytes(&bytes),
+        attrs.stars(),
+        attrs.pp(),
+    ))
+}
+
+// UTC, telling the saves apart needs no timezone
+fn timestamp() -> String {
+    let secs = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |since| since.as_secs());
+
+    format!(
+        "{:02}:{:02}:{:02} UTC",
+        secs / 3600 % 24,
+        secs / 60 % 60,
+        secs % 60
+    )
+}